@@ -4,26 +4,104 @@ use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 
+use super::layout::*;
+use super::t_avar::*;
+use super::t_cff::*;
+use super::t_cff2::*;
 use super::t_cmap::*;
+use super::t_cvar::*;
+use super::t_fvar::*;
+use super::t_gdef::*;
+use super::t_glyf::*;
+use super::t_gpos::*;
+use super::t_gsub::*;
+use super::t_gvar::*;
 use super::t_head::*;
+use super::t_hhea::*;
+use super::t_hmtx::*;
+use super::t_hvar::*;
+use super::t_kern::*;
+use super::t_loca::*;
+use super::t_maxp::*;
+use super::t_mvar::*;
 use super::t_name::*;
+use super::t_os2::*;
+use super::t_post::*;
+use super::t_stat::*;
+use super::t_vhea::*;
+use super::t_vmtx::*;
+use super::t_vorg::*;
+use super::t_vvar::*;
 use super::types::*;
 use super::utils::*;
 
+mod instancer;
+
 pub struct Font {
+    /// The font file, tables the crate doesn't parse are copied from it when instancing
+    data: Vec<u8>,
     table_directory: TableDirectory,
     t_name: Option<NamingTable>,
     t_cmap: Option<CharacterToGlyphIndexMappingTable>,
     t_head: Option<FontHeaderTable>,
+    t_hhea: Option<HorizontalHeaderTable>,
+    t_maxp: Option<MaximumProfileTable>,
+    t_hmtx: Option<HorizontalMetricsTable>,
+    t_os2: Option<OS2Table>,
+    t_post: Option<PostScriptTable>,
+    t_cff: Option<CompactFontFormatTable>,
+    t_cff2: Option<CompactFontFormat2Table>,
+    t_loca: Option<IndexToLocationTable>,
+    t_glyf: Option<GlyphDataTable>,
+    t_gsub: Option<GlyphSubstitutionTable>,
+    t_gpos: Option<GlyphPositioningTable>,
+    t_gdef: Option<GlyphDefinitionTable>,
+    t_kern: Option<KerningTable>,
+    t_vhea: Option<VerticalHeaderTable>,
+    t_vmtx: Option<VerticalMetricsTable>,
+    t_vorg: Option<VerticalOriginTable>,
+    t_fvar: Option<FontVariationsTable>,
+    t_avar: Option<AxisVariationsTable>,
+    t_gvar: Option<GlyphVariationsTable>,
+    t_cvar: Option<ControlValueVariationsTable>,
+    t_hvar: Option<HorizontalMetricsVariationsTable>,
+    t_vvar: Option<VerticalMetricsVariationsTable>,
+    t_mvar: Option<MetricsVariationsTable>,
+    t_stat: Option<StyleAttributesTable>,
 }
 
 impl Debug for Font {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Otf")
+            .field_with("data", |f| write!(f, "[u8; {}]", self.data.len()))
             .field("table_directory", &self.table_directory)
             .field("name", &self.t_name)
             .field("cmap", &self.t_cmap)
             .field("head", &self.t_head)
+            .field("hhea", &self.t_hhea)
+            .field("maxp", &self.t_maxp)
+            .field("hmtx", &self.t_hmtx)
+            .field("os2", &self.t_os2)
+            .field("post", &self.t_post)
+            .field("cff", &self.t_cff)
+            .field("cff2", &self.t_cff2)
+            .field("loca", &self.t_loca)
+            .field("glyf", &self.t_glyf)
+            .field("gsub", &self.t_gsub)
+            .field("gpos", &self.t_gpos)
+            .field("gdef", &self.t_gdef)
+            .field("kern", &self.t_kern)
+            .field("vhea", &self.t_vhea)
+            .field("vmtx", &self.t_vmtx)
+            .field("vorg", &self.t_vorg)
+            .field("fvar", &self.t_fvar)
+            .field("avar", &self.t_avar)
+            .field("gvar", &self.t_gvar)
+            .field("cvar", &self.t_cvar)
+            .field("hvar", &self.t_hvar)
+            .field("vvar", &self.t_vvar)
+            .field("mvar", &self.t_mvar)
+            .field("stat", &self.t_stat)
             .finish()
     }
 }
@@ -36,20 +114,587 @@ impl Font {
 
         let table_directory = TableDirectory::from_bytes(v.as_slice())?;
 
-        let mut t_name = None;
-        let mut t_cmap = None;
-        let mut t_head = None;
-        for tr in &table_directory.table_records {
-            match &tr.table_tag.0 {
-                b"name" => t_name = Some(NamingTable::from_bytes(&v.as_slice()[{ tr.offset as usize }..{ (tr.offset + tr.length) as usize }])?),
-                b"cmap" => t_cmap = Some(CharacterToGlyphIndexMappingTable::from_bytes(&v.as_slice()[{ tr.offset as usize }..{ (tr.offset + tr.length) as usize }])?),
-                b"head" => t_head = Some(FontHeaderTable::from_bytes(&v.as_slice()[{ tr.offset as usize }..{ (tr.offset + tr.length) as usize }])?),
-                _ => {}
+        // tables are parsed in dependency order rather than directory order, hmtx needs hhea and maxp, vmtx vhea and maxp, loca head and maxp
+        let t_head = table_directory.table_data(&v, b"head")?.map(FontHeaderTable::from_bytes).transpose()?;
+        let t_maxp = table_directory.table_data(&v, b"maxp")?.map(MaximumProfileTable::from_bytes).transpose()?;
+        let t_hhea = table_directory.table_data(&v, b"hhea")?.map(HorizontalHeaderTable::from_bytes).transpose()?;
+        let t_hmtx = match (table_directory.table_data(&v, b"hmtx")?, &t_hhea, &t_maxp) {
+            (Some(b), Some(hhea), Some(maxp)) => Some(HorizontalMetricsTable::from_bytes(b, hhea.number_of_h_metrics(), maxp.num_glyphs())?),
+            _ => None,
+        };
+        let t_vhea = table_directory.table_data(&v, b"vhea")?.map(VerticalHeaderTable::from_bytes).transpose()?;
+        let t_vmtx = match (table_directory.table_data(&v, b"vmtx")?, &t_vhea, &t_maxp) {
+            (Some(b), Some(vhea), Some(maxp)) => Some(VerticalMetricsTable::from_bytes(b, vhea.num_of_long_ver_metrics(), maxp.num_glyphs())?),
+            _ => None,
+        };
+        let t_name = table_directory.table_data(&v, b"name")?.map(NamingTable::from_bytes).transpose()?;
+        let t_cmap = table_directory.table_data(&v, b"cmap")?.map(CharacterToGlyphIndexMappingTable::from_bytes).transpose()?;
+        let t_os2 = table_directory.table_data(&v, b"OS/2")?.map(OS2Table::from_bytes).transpose()?;
+        let t_post = table_directory.table_data(&v, b"post")?.map(PostScriptTable::from_bytes).transpose()?;
+        let t_cff = table_directory.table_data(&v, b"CFF ")?.map(CompactFontFormatTable::from_bytes).transpose()?;
+        let t_cff2 = table_directory.table_data(&v, b"CFF2")?.map(CompactFontFormat2Table::from_bytes).transpose()?;
+        let t_loca = match (table_directory.table_data(&v, b"loca")?, &t_head, &t_maxp) {
+            (Some(b), Some(head), Some(maxp)) => Some(IndexToLocationTable::from_bytes(b, head.index_to_loc_format(), maxp.num_glyphs())?),
+            _ => None,
+        };
+        let t_glyf = table_directory.table_data(&v, b"glyf")?.map(GlyphDataTable::from_bytes).transpose()?;
+        let t_gsub = table_directory.table_data(&v, b"GSUB")?.map(GlyphSubstitutionTable::from_bytes).transpose()?;
+        let t_gpos = table_directory.table_data(&v, b"GPOS")?.map(GlyphPositioningTable::from_bytes).transpose()?;
+        let t_gdef = table_directory.table_data(&v, b"GDEF")?.map(GlyphDefinitionTable::from_bytes).transpose()?;
+        let t_kern = table_directory.table_data(&v, b"kern")?.map(KerningTable::from_bytes).transpose()?;
+        let t_vorg = table_directory.table_data(&v, b"VORG")?.map(VerticalOriginTable::from_bytes).transpose()?;
+        let t_fvar = table_directory.table_data(&v, b"fvar")?.map(FontVariationsTable::from_bytes).transpose()?;
+        let t_avar = table_directory.table_data(&v, b"avar")?.map(AxisVariationsTable::from_bytes).transpose()?;
+        let t_gvar = table_directory.table_data(&v, b"gvar")?.map(GlyphVariationsTable::from_bytes).transpose()?;
+        let t_cvar = match (table_directory.table_data(&v, b"cvar")?, table_directory.find_table(b"cvt "), &t_fvar) {
+            (Some(b), Some(cvt), Some(fvar)) => Some(ControlValueVariationsTable::from_bytes(b, fvar.axes().len() as u16, cvt.length as usize / 2)?),
+            _ => None,
+        };
+        let t_hvar = table_directory.table_data(&v, b"HVAR")?.map(HorizontalMetricsVariationsTable::from_bytes).transpose()?;
+        let t_vvar = table_directory.table_data(&v, b"VVAR")?.map(VerticalMetricsVariationsTable::from_bytes).transpose()?;
+        let t_mvar = table_directory.table_data(&v, b"MVAR")?.map(MetricsVariationsTable::from_bytes).transpose()?;
+        let t_stat = table_directory.table_data(&v, b"STAT")?.map(StyleAttributesTable::from_bytes).transpose()?;
+
+        if let (Some(cmap), Some(maxp)) = (&t_cmap, &t_maxp) {
+            cmap.check_glyph_ids(maxp.num_glyphs())?;
+        }
+        if let (Some(loca), Some(glyf)) = (&t_loca, &t_glyf)
+            && loca.glyf_length() > glyf.len()
+        {
+            return Err(format!("loca points past the end of glyf ({} > {})", loca.glyf_length(), glyf.len()).into());
+        }
+
+        Ok(Self {
+            data: v,
+            table_directory,
+            t_name,
+            t_cmap,
+            t_head,
+            t_hhea,
+            t_maxp,
+            t_hmtx,
+            t_os2,
+            t_post,
+            t_cff,
+            t_cff2,
+            t_loca,
+            t_glyf,
+            t_gsub,
+            t_gpos,
+            t_gdef,
+            t_kern,
+            t_vhea,
+            t_vmtx,
+            t_vorg,
+            t_fvar,
+            t_avar,
+            t_gvar,
+            t_cvar,
+            t_hvar,
+            t_vvar,
+            t_mvar,
+            t_stat,
+        })
+    }
+
+    pub fn head(&self) -> Option<&FontHeaderTable> {
+        self.t_head.as_ref()
+    }
+
+    pub fn cff(&self) -> Option<&CompactFontFormatTable> {
+        self.t_cff.as_ref()
+    }
+
+    pub fn cff2(&self) -> Option<&CompactFontFormat2Table> {
+        self.t_cff2.as_ref()
+    }
+
+    pub fn gsub(&self) -> Option<&GlyphSubstitutionTable> {
+        self.t_gsub.as_ref()
+    }
+
+    pub fn gdef(&self) -> Option<&GlyphDefinitionTable> {
+        self.t_gdef.as_ref()
+    }
+
+    pub fn gpos(&self) -> Option<&GlyphPositioningTable> {
+        self.t_gpos.as_ref()
+    }
+
+    pub fn hhea(&self) -> Option<&HorizontalHeaderTable> {
+        self.t_hhea.as_ref()
+    }
+
+    pub fn kern(&self) -> Option<&KerningTable> {
+        self.t_kern.as_ref()
+    }
+
+    pub fn maxp(&self) -> Option<&MaximumProfileTable> {
+        self.t_maxp.as_ref()
+    }
+
+    pub fn os2(&self) -> Option<&OS2Table> {
+        self.t_os2.as_ref()
+    }
+
+    pub fn post(&self) -> Option<&PostScriptTable> {
+        self.t_post.as_ref()
+    }
+
+    /// Glyph name from post, or from the CFF charset when post carries no names (version 3.0)
+    pub fn glyph_name(&self, glyph_id: GlyphId) -> Option<String> {
+        if let Some(post) = &self.t_post
+            && post.has_glyph_names()
+        {
+            return post.glyph_name(glyph_id).map(String::from);
+        }
+        self.t_cff.as_ref()?.glyph_name(glyph_id)
+    }
+
+    /// Advance width in font design units
+    pub fn advance_width(&self, glyph_id: GlyphId) -> Option<u16> {
+        self.t_hmtx.as_ref()?.advance_width(glyph_id)
+    }
+
+    /// Left side bearing in font design units
+    pub fn left_side_bearing(&self, glyph_id: GlyphId) -> Option<i16> {
+        self.t_hmtx.as_ref()?.left_side_bearing(glyph_id)
+    }
+
+    /// Advance width at normalized `coords`, from HVAR or else the gvar varied phantom points
+    pub fn advance_width_at(&self, glyph_id: GlyphId, coords: &[F2Dot14]) -> Option<f32> {
+        let advance_width = self.advance_width(glyph_id)? as f32;
+        if is_default_location(coords) {
+            return Some(advance_width);
+        }
+        if let Some(hvar) = &self.t_hvar {
+            return Some(advance_width + hvar.advance_width_delta(glyph_id, coords).unwrap_or(0.0));
+        }
+        match self.varied_glyf_outline(glyph_id, coords) {
+            Some(outline) => Some(outline.advance_width()),
+            None => Some(advance_width),
+        }
+    }
+
+    /// Left side bearing at normalized `coords`, from the HVAR lsb mapping or else the left of the varied glyf outline
+    pub fn left_side_bearing_at(&self, glyph_id: GlyphId, coords: &[F2Dot14]) -> Option<f32> {
+        let left_side_bearing = self.left_side_bearing(glyph_id)? as f32;
+        if is_default_location(coords) {
+            return Some(left_side_bearing);
+        }
+        if let Some(delta) = self.t_hvar.as_ref().and_then(|hvar| hvar.lsb_delta(glyph_id, coords)) {
+            return Some(left_side_bearing + delta);
+        }
+        let Some(outline) = self.varied_glyf_outline(glyph_id, coords) else {
+            return Some(left_side_bearing);
+        };
+        let x_min = outline.outline.points.iter().map(|p| p.x).reduce(f32::min);
+        Some(x_min.map_or(left_side_bearing, |x_min| x_min - outline.phantom_points[0].x))
+    }
+
+    /// Advances and offsets of a glyph buffer in logical order, from the varied hmtx advances and the GPOS lookups of `features`
+    pub fn position(&self, glyphs: &[GlyphInfo], script: Tag, language: Option<Tag>, features: &[Tag], coords: &[F2Dot14], right_to_left: bool) -> Vec<GlyphPosition> {
+        let mut positions: Vec<_> = glyphs.iter().map(|g| GlyphPosition::new(self.advance_width_at(g.glyph_id, coords).unwrap_or(0.0).round() as i32, 0)).collect();
+        if let Some(gpos) = &self.t_gpos {
+            let lookups = gpos.lookups(script, language, features, coords);
+            gpos.position(glyphs, &mut positions, &lookups, coords, right_to_left, self.t_gdef.as_ref());
+        }
+        positions
+    }
+
+    /// Horizontal kerning of a pair from the kern table, the value to add to the advance of `left`
+    ///
+    /// Only this value moves the pen in horizontal layout, callers positioning marks off the baseline or honoring
+    /// minimum values get the cross-stream and minimum parts through `self.kern()` and `KerningTable::pair_kerning`
+    pub fn kerning(&self, left: GlyphId, right: GlyphId) -> i16 {
+        self.t_kern.as_ref().map_or(0, |kern| kern.pair_kerning(left, right, false).value)
+    }
+
+    /// Class GDEF gives a glyph, `None` without GDEF or when the glyph is unclassified
+    pub fn glyph_class(&self, glyph_id: GlyphId) -> Option<GlyphClass> {
+        self.t_gdef.as_ref()?.glyph_class(glyph_id)
+    }
+
+    /// Caret positions inside a ligature glyph at normalized `coords`, contour point carets are read from the glyf outline
+    pub fn ligature_carets(&self, glyph_id: GlyphId, coords: &[F2Dot14]) -> Vec<f32> {
+        let Some(gdef) = &self.t_gdef else {
+            return Vec::new();
+        };
+        let Some(carets) = gdef.ligature_carets(glyph_id) else {
+            return Vec::new();
+        };
+
+        let mut glyf_outline = None;
+        carets
+            .iter()
+            .filter_map(|caret| match caret {
+                CaretValue::ContourPoint(point) => {
+                    let outline = glyf_outline.get_or_insert_with(|| self.glyf_outline_at(glyph_id, coords).ok()).as_ref()?;
+                    outline.outline.points.get(*point as usize).map(|p| p.x)
+                }
+                caret => caret.coordinate(gdef.item_var_store(), coords),
+            })
+            .collect()
+    }
+
+    pub fn vhea(&self) -> Option<&VerticalHeaderTable> {
+        self.t_vhea.as_ref()
+    }
+
+    /// Advance height in font design units
+    pub fn advance_height(&self, glyph_id: GlyphId) -> Option<u16> {
+        self.t_vmtx.as_ref()?.advance_height(glyph_id)
+    }
+
+    /// Top side bearing in font design units
+    pub fn top_side_bearing(&self, glyph_id: GlyphId) -> Option<i16> {
+        self.t_vmtx.as_ref()?.top_side_bearing(glyph_id)
+    }
+
+    /// Advance height at normalized `coords`, from VVAR or else the gvar varied phantom points
+    pub fn advance_height_at(&self, glyph_id: GlyphId, coords: &[F2Dot14]) -> Option<f32> {
+        let advance_height = self.advance_height(glyph_id)? as f32;
+        if is_default_location(coords) {
+            return Some(advance_height);
+        }
+        if let Some(vvar) = &self.t_vvar {
+            return Some(advance_height + vvar.advance_height_delta(glyph_id, coords).unwrap_or(0.0));
+        }
+        match self.varied_glyf_outline(glyph_id, coords) {
+            Some(outline) => Some(outline.advance_height()),
+            None => Some(advance_height),
+        }
+    }
+
+    /// Top side bearing at normalized `coords`, from the VVAR tsb mapping or else the top of the varied glyf outline
+    pub fn top_side_bearing_at(&self, glyph_id: GlyphId, coords: &[F2Dot14]) -> Option<f32> {
+        let top_side_bearing = self.top_side_bearing(glyph_id)? as f32;
+        if is_default_location(coords) {
+            return Some(top_side_bearing);
+        }
+        if let Some(delta) = self.t_vvar.as_ref().and_then(|vvar| vvar.tsb_delta(glyph_id, coords)) {
+            return Some(top_side_bearing + delta);
+        }
+        let Some(outline) = self.varied_glyf_outline(glyph_id, coords) else {
+            return Some(top_side_bearing);
+        };
+        let y_max = outline.outline.points.iter().map(|p| p.y).reduce(f32::max);
+        Some(y_max.map_or(top_side_bearing, |y_max| outline.phantom_points[2].y - y_max))
+    }
+
+    /// Y coordinate of the vertical origin from VORG, or else the top of the glyph bbox plus its vmtx top side bearing
+    pub fn vertical_origin_y(&self, glyph_id: GlyphId) -> Option<i16> {
+        if let Some(vorg) = &self.t_vorg {
+            return Some(vorg.vert_origin_y(glyph_id));
+        }
+        let top_side_bearing = self.top_side_bearing(glyph_id)?;
+        Some(self.glyph_y_max(glyph_id)?.saturating_add(top_side_bearing))
+    }
+
+    /// Y coordinate of the vertical origin at normalized `coords`, VORG values are varied by VVAR and glyf origins follow the varied phantom points
+    pub fn vertical_origin_y_at(&self, glyph_id: GlyphId, coords: &[F2Dot14]) -> Option<f32> {
+        if let Some(vorg) = &self.t_vorg {
+            let delta = self.t_vvar.as_ref().and_then(|vvar| vvar.v_org_delta(glyph_id, coords)).unwrap_or(0.0);
+            return Some(vorg.vert_origin_y(glyph_id) as f32 + delta);
+        }
+        let vertical_origin_y = self.vertical_origin_y(glyph_id)? as f32;
+        if is_default_location(coords) {
+            return Some(vertical_origin_y);
+        }
+        match self.varied_glyf_outline(glyph_id, coords) {
+            Some(outline) => Some(outline.phantom_points[2].y),
+            None => Some(vertical_origin_y),
+        }
+    }
+
+    /// Top of a glyph, from the glyf header or the control box of a CFF outline, 0 for empty glyphs
+    fn glyph_y_max(&self, glyph_id: GlyphId) -> Option<i16> {
+        if let (Some(loca), Some(glyf)) = (&self.t_loca, &self.t_glyf) {
+            return Some(glyf.glyph(loca, glyph_id).ok()?.map_or(0, |g| g.header().bbox().3));
+        }
+        let mut sink = YMaxSink(None);
+        self.outline(glyph_id, &mut sink).ok()?;
+        Some(sink.0.map_or(0, |y| y.ceil() as i16))
+    }
+
+    /// Draw a glyph outline from glyf, CFF or CFF2, in font design units
+    pub fn outline(&self, glyph_id: GlyphId, sink: &mut impl OutlineSink) -> Result<(), Box<dyn Error>> {
+        self.outline_at(glyph_id, &[], sink)
+    }
+
+    /// Draw a glyph outline at normalized variation coordinates, one per fvar axis
+    pub fn outline_at(&self, glyph_id: GlyphId, coords: &[F2Dot14], sink: &mut impl OutlineSink) -> Result<(), Box<dyn Error>> {
+        if self.t_glyf.is_some() {
+            self.glyf_outline_at(glyph_id, coords)?.outline.emit(sink);
+            return Ok(());
+        }
+        if let Some(cff2) = &self.t_cff2 {
+            return cff2.outline(glyph_id, coords, sink);
+        }
+        match &self.t_cff {
+            Some(cff) => cff.outline(glyph_id, sink),
+            None => Err("font has no glyf, CFF or CFF2 outlines".into()),
+        }
+    }
+
+    pub fn fvar(&self) -> Option<&FontVariationsTable> {
+        self.t_fvar.as_ref()
+    }
+
+    pub fn avar(&self) -> Option<&AxisVariationsTable> {
+        self.t_avar.as_ref()
+    }
+
+    /// A name string from the name table, see `NamingTable::name`
+    pub fn name(&self, name_id: u16) -> Option<String> {
+        self.t_name.as_ref()?.name(name_id)
+    }
+
+    /// Normalized coordinates, one per fvar axis, for user axis values, axes not given stay at their default
+    pub fn normalize_coords(&self, values: &[(Tag, f32)]) -> Vec<F2Dot14> {
+        let Some(fvar) = &self.t_fvar else {
+            return vec![];
+        };
+        // avar maps the 16.16 value, only the result is rounded (half up) to F2Dot14
+        let to_f2dot14 = |v: f32| (v * 16384.0 + 0.5).floor() / 16384.0;
+        fvar.axes()
+            .iter()
+            .enumerate()
+            .map(|(i, axis)| {
+                let value = values.iter().find(|(tag, _)| axis.axis_tag() == *tag).map_or(axis.default_value(), |&(_, v)| v);
+                let coord = fvar.normalize(i, value).unwrap_or(0.0);
+                to_f2dot14(self.t_avar.as_ref().map_or(coord, |avar| avar.map(i, coord)))
+            })
+            .collect()
+    }
+
+    /// Named instances of a variable font, with their names resolved from the name table
+    pub fn named_instances(&self) -> Vec<NamedInstance> {
+        let Some(fvar) = &self.t_fvar else {
+            return vec![];
+        };
+        fvar.instances()
+            .iter()
+            .map(|ir| NamedInstance {
+                subfamily_name: self.name(ir.subfamily_name_id()),
+                post_script_name: ir.post_script_name_id().and_then(|id| self.name(id)),
+                coordinates: fvar.axes().iter().zip(ir.coordinates()).map(|(axis, &v)| (axis.axis_tag(), v)).collect(),
+            })
+            .collect()
+    }
+
+    pub fn stat(&self) -> Option<&StyleAttributesTable> {
+        self.t_stat.as_ref()
+    }
+
+    /// Style name STAT composes for user `coords`, axes not given are at their fvar default
+    ///
+    /// Names of the matching axis values are joined in axis ordering, with `elide` the elidable ones are left out,
+    /// and a name left empty becomes the elided fallback name, such as "Regular".
+    pub fn style_name(&self, coords: &[(Tag, Fixed)], elide: bool) -> Option<String> {
+        let stat = self.t_stat.as_ref()?;
+        let names: Vec<_> = stat
+            .axis_values_at(&self.design_axis_coords(stat, coords))
+            .into_iter()
+            .filter(|av| !(elide && av.is_elidable()))
+            .filter_map(|av| self.name(av.value_name_id()))
+            .collect();
+        if names.is_empty() { self.name(stat.elided_fallback_name_id()) } else { Some(names.join(" ")) }
+    }
+
+    /// User coordinates of the style-linked counterpart of `coords`, such as Bold for Regular, from STAT format 3 axis values
+    pub fn style_linked_coords(&self, coords: &[(Tag, Fixed)]) -> Option<Vec<(Tag, Fixed)>> {
+        let stat = self.t_stat.as_ref()?;
+        let mut axis_coords = self.design_axis_coords(stat, coords);
+        let links: Vec<_> = stat.axis_values_at(&axis_coords).into_iter().filter_map(|av| av.linked_value()).collect();
+        if links.is_empty() {
+            return None;
+        }
+        for (axis_index, linked_value) in links {
+            axis_coords[axis_index as usize] = Some(linked_value);
+        }
+        Some(stat.design_axes().iter().zip(axis_coords).filter_map(|(axis, v)| Some((axis.axis_tag(), v?))).collect())
+    }
+
+    /// User coordinate of each STAT design axis, from `coords` or else the fvar default, `None` for axes neither knows
+    fn design_axis_coords(&self, stat: &StyleAttributesTable, coords: &[(Tag, Fixed)]) -> Vec<Option<Fixed>> {
+        let default = |tag: Tag| Some(self.t_fvar.as_ref()?.axes().iter().find(|axis| tag == axis.axis_tag())?.default_value());
+        stat.design_axes()
+            .iter()
+            .map(|axis| coords.iter().find(|(tag, _)| axis.axis_tag() == *tag).map(|&(_, v)| v).or_else(|| default(axis.axis_tag())))
+            .collect()
+    }
+
+    /// Encode a static instance of a variable font at user `location`, axes not given are pinned at their default
+    ///
+    /// Glyph outlines, metrics, control values, font-wide metrics and GDEF and GPOS values take their variations at the location,
+    /// FeatureVariations are resolved, the variation tables are dropped and the names describe the instance.
+    ///
+    /// Partial instancing, which limits an axis to a range and keeps it variable, isn't supported: every axis is pinned,
+    /// and an axis given more than once is an error.
+    pub fn instantiate(&self, location: &[(Tag, Fixed)]) -> Result<Vec<u8>, Box<dyn Error>> {
+        instancer::instantiate(self, location)
+    }
+
+    pub fn gvar(&self) -> Option<&GlyphVariationsTable> {
+        self.t_gvar.as_ref()
+    }
+
+    pub fn cvar(&self) -> Option<&ControlValueVariationsTable> {
+        self.t_cvar.as_ref()
+    }
+
+    pub fn hvar(&self) -> Option<&HorizontalMetricsVariationsTable> {
+        self.t_hvar.as_ref()
+    }
+
+    pub fn vvar(&self) -> Option<&VerticalMetricsVariationsTable> {
+        self.t_vvar.as_ref()
+    }
+
+    pub fn mvar(&self) -> Option<&MetricsVariationsTable> {
+        self.t_mvar.as_ref()
+    }
+
+    /// Font-wide metrics at normalized `coords`, varied by MVAR, an empty slice gives the default instance
+    pub fn metrics(&self, coords: &[F2Dot14]) -> FontMetrics {
+        let delta = |tag: Tag| self.t_mvar.as_ref().map_or(0.0, |mvar| mvar.delta(tag, coords));
+        let varied = |value: Option<i16>, tag: Tag| value.map(|v| v as f32 + delta(tag));
+        let os2 = self.t_os2.as_ref();
+        let post = self.t_post.as_ref();
+
+        // the OS/2 typo metrics win when USE_TYPO_METRICS is set or when there is no hhea, MVAR only varies the typo metrics
+        let use_typo_metrics = os2.is_some_and(|os2| os2.fs_selection().use_typo_metrics() && os2.typo_ascender().is_some()) || self.t_hhea.is_none();
+        let (ascender, descender, line_gap) = match (use_typo_metrics, &self.t_hhea) {
+            (false, Some(hhea)) => (Some(hhea.ascender() as f32), Some(hhea.descender() as f32), Some(hhea.line_gap() as f32)),
+            _ => (
+                varied(os2.and_then(|os2| os2.typo_ascender()), HORIZONTAL_ASCENDER),
+                varied(os2.and_then(|os2| os2.typo_descender()), HORIZONTAL_DESCENDER),
+                varied(os2.and_then(|os2| os2.typo_line_gap()), HORIZONTAL_LINE_GAP),
+            ),
+        };
+
+        FontMetrics {
+            units_per_em: self.t_head.as_ref().map(|head| head.units_per_em()),
+            ascender,
+            descender,
+            line_gap,
+            x_height: varied(os2.and_then(|os2| os2.x_height()), X_HEIGHT),
+            cap_height: varied(os2.and_then(|os2| os2.cap_height()), CAP_HEIGHT),
+            underline_position: varied(post.map(|post| post.underline_position()), UNDERLINE_OFFSET),
+            underline_thickness: varied(post.map(|post| post.underline_thickness()), UNDERLINE_SIZE),
+            strikeout_position: varied(os2.map(|os2| os2.strikeout_position()), STRIKEOUT_OFFSET),
+            strikeout_size: varied(os2.map(|os2| os2.strikeout_size()), STRIKEOUT_SIZE),
+        }
+    }
+
+    /// The glyf outline at `coords` when gvar varies it, the metric fallback for fonts without HVAR or VVAR
+    fn varied_glyf_outline(&self, glyph_id: GlyphId, coords: &[F2Dot14]) -> Option<GlyphOutline> {
+        self.t_gvar.as_ref()?;
+        self.glyf_outline_at(glyph_id, coords).ok()
+    }
+
+    /// TrueType outline and phantom points at normalized `coords`, the phantom points carry the gvar varied advances
+    pub fn glyf_outline_at(&self, glyph_id: GlyphId, coords: &[F2Dot14]) -> Result<GlyphOutline, Box<dyn Error>> {
+        let (Some(loca), Some(glyf)) = (&self.t_loca, &self.t_glyf) else {
+            return Err("font has no glyf outlines".into());
+        };
+        glyf.outline_points_at(loca, glyph_id, self.t_gvar.as_ref(), coords, &|glyph_id| self.phantom_metrics(glyph_id))
+    }
+
+    /// Without vmtx the vertical advance spans the hhea ascender to descender, with the origin on the ascender
+    fn phantom_metrics(&self, glyph_id: GlyphId) -> PhantomMetrics {
+        let (advance_height, top_side_bearing) = match (&self.t_vmtx, &self.t_hhea) {
+            (Some(vmtx), _) => (vmtx.advance_height(glyph_id).unwrap_or(0), vmtx.top_side_bearing(glyph_id).unwrap_or(0)),
+            (None, Some(hhea)) => {
+                let y_max = self.glyph_y_max(glyph_id).unwrap_or(0);
+                ((hhea.ascender() as i32 - hhea.descender() as i32) as u16, hhea.ascender().saturating_sub(y_max))
             }
+            (None, None) => (0, 0),
+        };
+        PhantomMetrics {
+            advance_width: self.advance_width(glyph_id).unwrap_or(0),
+            left_side_bearing: self.left_side_bearing(glyph_id).unwrap_or(0),
+            advance_height,
+            top_side_bearing,
         }
+    }
+
+    /// Glyph for a character, through the best Unicode subtable the cmap offers
+    pub fn glyph_index(&self, c: char) -> Option<GlyphId> {
+        self.t_cmap.as_ref()?.lookup(c as u32)
+    }
+
+    /// Glyph for the variation sequence `base` + `selector`, `None` when the font doesn't support the sequence
+    pub fn glyph_for_variation(&self, base: char, selector: char) -> Option<GlyphId> {
+        let t_cmap = self.t_cmap.as_ref()?;
+        match t_cmap.lookup_variation(base as u32, selector as u32)? {
+            VariationGlyph::Default => self.glyph_index(base),
+            VariationGlyph::Glyph(glyph_id) => Some(glyph_id),
+        }
+    }
+}
+
+/// A named instance from fvar
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedInstance {
+    pub subfamily_name: Option<String>,
+    pub post_script_name: Option<String>,
+    /// User coordinates, one per fvar axis
+    pub coordinates: Vec<(Tag, Fixed)>,
+}
+
+/// Font-wide metrics in font design units
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontMetrics {
+    pub units_per_em: Option<u16>,
+    /// From hhea, or from the OS/2 typo metrics when USE_TYPO_METRICS is set, MVAR only varies the latter
+    pub ascender: Option<f32>,
+    pub descender: Option<f32>,
+    pub line_gap: Option<f32>,
+    pub x_height: Option<f32>,
+    pub cap_height: Option<f32>,
+    pub underline_position: Option<f32>,
+    pub underline_thickness: Option<f32>,
+    pub strikeout_position: Option<f32>,
+    pub strikeout_size: Option<f32>,
+}
 
-        Ok(Self { table_directory, t_name, t_cmap, t_head })
+/// No coordinates, or all of them at the axis defaults
+fn is_default_location(coords: &[F2Dot14]) -> bool {
+    coords.iter().all(|&c| 0.0 == c)
+}
+
+/// Tracks the highest point of an outline, control points included
+struct YMaxSink(Option<f32>);
+
+impl YMaxSink {
+    fn add(&mut self, y: f32) {
+        self.0 = Some(self.0.map_or(y, |m| m.max(y)));
+    }
+}
+
+impl OutlineSink for YMaxSink {
+    fn move_to(&mut self, _x: f32, y: f32) {
+        self.add(y);
+    }
+
+    fn line_to(&mut self, _x: f32, y: f32) {
+        self.add(y);
+    }
+
+    fn quad_to(&mut self, _x1: f32, y1: f32, _x: f32, y: f32) {
+        self.add(y1);
+        self.add(y);
+    }
+
+    fn curve_to(&mut self, _x1: f32, y1: f32, _x2: f32, y2: f32, _x: f32, y: f32) {
+        self.add(y1);
+        self.add(y2);
+        self.add(y);
     }
+
+    fn close(&mut self) {}
 }
 
 pub struct TableDirectory {
@@ -99,6 +744,22 @@ impl TableDirectory {
             table_records,
         })
     }
+
+    pub fn find_table(&self, tag: &[u8; 4]) -> Option<&TableRecord> {
+        self.table_records.iter().find(|tr| tr.table_tag == *tag)
+    }
+
+    /// Bytes of the table `tag` inside the font file `b`, `None` when the font has no such table
+    pub fn table_data<'a>(&self, b: &'a [u8], tag: &[u8; 4]) -> Result<Option<&'a [u8]>, Box<dyn Error>> {
+        let Some(tr) = self.find_table(tag) else {
+            return Ok(None);
+        };
+        let start = tr.offset as usize;
+        match b.get(start..start + tr.length as usize) {
+            Some(t) => Ok(Some(t)),
+            None => Err(format!("table {:?} lies outside the font data", tr.table_tag).into()),
+        }
+    }
 }
 
 pub struct TableRecord {
@@ -130,3 +791,130 @@ impl TableRecord {
         Ok(Self { table_tag, checksum, offset, length })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A font file holding `tables` in the given order, checksums are left at 0
+    fn load(tables: &[(&[u8; 4], Vec<u8>)], name: &str) -> Font {
+        let mut w = Vec::new();
+        write_uint32(&mut w, 0x00010000);
+        [tables.len() as u16, 0, 0, 0].iter().for_each(|&v| write_uint16(&mut w, v));
+        let mut offset = 12 + 16 * tables.len();
+        for (tag, b) in tables {
+            write_tag(&mut w, Tag::new(tag));
+            write_uint32(&mut w, 0);
+            write_uint32(&mut w, offset as u32);
+            write_uint32(&mut w, b.len() as u32);
+            offset += b.len();
+        }
+        tables.iter().for_each(|(_, b)| w.extend(b));
+
+        let path = std::env::temp_dir().join(format!("otf-font-{}-{}.ttf", std::process::id(), name));
+        std::fs::write(&path, w).unwrap();
+        let font = Font::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        font.unwrap()
+    }
+
+    /// head, maxp, hhea with an 800 ascender and -200 descender, hmtx, loca and glyf for two glyphs, the first
+    /// topping out at 700 and the second empty
+    fn glyf_tables() -> Vec<(&'static [u8; 4], Vec<u8>)> {
+        let mut head = vec![0; 54];
+        put_uint16(&mut head, 0, 1).unwrap();
+        put_uint32(&mut head, 12, 0x5F0F3CF5).unwrap();
+        put_uint16(&mut head, 18, 1000).unwrap();
+
+        let mut maxp = Vec::new();
+        write_uint32(&mut maxp, 0x00005000);
+        write_uint16(&mut maxp, 2);
+
+        let mut hhea = vec![0; 36];
+        put_uint16(&mut hhea, 0, 1).unwrap();
+        put_int16(&mut hhea, 4, 800).unwrap();
+        put_int16(&mut hhea, 6, -200).unwrap();
+        put_uint16(&mut hhea, 34, 2).unwrap();
+
+        let mut hmtx = Vec::new();
+        [500, 0, 250, 0].iter().for_each(|&v| write_uint16(&mut hmtx, v));
+
+        // a single on-curve point at (100, 700)
+        let mut glyf = Vec::new();
+        [1, 100, 700, 100, 700, 0, 0].iter().for_each(|&v| write_int16(&mut glyf, v));
+        glyf.push(0x01);
+        [100, 700].iter().for_each(|&v| write_int16(&mut glyf, v));
+        glyf.push(0);
+        let mut loca = Vec::new();
+        [0, 10, 10].iter().for_each(|&v| write_uint16(&mut loca, v));
+
+        vec![(b"head", head), (b"maxp", maxp), (b"hhea", hhea), (b"hmtx", hmtx), (b"loca", loca), (b"glyf", glyf)]
+    }
+
+    /// vhea and vmtx with one long metric advancing 1000 with a top side bearing of 100, the second glyph's bearing is 50
+    fn vertical_tables() -> Vec<(&'static [u8; 4], Vec<u8>)> {
+        let mut vhea = vec![0; 36];
+        put_uint32(&mut vhea, 0, 0x00011000).unwrap();
+        put_uint16(&mut vhea, 34, 1).unwrap();
+
+        let mut vmtx = Vec::new();
+        [1000, 100, 50].iter().for_each(|&v| write_uint16(&mut vmtx, v));
+
+        vec![(b"vhea", vhea), (b"vmtx", vmtx)]
+    }
+
+    /// Version 0 naming table of Windows English records
+    fn naming_table(records: &[(u16, &str)]) -> Vec<u8> {
+        let mut w = Vec::new();
+        [0, records.len() as u16, 6 + 12 * records.len() as u16].iter().for_each(|&v| write_uint16(&mut w, v));
+        let mut storage: Vec<u8> = Vec::new();
+        for &(name_id, s) in records {
+            let b: Vec<_> = s.encode_utf16().flat_map(u16::to_be_bytes).collect();
+            [3, 1, 0x0409, name_id, b.len() as u16, storage.len() as u16].iter().for_each(|&v| write_uint16(&mut w, v));
+            storage.extend(b);
+        }
+        w.extend(storage);
+        w
+    }
+
+    #[test]
+    fn vertical_origin() {
+        let font = load(&[glyf_tables(), vertical_tables()].concat(), "vertical-origin");
+        assert_eq!((Some(800), Some(50)), (font.vertical_origin_y(0), font.vertical_origin_y(1)));
+
+        let mut vorg = Vec::new();
+        [1, 0, 880, 1, 1, 900].iter().for_each(|&v| write_int16(&mut vorg, v));
+        let font = load(&[glyf_tables(), vertical_tables(), vec![(b"VORG", vorg)]].concat(), "vertical-origin-vorg");
+        assert_eq!((Some(880), Some(900)), (font.vertical_origin_y(0), font.vertical_origin_y(1)));
+
+        let font = load(&glyf_tables(), "vertical-origin-no-vmtx");
+        assert_eq!((None, None), (font.vertical_origin_y(0), font.advance_height(0)));
+    }
+
+    #[test]
+    fn phantom_metrics_without_vmtx() {
+        let font = load(&[glyf_tables(), vertical_tables()].concat(), "phantom");
+        let m = font.phantom_metrics(1);
+        assert_eq!((250, 0, 1000, 50), (m.advance_width, m.left_side_bearing, m.advance_height, m.top_side_bearing));
+
+        let font = load(&glyf_tables(), "phantom-no-vmtx");
+        let (m0, m1) = (font.phantom_metrics(0), font.phantom_metrics(1));
+        assert_eq!((500, 1000, 100), (m0.advance_width, m0.advance_height, m0.top_side_bearing));
+        assert_eq!((250, 1000, 800), (m1.advance_width, m1.advance_height, m1.top_side_bearing));
+    }
+
+    #[test]
+    fn style_names() {
+        let name = naming_table(&[(2, "Book"), (258, "Regular"), (259, "Bold"), (260, "Semibold"), (261, "Upright"), (262, "Bold Italic"), (263, "Italic")]);
+        let font = load(&[(b"STAT", crate::t_stat::builders::weight_and_italic(0)), (b"name", name)], "style-names");
+        let (wght, ital) = (Tag::new(b"wght"), Tag::new(b"ital"));
+        assert_eq!(Some("Upright Bold".to_string()), font.style_name(&[(wght, 700.0), (ital, 0.0)], false));
+        assert_eq!(Some("Bold".to_string()), font.style_name(&[(ital, 0.0), (wght, 700.0)], true));
+        assert_eq!(Some("Semibold".to_string()), font.style_name(&[(wght, 650.0), (ital, 0.0)], true));
+        assert_eq!(Some("Bold Italic".to_string()), font.style_name(&[(wght, 700.0), (ital, 1.0)], true));
+        // with every name elided the style falls back to the subfamily name
+        assert_eq!(Some("Upright Regular".to_string()), font.style_name(&[(wght, 400.0), (ital, 0.0)], false));
+        assert_eq!(Some("Book".to_string()), font.style_name(&[(wght, 400.0), (ital, 0.0)], true));
+        assert_eq!(Some("Book".to_string()), font.style_name(&[(wght, 400.0)], true));
+    }
+}
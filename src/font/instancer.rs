@@ -0,0 +1,757 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use super::*;
+
+/// Tables that only carry variations, a static instance leaves them out
+const VARIATION_TABLES: [&[u8; 4]; 7] = [b"fvar", b"avar", b"gvar", b"cvar", b"HVAR", b"VVAR", b"MVAR"];
+
+/// The field each MVAR value tag varies: table, byte offset, and whether the field is unsigned
+///
+/// The `hasc`, `hdsc` and `hlgp` deltas go to the OS/2 typo metrics only, hhea keeps its own values, as in `Font::metrics`.
+const MVAR_FIELDS: [(Tag, &[u8; 4], usize, bool); 38] = [
+    (HORIZONTAL_ASCENDER, b"OS/2", 68, false),
+    (HORIZONTAL_DESCENDER, b"OS/2", 70, false),
+    (HORIZONTAL_LINE_GAP, b"OS/2", 72, false),
+    (HORIZONTAL_CLIPPING_ASCENT, b"OS/2", 74, true),
+    (HORIZONTAL_CLIPPING_DESCENT, b"OS/2", 76, true),
+    (X_HEIGHT, b"OS/2", 86, false),
+    (CAP_HEIGHT, b"OS/2", 88, false),
+    (Tag::new(b"sbxs"), b"OS/2", 10, false),
+    (Tag::new(b"sbys"), b"OS/2", 12, false),
+    (Tag::new(b"sbxo"), b"OS/2", 14, false),
+    (Tag::new(b"sbyo"), b"OS/2", 16, false),
+    (Tag::new(b"spxs"), b"OS/2", 18, false),
+    (Tag::new(b"spys"), b"OS/2", 20, false),
+    (Tag::new(b"spxo"), b"OS/2", 22, false),
+    (Tag::new(b"spyo"), b"OS/2", 24, false),
+    (STRIKEOUT_SIZE, b"OS/2", 26, false),
+    (STRIKEOUT_OFFSET, b"OS/2", 28, false),
+    (HORIZONTAL_CARET_RISE, b"hhea", 18, false),
+    (HORIZONTAL_CARET_RUN, b"hhea", 20, false),
+    (HORIZONTAL_CARET_OFFSET, b"hhea", 22, false),
+    (VERTICAL_ASCENDER, b"vhea", 4, false),
+    (VERTICAL_DESCENDER, b"vhea", 6, false),
+    (VERTICAL_LINE_GAP, b"vhea", 8, false),
+    (Tag::new(b"vcrs"), b"vhea", 18, false),
+    (Tag::new(b"vcrn"), b"vhea", 20, false),
+    (Tag::new(b"vcof"), b"vhea", 22, false),
+    (UNDERLINE_OFFSET, b"post", 8, false),
+    (UNDERLINE_SIZE, b"post", 10, false),
+    (Tag::new(b"gsp0"), b"gasp", 4, true),
+    (Tag::new(b"gsp1"), b"gasp", 8, true),
+    (Tag::new(b"gsp2"), b"gasp", 12, true),
+    (Tag::new(b"gsp3"), b"gasp", 16, true),
+    (Tag::new(b"gsp4"), b"gasp", 20, true),
+    (Tag::new(b"gsp5"), b"gasp", 24, true),
+    (Tag::new(b"gsp6"), b"gasp", 28, true),
+    (Tag::new(b"gsp7"), b"gasp", 32, true),
+    (Tag::new(b"gsp8"), b"gasp", 36, true),
+    (Tag::new(b"gsp9"), b"gasp", 40, true),
+];
+
+/// usWidthClass of the `wdth` percentages, values in between are interpolated
+const WIDTH_CLASSES: [(f32, f32); 9] = [(50.0, 1.0), (62.5, 2.0), (75.0, 3.0), (87.5, 4.0), (100.0, 5.0), (112.5, 6.0), (125.0, 7.0), (150.0, 8.0), (200.0, 9.0)];
+
+const CHECKSUM_MAGIC: u32 = 0xB1B0AFBA;
+
+/// Metrics of an instanced glyph in font design units
+struct GlyphMetrics {
+    advance_width: u16,
+    left_side_bearing: i16,
+    advance_height: u16,
+    top_side_bearing: i16,
+    /// xMin, yMin, xMax, yMax, `None` for empty glyphs
+    bbox: Option<(i16, i16, i16, i16)>,
+}
+
+/// Encode the static instance of `font` at the user `location`, see `Font::instantiate`
+pub(super) fn instantiate(font: &Font, location: &[(Tag, Fixed)]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let fvar = font.t_fvar.as_ref().ok_or("font has no fvar table, it isn't variable")?;
+    if let Some((tag, _)) = location.iter().find(|(tag, _)| !fvar.axes().iter().any(|axis| axis.axis_tag() == *tag)) {
+        return Err(format!("font has no {:?} axis", tag).into());
+    }
+    // this is full instancing only, every axis gets a single value, an axis given twice doesn't make a range
+    if let Some((_, (tag, _))) = location.iter().enumerate().find(|(i, (tag, _))| location[..*i].iter().any(|(t, _)| t == tag)) {
+        return Err(format!("duplicate {:?} axis in the instance location", tag).into());
+    }
+    // every axis gets pinned, those not given at their default, values beyond the axis range are clamped to it
+    let user: Vec<_> = fvar
+        .axes()
+        .iter()
+        .map(|axis| {
+            let value = location.iter().find(|(tag, _)| axis.axis_tag() == *tag).map_or(axis.default_value(), |&(_, v)| v);
+            (axis.axis_tag(), value.max(axis.min_value()).min(axis.max_value()))
+        })
+        .collect();
+    let coords = font.normalize_coords(&user);
+
+    let mut tables = BTreeMap::new();
+    for tr in &font.table_directory.table_records {
+        if let Some(b) = font.table_directory.table_data(&font.data, tr.table_tag.as_bytes())? {
+            tables.insert(tr.table_tag, b.to_vec());
+        }
+    }
+    for tag in VARIATION_TABLES {
+        tables.remove(&Tag::new(tag));
+    }
+
+    let num_glyphs = font.t_maxp.as_ref().map_or(0, |maxp| maxp.num_glyphs()) as usize;
+    let vert_origins = match (&font.t_vorg, &font.t_vvar) {
+        (Some(vorg), Some(_)) => {
+            let vert_origins: Vec<_> = (0..num_glyphs as GlyphId).map(|g| clamp_i16(ot_round(font.vertical_origin_y_at(g, &coords).unwrap_or(0.0)))).collect();
+            tables.insert(Tag::new(b"VORG"), vorg_bytes(vorg.default_vert_origin_y(), &vert_origins));
+            Some(vert_origins)
+        }
+        (Some(vorg), None) => Some((0..num_glyphs as GlyphId).map(|g| vorg.vert_origin_y(g)).collect()),
+        _ => None,
+    };
+
+    let metrics = match (&font.t_loca, &font.t_glyf, &font.t_gvar, &font.t_cff2) {
+        (Some(loca), Some(glyf), Some(gvar), _) => instance_glyf(font, loca, glyf, gvar, &coords, &mut tables)?,
+        (_, _, _, Some(cff2)) => {
+            let b = cff2.instance(&coords)?;
+            let cff2 = CompactFontFormat2Table::from_bytes(&b)?;
+            tables.insert(Tag::new(b"CFF2"), b);
+            let mut bboxes = Vec::with_capacity(num_glyphs);
+            for glyph_id in 0..num_glyphs as GlyphId {
+                let mut sink = BoundsSink::default();
+                cff2.outline(glyph_id, &[], &mut sink)?;
+                bboxes.push(sink.bounds.map(|(x_min, y_min, x_max, y_max)| (x_min.floor() as i16, y_min.floor() as i16, x_max.ceil() as i16, y_max.ceil() as i16)));
+            }
+            varied_metrics(font, &coords, &bboxes, vert_origins.as_deref())
+        }
+        (Some(loca), Some(glyf), None, _) => {
+            let bboxes: Vec<_> = (0..num_glyphs as GlyphId).map(|g| Ok(glyf.glyph(loca, g)?.map(|g| g.header().bbox()))).collect::<Result<_, Box<dyn Error>>>()?;
+            varied_metrics(font, &coords, &bboxes, vert_origins.as_deref())
+        }
+        _ => varied_metrics(font, &coords, &vec![None; num_glyphs], vert_origins.as_deref()),
+    };
+    update_metrics(font, &metrics, &mut tables)?;
+
+    if let (Some(cvar), Some(cvt)) = (&font.t_cvar, tables.get_mut(&Tag::new(b"cvt "))) {
+        for (i, delta) in cvar.deltas(&coords).into_iter().enumerate() {
+            add_delta(cvt, 2 * i, delta, false)?;
+        }
+    }
+    if let Some(mvar) = &font.t_mvar {
+        for vr in mvar.value_records() {
+            let Some(&(_, table, offset, unsigned)) = MVAR_FIELDS.iter().find(|(tag, ..)| vr.value_tag() == *tag) else {
+                continue;
+            };
+            if let Some(b) = tables.get_mut(&Tag::new(table))
+                && offset + 2 <= b.len()
+            {
+                add_delta(b, offset, mvar.delta(vr.value_tag(), &coords), unsigned)?;
+            }
+        }
+    }
+    update_style_fields(&user, &mut tables)?;
+
+    if let (Some(gdef), Some(b)) = (&font.t_gdef, tables.get_mut(&Tag::new(b"GDEF"))) {
+        *b = gdef.instance(b, &coords)?;
+    }
+    if let Some(b) = tables.get_mut(&Tag::new(b"GPOS")) {
+        instance_device_deltas(b, font.t_gdef.as_ref().and_then(|gdef| gdef.item_var_store()), &coords)?;
+        *b = instance_feature_variations(b, &coords)?;
+    }
+    if let Some(b) = tables.get_mut(&Tag::new(b"GSUB")) {
+        *b = instance_feature_variations(b, &coords)?;
+    }
+
+    if let Some(mut name) = tables.get(&Tag::new(b"name")).map(|b| NamingTable::from_bytes(b)).transpose()? {
+        update_names(font, &user, &mut name);
+        name.remove_names(25);
+        set_ribbi_bits(name.name(2).as_deref(), &mut tables)?;
+        tables.insert(Tag::new(b"name"), name.to_bytes());
+    }
+
+    write_font(font.table_directory.sfnt_version, tables)
+}
+
+/// Instance glyf and loca into `tables`, the metrics come from the varied phantom points
+fn instance_glyf(font: &Font, loca: &IndexToLocationTable, glyf: &GlyphDataTable, gvar: &GlyphVariationsTable, coords: &[F2Dot14], tables: &mut BTreeMap<Tag, Vec<u8>>) -> Result<Vec<GlyphMetrics>, Box<dyn Error>> {
+    let instance = glyf.instance(loca, gvar, coords, &|glyph_id| font.phantom_metrics(glyph_id))?;
+    let mut metrics = Vec::with_capacity(instance.phantom_points.len());
+    for (glyph_id, pp) in instance.phantom_points.iter().enumerate() {
+        let bbox = instance.glyf.glyph(&instance.loca, glyph_id as GlyphId)?.map(|g| g.header().bbox());
+        let (x_min, _, _, y_max) = bbox.unwrap_or_default();
+        metrics.push(GlyphMetrics {
+            advance_width: (pp[1].x - pp[0].x).clamp(0.0, u16::MAX as f32) as u16,
+            left_side_bearing: clamp_i16(x_min as i32 - pp[0].x as i32),
+            advance_height: (pp[2].y - pp[3].y).clamp(0.0, u16::MAX as f32) as u16,
+            top_side_bearing: clamp_i16(pp[2].y as i32 - y_max as i32),
+            bbox,
+        });
+    }
+
+    // the short format stores offsets halved in 16 bits, a grown glyf may need the long one
+    let short = font.t_head.as_ref().is_some_and(|head| 0 == head.index_to_loc_format()) && instance.loca.glyf_length() <= 0x1FFFE;
+    let index_to_loc_format = if short { 0 } else { 1 };
+    if let Some(head) = tables.get_mut(&Tag::new(b"head")) {
+        put_int16(head, 50, index_to_loc_format)?;
+    }
+    tables.insert(Tag::new(b"loca"), instance.loca.to_bytes(index_to_loc_format));
+    tables.insert(Tag::new(b"glyf"), instance.glyf.as_bytes().to_vec());
+    Ok(metrics)
+}
+
+/// Metrics of glyphs whose outlines are already instanced into `bboxes`, advances varied by HVAR and VVAR
+fn varied_metrics(font: &Font, coords: &[F2Dot14], bboxes: &[Option<(i16, i16, i16, i16)>], vert_origins: Option<&[i16]>) -> Vec<GlyphMetrics> {
+    let advance = |v: Option<f32>| ot_round(v.unwrap_or(0.0)).clamp(0, u16::MAX as i32) as u16;
+    bboxes
+        .iter()
+        .enumerate()
+        .map(|(i, &bbox)| {
+            let glyph_id = i as GlyphId;
+            let top_side_bearing = match (vert_origins, bbox) {
+                (Some(vert_origins), Some((_, _, _, y_max))) => clamp_i16(vert_origins[i] as i32 - y_max as i32),
+                _ => clamp_i16(ot_round(font.top_side_bearing_at(glyph_id, coords).unwrap_or(0.0))),
+            };
+            GlyphMetrics {
+                advance_width: advance(font.advance_width_at(glyph_id, coords)),
+                left_side_bearing: bbox.map_or(font.left_side_bearing(glyph_id).unwrap_or(0), |b| b.0),
+                advance_height: advance(font.advance_height_at(glyph_id, coords)),
+                top_side_bearing,
+                bbox,
+            }
+        })
+        .collect()
+}
+
+/// Rewrite hmtx and vmtx from the instanced metrics, and the hhea, vhea, head and OS/2 fields derived from them
+fn update_metrics(font: &Font, metrics: &[GlyphMetrics], tables: &mut BTreeMap<Tag, Vec<u8>>) -> Result<(), Box<dyn Error>> {
+    if font.t_hmtx.is_some() {
+        let (hmtx, number_of_h_metrics) = metrics_bytes(metrics.iter().map(|m| (m.advance_width, m.left_side_bearing)));
+        tables.insert(Tag::new(b"hmtx"), hmtx);
+        if let Some(hhea) = tables.get_mut(&Tag::new(b"hhea")) {
+            let extents = metrics.iter().filter_map(|m| Some((m.advance_width, m.left_side_bearing, m.bbox?.2 - m.bbox?.0)));
+            update_header(hhea, number_of_h_metrics, metrics.iter().map(|m| m.advance_width).max().unwrap_or(0), extents)?;
+        }
+    }
+    if font.t_vmtx.is_some() {
+        let (vmtx, num_of_long_ver_metrics) = metrics_bytes(metrics.iter().map(|m| (m.advance_height, m.top_side_bearing)));
+        tables.insert(Tag::new(b"vmtx"), vmtx);
+        if let Some(vhea) = tables.get_mut(&Tag::new(b"vhea")) {
+            let extents = metrics.iter().filter_map(|m| Some((m.advance_height, m.top_side_bearing, m.bbox?.3 - m.bbox?.1)));
+            update_header(vhea, num_of_long_ver_metrics, metrics.iter().map(|m| m.advance_height).max().unwrap_or(0), extents)?;
+        }
+    }
+
+    if let Some(head) = tables.get_mut(&Tag::new(b"head")) {
+        let bbox = metrics.iter().filter_map(|m| m.bbox).reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3))).unwrap_or_default();
+        put_int16(head, 36, bbox.0)?;
+        put_int16(head, 38, bbox.1)?;
+        put_int16(head, 40, bbox.2)?;
+        put_int16(head, 42, bbox.3)?;
+    }
+    if let Some(os2) = tables.get_mut(&Tag::new(b"OS/2")) {
+        let advances: Vec<_> = metrics.iter().map(|m| m.advance_width as f32).filter(|&a| 0.0 != a).collect();
+        if !advances.is_empty() {
+            put_int16(os2, 2, clamp_i16(ot_round(advances.iter().sum::<f32>() / advances.len() as f32)))?;
+        }
+    }
+    Ok(())
+}
+
+/// Long metrics up to the last change of advance, then the bearings alone
+fn metrics_bytes(metrics: impl Iterator<Item = (u16, i16)>) -> (Vec<u8>, u16) {
+    let metrics: Vec<_> = metrics.collect();
+    let mut number_of_long_metrics = metrics.len();
+    while number_of_long_metrics > 1 && metrics[number_of_long_metrics - 1].0 == metrics[number_of_long_metrics - 2].0 {
+        number_of_long_metrics -= 1;
+    }
+
+    let mut w = Vec::with_capacity(2 * metrics.len() + 2 * number_of_long_metrics);
+    for (i, &(advance, bearing)) in metrics.iter().enumerate() {
+        if i < number_of_long_metrics {
+            write_uint16(&mut w, advance);
+        }
+        write_int16(&mut w, bearing);
+    }
+    (w, number_of_long_metrics as u16)
+}
+
+/// Patch the hhea or vhea fields that follow from the metrics, `extents` holds the advance, leading bearing and size of each non-empty glyph
+fn update_header(b: &mut [u8], number_of_long_metrics: u16, advance_max: u16, extents: impl Iterator<Item = (u16, i16, i16)>) -> Result<(), Box<dyn Error>> {
+    let mut min_leading = None::<i32>;
+    let mut min_trailing = None::<i32>;
+    let mut max_extent = None::<i32>;
+    for (advance, leading, size) in extents {
+        let (leading, size) = (leading as i32, size as i32);
+        min_leading = Some(min_leading.map_or(leading, |v| v.min(leading)));
+        min_trailing = Some(min_trailing.map_or(advance as i32 - leading - size, |v| v.min(advance as i32 - leading - size)));
+        max_extent = Some(max_extent.map_or(leading + size, |v| v.max(leading + size)));
+    }
+    put_uint16(b, 10, advance_max)?;
+    put_int16(b, 12, clamp_i16(min_leading.unwrap_or(0)))?;
+    put_int16(b, 14, clamp_i16(min_trailing.unwrap_or(0)))?;
+    put_int16(b, 16, clamp_i16(max_extent.unwrap_or(0)))?;
+    put_uint16(b, 34, number_of_long_metrics)
+}
+
+/// VORG with a record for every glyph whose origin differs from the default
+fn vorg_bytes(default_vert_origin_y: i16, vert_origins: &[i16]) -> Vec<u8> {
+    let records: Vec<_> = vert_origins.iter().enumerate().filter(|&(_, &y)| y != default_vert_origin_y).collect();
+    let mut w = Vec::with_capacity(8 + 4 * records.len());
+    write_uint16(&mut w, 1);
+    write_uint16(&mut w, 0);
+    write_int16(&mut w, default_vert_origin_y);
+    write_uint16(&mut w, records.len() as u16);
+    for (glyph_id, &y) in records {
+        write_uint16(&mut w, glyph_id as u16);
+        write_int16(&mut w, y);
+    }
+    w
+}
+
+/// Set usWeightClass, usWidthClass and the italic angle from the `wght`, `wdth` and `slnt` axes of the instance
+fn update_style_fields(user: &[(Tag, Fixed)], tables: &mut BTreeMap<Tag, Vec<u8>>) -> Result<(), Box<dyn Error>> {
+    let axis = |tag: &[u8; 4]| user.iter().find(|(t, _)| Tag::new(tag) == *t).map(|&(_, v)| v);
+    if let Some(os2) = tables.get_mut(&Tag::new(b"OS/2")) {
+        if let Some(wght) = axis(b"wght") {
+            put_uint16(os2, 4, ot_round(wght.clamp(1.0, 1000.0)) as u16)?;
+        }
+        if let Some(wdth) = axis(b"wdth") {
+            put_uint16(os2, 6, ot_round(width_class(wdth)) as u16)?;
+        }
+    }
+    if let (Some(post), Some(slnt)) = (tables.get_mut(&Tag::new(b"post")), axis(b"slnt")) {
+        put_uint32(post, 4, (slnt.clamp(-90.0, 90.0) * 65536.0).round() as i32 as u32)?;
+    }
+    Ok(())
+}
+
+fn width_class(wdth: f32) -> f32 {
+    let (first, last) = (WIDTH_CLASSES[0], WIDTH_CLASSES[WIDTH_CLASSES.len() - 1]);
+    if wdth <= first.0 {
+        return first.1;
+    }
+    if wdth >= last.0 {
+        return last.1;
+    }
+    let i = WIDTH_CLASSES.iter().position(|&(percent, _)| wdth < percent).unwrap_or(WIDTH_CLASSES.len() - 1);
+    let ((p0, c0), (p1, c1)) = (WIDTH_CLASSES[i - 1], WIDTH_CLASSES[i]);
+    c0 + (c1 - c0) * (wdth - p0) / (p1 - p0)
+}
+
+/// Rename the family and style after the instance, the way fontTools names instances from STAT
+///
+/// The style comes from the non-elidable STAT axis values at the location, or else from the fvar named instance there.
+/// Regular, Italic, Bold and Bold Italic make up the legacy subfamily, any other style name joins the legacy family name
+/// and the typographic names carry the full style.
+fn update_names(font: &Font, user: &[(Tag, Fixed)], name: &mut NamingTable) {
+    let (style_names, elided_fallback) = match (&font.t_stat, &font.t_fvar) {
+        (Some(stat), _) => {
+            let names = stat.axis_values_at(&font.design_axis_coords(stat, user)).into_iter().filter(|av| !av.is_elidable()).filter_map(|av| font.name(av.value_name_id())).collect();
+            (names, font.name(stat.elided_fallback_name_id()))
+        }
+        (None, Some(fvar)) => match fvar.instances().iter().find(|ir| ir.coordinates().iter().zip(user).all(|(&c, &(_, v))| c == v)) {
+            Some(ir) => (font.name(ir.subfamily_name_id()).into_iter().collect::<Vec<_>>(), None),
+            None => return,
+        },
+        (None, None) => return,
+    };
+    let Some(family) = font.name(16).or_else(|| font.name(1)) else {
+        return;
+    };
+
+    let is_ribbi = |s: &str| matches!(s, "Regular" | "Italic" | "Bold" | "Bold Italic");
+    let (ribbi, non_ribbi): (Vec<&str>, Vec<&str>) = style_names.iter().map(String::as_str).partition(|s| is_ribbi(s));
+    let mut subfamily = ribbi.join(" ");
+    let mut typo_subfamily = if non_ribbi.is_empty() { String::new() } else { style_names.join(" ") };
+    if subfamily.is_empty() && typo_subfamily.is_empty() {
+        match elided_fallback {
+            Some(fallback) if !is_ribbi(&fallback) => typo_subfamily = fallback,
+            Some(fallback) => subfamily = fallback,
+            None => {}
+        }
+    }
+    if subfamily.is_empty() {
+        subfamily = "Regular".to_string();
+    }
+
+    let mut names = BTreeMap::new();
+    names.insert(1, family.clone());
+    names.insert(2, subfamily.clone());
+    let (new_family, new_style) = if typo_subfamily.is_empty() {
+        name.remove_names(16);
+        name.remove_names(17);
+        (family.clone(), subfamily)
+    } else {
+        names.insert(1, format!("{} {}", family, non_ribbi.join(" ")).trim().to_string());
+        names.insert(16, family.clone());
+        names.insert(17, typo_subfamily.clone());
+        (family.clone(), typo_subfamily)
+    };
+    names.insert(4, format!("{} {}", new_family, new_style));
+
+    // PostScript names are printable ASCII without spaces, up to 127 characters
+    let ps_name: String = format!("{}-{}", font.name(25).unwrap_or(new_family), new_style).chars().filter(|c| c.is_ascii_alphanumeric() || '-' == *c).collect();
+    let ps_name = if ps_name.len() > 127 { format!("{}...", &ps_name[..124]) } else { ps_name };
+    names.insert(6, ps_name.clone());
+
+    if let Some(unique_id) = font.name(3) {
+        let replaced = [4, 6].into_iter().find_map(|id| {
+            let old = font.name(id).filter(|old| unique_id.contains(old.as_str()))?;
+            Some(unique_id.replace(old.as_str(), &names[&id]))
+        });
+        let unique_id = replaced.unwrap_or_else(|| {
+            let version = font.name(5).map(|v| v.split(';').next().unwrap_or_default().trim_start_matches("Version ").trim().to_string()).unwrap_or_default();
+            let vendor = font.t_os2.as_ref().map(|os2| String::from_utf8_lossy(os2.vendor_id().as_bytes()).trim().to_string()).unwrap_or_default();
+            format!("{};{};{}", version, vendor, ps_name)
+        });
+        names.insert(3, unique_id);
+    }
+
+    for (name_id, value) in names {
+        name.set_name(name_id, &value);
+    }
+}
+
+/// Match the OS/2 fsSelection and head macStyle bits to a Regular, Italic, Bold or Bold Italic `subfamily`, other styles leave them alone
+fn set_ribbi_bits(subfamily: Option<&str>, tables: &mut BTreeMap<Tag, Vec<u8>>) -> Result<(), Box<dyn Error>> {
+    let Some(style) = subfamily.map(str::to_lowercase).filter(|s| matches!(s.as_str(), "regular" | "italic" | "bold" | "bold italic")) else {
+        return Ok(());
+    };
+    let (bold, italic) = (style.contains("bold"), style.contains("italic"));
+
+    if let Some(os2) = tables.get_mut(&Tag::new(b"OS/2")) {
+        let mut fs_selection = get_uint16(os2, 62)? & !(0x0001 | 0x0020 | 0x0040);
+        if "regular" == style {
+            fs_selection |= 0x0040;
+        }
+        if bold {
+            fs_selection |= 0x0020;
+        }
+        if italic {
+            fs_selection |= 0x0001;
+        }
+        put_uint16(os2, 62, fs_selection)?;
+    }
+    if let Some(head) = tables.get_mut(&Tag::new(b"head")) {
+        let mut mac_style = get_uint16(head, 44)? & !(0x0001 | 0x0002);
+        if bold {
+            mac_style |= 0x0001;
+        }
+        if italic {
+            mac_style |= 0x0002;
+        }
+        put_uint16(head, 44, mac_style)?;
+    }
+    Ok(())
+}
+
+/// Add the rounded `delta` to the 16-bit field at `pos`, saturating at the limits of the field
+fn add_delta(b: &mut [u8], pos: usize, delta: f32, unsigned: bool) -> Result<(), Box<dyn Error>> {
+    if unsigned {
+        let value = get_uint16(b, pos)? as i32 + ot_round(delta);
+        put_uint16(b, pos, value.clamp(0, u16::MAX as i32) as u16)
+    } else {
+        let value = get_int16(b, pos)? as i32 + ot_round(delta);
+        put_int16(b, pos, clamp_i16(value))
+    }
+}
+
+fn clamp_i16(v: i32) -> i16 {
+    v.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+}
+
+/// Lay out the tables in tag order after a fresh table directory, with their checksums and the head checkSumAdjustment
+fn write_font(sfnt_version: u32, mut tables: BTreeMap<Tag, Vec<u8>>) -> Result<Vec<u8>, Box<dyn Error>> {
+    if let Some(head) = tables.get_mut(&Tag::new(b"head")) {
+        put_uint32(head, 8, 0)?;
+    }
+
+    let num_tables = tables.len() as u16;
+    let entry_selector = if 0 == num_tables { 0 } else { num_tables.ilog2() as u16 };
+    let search_range = 16 * (1 << entry_selector);
+    let mut w = Vec::new();
+    write_uint32(&mut w, sfnt_version);
+    write_uint16(&mut w, num_tables);
+    write_uint16(&mut w, search_range);
+    write_uint16(&mut w, entry_selector);
+    write_uint16(&mut w, 16 * num_tables - search_range);
+
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, b) in &tables {
+        write_tag(&mut w, *tag);
+        write_uint32(&mut w, checksum(b));
+        write_uint32(&mut w, offset as u32);
+        write_uint32(&mut w, b.len() as u32);
+        offset += b.len().next_multiple_of(4);
+    }
+    let mut head = None;
+    for (tag, b) in &tables {
+        if Tag::new(b"head") == *tag {
+            head = Some(w.len());
+        }
+        w.extend(b);
+        w.resize(w.len().next_multiple_of(4), 0);
+    }
+
+    if let Some(head) = head {
+        let adjustment = CHECKSUM_MAGIC.wrapping_sub(checksum(&w));
+        put_uint32(&mut w, head + 8, adjustment)?;
+    }
+    Ok(w)
+}
+
+/// Sum of the big-endian u32 words of `b`, zero padded
+fn checksum(b: &[u8]) -> u32 {
+    b.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+/// Bounding box of an outline, curves bounded by their extrema rather than their control points
+#[derive(Default)]
+struct BoundsSink {
+    bounds: Option<(f32, f32, f32, f32)>,
+    current: (f32, f32),
+}
+
+impl BoundsSink {
+    fn add(&mut self, x: f32, y: f32) {
+        self.bounds = Some(match self.bounds {
+            Some((x_min, y_min, x_max, y_max)) => (x_min.min(x), y_min.min(y), x_max.max(x), y_max.max(y)),
+            None => (x, y, x, y),
+        });
+    }
+}
+
+impl OutlineSink for BoundsSink {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.add(x, y);
+        self.current = (x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.add(x, y);
+        self.current = (x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (x0, y0) = self.current;
+        self.curve_to(x0 + 2.0 / 3.0 * (x1 - x0), y0 + 2.0 / 3.0 * (y1 - y0), x + 2.0 / 3.0 * (x1 - x), y + 2.0 / 3.0 * (y1 - y), x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (x0, y0) = self.current;
+        let at = |t: f32, p0: f32, p1: f32, p2: f32, p3: f32| {
+            let s = 1.0 - t;
+            s * s * s * p0 + 3.0 * s * s * t * p1 + 3.0 * s * t * t * p2 + t * t * t * p3
+        };
+        for t in cubic_extrema(x0, x1, x2, x).into_iter().chain(cubic_extrema(y0, y1, y2, y)).flatten() {
+            self.add(at(t, x0, x1, x2, x), at(t, y0, y1, y2, y));
+        }
+        self.add(x, y);
+        self.current = (x, y);
+    }
+
+    fn close(&mut self) {}
+}
+
+/// Parameters inside (0, 1) where one coordinate of a cubic curve turns
+fn cubic_extrema(p0: f32, p1: f32, p2: f32, p3: f32) -> [Option<f32>; 2] {
+    // the derivative divided by 3 is a t² + b t + c
+    let a = p3 - p0 + 3.0 * (p1 - p2);
+    let b = 2.0 * (p0 - 2.0 * p1 + p2);
+    let c = p1 - p0;
+    let inside = |t: f32| Some(t).filter(|t| 0.0 < *t && *t < 1.0);
+    if a.abs() < 1e-6 {
+        return [if b.abs() < 1e-6 { None } else { inside(-c / b) }, None];
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return [None, None];
+    }
+    let root = discriminant.sqrt();
+    [inside((-b + root) / (2.0 * a)), inside((-b - root) / (2.0 * a))]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A triangle with its corners at (0, 0), (100, 0) and (0, 100)
+    fn triangle() -> Vec<u8> {
+        let mut w = Vec::new();
+        [1, 0, 0, 100, 100, 2, 0].iter().for_each(|&v| write_int16(&mut w, v));
+        w.extend([0x01; 3]);
+        [0, 100, -100, 0, 0, 100].iter().for_each(|&v| write_int16(&mut w, v));
+        w
+    }
+
+    /// A TrueType font with a wght axis from 100 to 900, default 400, and two glyphs: a triangle advancing 500
+    /// whose second corner and advance move 100 units right at 900, and an empty glyph advancing 250
+    fn variable_font(index_to_loc_format: i16) -> Vec<u8> {
+        write_font(0x00010000, variable_tables(index_to_loc_format)).unwrap()
+    }
+
+    fn variable_tables(index_to_loc_format: i16) -> BTreeMap<Tag, Vec<u8>> {
+        let mut tables = BTreeMap::new();
+
+        let mut head = vec![0; 54];
+        put_uint16(&mut head, 0, 1).unwrap();
+        put_uint32(&mut head, 12, 0x5F0F3CF5).unwrap();
+        put_uint16(&mut head, 18, 1000).unwrap();
+        put_int16(&mut head, 50, index_to_loc_format).unwrap();
+        tables.insert(Tag::new(b"head"), head);
+
+        let mut maxp = Vec::new();
+        write_uint32(&mut maxp, 0x00005000);
+        write_uint16(&mut maxp, 2);
+        tables.insert(Tag::new(b"maxp"), maxp);
+
+        let mut hhea = vec![0; 36];
+        put_uint16(&mut hhea, 0, 1).unwrap();
+        put_int16(&mut hhea, 4, 800).unwrap();
+        put_int16(&mut hhea, 6, -200).unwrap();
+        put_uint16(&mut hhea, 34, 2).unwrap();
+        tables.insert(Tag::new(b"hhea"), hhea);
+
+        let mut hmtx = Vec::new();
+        [500, 0, 250, 0].iter().for_each(|&v| write_uint16(&mut hmtx, v));
+        tables.insert(Tag::new(b"hmtx"), hmtx);
+
+        // padded so the short loca format can hold the end offset
+        let mut glyf = triangle();
+        glyf.resize(glyf.len().next_multiple_of(4), 0);
+        let loca = IndexToLocationTable::from_offsets(vec![0, glyf.len() as Offset32, glyf.len() as Offset32]);
+        tables.insert(Tag::new(b"loca"), loca.to_bytes(index_to_loc_format));
+        tables.insert(Tag::new(b"glyf"), glyf);
+
+        let mut fvar = Vec::new();
+        [1, 0, 16, 2, 1, 20, 0, 8].iter().for_each(|&v| write_uint16(&mut fvar, v));
+        write_tag(&mut fvar, Tag::new(b"wght"));
+        [100.0, 400.0, 900.0].iter().for_each(|&v| write_fixed(&mut fvar, v));
+        [0, 256].iter().for_each(|&v| write_uint16(&mut fvar, v));
+        tables.insert(Tag::new(b"fvar"), fvar);
+
+        // one tuple peaking at 1.0 with x and y deltas for all seven points, the phantom points included
+        let mut gvar = Vec::new();
+        [1, 0, 1, 0].iter().for_each(|&v| write_uint16(&mut gvar, v));
+        write_uint32(&mut gvar, 26);
+        [2, 0].iter().for_each(|&v| write_uint16(&mut gvar, v));
+        write_uint32(&mut gvar, 26);
+        [0, 10, 10].iter().for_each(|&v| write_uint16(&mut gvar, v));
+        // an embedded peak tuple
+        [1, 10, 10, 0x8000].iter().for_each(|&v| write_uint16(&mut gvar, v));
+        write_f2dot14(&mut gvar, 1.0);
+        gvar.extend([0x06, 0, 100, 0, 0, 100, 0, 0, 0x86]);
+        gvar.push(0);
+        tables.insert(Tag::new(b"gvar"), gvar);
+
+        tables
+    }
+
+    /// The variable font with OS/2 typo metrics, USE_TYPO_METRICS per `use_typo_metrics`, a post table, and an MVAR
+    /// moving the typo ascender, typo descender, underline position and x height by 50, -30, -20 and 40 units at 900
+    fn metrics_font(use_typo_metrics: bool) -> Vec<u8> {
+        let mut tables = variable_tables(0);
+
+        let mut os2 = vec![0; 96];
+        put_uint16(&mut os2, 0, 4).unwrap();
+        put_uint16(&mut os2, 4, 400).unwrap();
+        put_uint16(&mut os2, 6, 5).unwrap();
+        put_int16(&mut os2, 26, 50).unwrap();
+        put_int16(&mut os2, 28, 250).unwrap();
+        put_uint16(&mut os2, 62, if use_typo_metrics { 0x00C0 } else { 0x0040 }).unwrap();
+        [750, -250, 100].iter().enumerate().for_each(|(i, &v)| put_int16(&mut os2, 68 + 2 * i, v).unwrap());
+        [500, 700].iter().enumerate().for_each(|(i, &v)| put_int16(&mut os2, 86 + 2 * i, v).unwrap());
+        tables.insert(Tag::new(b"OS/2"), os2);
+
+        let mut post = vec![0; 32];
+        put_uint32(&mut post, 0, 0x00030000).unwrap();
+        put_int16(&mut post, 8, -100).unwrap();
+        put_int16(&mut post, 10, 50).unwrap();
+        tables.insert(Tag::new(b"post"), post);
+
+        let mut mvar = Vec::new();
+        [1, 0, 0, 8, 4, 44].iter().for_each(|&v| write_uint16(&mut mvar, v));
+        for (i, tag) in [HORIZONTAL_ASCENDER, HORIZONTAL_DESCENDER, UNDERLINE_OFFSET, X_HEIGHT].into_iter().enumerate() {
+            write_tag(&mut mvar, tag);
+            [0, i as u16].iter().for_each(|&v| write_uint16(&mut mvar, v));
+        }
+        mvar.extend(crate::variations::builders::item_variation_store(&[&[(0.0, 1.0, 1.0)]], &[&[50], &[-30], &[-20], &[40]]));
+        tables.insert(Tag::new(b"MVAR"), mvar);
+
+        write_font(0x00010000, tables).unwrap()
+    }
+
+    fn load(b: &[u8], name: &str) -> Font {
+        let path = std::env::temp_dir().join(format!("otf-instancer-{}-{}.ttf", std::process::id(), name));
+        std::fs::write(&path, b).unwrap();
+        let font = Font::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        font.unwrap()
+    }
+
+    #[test]
+    fn checksums() {
+        let font = load(&variable_font(0), "checksums");
+        let b = font.instantiate(&[(Tag::new(b"wght"), 650.0)]).unwrap();
+        assert_eq!(checksum(&b), CHECKSUM_MAGIC);
+
+        let instance = load(&b, "checksums-instance");
+        for tr in &instance.table_directory.table_records {
+            let data = instance.table_directory.table_data(&b, tr.table_tag.as_bytes()).unwrap().unwrap();
+            let data = if Tag::new(b"head") == tr.table_tag { [&data[..8], &[0; 4], &data[12..]].concat() } else { data.to_vec() };
+            assert_eq!(tr.checksum, checksum(&data), "{:?} checksum", tr.table_tag);
+        }
+        assert!(VARIATION_TABLES.iter().all(|tag| instance.table_directory.find_table(tag).is_none()));
+    }
+
+    #[test]
+    fn instanced_outline_and_metrics() {
+        let font = load(&variable_font(0), "outline");
+        let b = font.instantiate(&[(Tag::new(b"wght"), 650.0)]).unwrap();
+        let instance = load(&b, "outline-instance");
+        let (loca, glyf) = (instance.t_loca.as_ref().unwrap(), instance.t_glyf.as_ref().unwrap());
+        let points: Vec<_> = glyf.outline_points(loca, 0).unwrap().points.iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(points, [(0.0, 0.0), (150.0, 0.0), (0.0, 100.0)]);
+        assert_eq!(instance.advance_width(0), Some(550));
+        assert_eq!(instance.advance_width(1), Some(250));
+        let head = instance.table_directory.table_data(&b, b"head").unwrap().unwrap();
+        assert_eq!((get_int16(head, 40).unwrap(), get_int16(head, 42).unwrap()), (150, 100));
+    }
+
+    #[test]
+    fn instanced_font_metrics() {
+        let wght = Tag::new(b"wght");
+        for use_typo_metrics in [false, true] {
+            let font = load(&metrics_font(use_typo_metrics), "metrics");
+            let instance = load(&font.instantiate(&[(wght, 650.0)]).unwrap(), "metrics-instance");
+            let metrics = font.metrics(&font.normalize_coords(&[(wght, 650.0)]));
+            assert_eq!(metrics, instance.metrics(&[]));
+            let expected = if use_typo_metrics { (775.0, -265.0, 100.0) } else { (800.0, -200.0, 0.0) };
+            assert_eq!((Some(expected.0), Some(expected.1), Some(expected.2)), (metrics.ascender, metrics.descender, metrics.line_gap));
+            assert_eq!((Some(520.0), Some(700.0), Some(-110.0)), (metrics.x_height, metrics.cap_height, metrics.underline_position));
+        }
+    }
+
+    #[test]
+    fn loca_format() {
+        for index_to_loc_format in [0, 1] {
+            let font = load(&variable_font(index_to_loc_format), "loca");
+            let instance = load(&font.instantiate(&[]).unwrap(), "loca-instance");
+            assert_eq!(instance.head().map(|head| head.index_to_loc_format()), Some(index_to_loc_format));
+            let loca = instance.table_directory.find_table(b"loca").unwrap();
+            assert_eq!(loca.length, 3 * if 0 == index_to_loc_format { 2 } else { 4 });
+            let glyph = instance.t_glyf.as_ref().unwrap().glyph(instance.t_loca.as_ref().unwrap(), 0).unwrap();
+            assert_eq!(glyph.map(|g| g.header().bbox()), Some((0, 0, 100, 100)));
+        }
+    }
+
+    #[test]
+    fn duplicate_and_missing_axes() {
+        let font = load(&variable_font(0), "duplicate");
+        let wght = Tag::new(b"wght");
+        let e = font.instantiate(&[(wght, 300.0), (wght, 700.0)]).unwrap_err();
+        assert_eq!("duplicate Tag(wght) axis in the instance location", e.to_string());
+        assert!(font.instantiate(&[(Tag::new(b"wdth"), 100.0)]).is_err());
+    }
+}
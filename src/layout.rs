@@ -0,0 +1,1338 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::t_gdef::*;
+use super::types::*;
+use super::utils::*;
+use super::variations::*;
+
+/// Lookups nested through contextual lookups deeper than this are not applied
+pub const MAX_NESTING_LEVEL: usize = 64;
+
+/// This bit relates only to the correct processing of GPOS type 3 (cursive attachment) lookups
+pub const RIGHT_TO_LEFT: u16 = 0x0001;
+/// If set, skips over base glyphs
+pub const IGNORE_BASE_GLYPHS: u16 = 0x0002;
+/// If set, skips over ligatures
+pub const IGNORE_LIGATURES: u16 = 0x0004;
+/// If set, skips over all combining marks
+pub const IGNORE_MARKS: u16 = 0x0008;
+/// If set, indicates that the lookup table structure is followed by a MarkFilteringSet field
+pub const USE_MARK_FILTERING_SET: u16 = 0x0010;
+/// If not zero, skips over all marks of attachment type different from specified
+pub const MARK_ATTACHMENT_CLASS_FILTER: u16 = 0xFF00;
+
+/// A glyph in a buffer being shaped by GSUB and GPOS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlyphInfo {
+    pub glyph_id: GlyphId,
+    /// Index of the character the glyph came from, a ligature keeps the cluster of its first component
+    pub cluster: usize,
+    /// Ligature formed by GSUB that this glyph is, or whose marks it is, 0 for none
+    pub ligature_id: u16,
+    /// For a mark inside a ligature, the 1-based component it followed, 0 otherwise
+    pub ligature_component: u16,
+}
+
+impl GlyphInfo {
+    pub fn new(glyph_id: GlyphId, cluster: usize) -> Self {
+        Self {
+            glyph_id,
+            cluster,
+            ligature_id: 0,
+            ligature_component: 0,
+        }
+    }
+}
+
+pub struct ScriptList {
+    script_records: Vec<ScriptRecord>,
+}
+
+impl Debug for ScriptList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScriptList").field("script_records", &self.script_records).finish()
+    }
+}
+
+impl ScriptList {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let script_count = read_uint16(r)?;
+        let mut script_records = Vec::with_capacity(script_count as usize);
+        for _ in 0..script_count {
+            let script_tag = read_tag(r)?;
+            let script_offset = read_offset16(r)?;
+            let script = Script::from_bytes(o.get(script_offset as usize..).ok_or("Script offset out of range")?)?;
+            script_records.push(ScriptRecord { script_tag, script });
+        }
+
+        Ok(Self { script_records })
+    }
+
+    pub fn script_records(&self) -> &[ScriptRecord] {
+        &self.script_records
+    }
+
+    pub fn script(&self, script_tag: Tag) -> Option<&Script> {
+        self.script_records.iter().find(|sr| sr.script_tag == script_tag).map(|sr| &sr.script)
+    }
+}
+
+#[derive(Debug)]
+pub struct ScriptRecord {
+    script_tag: Tag,
+    script: Script,
+}
+
+impl ScriptRecord {
+    pub fn script_tag(&self) -> Tag {
+        self.script_tag
+    }
+
+    pub fn script(&self) -> &Script {
+        &self.script
+    }
+}
+
+pub struct Script {
+    default_lang_sys: Option<LangSys>,
+    lang_sys_records: Vec<LangSysRecord>,
+}
+
+impl Debug for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Script").field("default_lang_sys", &self.default_lang_sys).field("lang_sys_records", &self.lang_sys_records).finish()
+    }
+}
+
+impl Script {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let default_lang_sys_offset = read_offset16(r)?;
+        let lang_sys_count = read_uint16(r)?;
+        let mut lang_sys_records = Vec::with_capacity(lang_sys_count as usize);
+        for _ in 0..lang_sys_count {
+            let lang_sys_tag = read_tag(r)?;
+            let lang_sys_offset = read_offset16(r)?;
+            let lang_sys = LangSys::from_bytes(o.get(lang_sys_offset as usize..).ok_or("LangSys offset out of range")?)?;
+            lang_sys_records.push(LangSysRecord { lang_sys_tag, lang_sys });
+        }
+
+        let default_lang_sys = match default_lang_sys_offset {
+            0 => None,
+            offset => Some(LangSys::from_bytes(o.get(offset as usize..).ok_or("default LangSys offset out of range")?)?),
+        };
+
+        Ok(Self { default_lang_sys, lang_sys_records })
+    }
+
+    pub fn default_lang_sys(&self) -> Option<&LangSys> {
+        self.default_lang_sys.as_ref()
+    }
+
+    pub fn lang_sys_records(&self) -> &[LangSysRecord] {
+        &self.lang_sys_records
+    }
+
+    /// The language system for `lang_sys_tag`, or the default one
+    pub fn lang_sys(&self, lang_sys_tag: Option<Tag>) -> Option<&LangSys> {
+        lang_sys_tag.and_then(|tag| self.lang_sys_records.iter().find(|lr| lr.lang_sys_tag == tag)).map(|lr| &lr.lang_sys).or(self.default_lang_sys.as_ref())
+    }
+}
+
+#[derive(Debug)]
+pub struct LangSysRecord {
+    lang_sys_tag: Tag,
+    lang_sys: LangSys,
+}
+
+impl LangSysRecord {
+    pub fn lang_sys_tag(&self) -> Tag {
+        self.lang_sys_tag
+    }
+
+    pub fn lang_sys(&self) -> &LangSys {
+        &self.lang_sys
+    }
+}
+
+#[derive(Debug)]
+pub struct LangSys {
+    /// Index of a feature required for this language system; if no required features = 0xFFFF
+    required_feature_index: u16,
+    /// Array of indices into the FeatureList, in arbitrary order
+    feature_indices: Vec<u16>,
+}
+
+impl LangSys {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let _lookup_order_offset = read_offset16(r)?;
+        let required_feature_index = read_uint16(r)?;
+        let feature_index_count = read_uint16(r)?;
+        let feature_indices = read_array(r, feature_index_count as usize, read_uint16)?;
+
+        Ok(Self { required_feature_index, feature_indices })
+    }
+
+    pub fn required_feature_index(&self) -> Option<u16> {
+        if 0xFFFF == self.required_feature_index { None } else { Some(self.required_feature_index) }
+    }
+
+    pub fn feature_indices(&self) -> &[u16] {
+        &self.feature_indices
+    }
+}
+
+pub struct FeatureList {
+    feature_records: Vec<FeatureRecord>,
+}
+
+impl Debug for FeatureList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FeatureList").field("feature_records", &self.feature_records).finish()
+    }
+}
+
+impl FeatureList {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let feature_count = read_uint16(r)?;
+        let mut feature_records = Vec::with_capacity(feature_count as usize);
+        for _ in 0..feature_count {
+            let feature_tag = read_tag(r)?;
+            let feature_offset = read_offset16(r)?;
+            let feature = Feature::from_bytes(o.get(feature_offset as usize..).ok_or("Feature offset out of range")?)?;
+            feature_records.push(FeatureRecord { feature_tag, feature });
+        }
+
+        Ok(Self { feature_records })
+    }
+
+    pub fn feature_records(&self) -> &[FeatureRecord] {
+        &self.feature_records
+    }
+}
+
+#[derive(Debug)]
+pub struct FeatureRecord {
+    feature_tag: Tag,
+    feature: Feature,
+}
+
+impl FeatureRecord {
+    pub fn feature_tag(&self) -> Tag {
+        self.feature_tag
+    }
+
+    pub fn feature(&self) -> &Feature {
+        &self.feature
+    }
+}
+
+#[derive(Debug)]
+pub struct Feature {
+    /// Offset from start of Feature table to FeatureParams table, if defined for the feature and present, else NULL
+    feature_params_offset: Offset16,
+    /// Array of indices into the LookupList, zero-based (first lookup is LookupListIndex = 0)
+    lookup_list_indices: Vec<u16>,
+}
+
+impl Feature {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let feature_params_offset = read_offset16(r)?;
+        let lookup_index_count = read_uint16(r)?;
+        let lookup_list_indices = read_array(r, lookup_index_count as usize, read_uint16)?;
+
+        Ok(Self { feature_params_offset, lookup_list_indices })
+    }
+
+    pub fn lookup_list_indices(&self) -> &[u16] {
+        &self.lookup_list_indices
+    }
+}
+
+/// Alternate feature tables used in regions of a variable font's design space
+pub struct FeatureVariations {
+    major_version: u16,
+    minor_version: u16,
+    feature_variation_records: Vec<FeatureVariationRecord>,
+}
+
+impl Debug for FeatureVariations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FeatureVariations")
+            .field("major_version", &self.major_version)
+            .field("minor_version", &self.minor_version)
+            .field("feature_variation_records", &self.feature_variation_records)
+            .finish()
+    }
+}
+
+impl FeatureVariations {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let major_version = read_uint16(r)?;
+        let minor_version = read_uint16(r)?;
+        let feature_variation_record_count = read_uint32(r)?;
+        let mut feature_variation_records = Vec::with_capacity((feature_variation_record_count as usize).min(u16::MAX as usize));
+        for _ in 0..feature_variation_record_count {
+            let condition_set_offset = read_offset32(r)?;
+            let feature_table_substitution_offset = read_offset32(r)?;
+
+            let condition_set = match condition_set_offset {
+                0 => Vec::new(),
+                offset => read_condition_set(o.get(offset as usize..).ok_or("ConditionSet offset out of range")?)?,
+            };
+            let substitutions = match feature_table_substitution_offset {
+                0 => Vec::new(),
+                offset => read_feature_table_substitution(o.get(offset as usize..).ok_or("FeatureTableSubstitution offset out of range")?)?,
+            };
+            feature_variation_records.push(FeatureVariationRecord { condition_set, substitutions });
+        }
+
+        Ok(Self {
+            major_version,
+            minor_version,
+            feature_variation_records,
+        })
+    }
+
+    pub fn feature_variation_records(&self) -> &[FeatureVariationRecord] {
+        &self.feature_variation_records
+    }
+
+    /// The first record whose conditions all hold at `coords`
+    pub fn find(&self, coords: &[F2Dot14]) -> Option<&FeatureVariationRecord> {
+        self.feature_variation_records.iter().find(|fvr| fvr.condition_set.iter().all(|c| c.matches(coords)))
+    }
+}
+
+fn read_condition_set(mut b: &[u8]) -> Result<Vec<Condition>, Box<dyn Error>> {
+    let o = b;
+    let r = &mut b;
+
+    let condition_count = read_uint16(r)?;
+    let mut conditions = Vec::with_capacity(condition_count as usize);
+    for offset in read_array(r, condition_count as usize, read_offset32)? {
+        conditions.push(Condition::from_bytes(o.get(offset as usize..).ok_or("Condition offset out of range")?)?);
+    }
+    Ok(conditions)
+}
+
+fn read_feature_table_substitution(mut b: &[u8]) -> Result<Vec<(u16, Feature)>, Box<dyn Error>> {
+    let o = b;
+    let r = &mut b;
+
+    let _major_version = read_uint16(r)?;
+    let _minor_version = read_uint16(r)?;
+    let substitution_count = read_uint16(r)?;
+    let mut substitutions = Vec::with_capacity(substitution_count as usize);
+    for _ in 0..substitution_count {
+        let feature_index = read_uint16(r)?;
+        let alternate_feature_offset = read_offset32(r)?;
+        let feature = Feature::from_bytes(o.get(alternate_feature_offset as usize..).ok_or("alternate Feature offset out of range")?)?;
+        substitutions.push((feature_index, feature));
+    }
+    Ok(substitutions)
+}
+
+#[derive(Debug)]
+pub struct FeatureVariationRecord {
+    /// All conditions must hold, an empty set always matches
+    condition_set: Vec<Condition>,
+    /// Feature index and the feature table that replaces it
+    substitutions: Vec<(u16, Feature)>,
+}
+
+impl FeatureVariationRecord {
+    pub fn condition_set(&self) -> &[Condition] {
+        &self.condition_set
+    }
+
+    pub fn substitutions(&self) -> &[(u16, Feature)] {
+        &self.substitutions
+    }
+
+    /// The feature table that replaces `feature_index`, if any
+    pub fn substitution(&self, feature_index: u16) -> Option<&Feature> {
+        self.substitutions.iter().find(|(i, _)| *i == feature_index).map(|(_, f)| f)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Condition {
+    /// Format 1, the normalized coordinate of an axis lies in a range
+    AxisRange { axis_index: u16, filter_range_min_value: F2Dot14, filter_range_max_value: F2Dot14 },
+    /// Conditions of a format this crate doesn't know never hold
+    Unknown(u16),
+}
+
+impl Condition {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        match format {
+            1 => Ok(Self::AxisRange {
+                axis_index: read_uint16(r)?,
+                filter_range_min_value: read_f2dot14(r)?,
+                filter_range_max_value: read_f2dot14(r)?,
+            }),
+            _ => Ok(Self::Unknown(format)),
+        }
+    }
+
+    pub fn matches(&self, coords: &[F2Dot14]) -> bool {
+        match *self {
+            Self::AxisRange { axis_index, filter_range_min_value, filter_range_max_value } => {
+                let coord = coords.get(axis_index as usize).copied().unwrap_or(0.0);
+                filter_range_min_value <= coord && coord <= filter_range_max_value
+            }
+            Self::Unknown(_) => false,
+        }
+    }
+}
+
+/// Lookup indices to apply for `features` under a script and language, in LookupList order
+pub fn select_lookups(script_list: &ScriptList, feature_list: &FeatureList, feature_variations: Option<&FeatureVariations>, script: Tag, language: Option<Tag>, features: &[Tag], coords: &[F2Dot14]) -> Vec<u16> {
+    let Some(lang_sys) = script_list.script(script).or_else(|| script_list.script(Tag::new(b"DFLT"))).and_then(|s| s.lang_sys(language)) else {
+        return Vec::new();
+    };
+    let variation = feature_variations.and_then(|fv| fv.find(coords));
+
+    let mut lookups = Vec::new();
+    let required = lang_sys.required_feature_index();
+    for &feature_index in required.iter().chain(lang_sys.feature_indices()) {
+        let Some(fr) = feature_list.feature_records.get(feature_index as usize) else {
+            continue;
+        };
+        if Some(feature_index) != required && !features.contains(&fr.feature_tag) {
+            continue;
+        }
+        let feature = variation.and_then(|v| v.substitution(feature_index)).unwrap_or(&fr.feature);
+        lookups.extend_from_slice(&feature.lookup_list_indices);
+    }
+
+    lookups.sort_unstable();
+    lookups.dedup();
+    lookups
+}
+
+/// A lookup subtable type of GSUB or GPOS
+pub trait LookupSubtable: Sized {
+    /// Lookup type whose subtables point to a subtable of another type
+    const EXTENSION_LOOKUP_TYPE: u16;
+
+    fn from_bytes(b: &[u8], lookup_type: u16) -> Result<Self, Box<dyn Error>>;
+}
+
+pub struct LookupList<T> {
+    lookups: Vec<Lookup<T>>,
+}
+
+impl<T: Debug> Debug for LookupList<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LookupList").field("lookups", &self.lookups).finish()
+    }
+}
+
+impl<T: LookupSubtable> LookupList<T> {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let lookup_count = read_uint16(r)?;
+        let mut lookups = Vec::with_capacity(lookup_count as usize);
+        for offset in read_array(r, lookup_count as usize, read_offset16)? {
+            lookups.push(Lookup::from_bytes(o.get(offset as usize..).ok_or("Lookup offset out of range")?)?);
+        }
+
+        Ok(Self { lookups })
+    }
+}
+
+impl<T> LookupList<T> {
+    pub fn lookups(&self) -> &[Lookup<T>] {
+        &self.lookups
+    }
+
+    pub fn get(&self, lookup_index: u16) -> Option<&Lookup<T>> {
+        self.lookups.get(lookup_index as usize)
+    }
+}
+
+pub struct Lookup<T> {
+    /// Different enumerations for GSUB and GPOS, extension lookups carry the type of the subtables they point to
+    lookup_type: u16,
+    /// Lookup qualifiers
+    lookup_flag: u16,
+    subtables: Vec<T>,
+    /// Index (base 0) into GDEF mark glyph sets structure, present when USE_MARK_FILTERING_SET is set
+    mark_filtering_set: Option<u16>,
+}
+
+impl<T: Debug> Debug for Lookup<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Lookup")
+            .field("lookup_type", &self.lookup_type)
+            .field_with("lookup_flag", |f| write!(f, "0x{:04x}", self.lookup_flag))
+            .field("subtables", &self.subtables)
+            .field("mark_filtering_set", &self.mark_filtering_set)
+            .finish()
+    }
+}
+
+impl<T: LookupSubtable> Lookup<T> {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let lookup_type = read_uint16(r)?;
+        let lookup_flag = read_uint16(r)?;
+        let sub_table_count = read_uint16(r)?;
+        let subtable_offsets = read_array(r, sub_table_count as usize, read_offset16)?;
+        let mark_filtering_set = if 0 != lookup_flag & USE_MARK_FILTERING_SET { Some(read_uint16(r)?) } else { None };
+
+        let is_extension = T::EXTENSION_LOOKUP_TYPE == lookup_type;
+        // type of the subtables an extension lookup points to, set by its first subtable
+        let mut extension_lookup_type = None;
+        let mut subtables = Vec::with_capacity(subtable_offsets.len());
+        for offset in subtable_offsets {
+            let mut st = o.get(offset as usize..).ok_or("lookup subtable offset out of range")?;
+            if is_extension {
+                // extension subtables hold a 32 bit offset to the real subtable, all of the same type
+                let o = st;
+                let r = &mut st;
+                let _format = read_uint16(r)?;
+                let subtable_type = read_uint16(r)?;
+                let extension_offset = read_offset32(r)?;
+                if T::EXTENSION_LOOKUP_TYPE == subtable_type || extension_lookup_type.is_some_and(|t| t != subtable_type) {
+                    return Err(format!("invalid extension lookup type {}", subtable_type).into());
+                }
+                extension_lookup_type = Some(subtable_type);
+                st = o.get(extension_offset as usize..).ok_or("extension offset out of range")?;
+            }
+            subtables.push(T::from_bytes(st, extension_lookup_type.unwrap_or(lookup_type))?);
+        }
+        let lookup_type = extension_lookup_type.unwrap_or(lookup_type);
+
+        Ok(Self {
+            lookup_type,
+            lookup_flag,
+            subtables,
+            mark_filtering_set,
+        })
+    }
+}
+
+impl<T> Lookup<T> {
+    pub fn lookup_type(&self) -> u16 {
+        self.lookup_type
+    }
+
+    pub fn lookup_flag(&self) -> u16 {
+        self.lookup_flag
+    }
+
+    pub fn subtables(&self) -> &[T] {
+        &self.subtables
+    }
+
+    pub fn mark_filtering_set(&self) -> Option<u16> {
+        self.mark_filtering_set
+    }
+}
+
+/// Glyphs covered by a subtable, each with its coverage index
+pub enum Coverage {
+    /// Individual glyph indices, in numerical order
+    Format1(Vec<GlyphId>),
+    /// Ranges of consecutive glyph indices
+    Format2(Vec<RangeRecord>),
+}
+
+impl Debug for Coverage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Format1(v) => f.debug_tuple("Format1").field(v).finish(),
+            Self::Format2(v) => f.debug_tuple("Format2").field(v).finish(),
+        }
+    }
+}
+
+impl Coverage {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        match format {
+            1 => {
+                let glyph_count = read_uint16(r)?;
+                Ok(Self::Format1(read_array(r, glyph_count as usize, read_uint16)?))
+            }
+            2 => {
+                let range_count = read_uint16(r)?;
+                let mut ranges = Vec::with_capacity(range_count as usize);
+                for _ in 0..range_count {
+                    ranges.push(RangeRecord {
+                        start_glyph_id: read_uint16(r)?,
+                        end_glyph_id: read_uint16(r)?,
+                        start_coverage_index: read_uint16(r)?,
+                    });
+                }
+                Ok(Self::Format2(ranges))
+            }
+            _ => Err(format!("unknown coverage format {}", format).into()),
+        }
+    }
+
+    /// Read a coverage at `offset` from the start of the subtable `o`
+    pub fn at(o: &[u8], offset: Offset16) -> Result<Self, Box<dyn Error>> {
+        Self::from_bytes(o.get(offset as usize..).ok_or("Coverage offset out of range")?)
+    }
+
+    /// Coverage index of a glyph, `None` when the glyph isn't covered
+    pub fn index(&self, glyph_id: GlyphId) -> Option<u16> {
+        match self {
+            Self::Format1(glyphs) => glyphs.binary_search(&glyph_id).ok().map(|i| i as u16),
+            Self::Format2(ranges) => {
+                let i = ranges.partition_point(|rr| rr.end_glyph_id < glyph_id);
+                let rr = ranges.get(i)?;
+                if rr.start_glyph_id <= glyph_id { Some(rr.start_coverage_index.wrapping_add(glyph_id - rr.start_glyph_id)) } else { None }
+            }
+        }
+    }
+
+    pub fn contains(&self, glyph_id: GlyphId) -> bool {
+        self.index(glyph_id).is_some()
+    }
+
+    /// Covered glyphs in coverage index order
+    pub fn glyphs(&self) -> Box<dyn Iterator<Item = GlyphId> + '_> {
+        match self {
+            Self::Format1(glyphs) => Box::new(glyphs.iter().copied()),
+            Self::Format2(ranges) => Box::new(ranges.iter().flat_map(|rr| rr.start_glyph_id..=rr.end_glyph_id)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RangeRecord {
+    /// First glyph ID in the range
+    start_glyph_id: GlyphId,
+    /// Last glyph ID in the range
+    end_glyph_id: GlyphId,
+    /// Coverage Index of first glyph ID in range
+    start_coverage_index: u16,
+}
+
+/// Assigns glyphs to classes, glyphs not listed are in class 0
+pub enum ClassDef {
+    Format1 { start_glyph_id: GlyphId, class_values: Vec<u16> },
+    Format2(Vec<ClassRangeRecord>),
+}
+
+impl Debug for ClassDef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Format1 { start_glyph_id, class_values } => f.debug_struct("Format1").field("start_glyph_id", start_glyph_id).field("class_values", class_values).finish(),
+            Self::Format2(v) => f.debug_tuple("Format2").field(v).finish(),
+        }
+    }
+}
+
+impl ClassDef {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        match format {
+            1 => {
+                let start_glyph_id = read_uint16(r)?;
+                let glyph_count = read_uint16(r)?;
+                let class_values = read_array(r, glyph_count as usize, read_uint16)?;
+                Ok(Self::Format1 { start_glyph_id, class_values })
+            }
+            2 => {
+                let class_range_count = read_uint16(r)?;
+                let mut ranges = Vec::with_capacity(class_range_count as usize);
+                for _ in 0..class_range_count {
+                    ranges.push(ClassRangeRecord {
+                        start_glyph_id: read_uint16(r)?,
+                        end_glyph_id: read_uint16(r)?,
+                        class: read_uint16(r)?,
+                    });
+                }
+                Ok(Self::Format2(ranges))
+            }
+            _ => Err(format!("unknown class definition format {}", format).into()),
+        }
+    }
+
+    /// Read an optional class definition at `offset` from the start of the subtable `o`, a NULL offset puts every glyph in class 0
+    pub fn at(o: &[u8], offset: Offset16) -> Result<Self, Box<dyn Error>> {
+        match offset {
+            0 => Ok(Self::Format2(Vec::new())),
+            offset => Self::from_bytes(o.get(offset as usize..).ok_or("ClassDef offset out of range")?),
+        }
+    }
+
+    pub fn class(&self, glyph_id: GlyphId) -> u16 {
+        match self {
+            Self::Format1 { start_glyph_id, class_values } => glyph_id.checked_sub(*start_glyph_id).and_then(|i| class_values.get(i as usize)).copied().unwrap_or(0),
+            Self::Format2(ranges) => {
+                let i = ranges.partition_point(|cr| cr.end_glyph_id < glyph_id);
+                ranges.get(i).filter(|cr| cr.start_glyph_id <= glyph_id).map_or(0, |cr| cr.class)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClassRangeRecord {
+    /// First glyph ID in the range
+    start_glyph_id: GlyphId,
+    /// Last glyph ID in the range
+    end_glyph_id: GlyphId,
+    /// Applied to all glyphs in the range
+    class: u16,
+}
+
+const LOCAL_2_BIT_DELTAS: u16 = 0x0001;
+const LOCAL_4_BIT_DELTAS: u16 = 0x0002;
+const LOCAL_8_BIT_DELTAS: u16 = 0x0003;
+const VARIATION_INDEX: u16 = 0x8000;
+
+/// Adjustment of a value in a Device or VariationIndex table
+pub enum Device {
+    /// Pixel adjustments for each size from start_size to end_size
+    Hinting { start_size: u16, end_size: u16, delta_values: Vec<i8> },
+    /// Delta set in the ItemVariationStore of GDEF
+    VariationIndex { delta_set_outer_index: u16, delta_set_inner_index: u16 },
+}
+
+impl Debug for Device {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Hinting { start_size, end_size, delta_values } => f.debug_struct("Device").field("start_size", start_size).field("end_size", end_size).field("delta_values", delta_values).finish(),
+            Self::VariationIndex {
+                delta_set_outer_index,
+                delta_set_inner_index,
+            } => f.debug_struct("VariationIndex").field("delta_set_outer_index", delta_set_outer_index).field("delta_set_inner_index", delta_set_inner_index).finish(),
+        }
+    }
+}
+
+impl Device {
+    /// Read a Device or VariationIndex table, `None` for delta formats this crate doesn't know
+    pub fn from_bytes(mut b: &[u8]) -> Result<Option<Self>, Box<dyn Error>> {
+        let r = &mut b;
+
+        let start_size = read_uint16(r)?;
+        let end_size = read_uint16(r)?;
+        let delta_format = read_uint16(r)?;
+
+        let bits = match delta_format {
+            LOCAL_2_BIT_DELTAS => 2,
+            LOCAL_4_BIT_DELTAS => 4,
+            LOCAL_8_BIT_DELTAS => 8,
+            VARIATION_INDEX => {
+                return Ok(Some(Self::VariationIndex {
+                    delta_set_outer_index: start_size,
+                    delta_set_inner_index: end_size,
+                }));
+            }
+            _ => return Ok(None),
+        };
+
+        // deltas are packed from the most significant bits of each uint16, sign extended
+        let count = (end_size as usize + 1).saturating_sub(start_size as usize);
+        let per_word = 16 / bits;
+        let words = read_array(r, count.div_ceil(per_word), read_uint16)?;
+        let delta_values = (0..count)
+            .map(|i| ((words[i / per_word] << (bits * (i % per_word))) as i16 >> (16 - bits)) as i8)
+            .collect();
+
+        Ok(Some(Self::Hinting { start_size, end_size, delta_values }))
+    }
+
+    /// Read an optional Device table at `offset` from the start of the table `o`
+    pub fn at(o: &[u8], offset: Offset16) -> Result<Option<Self>, Box<dyn Error>> {
+        match offset {
+            0 => Ok(None),
+            offset => Self::from_bytes(o.get(offset as usize..).ok_or("Device offset out of range")?),
+        }
+    }
+
+    /// Pixel adjustment at `ppem`, VariationIndex tables have none
+    pub fn ppem_delta(&self, ppem: u16) -> i8 {
+        match self {
+            Self::Hinting { start_size, delta_values, .. } => ppem.checked_sub(*start_size).and_then(|i| delta_values.get(i as usize)).copied().unwrap_or(0),
+            Self::VariationIndex { .. } => 0,
+        }
+    }
+
+    /// Design unit adjustment at normalized `coords`, hinting Device tables have none
+    pub fn variation_delta(&self, var_store: Option<&ItemVariationStore>, coords: &[F2Dot14]) -> f32 {
+        match (self, var_store) {
+            (
+                Self::VariationIndex {
+                    delta_set_outer_index,
+                    delta_set_inner_index,
+                },
+                Some(var_store),
+            ) if !coords.is_empty() => var_store.delta(*delta_set_outer_index, *delta_set_inner_index, coords).unwrap_or(0.0),
+            _ => 0.0,
+        }
+    }
+}
+
+/// Folds the deltas of VariationIndex tables into the values they adjust, for static instances of GDEF and GPOS bytes
+pub struct VariationIndexFolder<'a> {
+    var_store: Option<&'a ItemVariationStore>,
+    coords: &'a [F2Dot14],
+    /// Tables already folded, shared anchors and subtables must be folded only once
+    visited: HashSet<usize>,
+}
+
+impl<'a> VariationIndexFolder<'a> {
+    pub fn new(var_store: Option<&'a ItemVariationStore>, coords: &'a [F2Dot14]) -> Self {
+        Self { var_store, coords, visited: HashSet::new() }
+    }
+
+    /// True the first time a table at `pos` is seen
+    pub fn first_visit(&mut self, pos: usize) -> bool {
+        self.visited.insert(pos)
+    }
+
+    /// Add the rounded delta of the device at the Offset16 `offset_pos`, counted from `base`, to the value at `value_pos` and clear the offset
+    ///
+    /// Hinting Device tables are kept, a value the record leaves out can't take the delta
+    pub fn fold(&self, b: &mut [u8], base: usize, offset_pos: usize, value_pos: Option<usize>) -> Result<(), Box<dyn Error>> {
+        let offset = get_uint16(b, offset_pos)?;
+        if 0 == offset {
+            return Ok(());
+        }
+        let device = base + offset as usize;
+        if VARIATION_INDEX != get_uint16(b, device + 4)? {
+            return Ok(());
+        }
+        let delta = match self.var_store {
+            Some(var_store) => var_store.delta(get_uint16(b, device)?, get_uint16(b, device + 2)?, self.coords).unwrap_or(0.0),
+            None => 0.0,
+        };
+        if let Some(value_pos) = value_pos {
+            let value = get_int16(b, value_pos)? as i32 + ot_round(delta);
+            put_int16(b, value_pos, value.clamp(i16::MIN as i32, i16::MAX as i32) as i16)?;
+        }
+        put_uint16(b, offset_pos, 0)
+    }
+}
+
+/// Bake the FeatureVariations of GSUB or GPOS bytes `b` at normalized `coords` into a version 1.0 table
+///
+/// When a record matches, a new FeatureList with its alternate features follows the header and the rest of the table moves along unchanged
+pub fn instance_feature_variations(b: &[u8], coords: &[F2Dot14]) -> Result<Vec<u8>, Box<dyn Error>> {
+    if get_uint16(b, 2)? < 1 {
+        return Ok(b.to_vec());
+    }
+    let feature_variations_offset = get_uint32(b, 10)? as usize;
+    let feature_variations = match feature_variations_offset {
+        0 => None,
+        offset => Some(FeatureVariations::from_bytes(b.get(offset..).ok_or("FeatureVariations offset out of range")?)?),
+    };
+    let Some(record) = feature_variations.as_ref().and_then(|fv| fv.find(coords)).filter(|fvr| !fvr.substitutions.is_empty()) else {
+        let mut v = b.to_vec();
+        put_uint16(&mut v, 2, 0)?;
+        put_uint32(&mut v, 10, 0)?;
+        return Ok(v);
+    };
+
+    let feature_list_offset = get_uint16(b, 6)? as usize;
+    let feature_count = get_uint16(b, feature_list_offset)? as usize;
+    let records_len = 2 + 6 * feature_count;
+
+    // substituted features and features without FeatureParams are written fresh after the records,
+    // the others keep their table as the params offset is relative to it
+    let mut records = Vec::with_capacity(feature_count);
+    let mut features = Vec::new();
+    for i in 0..feature_count {
+        let record_pos = feature_list_offset + 2 + 6 * i;
+        let feature_tag = read_tag(&mut b.get(record_pos..).ok_or("FeatureRecord out of range")?)?;
+        let original = feature_list_offset + get_uint16(b, record_pos + 4)? as usize;
+        let lookup_list_indices = match record.substitution(i as u16) {
+            Some(feature) => feature.lookup_list_indices.clone(),
+            None => {
+                let feature = Feature::from_bytes(b.get(original..).ok_or("Feature offset out of range")?)?;
+                if 0 != feature.feature_params_offset {
+                    records.push((feature_tag, None, original));
+                    continue;
+                }
+                feature.lookup_list_indices
+            }
+        };
+        records.push((feature_tag, Some(records_len + features.len()), original));
+        write_uint16(&mut features, 0);
+        write_uint16(&mut features, lookup_list_indices.len() as u16);
+        lookup_list_indices.iter().for_each(|&l| write_uint16(&mut features, l));
+    }
+
+    // a version 1.0 header, the new FeatureList, then everything that followed the version 1.1 header
+    let feature_list_len = records_len + features.len();
+    let moved = |pos: usize| pos + 10 + feature_list_len - 14;
+    let offset16 = |offset: usize| u16::try_from(offset).map_err(|_| "FeatureVariations can't be baked in, offsets no longer fit in 16 bits");
+    let mut v = Vec::with_capacity(b.len() + feature_list_len);
+    write_uint16(&mut v, 1);
+    write_uint16(&mut v, 0);
+    for (pos, feature_list) in [(4, false), (6, true), (8, false)] {
+        let offset = get_uint16(b, pos)? as usize;
+        write_uint16(&mut v, if feature_list { 10 } else if 0 == offset { 0 } else { offset16(moved(offset))? });
+    }
+    write_uint16(&mut v, feature_count as u16);
+    for (feature_tag, fresh, original) in records {
+        write_tag(&mut v, feature_tag);
+        write_uint16(&mut v, offset16(fresh.unwrap_or_else(|| moved(original) - 10))?);
+    }
+    v.extend(features);
+    v.extend(b.get(14..).ok_or("GSUB or GPOS header truncated")?);
+    Ok(v)
+}
+
+/// A lookup to apply at one position of a matched input sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceLookupRecord {
+    /// Index (zero-based) into the input glyph sequence
+    pub sequence_index: u16,
+    /// Index (zero-based) into the LookupList
+    pub lookup_list_index: u16,
+}
+
+fn read_sequence_lookup_records(r: &mut &[u8], count: u16) -> Result<Vec<SequenceLookupRecord>, Box<dyn Error>> {
+    let mut records = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        records.push(SequenceLookupRecord {
+            sequence_index: read_uint16(r)?,
+            lookup_list_index: read_uint16(r)?,
+        });
+    }
+    Ok(records)
+}
+
+/// Read an array of offsets to rule sets, each an array of offsets to rules, NULL rule set offsets give empty sets
+fn read_rule_sets<T, F: Fn(&[u8]) -> Result<T, Box<dyn Error>>>(o: &[u8], r: &mut &[u8], read_rule: F) -> Result<Vec<Vec<T>>, Box<dyn Error>> {
+    let count = read_uint16(r)?;
+    let mut rule_sets = Vec::with_capacity(count as usize);
+    for offset in read_array(r, count as usize, read_offset16)? {
+        if 0 == offset {
+            rule_sets.push(Vec::new());
+            continue;
+        }
+
+        let mut b = o.get(offset as usize..).ok_or("rule set offset out of range")?;
+        let rs = b;
+        let rule_count = read_uint16(&mut b)?;
+        let mut rules = Vec::with_capacity(rule_count as usize);
+        for rule_offset in read_array(&mut b, rule_count as usize, read_offset16)? {
+            rules.push(read_rule(rs.get(rule_offset as usize..).ok_or("rule offset out of range")?)?);
+        }
+        rule_sets.push(rules);
+    }
+    Ok(rule_sets)
+}
+
+fn read_coverages(o: &[u8], r: &mut &[u8], count: u16) -> Result<Vec<Coverage>, Box<dyn Error>> {
+    let mut coverages = Vec::with_capacity(count as usize);
+    for offset in read_array(r, count as usize, read_offset16)? {
+        coverages.push(Coverage::at(o, offset)?);
+    }
+    Ok(coverages)
+}
+
+/// Input sequence after the first glyph, as glyph IDs in format 1 and classes in format 2
+#[derive(Debug)]
+pub struct SequenceRule {
+    input_sequence: Vec<u16>,
+    seq_lookup_records: Vec<SequenceLookupRecord>,
+}
+
+impl SequenceRule {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let glyph_count = read_uint16(r)?;
+        let seq_lookup_count = read_uint16(r)?;
+        let input_sequence = read_array(r, (glyph_count as usize).saturating_sub(1), read_uint16)?;
+        let seq_lookup_records = read_sequence_lookup_records(r, seq_lookup_count)?;
+
+        Ok(Self { input_sequence, seq_lookup_records })
+    }
+}
+
+/// Contextual lookup subtable, GSUB type 5 and GPOS type 7
+pub enum SequenceContext {
+    Format1 { coverage: Coverage, seq_rule_sets: Vec<Vec<SequenceRule>> },
+    Format2 { coverage: Coverage, class_def: ClassDef, class_seq_rule_sets: Vec<Vec<SequenceRule>> },
+    Format3 { coverages: Vec<Coverage>, seq_lookup_records: Vec<SequenceLookupRecord> },
+}
+
+impl Debug for SequenceContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Format1 { coverage, seq_rule_sets } => f.debug_struct("Format1").field("coverage", coverage).field("seq_rule_sets", seq_rule_sets).finish(),
+            Self::Format2 { coverage, class_def, class_seq_rule_sets } => {
+                f.debug_struct("Format2").field("coverage", coverage).field("class_def", class_def).field("class_seq_rule_sets", class_seq_rule_sets).finish()
+            }
+            Self::Format3 { coverages, seq_lookup_records } => f.debug_struct("Format3").field("coverages", coverages).field("seq_lookup_records", seq_lookup_records).finish(),
+        }
+    }
+}
+
+impl SequenceContext {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        match format {
+            1 => {
+                let coverage = Coverage::at(o, read_offset16(r)?)?;
+                let seq_rule_sets = read_rule_sets(o, r, SequenceRule::from_bytes)?;
+                Ok(Self::Format1 { coverage, seq_rule_sets })
+            }
+            2 => {
+                let coverage = Coverage::at(o, read_offset16(r)?)?;
+                let class_def = ClassDef::at(o, read_offset16(r)?)?;
+                let class_seq_rule_sets = read_rule_sets(o, r, SequenceRule::from_bytes)?;
+                Ok(Self::Format2 { coverage, class_def, class_seq_rule_sets })
+            }
+            3 => {
+                let glyph_count = read_uint16(r)?;
+                let seq_lookup_count = read_uint16(r)?;
+                let coverages = read_coverages(o, r, glyph_count)?;
+                let seq_lookup_records = read_sequence_lookup_records(r, seq_lookup_count)?;
+                Ok(Self::Format3 { coverages, seq_lookup_records })
+            }
+            _ => Err(format!("unknown sequence context format {}", format).into()),
+        }
+    }
+
+    /// Match at `i`, giving the buffer positions of the input sequence and the lookups to apply to it
+    pub fn matches(&self, glyphs: &[GlyphInfo], i: usize, skip: &dyn Fn(&GlyphInfo) -> bool) -> Option<(Vec<usize>, &[SequenceLookupRecord])> {
+        let first = glyphs.get(i)?.glyph_id;
+        match self {
+            Self::Format1 { coverage, seq_rule_sets } => {
+                let rules = seq_rule_sets.get(coverage.index(first)? as usize)?;
+                rules.iter().find_map(|rule| Some((match_input(glyphs, i, rule.input_sequence.len(), skip, |k, g| g == rule.input_sequence[k])?, rule.seq_lookup_records.as_slice())))
+            }
+            Self::Format2 { coverage, class_def, class_seq_rule_sets } => {
+                coverage.index(first)?;
+                let rules = class_seq_rule_sets.get(class_def.class(first) as usize)?;
+                rules.iter().find_map(|rule| Some((match_input(glyphs, i, rule.input_sequence.len(), skip, |k, g| class_def.class(g) == rule.input_sequence[k])?, rule.seq_lookup_records.as_slice())))
+            }
+            Self::Format3 { coverages, seq_lookup_records } => {
+                coverages.first()?.index(first)?;
+                let positions = match_input(glyphs, i, coverages.len() - 1, skip, |k, g| coverages[k + 1].contains(g))?;
+                Some((positions, seq_lookup_records.as_slice()))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ChainedSequenceRule {
+    /// Backtrack sequence, closest glyph first
+    backtrack_sequence: Vec<u16>,
+    /// Input sequence after the first glyph
+    input_sequence: Vec<u16>,
+    lookahead_sequence: Vec<u16>,
+    seq_lookup_records: Vec<SequenceLookupRecord>,
+}
+
+impl ChainedSequenceRule {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let backtrack_glyph_count = read_uint16(r)?;
+        let backtrack_sequence = read_array(r, backtrack_glyph_count as usize, read_uint16)?;
+        let input_glyph_count = read_uint16(r)?;
+        let input_sequence = read_array(r, (input_glyph_count as usize).saturating_sub(1), read_uint16)?;
+        let lookahead_glyph_count = read_uint16(r)?;
+        let lookahead_sequence = read_array(r, lookahead_glyph_count as usize, read_uint16)?;
+        let seq_lookup_count = read_uint16(r)?;
+        let seq_lookup_records = read_sequence_lookup_records(r, seq_lookup_count)?;
+
+        Ok(Self {
+            backtrack_sequence,
+            input_sequence,
+            lookahead_sequence,
+            seq_lookup_records,
+        })
+    }
+}
+
+/// Chained contextual lookup subtable, GSUB type 6 and GPOS type 8
+pub enum ChainedSequenceContext {
+    Format1 {
+        coverage: Coverage,
+        chained_seq_rule_sets: Vec<Vec<ChainedSequenceRule>>,
+    },
+    Format2 {
+        coverage: Coverage,
+        backtrack_class_def: ClassDef,
+        input_class_def: ClassDef,
+        lookahead_class_def: ClassDef,
+        chained_class_seq_rule_sets: Vec<Vec<ChainedSequenceRule>>,
+    },
+    Format3 {
+        /// Closest glyph first
+        backtrack_coverages: Vec<Coverage>,
+        input_coverages: Vec<Coverage>,
+        lookahead_coverages: Vec<Coverage>,
+        seq_lookup_records: Vec<SequenceLookupRecord>,
+    },
+}
+
+impl Debug for ChainedSequenceContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Format1 { coverage, chained_seq_rule_sets } => f.debug_struct("Format1").field("coverage", coverage).field("chained_seq_rule_sets", chained_seq_rule_sets).finish(),
+            Self::Format2 {
+                coverage,
+                backtrack_class_def,
+                input_class_def,
+                lookahead_class_def,
+                chained_class_seq_rule_sets,
+            } => f
+                .debug_struct("Format2")
+                .field("coverage", coverage)
+                .field("backtrack_class_def", backtrack_class_def)
+                .field("input_class_def", input_class_def)
+                .field("lookahead_class_def", lookahead_class_def)
+                .field("chained_class_seq_rule_sets", chained_class_seq_rule_sets)
+                .finish(),
+            Self::Format3 {
+                backtrack_coverages,
+                input_coverages,
+                lookahead_coverages,
+                seq_lookup_records,
+            } => f
+                .debug_struct("Format3")
+                .field("backtrack_coverages", backtrack_coverages)
+                .field("input_coverages", input_coverages)
+                .field("lookahead_coverages", lookahead_coverages)
+                .field("seq_lookup_records", seq_lookup_records)
+                .finish(),
+        }
+    }
+}
+
+impl ChainedSequenceContext {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        match format {
+            1 => {
+                let coverage = Coverage::at(o, read_offset16(r)?)?;
+                let chained_seq_rule_sets = read_rule_sets(o, r, ChainedSequenceRule::from_bytes)?;
+                Ok(Self::Format1 { coverage, chained_seq_rule_sets })
+            }
+            2 => {
+                let coverage = Coverage::at(o, read_offset16(r)?)?;
+                let backtrack_class_def = ClassDef::at(o, read_offset16(r)?)?;
+                let input_class_def = ClassDef::at(o, read_offset16(r)?)?;
+                let lookahead_class_def = ClassDef::at(o, read_offset16(r)?)?;
+                let chained_class_seq_rule_sets = read_rule_sets(o, r, ChainedSequenceRule::from_bytes)?;
+                Ok(Self::Format2 {
+                    coverage,
+                    backtrack_class_def,
+                    input_class_def,
+                    lookahead_class_def,
+                    chained_class_seq_rule_sets,
+                })
+            }
+            3 => {
+                let backtrack_glyph_count = read_uint16(r)?;
+                let backtrack_coverages = read_coverages(o, r, backtrack_glyph_count)?;
+                let input_glyph_count = read_uint16(r)?;
+                let input_coverages = read_coverages(o, r, input_glyph_count)?;
+                let lookahead_glyph_count = read_uint16(r)?;
+                let lookahead_coverages = read_coverages(o, r, lookahead_glyph_count)?;
+                let seq_lookup_count = read_uint16(r)?;
+                let seq_lookup_records = read_sequence_lookup_records(r, seq_lookup_count)?;
+                Ok(Self::Format3 {
+                    backtrack_coverages,
+                    input_coverages,
+                    lookahead_coverages,
+                    seq_lookup_records,
+                })
+            }
+            _ => Err(format!("unknown chained sequence context format {}", format).into()),
+        }
+    }
+
+    /// Match at `i`, giving the buffer positions of the input sequence and the lookups to apply to it
+    pub fn matches(&self, glyphs: &[GlyphInfo], i: usize, skip: &dyn Fn(&GlyphInfo) -> bool) -> Option<(Vec<usize>, &[SequenceLookupRecord])> {
+        let first = glyphs.get(i)?.glyph_id;
+        match self {
+            Self::Format1 { coverage, chained_seq_rule_sets } => {
+                let rules = chained_seq_rule_sets.get(coverage.index(first)? as usize)?;
+                rules.iter().find_map(|rule| {
+                    let positions = match_input(glyphs, i, rule.input_sequence.len(), skip, |k, g| g == rule.input_sequence[k])?;
+                    match_backtrack(glyphs, i, rule.backtrack_sequence.len(), skip, |k, g| g == rule.backtrack_sequence[k])?;
+                    match_lookahead(glyphs, *positions.last()?, rule.lookahead_sequence.len(), skip, |k, g| g == rule.lookahead_sequence[k])?;
+                    Some((positions, rule.seq_lookup_records.as_slice()))
+                })
+            }
+            Self::Format2 {
+                coverage,
+                backtrack_class_def,
+                input_class_def,
+                lookahead_class_def,
+                chained_class_seq_rule_sets,
+            } => {
+                coverage.index(first)?;
+                let rules = chained_class_seq_rule_sets.get(input_class_def.class(first) as usize)?;
+                rules.iter().find_map(|rule| {
+                    let positions = match_input(glyphs, i, rule.input_sequence.len(), skip, |k, g| input_class_def.class(g) == rule.input_sequence[k])?;
+                    match_backtrack(glyphs, i, rule.backtrack_sequence.len(), skip, |k, g| backtrack_class_def.class(g) == rule.backtrack_sequence[k])?;
+                    match_lookahead(glyphs, *positions.last()?, rule.lookahead_sequence.len(), skip, |k, g| lookahead_class_def.class(g) == rule.lookahead_sequence[k])?;
+                    Some((positions, rule.seq_lookup_records.as_slice()))
+                })
+            }
+            Self::Format3 {
+                backtrack_coverages,
+                input_coverages,
+                lookahead_coverages,
+                seq_lookup_records,
+            } => {
+                input_coverages.first()?.index(first)?;
+                let positions = match_input(glyphs, i, input_coverages.len() - 1, skip, |k, g| input_coverages[k + 1].contains(g))?;
+                match_backtrack(glyphs, i, backtrack_coverages.len(), skip, |k, g| backtrack_coverages[k].contains(g))?;
+                match_lookahead(glyphs, *positions.last()?, lookahead_coverages.len(), skip, |k, g| lookahead_coverages[k].contains(g))?;
+                Some((positions, seq_lookup_records.as_slice()))
+            }
+        }
+    }
+}
+
+/// Whether a lookup passes over the glyph for its flags, nothing is skipped without GDEF
+pub fn lookup_skips<T>(gdef: Option<&GlyphDefinitionTable>, lookup: &Lookup<T>, glyph: &GlyphInfo) -> bool {
+    gdef.is_some_and(|gdef| gdef.skips(lookup.lookup_flag(), lookup.mark_filtering_set(), glyph.glyph_id))
+}
+
+/// Positions of the glyph at `i` and the `count` glyphs after it that aren't skipped, if `f(k, glyph)` accepts each of those
+pub fn match_input(glyphs: &[GlyphInfo], i: usize, count: usize, skip: &dyn Fn(&GlyphInfo) -> bool, f: impl Fn(usize, GlyphId) -> bool) -> Option<Vec<usize>> {
+    let mut positions = Vec::with_capacity(count + 1);
+    positions.push(i);
+    let mut j = i;
+    for k in 0..count {
+        j = next_unskipped(glyphs, j, skip)?;
+        if !f(k, glyphs[j].glyph_id) {
+            return None;
+        }
+        positions.push(j);
+    }
+    Some(positions)
+}
+
+/// Check the `count` glyphs before `i`, closest first
+pub fn match_backtrack(glyphs: &[GlyphInfo], i: usize, count: usize, skip: &dyn Fn(&GlyphInfo) -> bool, f: impl Fn(usize, GlyphId) -> bool) -> Option<()> {
+    let mut j = i;
+    for k in 0..count {
+        j = prev_unskipped(glyphs, j, skip)?;
+        if !f(k, glyphs[j].glyph_id) {
+            return None;
+        }
+    }
+    Some(())
+}
+
+/// Check the `count` glyphs after the last input glyph at `last`
+pub fn match_lookahead(glyphs: &[GlyphInfo], last: usize, count: usize, skip: &dyn Fn(&GlyphInfo) -> bool, f: impl Fn(usize, GlyphId) -> bool) -> Option<()> {
+    let mut j = last;
+    for k in 0..count {
+        j = next_unskipped(glyphs, j, skip)?;
+        if !f(k, glyphs[j].glyph_id) {
+            return None;
+        }
+    }
+    Some(())
+}
+
+pub fn next_unskipped(glyphs: &[GlyphInfo], i: usize, skip: &dyn Fn(&GlyphInfo) -> bool) -> Option<usize> {
+    (i + 1..glyphs.len()).find(|&j| !skip(&glyphs[j]))
+}
+
+pub fn prev_unskipped(glyphs: &[GlyphInfo], i: usize, skip: &dyn Fn(&GlyphInfo) -> bool) -> Option<usize> {
+    (0..i).rev().find(|&j| !skip(&glyphs[j]))
+}
+
+/// Byte builders for GSUB and GPOS test fixtures
+#[cfg(test)]
+pub(crate) mod builders {
+    use super::*;
+
+    /// Coverage format 1 of sorted `glyphs`
+    pub fn coverage(glyphs: &[GlyphId]) -> Vec<u8> {
+        let mut w = Vec::new();
+        write_uint16(&mut w, 1);
+        write_uint16(&mut w, glyphs.len() as u16);
+        glyphs.iter().for_each(|&g| write_uint16(&mut w, g));
+        w
+    }
+
+    /// ClassDef format 2 of (start glyph, end glyph, class) ranges
+    pub fn class_def(ranges: &[(GlyphId, GlyphId, u16)]) -> Vec<u8> {
+        let mut w = Vec::new();
+        write_uint16(&mut w, 2);
+        write_uint16(&mut w, ranges.len() as u16);
+        for &(start, end, class) in ranges {
+            [start, end, class].iter().for_each(|&v| write_uint16(&mut w, v));
+        }
+        w
+    }
+
+    pub fn lookup(lookup_type: u16, lookup_flag: u16, subtables: &[Vec<u8>]) -> Vec<u8> {
+        let mut w = Vec::new();
+        write_uint16(&mut w, lookup_type);
+        write_uint16(&mut w, lookup_flag);
+        write_uint16(&mut w, subtables.len() as u16);
+        let mut offset = 6 + 2 * subtables.len();
+        for st in subtables {
+            write_uint16(&mut w, offset as u16);
+            offset += st.len();
+        }
+        subtables.iter().for_each(|st| w.extend(st));
+        w
+    }
+
+    /// GSUB or GPOS 1.0 with empty script and feature lists around `lookups`
+    pub fn layout_table(lookups: &[Vec<u8>]) -> Vec<u8> {
+        let mut w = Vec::new();
+        [1, 0, 10, 12, 14, 0, 0].iter().for_each(|&v| write_uint16(&mut w, v));
+        write_uint16(&mut w, lookups.len() as u16);
+        let mut offset = 2 + 2 * lookups.len();
+        for l in lookups {
+            write_uint16(&mut w, offset as u16);
+            offset += l.len();
+        }
+        lookups.iter().for_each(|l| w.extend(l));
+        w
+    }
+}
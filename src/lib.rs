@@ -5,9 +5,34 @@
 mod font;
 pub use font::*;
 
+pub mod layout;
+pub mod t_avar;
+pub mod t_cff;
+pub mod t_cff2;
 pub mod t_cmap;
+pub mod t_cvar;
+pub mod t_fvar;
+pub mod t_gdef;
+pub mod t_glyf;
+pub mod t_gpos;
+pub mod t_gsub;
+pub mod t_gvar;
 pub mod t_head;
 pub mod t_hhea;
+pub mod t_hmtx;
+pub mod t_hvar;
+pub mod t_kern;
+pub mod t_loca;
+pub mod t_maxp;
+pub mod t_mvar;
 pub mod t_name;
+pub mod t_os2;
+pub mod t_post;
+pub mod t_stat;
+pub mod t_vhea;
+pub mod t_vmtx;
+pub mod t_vorg;
+pub mod t_vvar;
 pub mod types;
 pub mod utils;
+pub mod variations;
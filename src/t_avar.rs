@@ -0,0 +1,163 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::types::*;
+use super::utils::*;
+
+/// Piecewise linear remapping of normalized axis coordinates
+pub struct AxisVariationsTable {
+    /// Major version number of the axis variations table — set to 1 or 2
+    major_version: u16,
+    /// Minor version number of the axis variations table — set to 0
+    minor_version: u16,
+    /// One segment map per fvar axis, version 2 data after the maps is not read
+    axis_segment_maps: Vec<SegmentMaps>,
+}
+
+impl Debug for AxisVariationsTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AxisVariationsTable")
+            .field("major_version", &self.major_version)
+            .field("minor_version", &self.minor_version)
+            .field("axis_segment_maps", &self.axis_segment_maps)
+            .finish()
+    }
+}
+
+impl AxisVariationsTable {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let major_version = read_uint16(r)?;
+        let minor_version = read_uint16(r)?;
+        if !(1..=2).contains(&major_version) {
+            return Err(format!("unsupported avar version {}.{}", major_version, minor_version).into());
+        }
+        let _reserved = read_uint16(r)?;
+        let axis_count = read_uint16(r)?;
+        let axis_segment_maps = read_array(r, axis_count as usize, SegmentMaps::from_bytes)?;
+
+        Ok(Self {
+            major_version,
+            minor_version,
+            axis_segment_maps,
+        })
+    }
+
+    pub fn axis_segment_maps(&self) -> &[SegmentMaps] {
+        &self.axis_segment_maps
+    }
+
+    /// Remap a normalized coordinate of axis `axis_index`, axes without a segment map are left as is
+    pub fn map(&self, axis_index: usize, coord: F2Dot14) -> F2Dot14 {
+        self.axis_segment_maps.get(axis_index).map_or(coord, |sm| sm.map(coord))
+    }
+}
+
+pub struct SegmentMaps {
+    /// Array of axis value map records, sorted by fromCoordinate
+    axis_value_maps: Vec<AxisValueMap>,
+}
+
+impl Debug for SegmentMaps {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SegmentMaps").field("axis_value_maps", &self.axis_value_maps).finish()
+    }
+}
+
+impl SegmentMaps {
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let position_map_count = read_uint16(r)?;
+        let axis_value_maps = read_array(r, position_map_count as usize, AxisValueMap::from_bytes)?;
+
+        Ok(Self { axis_value_maps })
+    }
+
+    /// Interpolate between the neighbouring map records, values outside the mapped range are shifted by the nearest record.
+    /// Records out of fromCoordinate order never panic, a coordinate without records around it is left as is
+    pub fn map(&self, coord: F2Dot14) -> F2Dot14 {
+        let maps = &self.axis_value_maps;
+        let (Some(first), Some(last)) = (maps.first(), maps.last()) else {
+            return coord;
+        };
+        if let Some(m) = maps.iter().find(|m| coord == m.from_coordinate) {
+            return m.to_coordinate;
+        }
+        if coord < first.from_coordinate {
+            return coord + first.to_coordinate - first.from_coordinate;
+        }
+        if coord > last.from_coordinate {
+            return coord + last.to_coordinate - last.from_coordinate;
+        }
+        let i = maps.partition_point(|m| m.from_coordinate < coord);
+        let (Some(a), Some(b)) = (i.checked_sub(1).and_then(|i| maps.get(i)), maps.get(i)) else {
+            return coord;
+        };
+        // interpolated in 16.16, the quotient's magnitude rounded to nearest
+        let to_fixed = |v: f32| (v as f64 * 65536.0).round() as i64;
+        let n = (to_fixed(b.to_coordinate) - to_fixed(a.to_coordinate)) * (to_fixed(coord) - to_fixed(a.from_coordinate));
+        let d = to_fixed(b.from_coordinate) - to_fixed(a.from_coordinate);
+        if d <= 0 {
+            return coord;
+        }
+        let q = n.signum() * ((n.abs() + d / 2) / d);
+        (to_fixed(a.to_coordinate) + q) as f32 / 65536.0
+    }
+}
+
+pub struct AxisValueMap {
+    /// A normalized coordinate value obtained using default normalization
+    from_coordinate: F2Dot14,
+    /// The modified, normalized coordinate value
+    to_coordinate: F2Dot14,
+}
+
+impl Debug for AxisValueMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AxisValueMap").field("from_coordinate", &self.from_coordinate).field("to_coordinate", &self.to_coordinate).finish()
+    }
+}
+
+impl AxisValueMap {
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let from_coordinate = read_f2dot14(r)?;
+        let to_coordinate = read_f2dot14(r)?;
+
+        Ok(Self { from_coordinate, to_coordinate })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment_maps(maps: &[(F2Dot14, F2Dot14)]) -> SegmentMaps {
+        let mut w = Vec::new();
+        write_uint16(&mut w, maps.len() as u16);
+        for &(from, to) in maps {
+            write_f2dot14(&mut w, from);
+            write_f2dot14(&mut w, to);
+        }
+        SegmentMaps::from_bytes(&mut w.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn map() {
+        let sm = segment_maps(&[(-1.0, -1.0), (0.0, 0.0), (0.5, 0.75), (1.0, 1.0)]);
+        assert_eq!(sm.map(0.5), 0.75);
+        assert_eq!(sm.map(0.25), 0.375);
+        assert_eq!(sm.map(0.75), 0.875);
+        assert_eq!(sm.map(-0.5), -0.5);
+        assert_eq!(segment_maps(&[]).map(0.3), 0.3);
+    }
+
+    #[test]
+    fn out_of_order_maps() {
+        let sm = segment_maps(&[(-1.0, -1.0), (0.5, 0.75), (0.0, 0.0), (0.5, 0.25), (1.0, 1.0)]);
+        assert_eq!(sm.map(0.5), 0.75);
+        for i in -16..=16 {
+            let coord = i as f32 / 16.0;
+            assert!((-1.0..=1.0).contains(&sm.map(coord)), "{} maps out of range", coord);
+        }
+    }
+}
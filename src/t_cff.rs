@@ -0,0 +1,1608 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::types::*;
+use super::utils::*;
+use super::variations::*;
+
+pub struct CompactFontFormatTable {
+    /// Format major version (starting at 1)
+    major: u8,
+    /// Format minor version (starting at 0)
+    minor: u8,
+    /// Header size (bytes)
+    hdr_size: u8,
+    /// Absolute offset (0) size
+    off_size: u8,
+    name_index: Index,
+    top_dict_index: Index,
+    string_index: Index,
+    global_subr_index: Index,
+    /// The Top DICT of the first (and in OpenType only) font
+    top_dict: Dict,
+    char_strings: Index,
+    charset: Charset,
+    /// Private DICT and local subrs of a name-keyed font
+    private: Option<PrivateDict>,
+    /// Font DICTs of a CID-keyed font, each with its own Private DICT
+    fd_array: Vec<FontDict>,
+    /// Font DICT of each glyph in a CID-keyed font
+    fd_select: Option<FdSelect>,
+}
+
+impl Debug for CompactFontFormatTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompactFontFormatTable")
+            .field("major", &self.major)
+            .field("minor", &self.minor)
+            .field("hdr_size", &self.hdr_size)
+            .field("off_size", &self.off_size)
+            .field("name_index", &self.name_index)
+            .field("top_dict_index", &self.top_dict_index)
+            .field("string_index", &self.string_index)
+            .field("global_subr_index", &self.global_subr_index)
+            .field("top_dict", &self.top_dict)
+            .field("char_strings", &self.char_strings)
+            .field("charset", &self.charset)
+            .field("private", &self.private)
+            .field("fd_array", &self.fd_array)
+            .field("fd_select", &self.fd_select)
+            .finish()
+    }
+}
+
+impl CompactFontFormatTable {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let major = read_uint8(r)?;
+        let minor = read_uint8(r)?;
+        let hdr_size = read_uint8(r)?;
+        let off_size = read_uint8(r)?;
+
+        if 1 != major {
+            return Err(format!("unsupported CFF version {}.{}", major, minor).into());
+        }
+
+        *r = o.get(hdr_size as usize..).ok_or("CFF header size out of range")?;
+        let name_index = Index::from_bytes(r)?;
+        let top_dict_index = Index::from_bytes(r)?;
+        let string_index = Index::from_bytes(r)?;
+        let global_subr_index = Index::from_bytes(r)?;
+
+        let top_dict = Dict::from_bytes(top_dict_index.get(0).ok_or("CFF has no Top DICT")?)?;
+
+        let char_strings_offset = top_dict.get_offset(op::CHAR_STRINGS).ok_or("CFF Top DICT has no CharStrings")?;
+        let char_strings = Index::from_bytes(&mut o.get(char_strings_offset..).ok_or("CFF CharStrings offset out of range")?)?;
+
+        let charset = match top_dict.get_offset(op::CHARSET).unwrap_or(0) {
+            0 => Charset::ISOAdobe,
+            1 => Charset::Expert,
+            2 => Charset::ExpertSubset,
+            offset => Charset::from_bytes(o.get(offset..).ok_or("CFF charset offset out of range")?, char_strings.len())?,
+        };
+
+        let private = match top_dict.get(op::PRIVATE) {
+            Some(&[size, offset]) => Some(PrivateDict::from_bytes(o, size as usize, offset as usize)?),
+            _ => None,
+        };
+
+        // CID-keyed fonts keep their Private DICTs in the FDArray and pick one per glyph through FDSelect
+        let mut fd_array = Vec::new();
+        let mut fd_select = None;
+        if top_dict.get(op::ROS).is_some() {
+            let fd_array_offset = top_dict.get_offset(op::FD_ARRAY).ok_or("CID-keyed CFF has no FDArray")?;
+            let fd_array_index = Index::from_bytes(&mut o.get(fd_array_offset..).ok_or("CFF FDArray offset out of range")?)?;
+            for i in 0..fd_array_index.len() {
+                fd_array.push(FontDict::from_bytes(o, fd_array_index.get(i).ok_or("CFF FDArray truncated")?)?);
+            }
+
+            let fd_select_offset = top_dict.get_offset(op::FD_SELECT).ok_or("CID-keyed CFF has no FDSelect")?;
+            let fs = FdSelect::from_bytes(o.get(fd_select_offset..).ok_or("CFF FDSelect offset out of range")?, char_strings.len())?;
+            if let Some(fd) = fs.max_fd()
+                && fd as usize >= fd_array.len()
+            {
+                return Err(format!("CFF FDSelect references Font DICT {} of {}", fd, fd_array.len()).into());
+            }
+            fd_select = Some(fs);
+        }
+
+        Ok(Self {
+            major,
+            minor,
+            hdr_size,
+            off_size,
+            name_index,
+            top_dict_index,
+            string_index,
+            global_subr_index,
+            top_dict,
+            char_strings,
+            charset,
+            private,
+            fd_array,
+            fd_select,
+        })
+    }
+
+    pub fn num_glyphs(&self) -> usize {
+        self.char_strings.len()
+    }
+
+    /// CID-keyed fonts start their Top DICT with the ROS operator
+    pub fn is_cid_keyed(&self) -> bool {
+        self.top_dict.get(op::ROS).is_some()
+    }
+
+    /// Registry, ordering and supplement from the ROS operator of a CID-keyed font
+    pub fn cid_system_info(&self) -> Option<CidSystemInfo> {
+        let &[registry, ordering, supplement] = self.top_dict.get(op::ROS)? else {
+            return None;
+        };
+
+        Some(CidSystemInfo {
+            registry: self.string(registry as u16)?.to_string(),
+            ordering: self.string(ordering as u16)?.to_string(),
+            supplement: supplement as i32,
+        })
+    }
+
+    /// CID of a glyph in a CID-keyed font, the charset maps glyphs to CIDs instead of names
+    pub fn cid(&self, glyph_id: GlyphId) -> Option<u16> {
+        if !self.is_cid_keyed() || glyph_id as usize >= self.num_glyphs() {
+            return None;
+        }
+        self.charset.get(glyph_id)
+    }
+
+    /// Number of CIDs the font covers, 8720 unless the Top DICT says otherwise
+    pub fn cid_count(&self) -> Option<u32> {
+        if !self.is_cid_keyed() {
+            return None;
+        }
+        Some(self.top_dict.get(op::CID_COUNT).and_then(|v| v.first()).map_or(8720, |&v| v as u32))
+    }
+
+    /// Index of the Font DICT a glyph uses in a CID-keyed font
+    pub fn font_dict_index(&self, glyph_id: GlyphId) -> Option<u16> {
+        self.fd_select.as_ref()?.get(glyph_id)
+    }
+
+    pub fn fd_array(&self) -> &[FontDict] {
+        &self.fd_array
+    }
+
+    /// Private DICT used to interpret a glyph's charstring
+    pub fn private_dict(&self, glyph_id: GlyphId) -> Option<&PrivateDict> {
+        match &self.fd_select {
+            Some(fd_select) => self.fd_array.get(fd_select.get(glyph_id)? as usize)?.private.as_ref(),
+            None => self.private.as_ref(),
+        }
+    }
+
+    /// String for a SID, from the standard strings or the String INDEX
+    pub fn string(&self, sid: u16) -> Option<&str> {
+        match STANDARD_STRINGS.get(sid as usize) {
+            Some(s) => Some(s),
+            None => str::from_utf8(self.string_index.get(sid as usize - STANDARD_STRINGS.len())?).ok(),
+        }
+    }
+
+    /// Glyph name from the charset, CID-keyed fonts have no names so their glyphs are called `cid00042` and so on
+    pub fn glyph_name(&self, glyph_id: GlyphId) -> Option<String> {
+        if glyph_id as usize >= self.num_glyphs() {
+            return None;
+        }
+
+        let id = self.charset.get(glyph_id)?;
+        if self.is_cid_keyed() { Some(format!("cid{:05}", id)) } else { self.string(id).map(String::from) }
+    }
+
+    /// Draw a glyph by interpreting its Type 2 charstring
+    pub fn outline(&self, glyph_id: GlyphId, sink: &mut impl OutlineSink) -> Result<(), Box<dyn Error>> {
+        let seac = self.draw_char_string(glyph_id, (0.0, 0.0), sink)?;
+
+        // endchar with 4 arguments builds an accented glyph from two StandardEncoding characters
+        if let Some([adx, ady, bchar, achar]) = seac {
+            let base = self.standard_encoding_glyph(bchar).ok_or("seac base character is not in the font")?;
+            let accent = self.standard_encoding_glyph(achar).ok_or("seac accent character is not in the font")?;
+            self.draw_char_string(base, (0.0, 0.0), sink)?;
+            self.draw_char_string(accent, (adx, ady), sink)?;
+        }
+        Ok(())
+    }
+
+    fn draw_char_string(&self, glyph_id: GlyphId, offset: (f32, f32), sink: &mut impl OutlineSink) -> Result<Option<[f32; 4]>, Box<dyn Error>> {
+        let cs = self.char_strings.get(glyph_id as usize).ok_or_else(|| format!("glyph {} has no CFF charstring", glyph_id))?;
+        let local_subrs = self.private_dict(glyph_id).map(|p| &p.subrs);
+
+        let mut interpreter = CharStringInterpreter::new(&self.global_subr_index, local_subrs, offset, sink);
+        interpreter.execute(cs, 0)?;
+        interpreter.finish();
+        Ok(interpreter.seac)
+    }
+
+    /// Glyph whose charset SID matches a StandardEncoding code, as referenced by seac
+    fn standard_encoding_glyph(&self, code: f32) -> Option<GlyphId> {
+        let sid = standard_encoding_sid(u8::try_from(code as i32).ok()?);
+        if 0 == sid {
+            return None;
+        }
+        (0..self.num_glyphs() as GlyphId).find(|&gid| self.charset.get(gid) == Some(sid))
+    }
+}
+
+/// Top DICT and Private DICT operators, two byte operators are `12 << 8 | second byte`
+pub mod op {
+    pub const VERSION: u16 = 0;
+    pub const NOTICE: u16 = 1;
+    pub const FULL_NAME: u16 = 2;
+    pub const FAMILY_NAME: u16 = 3;
+    pub const WEIGHT: u16 = 4;
+    pub const FONT_BBOX: u16 = 5;
+    pub const UNIQUE_ID: u16 = 13;
+    pub const XUID: u16 = 14;
+    pub const CHARSET: u16 = 15;
+    pub const ENCODING: u16 = 16;
+    pub const CHAR_STRINGS: u16 = 17;
+    pub const PRIVATE: u16 = 18;
+    pub const COPYRIGHT: u16 = 12 << 8;
+    pub const IS_FIXED_PITCH: u16 = 12 << 8 | 1;
+    pub const ITALIC_ANGLE: u16 = 12 << 8 | 2;
+    pub const UNDERLINE_POSITION: u16 = 12 << 8 | 3;
+    pub const UNDERLINE_THICKNESS: u16 = 12 << 8 | 4;
+    pub const PAINT_TYPE: u16 = 12 << 8 | 5;
+    pub const CHARSTRING_TYPE: u16 = 12 << 8 | 6;
+    pub const FONT_MATRIX: u16 = 12 << 8 | 7;
+    pub const STROKE_WIDTH: u16 = 12 << 8 | 8;
+    pub const SYNTHETIC_BASE: u16 = 12 << 8 | 20;
+    pub const POST_SCRIPT: u16 = 12 << 8 | 21;
+    pub const BASE_FONT_NAME: u16 = 12 << 8 | 22;
+    pub const BASE_FONT_BLEND: u16 = 12 << 8 | 23;
+    pub const ROS: u16 = 12 << 8 | 30;
+    pub const CID_FONT_VERSION: u16 = 12 << 8 | 31;
+    pub const CID_FONT_REVISION: u16 = 12 << 8 | 32;
+    pub const CID_FONT_TYPE: u16 = 12 << 8 | 33;
+    pub const CID_COUNT: u16 = 12 << 8 | 34;
+    pub const UID_BASE: u16 = 12 << 8 | 35;
+    pub const FD_ARRAY: u16 = 12 << 8 | 36;
+    pub const FD_SELECT: u16 = 12 << 8 | 37;
+    pub const FONT_NAME: u16 = 12 << 8 | 38;
+
+    pub const BLUE_VALUES: u16 = 6;
+    pub const OTHER_BLUES: u16 = 7;
+    pub const FAMILY_BLUES: u16 = 8;
+    pub const FAMILY_OTHER_BLUES: u16 = 9;
+    pub const STD_HW: u16 = 10;
+    pub const STD_VW: u16 = 11;
+    pub const SUBRS: u16 = 19;
+    pub const DEFAULT_WIDTH_X: u16 = 20;
+    pub const NOMINAL_WIDTH_X: u16 = 21;
+    pub const BLUE_SCALE: u16 = 12 << 8 | 9;
+    pub const BLUE_SHIFT: u16 = 12 << 8 | 10;
+    pub const BLUE_FUZZ: u16 = 12 << 8 | 11;
+    pub const STEM_SNAP_H: u16 = 12 << 8 | 12;
+    pub const STEM_SNAP_V: u16 = 12 << 8 | 13;
+    pub const FORCE_BOLD: u16 = 12 << 8 | 14;
+    pub const LANGUAGE_GROUP: u16 = 12 << 8 | 17;
+    pub const EXPANSION_FACTOR: u16 = 12 << 8 | 18;
+    pub const INITIAL_RANDOM_SEED: u16 = 12 << 8 | 19;
+
+    pub const VSINDEX: u16 = 22;
+    pub const BLEND: u16 = 23;
+    pub const VSTORE: u16 = 24;
+}
+
+/// An array of variable-sized objects
+pub struct Index {
+    /// Number of objects stored in INDEX, 16 bit in CFF and 32 bit in CFF2
+    count: u32,
+    /// Offset array element size
+    off_size: u8,
+    /// Offsets into data, 1-based like in the file
+    offsets: Vec<u32>,
+    /// Object data
+    data: Vec<u8>,
+}
+
+impl Debug for Index {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Index")
+            .field("count", &self.count)
+            .field("off_size", &self.off_size)
+            .field_with("offsets", |f| write!(f, "[u32; {}]", self.offsets.len()))
+            .field_with("data", |f| write!(f, "[u8; {}]", self.data.len()))
+            .finish()
+    }
+}
+
+impl Index {
+    /// Reads the INDEX and leaves `r` just past its data
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let count = read_uint16(r)? as u32;
+        Self::read_objects(r, count)
+    }
+
+    /// Reads a CFF2 INDEX, which differs only by its 32 bit count
+    pub fn from_bytes_cff2(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let count = read_uint32(r)?;
+        Self::read_objects(r, count)
+    }
+
+    fn read_objects(r: &mut &[u8], count: u32) -> Result<Self, Box<dyn Error>> {
+        if 0 == count {
+            return Ok(Self {
+                count,
+                off_size: 0,
+                offsets: Vec::new(),
+                data: Vec::new(),
+            });
+        }
+
+        let off_size = read_uint8(r)?;
+        if !(1..=4).contains(&off_size) {
+            return Err(format!("CFF INDEX offSize {} out of range", off_size).into());
+        }
+
+        let mut offsets = Vec::with_capacity((count as usize + 1).min(u16::MAX as usize));
+        for _ in 0..=count {
+            offsets.push(read_offset_sized(r, off_size)?);
+        }
+
+        if 1 != offsets[0] || offsets.windows(2).any(|w| w[0] > w[1]) {
+            return Err("CFF INDEX offsets must start at 1 and never decrease".into());
+        }
+
+        let data_len = offsets[count as usize] as usize - 1;
+        let data = r.get(..data_len).ok_or("CFF INDEX data truncated")?.to_vec();
+        *r = &r[data_len..];
+
+        Ok(Self { count, off_size, offsets, data })
+    }
+
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        0 == self.count
+    }
+
+    pub fn get(&self, i: usize) -> Option<&[u8]> {
+        let start = *self.offsets.get(i)? as usize - 1;
+        let end = *self.offsets.get(i + 1)? as usize - 1;
+        self.data.get(start..end)
+    }
+
+    /// Encode `objects` as a CFF2 INDEX with the smallest offset size that fits, an empty INDEX is just its count
+    pub fn write_cff2(w: &mut Vec<u8>, objects: &[Vec<u8>]) {
+        write_uint32(w, objects.len() as u32);
+        if objects.is_empty() {
+            return;
+        }
+        let last_offset = 1 + objects.iter().map(|o| o.len()).sum::<usize>();
+        let off_size = match last_offset {
+            0..0x100 => 1,
+            0x100..0x10000 => 2,
+            0x10000..0x1000000 => 3,
+            _ => 4,
+        };
+        write_uint8(w, off_size);
+        let mut offset = 1;
+        for o in std::iter::once(&[][..]).chain(objects.iter().map(|o| o.as_slice())) {
+            offset += o.len();
+            w.extend(&(offset as u32).to_be_bytes()[4 - off_size as usize..]);
+        }
+        objects.iter().for_each(|o| w.extend(o));
+    }
+}
+
+/// Read a 1 to 4 byte big-endian offset
+fn read_offset_sized(r: &mut &[u8], off_size: u8) -> Result<u32, Box<dyn Error>> {
+    let mut v = 0;
+    for _ in 0..off_size {
+        v = v << 8 | read_uint8(r)? as u32;
+    }
+    Ok(v)
+}
+
+/// Key-value pairs in DICT data, the operands come before their operator
+pub struct Dict {
+    entries: Vec<(u16, Vec<f64>)>,
+}
+
+impl Debug for Dict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.entries.iter().map(|(k, v)| (k, v))).finish()
+    }
+}
+
+impl Dict {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let mut entries = Vec::new();
+        let mut operands = Vec::new();
+
+        while !r.is_empty() {
+            let b0 = read_uint8(r)?;
+            match b0 {
+                12 => entries.push((12 << 8 | read_uint8(r)? as u16, std::mem::take(&mut operands))),
+                // 22 to 24 are only defined in CFF2, blended operands stay with the blend entry
+                0..=24 => entries.push((b0 as u16, std::mem::take(&mut operands))),
+                28 => operands.push(read_int16(r)? as f64),
+                29 => operands.push(read_int32(r)? as f64),
+                30 => operands.push(read_real(r)?),
+                32..=246 => operands.push(b0 as f64 - 139.0),
+                247..=250 => operands.push(((b0 as f64 - 247.0) * 256.0) + read_uint8(r)? as f64 + 108.0),
+                251..=254 => operands.push(-((b0 as f64 - 251.0) * 256.0) - read_uint8(r)? as f64 - 108.0),
+                _ => return Err(format!("reserved CFF DICT byte {}", b0).into()),
+            }
+        }
+
+        Ok(Self { entries })
+    }
+
+    pub fn get(&self, op: u16) -> Option<&[f64]> {
+        self.entries.iter().find(|(k, _)| *k == op).map(|(_, v)| v.as_slice())
+    }
+
+    /// An operator with a single offset operand, like charset or CharStrings
+    pub fn get_offset(&self, op: u16) -> Option<usize> {
+        let v = *self.get(op)?.first()?;
+        if v < 0.0 { None } else { Some(v as usize) }
+    }
+
+    /// Operators with their operands in file order
+    pub fn entries(&self) -> &[(u16, Vec<f64>)] {
+        &self.entries
+    }
+
+    pub fn from_entries(entries: Vec<(u16, Vec<f64>)>) -> Self {
+        Self { entries }
+    }
+
+    /// Encode the entries, operands of `offset_ops` always take five bytes so the DICT keeps its size once the offsets are known
+    pub fn write(&self, w: &mut Vec<u8>, offset_ops: &[u16]) {
+        for (op, operands) in &self.entries {
+            for &v in operands {
+                if offset_ops.contains(op) {
+                    write_uint8(w, 29);
+                    write_int32(w, v as i32);
+                } else {
+                    write_dict_number(w, v);
+                }
+            }
+            if *op > 0xFF {
+                write_uint8(w, 12);
+            }
+            write_uint8(w, *op as u8);
+        }
+    }
+}
+
+/// Integers in their shortest form, anything else as a real number
+fn write_dict_number(w: &mut Vec<u8>, v: f64) {
+    if v.fract() != 0.0 || !(i32::MIN as f64..=i32::MAX as f64).contains(&v) {
+        write_real(w, v);
+        return;
+    }
+    match v as i32 {
+        i @ -107..=107 => write_uint8(w, (i + 139) as u8),
+        i @ 108..=1131 => w.extend([((i - 108) >> 8) as u8 + 247, (i - 108) as u8]),
+        i @ -1131..=-108 => w.extend([((-i - 108) >> 8) as u8 + 251, (-i - 108) as u8]),
+        i @ -32768..=32767 => {
+            write_uint8(w, 28);
+            write_int16(w, i as i16);
+        }
+        i => {
+            write_uint8(w, 29);
+            write_int32(w, i);
+        }
+    }
+}
+
+/// Real number operand, the decimal digits packed two to a byte as `read_real` expects them
+fn write_real(w: &mut Vec<u8>, v: f64) {
+    let s = format!("{:e}", v);
+    let mut nibbles = Vec::with_capacity(s.len() + 2);
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '0'..='9' => nibbles.push(c as u8 - b'0'),
+            '.' => nibbles.push(0xA),
+            '-' => nibbles.push(0xE),
+            'e' if chars.peek() == Some(&'-') => {
+                chars.next();
+                nibbles.push(0xC);
+            }
+            'e' if chars.peek() == Some(&'0') => break,
+            'e' => nibbles.push(0xB),
+            _ => {}
+        }
+    }
+    nibbles.push(0xF);
+    if 1 == nibbles.len() % 2 {
+        nibbles.push(0xF);
+    }
+    write_uint8(w, 30);
+    w.extend(nibbles.chunks(2).map(|n| n[0] << 4 | n[1]));
+}
+
+/// Real number operand, packed BCD nibbles terminated by 0xf
+fn read_real(r: &mut &[u8]) -> Result<f64, Box<dyn Error>> {
+    let mut s = String::new();
+    'bytes: loop {
+        let b = read_uint8(r)?;
+        for nibble in [b >> 4, b & 0xF] {
+            match nibble {
+                0..=9 => s.push((b'0' + nibble) as char),
+                0xA => s.push('.'),
+                0xB => s.push('E'),
+                0xC => s.push_str("E-"),
+                0xE => s.push('-'),
+                0xF => break 'bytes,
+                _ => return Err("reserved nibble in CFF real number".into()),
+            }
+        }
+    }
+    Ok(s.parse().unwrap_or(0.0))
+}
+
+/// Maps glyph IDs to SIDs, or to CIDs in CID-keyed fonts
+pub enum Charset {
+    ISOAdobe,
+    Expert,
+    ExpertSubset,
+    /// Format 0, 1 or 2 expanded to one SID/CID per glyph
+    Custom(Vec<u16>),
+}
+
+impl Debug for Charset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ISOAdobe => write!(f, "ISOAdobe"),
+            Self::Expert => write!(f, "Expert"),
+            Self::ExpertSubset => write!(f, "ExpertSubset"),
+            Self::Custom(v) => write!(f, "Custom([u16; {}])", v.len()),
+        }
+    }
+}
+
+impl Charset {
+    pub fn from_bytes(mut b: &[u8], num_glyphs: usize) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let format = read_uint8(r)?;
+        // .notdef is always glyph 0 and isn't stored
+        let mut ids = Vec::with_capacity(num_glyphs);
+        ids.push(0);
+
+        match format {
+            0 => {
+                while ids.len() < num_glyphs {
+                    ids.push(read_uint16(r)?);
+                }
+            }
+            1 | 2 => {
+                while ids.len() < num_glyphs {
+                    let first = read_uint16(r)?;
+                    let n_left = if 1 == format { read_uint8(r)? as u16 } else { read_uint16(r)? };
+                    for i in 0..=n_left {
+                        ids.push(first.wrapping_add(i));
+                    }
+                }
+                ids.truncate(num_glyphs);
+            }
+            _ => return Err(format!("unsupported CFF charset format {}", format).into()),
+        }
+
+        Ok(Self::Custom(ids))
+    }
+
+    /// SID (CID in CID-keyed fonts) of a glyph, the predefined expert charsets are not supported
+    pub fn get(&self, glyph_id: GlyphId) -> Option<u16> {
+        match self {
+            Self::ISOAdobe => if glyph_id <= 228 { Some(glyph_id) } else { None },
+            Self::Expert | Self::ExpertSubset => None,
+            Self::Custom(v) => v.get(glyph_id as usize).copied(),
+        }
+    }
+}
+
+/// Character collection of a CID-keyed font, as PDF CIDSystemInfo needs it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CidSystemInfo {
+    registry: String,
+    ordering: String,
+    supplement: i32,
+}
+
+impl CidSystemInfo {
+    pub fn registry(&self) -> &str {
+        &self.registry
+    }
+
+    pub fn ordering(&self) -> &str {
+        &self.ordering
+    }
+
+    pub fn supplement(&self) -> i32 {
+        self.supplement
+    }
+}
+
+/// An FDArray entry, a Top DICT subset that points to the Private DICT of a group of glyphs
+pub struct FontDict {
+    dict: Dict,
+    private: Option<PrivateDict>,
+}
+
+impl Debug for FontDict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FontDict").field("dict", &self.dict).field("private", &self.private).finish()
+    }
+}
+
+impl FontDict {
+    /// `o` is the whole CFF table, the Private DICT offset is relative to it
+    pub fn from_bytes(o: &[u8], b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        Self::read(o, b, false)
+    }
+
+    pub fn from_bytes_cff2(o: &[u8], b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        Self::read(o, b, true)
+    }
+
+    fn read(o: &[u8], b: &[u8], cff2: bool) -> Result<Self, Box<dyn Error>> {
+        let dict = Dict::from_bytes(b)?;
+        let private = match dict.get(op::PRIVATE) {
+            Some(&[size, offset]) => Some(PrivateDict::read(o, size as usize, offset as usize, cff2)?),
+            _ => None,
+        };
+
+        Ok(Self { dict, private })
+    }
+
+    pub fn dict(&self) -> &Dict {
+        &self.dict
+    }
+
+    pub fn private(&self) -> Option<&PrivateDict> {
+        self.private.as_ref()
+    }
+}
+
+/// Maps glyphs to FDArray entries
+pub enum FdSelect {
+    /// One Font DICT index per glyph
+    Format0(Vec<u8>),
+    /// Ranges of glyphs sharing a Font DICT, each range runs up to the next one's first glyph or the sentinel
+    Format3 { ranges: Vec<(GlyphId, u8)>, sentinel: GlyphId },
+    /// CFF2 only, format 3 with 32 bit glyph numbers and 16 bit Font DICT indexes
+    Format4 { ranges: Vec<(u32, u16)>, sentinel: u32 },
+}
+
+impl Debug for FdSelect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Format0(v) => write!(f, "Format0([u8; {}])", v.len()),
+            Self::Format3 { ranges, sentinel } => f.debug_struct("Format3").field("ranges", ranges).field("sentinel", sentinel).finish(),
+            Self::Format4 { ranges, sentinel } => f.debug_struct("Format4").field("ranges", ranges).field("sentinel", sentinel).finish(),
+        }
+    }
+}
+
+impl FdSelect {
+    pub fn from_bytes(mut b: &[u8], num_glyphs: usize) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let format = read_uint8(r)?;
+        match format {
+            0 => Ok(Self::Format0(read_array(r, num_glyphs, read_uint8)?)),
+            3 => {
+                let n_ranges = read_uint16(r)?;
+                let mut ranges = Vec::with_capacity(n_ranges as usize);
+                for _ in 0..n_ranges {
+                    ranges.push((read_uint16(r)?, read_uint8(r)?));
+                }
+                let sentinel = read_uint16(r)?;
+
+                if ranges.first().is_some_and(|&(first, _)| 0 != first) || ranges.windows(2).any(|w| w[0].0 >= w[1].0) || ranges.last().is_some_and(|&(first, _)| first >= sentinel) {
+                    return Err("CFF FDSelect ranges must start at glyph 0 and increase".into());
+                }
+                Ok(Self::Format3 { ranges, sentinel })
+            }
+            4 => {
+                let n_ranges = read_uint32(r)?;
+                let mut ranges = Vec::with_capacity((n_ranges as usize).min(u16::MAX as usize));
+                for _ in 0..n_ranges {
+                    ranges.push((read_uint32(r)?, read_uint16(r)?));
+                }
+                let sentinel = read_uint32(r)?;
+
+                if ranges.first().is_some_and(|&(first, _)| 0 != first) || ranges.windows(2).any(|w| w[0].0 >= w[1].0) || ranges.last().is_some_and(|&(first, _)| first >= sentinel) {
+                    return Err("CFF2 FDSelect ranges must start at glyph 0 and increase".into());
+                }
+                Ok(Self::Format4 { ranges, sentinel })
+            }
+            _ => Err(format!("unsupported CFF FDSelect format {}", format).into()),
+        }
+    }
+
+    pub fn get(&self, glyph_id: GlyphId) -> Option<u16> {
+        match self {
+            Self::Format0(v) => v.get(glyph_id as usize).map(|&fd| fd as u16),
+            Self::Format3 { ranges, sentinel } => {
+                if glyph_id >= *sentinel {
+                    return None;
+                }
+                let i = ranges.partition_point(|&(first, _)| first <= glyph_id);
+                ranges.get(i.checked_sub(1)?).map(|&(_, fd)| fd as u16)
+            }
+            Self::Format4 { ranges, sentinel } => {
+                if glyph_id as u32 >= *sentinel {
+                    return None;
+                }
+                let i = ranges.partition_point(|&(first, _)| first <= glyph_id as u32);
+                ranges.get(i.checked_sub(1)?).map(|&(_, fd)| fd)
+            }
+        }
+    }
+
+    /// Largest Font DICT index in use, to check against the FDArray length
+    pub fn max_fd(&self) -> Option<u16> {
+        match self {
+            Self::Format0(v) => v.iter().map(|&fd| fd as u16).max(),
+            Self::Format3 { ranges, .. } => ranges.iter().map(|&(_, fd)| fd as u16).max(),
+            Self::Format4 { ranges, .. } => ranges.iter().map(|&(_, fd)| fd).max(),
+        }
+    }
+}
+
+/// A Private DICT with the local subrs it points to
+pub struct PrivateDict {
+    dict: Dict,
+    /// Local subroutines, empty when the Private DICT has no Subrs
+    subrs: Index,
+}
+
+impl Debug for PrivateDict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrivateDict").field("dict", &self.dict).field("subrs", &self.subrs).finish()
+    }
+}
+
+impl PrivateDict {
+    /// `b` is the whole CFF table, the Private DICT lives at `offset` and is `size` bytes long
+    pub fn from_bytes(b: &[u8], size: usize, offset: usize) -> Result<Self, Box<dyn Error>> {
+        Self::read(b, size, offset, false)
+    }
+
+    pub fn from_bytes_cff2(b: &[u8], size: usize, offset: usize) -> Result<Self, Box<dyn Error>> {
+        Self::read(b, size, offset, true)
+    }
+
+    fn read(b: &[u8], size: usize, offset: usize, cff2: bool) -> Result<Self, Box<dyn Error>> {
+        let dict = Dict::from_bytes(b.get(offset..offset + size).ok_or("CFF Private DICT out of range")?)?;
+
+        // Subrs is an offset from the start of the Private DICT
+        let subrs = match dict.get_offset(op::SUBRS) {
+            Some(subrs_offset) => {
+                let r = &mut b.get(offset + subrs_offset..).ok_or("CFF local subrs offset out of range")?;
+                if cff2 { Index::from_bytes_cff2(r)? } else { Index::from_bytes(r)? }
+            }
+            None => Index::read_objects(&mut &[][..], 0)?,
+        };
+
+        Ok(Self { dict, subrs })
+    }
+
+    /// Default vsindex of the CFF2 charstrings using this Private DICT
+    pub fn vsindex(&self) -> u16 {
+        self.dict.get(op::VSINDEX).and_then(|v| v.first()).map_or(0, |&v| v as u16)
+    }
+
+    pub fn dict(&self) -> &Dict {
+        &self.dict
+    }
+
+    pub fn subrs(&self) -> &Index {
+        &self.subrs
+    }
+}
+
+/// Type 2 charstring operators, two byte operators are `12 << 8 | second byte`
+pub(crate) mod cs_op {
+    pub const HSTEM: u16 = 1;
+    pub const VSTEM: u16 = 3;
+    pub const VMOVETO: u16 = 4;
+    pub const RLINETO: u16 = 5;
+    pub const HLINETO: u16 = 6;
+    pub const VLINETO: u16 = 7;
+    pub const RRCURVETO: u16 = 8;
+    pub const CALLSUBR: u16 = 10;
+    pub const RETURN: u16 = 11;
+    pub const ENDCHAR: u16 = 14;
+    pub const VSINDEX: u16 = 15;
+    pub const BLEND: u16 = 16;
+    pub const HSTEMHM: u16 = 18;
+    pub const HINTMASK: u16 = 19;
+    pub const CNTRMASK: u16 = 20;
+    pub const RMOVETO: u16 = 21;
+    pub const HMOVETO: u16 = 22;
+    pub const VSTEMHM: u16 = 23;
+    pub const RCURVELINE: u16 = 24;
+    pub const RLINECURVE: u16 = 25;
+    pub const VVCURVETO: u16 = 26;
+    pub const HHCURVETO: u16 = 27;
+    pub const CALLGSUBR: u16 = 29;
+    pub const VHCURVETO: u16 = 30;
+    pub const HVCURVETO: u16 = 31;
+    pub const DOTSECTION: u16 = 12 << 8;
+    pub const AND: u16 = 12 << 8 | 3;
+    pub const OR: u16 = 12 << 8 | 4;
+    pub const NOT: u16 = 12 << 8 | 5;
+    pub const ABS: u16 = 12 << 8 | 9;
+    pub const ADD: u16 = 12 << 8 | 10;
+    pub const SUB: u16 = 12 << 8 | 11;
+    pub const DIV: u16 = 12 << 8 | 12;
+    pub const NEG: u16 = 12 << 8 | 14;
+    pub const EQ: u16 = 12 << 8 | 15;
+    pub const DROP: u16 = 12 << 8 | 18;
+    pub const PUT: u16 = 12 << 8 | 20;
+    pub const GET: u16 = 12 << 8 | 21;
+    pub const IFELSE: u16 = 12 << 8 | 22;
+    pub const RANDOM: u16 = 12 << 8 | 23;
+    pub const MUL: u16 = 12 << 8 | 24;
+    pub const SQRT: u16 = 12 << 8 | 26;
+    pub const DUP: u16 = 12 << 8 | 27;
+    pub const EXCH: u16 = 12 << 8 | 28;
+    pub const INDEX: u16 = 12 << 8 | 29;
+    pub const ROLL: u16 = 12 << 8 | 30;
+    pub const HFLEX: u16 = 12 << 8 | 34;
+    pub const FLEX: u16 = 12 << 8 | 35;
+    pub const HFLEX1: u16 = 12 << 8 | 36;
+    pub const FLEX1: u16 = 12 << 8 | 37;
+}
+
+/// Argument stack limit of Type 2 charstrings
+const MAX_STACK: usize = 48;
+/// Argument stack limit of CFF2 charstrings, raised to hold blend deltas
+const MAX_STACK_CFF2: usize = 513;
+/// Subroutine nesting limit of Type 2 charstrings
+pub(crate) const MAX_SUBR_DEPTH: usize = 10;
+const TRANSIENT_ARRAY_SIZE: usize = 32;
+
+/// Subroutine numbers are stored biased so that small charstrings can use one byte numbers
+pub(crate) fn subr_bias(subrs: &Index) -> i32 {
+    match subrs.len() {
+        0..1240 => 107,
+        1240..33900 => 1131,
+        _ => 32768,
+    }
+}
+
+/// Evaluates Type 2 charstrings and sends the path to an `OutlineSink`, hints are counted but otherwise ignored
+pub(crate) struct CharStringInterpreter<'a, S: OutlineSink> {
+    global_subrs: &'a Index,
+    local_subrs: Option<&'a Index>,
+    sink: &'a mut S,
+    stack: Vec<f32>,
+    transient: [f32; TRANSIENT_ARRAY_SIZE],
+    x: f32,
+    y: f32,
+    /// Added to every point, used to place the accent of a seac glyph
+    offset: (f32, f32),
+    num_stems: usize,
+    /// The advance width may precede the arguments of the first stack clearing operator
+    width_parsed: bool,
+    contour_open: bool,
+    /// Arguments of an endchar that works like seac: adx ady bchar achar
+    seac: Option<[f32; 4]>,
+    is_cff2: bool,
+    /// Variation store, normalized coordinates and current vsindex of CFF2 charstrings
+    variations: Option<(&'a ItemVariationStore, &'a [F2Dot14], u16)>,
+    /// Region scalars for the current vsindex, computed at the first blend
+    scalars: Option<Vec<f32>>,
+}
+
+impl<'a, S: OutlineSink> CharStringInterpreter<'a, S> {
+    pub(crate) fn new(global_subrs: &'a Index, local_subrs: Option<&'a Index>, offset: (f32, f32), sink: &'a mut S) -> Self {
+        Self {
+            global_subrs,
+            local_subrs,
+            sink,
+            stack: Vec::with_capacity(MAX_STACK),
+            transient: [0.0; TRANSIENT_ARRAY_SIZE],
+            x: 0.0,
+            y: 0.0,
+            offset,
+            num_stems: 0,
+            width_parsed: false,
+            contour_open: false,
+            seac: None,
+            is_cff2: false,
+            variations: None,
+            scalars: None,
+        }
+    }
+
+    /// Interpret CFF2 charstrings, which have no width or endchar but may blend their operands
+    pub(crate) fn with_variations(mut self, vstore: Option<&'a ItemVariationStore>, coords: &'a [F2Dot14], vsindex: u16) -> Self {
+        self.width_parsed = true;
+        self.stack.reserve(MAX_STACK_CFF2);
+        self.variations = vstore.map(|vstore| (vstore, coords, vsindex));
+        self.is_cff2 = true;
+        self
+    }
+
+    /// Returns true once endchar is reached
+    pub(crate) fn execute(&mut self, mut b: &[u8], depth: usize) -> Result<bool, Box<dyn Error>> {
+        if depth > MAX_SUBR_DEPTH {
+            return Err("CFF subroutines nested too deep".into());
+        }
+
+        let r = &mut b;
+        while !r.is_empty() {
+            let b0 = read_uint8(r)?;
+            let operator = match b0 {
+                12 => 12 << 8 | read_uint8(r)? as u16,
+                0..=31 if 28 != b0 => b0 as u16,
+                _ => {
+                    let v = match b0 {
+                        28 => read_int16(r)? as f32,
+                        32..=246 => b0 as f32 - 139.0,
+                        247..=250 => (b0 as f32 - 247.0) * 256.0 + read_uint8(r)? as f32 + 108.0,
+                        251..=254 => -(b0 as f32 - 251.0) * 256.0 - read_uint8(r)? as f32 - 108.0,
+                        _ => read_fixed(r)?,
+                    };
+                    self.push(v)?;
+                    continue;
+                }
+            };
+
+            match operator {
+                cs_op::HSTEM | cs_op::VSTEM | cs_op::HSTEMHM | cs_op::VSTEMHM => {
+                    self.parse_width(1 == self.stack.len() % 2);
+                    self.num_stems += self.stack.len() / 2;
+                    self.stack.clear();
+                }
+                cs_op::HINTMASK | cs_op::CNTRMASK => {
+                    // arguments left on the stack are an implied vstem
+                    self.parse_width(1 == self.stack.len() % 2);
+                    self.num_stems += self.stack.len() / 2;
+                    self.stack.clear();
+
+                    let mask_len = self.num_stems.div_ceil(8);
+                    *r = r.get(mask_len..).ok_or("CFF hintmask truncated")?;
+                }
+                cs_op::RMOVETO => {
+                    self.parse_width(self.stack.len() > 2);
+                    let [dx, dy] = self.args()?;
+                    self.move_to(self.x + dx, self.y + dy);
+                }
+                cs_op::HMOVETO => {
+                    self.parse_width(self.stack.len() > 1);
+                    let [dx] = self.args()?;
+                    self.move_to(self.x + dx, self.y);
+                }
+                cs_op::VMOVETO => {
+                    self.parse_width(self.stack.len() > 1);
+                    let [dy] = self.args()?;
+                    self.move_to(self.x, self.y + dy);
+                }
+                cs_op::RLINETO => {
+                    for i in (0..self.stack.len() - self.stack.len() % 2).step_by(2) {
+                        self.line_to(self.x + self.stack[i], self.y + self.stack[i + 1]);
+                    }
+                    self.stack.clear();
+                }
+                cs_op::HLINETO | cs_op::VLINETO => {
+                    let mut horizontal = cs_op::HLINETO == operator;
+                    for i in 0..self.stack.len() {
+                        let d = self.stack[i];
+                        if horizontal { self.line_to(self.x + d, self.y) } else { self.line_to(self.x, self.y + d) }
+                        horizontal = !horizontal;
+                    }
+                    self.stack.clear();
+                }
+                cs_op::RRCURVETO => {
+                    for i in (0..self.stack.len() - self.stack.len() % 6).step_by(6) {
+                        let s = &self.stack[i..i + 6];
+                        let (a, b, c, d, e, f) = (s[0], s[1], s[2], s[3], s[4], s[5]);
+                        self.curve_to(a, b, c, d, e, f);
+                    }
+                    self.stack.clear();
+                }
+                cs_op::RCURVELINE => {
+                    if self.stack.len() < 8 {
+                        return Err("CFF rcurveline needs at least 8 arguments".into());
+                    }
+                    let curves = (self.stack.len() - 2) / 6 * 6;
+                    for i in (0..curves).step_by(6) {
+                        let s = &self.stack[i..i + 6];
+                        let (a, b, c, d, e, f) = (s[0], s[1], s[2], s[3], s[4], s[5]);
+                        self.curve_to(a, b, c, d, e, f);
+                    }
+                    self.line_to(self.x + self.stack[curves], self.y + self.stack[curves + 1]);
+                    self.stack.clear();
+                }
+                cs_op::RLINECURVE => {
+                    if self.stack.len() < 8 {
+                        return Err("CFF rlinecurve needs at least 8 arguments".into());
+                    }
+                    let lines = (self.stack.len() - 6) / 2 * 2;
+                    for i in (0..lines).step_by(2) {
+                        self.line_to(self.x + self.stack[i], self.y + self.stack[i + 1]);
+                    }
+                    let s = &self.stack[lines..lines + 6];
+                    let (a, b, c, d, e, f) = (s[0], s[1], s[2], s[3], s[4], s[5]);
+                    self.curve_to(a, b, c, d, e, f);
+                    self.stack.clear();
+                }
+                cs_op::VVCURVETO => {
+                    // dx1? {dya dxb dyb dyc}+
+                    let mut i = self.stack.len() % 4;
+                    let mut dx1 = if 1 == i { self.stack[0] } else { 0.0 };
+                    while i + 4 <= self.stack.len() {
+                        let s = &self.stack[i..i + 4];
+                        let (dya, dxb, dyb, dyc) = (s[0], s[1], s[2], s[3]);
+                        self.curve_to(dx1, dya, dxb, dyb, 0.0, dyc);
+                        dx1 = 0.0;
+                        i += 4;
+                    }
+                    self.stack.clear();
+                }
+                cs_op::HHCURVETO => {
+                    // dy1? {dxa dxb dyb dxc}+
+                    let mut i = self.stack.len() % 4;
+                    let mut dy1 = if 1 == i { self.stack[0] } else { 0.0 };
+                    while i + 4 <= self.stack.len() {
+                        let s = &self.stack[i..i + 4];
+                        let (dxa, dxb, dyb, dxc) = (s[0], s[1], s[2], s[3]);
+                        self.curve_to(dxa, dy1, dxb, dyb, dxc, 0.0);
+                        dy1 = 0.0;
+                        i += 4;
+                    }
+                    self.stack.clear();
+                }
+                cs_op::VHCURVETO | cs_op::HVCURVETO => {
+                    // curves alternate between starting horizontal and vertical, the last one may end with an extra coordinate
+                    let mut horizontal = cs_op::HVCURVETO == operator;
+                    let mut i = 0;
+                    while i + 4 <= self.stack.len() {
+                        let s = &self.stack[i..i + 4];
+                        let (d1, d2, d3, d4) = (s[0], s[1], s[2], s[3]);
+                        let last = if 5 == self.stack.len() - i { self.stack[i + 4] } else { 0.0 };
+                        if horizontal { self.curve_to(d1, 0.0, d2, d3, last, d4) } else { self.curve_to(0.0, d1, d2, d3, d4, last) }
+                        horizontal = !horizontal;
+                        i += 4;
+                    }
+                    self.stack.clear();
+                }
+                cs_op::HFLEX => {
+                    let [dx1, dx2, dy2, dx3, dx4, dx5, dx6] = self.args()?;
+                    self.curve_to(dx1, 0.0, dx2, dy2, dx3, 0.0);
+                    self.curve_to(dx4, 0.0, dx5, -dy2, dx6, 0.0);
+                }
+                cs_op::FLEX => {
+                    let [dx1, dy1, dx2, dy2, dx3, dy3, dx4, dy4, dx5, dy5, dx6, dy6, _fd] = self.args()?;
+                    self.curve_to(dx1, dy1, dx2, dy2, dx3, dy3);
+                    self.curve_to(dx4, dy4, dx5, dy5, dx6, dy6);
+                }
+                cs_op::HFLEX1 => {
+                    let [dx1, dy1, dx2, dy2, dx3, dx4, dx5, dy5, dx6] = self.args()?;
+                    self.curve_to(dx1, dy1, dx2, dy2, dx3, 0.0);
+                    self.curve_to(dx4, 0.0, dx5, dy5, dx6, -(dy1 + dy2 + dy5));
+                }
+                cs_op::FLEX1 => {
+                    let [dx1, dy1, dx2, dy2, dx3, dy3, dx4, dy4, dx5, dy5, d6] = self.args()?;
+                    let dx = dx1 + dx2 + dx3 + dx4 + dx5;
+                    let dy = dy1 + dy2 + dy3 + dy4 + dy5;
+                    // the last point returns to the start on the axis the flex moves less along
+                    let (dx6, dy6) = if dx.abs() > dy.abs() { (d6, -dy) } else { (-dx, d6) };
+                    self.curve_to(dx1, dy1, dx2, dy2, dx3, dy3);
+                    self.curve_to(dx4, dy4, dx5, dy5, dx6, dy6);
+                }
+                cs_op::ENDCHAR => {
+                    self.parse_width(1 == self.stack.len() || 5 == self.stack.len());
+                    if 4 == self.stack.len() {
+                        self.seac = Some([self.stack[0], self.stack[1], self.stack[2], self.stack[3]]);
+                    }
+                    self.stack.clear();
+                    return Ok(true);
+                }
+                cs_op::CALLSUBR | cs_op::CALLGSUBR => {
+                    let subrs = if cs_op::CALLSUBR == operator { self.local_subrs.ok_or("CFF callsubr without local subrs")? } else { self.global_subrs };
+                    let i = self.pop()? as i32 + subr_bias(subrs);
+                    let subr = usize::try_from(i).ok().and_then(|i| subrs.get(i)).ok_or_else(|| format!("CFF subroutine {} out of range", i))?;
+                    if self.execute(subr, depth + 1)? {
+                        return Ok(true);
+                    }
+                }
+                cs_op::RETURN => return Ok(false),
+                cs_op::VSINDEX if self.is_cff2 => {
+                    let [vsindex] = self.args()?;
+                    if let Some((_, _, i)) = &mut self.variations {
+                        *i = vsindex as u16;
+                    }
+                    self.scalars = None;
+                }
+                cs_op::BLEND if self.is_cff2 => self.blend()?,
+                cs_op::DOTSECTION => self.stack.clear(),
+                cs_op::AND => {
+                    let [a, b] = self.pop_n()?;
+                    self.push((0.0 != a && 0.0 != b) as u8 as f32)?;
+                }
+                cs_op::OR => {
+                    let [a, b] = self.pop_n()?;
+                    self.push((0.0 != a || 0.0 != b) as u8 as f32)?;
+                }
+                cs_op::NOT => {
+                    let [a] = self.pop_n()?;
+                    self.push((0.0 == a) as u8 as f32)?;
+                }
+                cs_op::ABS => {
+                    let [a] = self.pop_n()?;
+                    self.push(a.abs())?;
+                }
+                cs_op::ADD => {
+                    let [a, b] = self.pop_n()?;
+                    self.push(a + b)?;
+                }
+                cs_op::SUB => {
+                    let [a, b] = self.pop_n()?;
+                    self.push(a - b)?;
+                }
+                cs_op::DIV => {
+                    let [a, b] = self.pop_n()?;
+                    self.push(if 0.0 == b { 0.0 } else { a / b })?;
+                }
+                cs_op::NEG => {
+                    let [a] = self.pop_n()?;
+                    self.push(-a)?;
+                }
+                cs_op::EQ => {
+                    let [a, b] = self.pop_n()?;
+                    self.push((a == b) as u8 as f32)?;
+                }
+                cs_op::DROP => {
+                    self.pop()?;
+                }
+                cs_op::PUT => {
+                    let [v, i] = self.pop_n()?;
+                    *self.transient.get_mut(i as usize).ok_or("CFF transient array index out of range")? = v;
+                }
+                cs_op::GET => {
+                    let [i] = self.pop_n()?;
+                    self.push(*self.transient.get(i as usize).ok_or("CFF transient array index out of range")?)?;
+                }
+                cs_op::IFELSE => {
+                    let [s1, s2, v1, v2] = self.pop_n()?;
+                    self.push(if v1 <= v2 { s1 } else { s2 })?;
+                }
+                cs_op::RANDOM => {
+                    // any value in (0, 1] is allowed, a fixed one keeps outlines reproducible
+                    self.push(0.5)?;
+                }
+                cs_op::MUL => {
+                    let [a, b] = self.pop_n()?;
+                    self.push(a * b)?;
+                }
+                cs_op::SQRT => {
+                    let [a] = self.pop_n()?;
+                    self.push(a.abs().sqrt())?;
+                }
+                cs_op::DUP => {
+                    let a = *self.stack.last().ok_or("CFF charstring stack underflow")?;
+                    self.push(a)?;
+                }
+                cs_op::EXCH => {
+                    let [a, b] = self.pop_n()?;
+                    self.push(b)?;
+                    self.push(a)?;
+                }
+                cs_op::INDEX => {
+                    let i = self.pop()?.max(0.0) as usize;
+                    let len = self.stack.len();
+                    let v = *self.stack.get(len.wrapping_sub(i + 1)).ok_or("CFF index out of range")?;
+                    self.push(v)?;
+                }
+                cs_op::ROLL => {
+                    let [n, j] = self.pop_n()?;
+                    let n = n as usize;
+                    let len = self.stack.len();
+                    if n > len {
+                        return Err("CFF roll out of range".into());
+                    }
+                    if n > 0 {
+                        let shift = (j as i32).rem_euclid(n as i32) as usize;
+                        self.stack[len - n..].rotate_right(shift);
+                    }
+                }
+                _ => return Err(format!("unknown CFF charstring operator {}", operator).into()),
+            }
+        }
+        Ok(false)
+    }
+
+    /// Replace n default values and their n * region count deltas with the interpolated values
+    fn blend(&mut self) -> Result<(), Box<dyn Error>> {
+        let n = self.pop()?.max(0.0) as usize;
+
+        if self.scalars.is_none() {
+            self.scalars = Some(match self.variations {
+                Some((vstore, coords, vsindex)) => vstore.region_scalars(vsindex, coords).ok_or_else(|| format!("CFF2 vsindex {} out of range", vsindex))?,
+                None => Vec::new(),
+            });
+        }
+        let scalars = self.scalars.as_deref().unwrap_or_default();
+
+        let k = scalars.len();
+        let base = self.stack.len().checked_sub(n * (k + 1)).ok_or("CFF2 blend stack underflow")?;
+        for i in 0..n {
+            let deltas = &self.stack[base + n + i * k..base + n + (i + 1) * k];
+            let delta: f32 = deltas.iter().zip(scalars).map(|(d, s)| d * s).sum();
+            self.stack[base + i] += delta;
+        }
+        self.stack.truncate(base + n);
+        Ok(())
+    }
+
+    /// Close the last contour, charstrings leave it open
+    pub(crate) fn finish(&mut self) {
+        if self.contour_open {
+            self.sink.close();
+            self.contour_open = false;
+        }
+    }
+
+    fn parse_width(&mut self, has_width: bool) {
+        if !self.width_parsed && has_width {
+            self.stack.remove(0);
+        }
+        self.width_parsed = true;
+    }
+
+    fn push(&mut self, v: f32) -> Result<(), Box<dyn Error>> {
+        if self.stack.len() >= if self.is_cff2 { MAX_STACK_CFF2 } else { MAX_STACK } {
+            return Err("CFF charstring stack overflow".into());
+        }
+        self.stack.push(v);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<f32, Box<dyn Error>> {
+        self.stack.pop().ok_or_else(|| "CFF charstring stack underflow".into())
+    }
+
+    /// Pop the last N operands, those below stay on the stack
+    fn pop_n<const N: usize>(&mut self) -> Result<[f32; N], Box<dyn Error>> {
+        let start = self.stack.len().checked_sub(N).ok_or("CFF charstring stack underflow")?;
+        let mut a = [0.0; N];
+        a.copy_from_slice(&self.stack[start..]);
+        self.stack.truncate(start);
+        Ok(a)
+    }
+
+    /// Take the last N arguments of a path or hint operator, which clears the stack
+    fn args<const N: usize>(&mut self) -> Result<[f32; N], Box<dyn Error>> {
+        let a = self.pop_n()?;
+        self.stack.clear();
+        Ok(a)
+    }
+
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.finish();
+        (self.x, self.y) = (x, y);
+        self.sink.move_to(x + self.offset.0, y + self.offset.1);
+        self.contour_open = true;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        (self.x, self.y) = (x, y);
+        self.sink.line_to(x + self.offset.0, y + self.offset.1);
+    }
+
+    /// Relative cubic curve, each control point is relative to the previous one
+    fn curve_to(&mut self, dx1: f32, dy1: f32, dx2: f32, dy2: f32, dx3: f32, dy3: f32) {
+        let (x1, y1) = (self.x + dx1, self.y + dy1);
+        let (x2, y2) = (x1 + dx2, y1 + dy2);
+        (self.x, self.y) = (x2 + dx3, y2 + dy3);
+
+        let (ox, oy) = self.offset;
+        self.sink.curve_to(x1 + ox, y1 + oy, x2 + ox, y2 + oy, self.x + ox, self.y + oy);
+    }
+}
+
+/// SID of a StandardEncoding character code, 0 (.notdef) for unassigned codes
+fn standard_encoding_sid(code: u8) -> u16 {
+    match code {
+        32..=126 => code as u16 - 31,
+        161..=175 => code as u16 - 65,
+        177..=180 => code as u16 - 66,
+        182..=189 => code as u16 - 67,
+        191 => 123,
+        193..=200 => code as u16 - 69,
+        202 | 203 => code as u16 - 70,
+        205..=208 => code as u16 - 71,
+        225 => 138,
+        227 => 139,
+        232..=235 => code as u16 - 92,
+        241 => 144,
+        245 => 145,
+        248..=251 => code as u16 - 102,
+        _ => 0,
+    }
+}
+
+/// The 391 predefined strings, SIDs 0 to 390
+const STANDARD_STRINGS: [&str; 391] = [
+    ".notdef", "space", "exclam", "quotedbl", "numbersign", "dollar", "percent", "ampersand", "quoteright", "parenleft", "parenright", "asterisk",
+    "plus", "comma", "hyphen", "period", "slash", "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "colon",
+    "semicolon", "less", "equal", "greater", "question", "at", "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", "P", "Q",
+    "R", "S", "T", "U", "V", "W", "X", "Y", "Z", "bracketleft", "backslash", "bracketright", "asciicircum", "underscore", "quoteleft", "a", "b", "c",
+    "d", "e", "f", "g", "h", "i", "j", "k", "l", "m", "n", "o", "p", "q", "r", "s", "t", "u", "v", "w", "x", "y", "z", "braceleft", "bar",
+    "braceright", "asciitilde", "exclamdown", "cent", "sterling", "fraction", "yen", "florin", "section", "currency", "quotesingle", "quotedblleft",
+    "guillemotleft", "guilsinglleft", "guilsinglright", "fi", "fl", "endash", "dagger", "daggerdbl", "periodcentered", "paragraph", "bullet",
+    "quotesinglbase", "quotedblbase", "quotedblright", "guillemotright", "ellipsis", "perthousand", "questiondown", "grave", "acute", "circumflex",
+    "tilde", "macron", "breve", "dotaccent", "dieresis", "ring", "cedilla", "hungarumlaut", "ogonek", "caron", "emdash", "AE", "ordfeminine",
+    "Lslash", "Oslash", "OE", "ordmasculine", "ae", "dotlessi", "lslash", "oslash", "oe", "germandbls", "onesuperior", "logicalnot", "mu",
+    "trademark", "Eth", "onehalf", "plusminus", "Thorn", "onequarter", "divide", "brokenbar", "degree", "thorn", "threequarters", "twosuperior",
+    "registered", "minus", "eth", "multiply", "threesuperior", "copyright", "Aacute", "Acircumflex", "Adieresis", "Agrave", "Aring", "Atilde",
+    "Ccedilla", "Eacute", "Ecircumflex", "Edieresis", "Egrave", "Iacute", "Icircumflex", "Idieresis", "Igrave", "Ntilde", "Oacute", "Ocircumflex",
+    "Odieresis", "Ograve", "Otilde", "Scaron", "Uacute", "Ucircumflex", "Udieresis", "Ugrave", "Yacute", "Ydieresis", "Zcaron", "aacute",
+    "acircumflex", "adieresis", "agrave", "aring", "atilde", "ccedilla", "eacute", "ecircumflex", "edieresis", "egrave", "iacute", "icircumflex",
+    "idieresis", "igrave", "ntilde", "oacute", "ocircumflex", "odieresis", "ograve", "otilde", "scaron", "uacute", "ucircumflex", "udieresis",
+    "ugrave", "yacute", "ydieresis", "zcaron", "exclamsmall", "Hungarumlautsmall", "dollaroldstyle", "dollarsuperior", "ampersandsmall", "Acutesmall",
+    "parenleftsuperior", "parenrightsuperior", "twodotenleader", "onedotenleader", "zerooldstyle", "oneoldstyle", "twooldstyle", "threeoldstyle",
+    "fouroldstyle", "fiveoldstyle", "sixoldstyle", "sevenoldstyle", "eightoldstyle", "nineoldstyle", "commasuperior", "threequartersemdash",
+    "periodsuperior", "questionsmall", "asuperior", "bsuperior", "centsuperior", "dsuperior", "esuperior", "isuperior", "lsuperior", "msuperior",
+    "nsuperior", "osuperior", "rsuperior", "ssuperior", "tsuperior", "ff", "ffi", "ffl", "parenleftinferior", "parenrightinferior", "Circumflexsmall",
+    "hyphensuperior", "Gravesmall", "Asmall", "Bsmall", "Csmall", "Dsmall", "Esmall", "Fsmall", "Gsmall", "Hsmall", "Ismall", "Jsmall", "Ksmall",
+    "Lsmall", "Msmall", "Nsmall", "Osmall", "Psmall", "Qsmall", "Rsmall", "Ssmall", "Tsmall", "Usmall", "Vsmall", "Wsmall", "Xsmall", "Ysmall",
+    "Zsmall", "colonmonetary", "onefitted", "rupiah", "Tildesmall", "exclamdownsmall", "centoldstyle", "Lslashsmall", "Scaronsmall", "Zcaronsmall",
+    "Dieresissmall", "Brevesmall", "Caronsmall", "Dotaccentsmall", "Macronsmall", "figuredash", "hypheninferior", "Ogoneksmall", "Ringsmall",
+    "Cedillasmall", "questiondownsmall", "oneeighth", "threeeighths", "fiveeighths", "seveneighths", "onethird", "twothirds", "zerosuperior",
+    "foursuperior", "fivesuperior", "sixsuperior", "sevensuperior", "eightsuperior", "ninesuperior", "zeroinferior", "oneinferior", "twoinferior",
+    "threeinferior", "fourinferior", "fiveinferior", "sixinferior", "seveninferior", "eightinferior", "nineinferior", "centinferior",
+    "dollarinferior", "periodinferior", "commainferior", "Agravesmall", "Aacutesmall", "Acircumflexsmall", "Atildesmall", "Adieresissmall",
+    "Aringsmall", "AEsmall", "Ccedillasmall", "Egravesmall", "Eacutesmall", "Ecircumflexsmall", "Edieresissmall", "Igravesmall", "Iacutesmall",
+    "Icircumflexsmall", "Idieresissmall", "Ethsmall", "Ntildesmall", "Ogravesmall", "Oacutesmall", "Ocircumflexsmall", "Otildesmall",
+    "Odieresissmall", "OEsmall", "Oslashsmall", "Ugravesmall", "Uacutesmall", "Ucircumflexsmall", "Udieresissmall", "Yacutesmall", "Thornsmall",
+    "Ydieresissmall", "001.000", "001.001", "001.002", "001.003", "Black", "Bold", "Book", "Light", "Medium", "Regular", "Roman", "Semibold",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// End point of every path segment, 'M' move, 'L' line, 'C' curve and 'Z' close
+    type Segments = Vec<(char, f32, f32)>;
+
+    #[derive(Default)]
+    struct Recorder(Segments);
+
+    impl OutlineSink for Recorder {
+        fn move_to(&mut self, x: f32, y: f32) {
+            self.0.push(('M', x, y));
+        }
+        fn line_to(&mut self, x: f32, y: f32) {
+            self.0.push(('L', x, y));
+        }
+        fn quad_to(&mut self, _: f32, _: f32, x: f32, y: f32) {
+            self.0.push(('Q', x, y));
+        }
+        fn curve_to(&mut self, _: f32, _: f32, _: f32, _: f32, x: f32, y: f32) {
+            self.0.push(('C', x, y));
+        }
+        fn close(&mut self) {
+            self.0.push(('Z', 0.0, 0.0));
+        }
+    }
+
+    /// A charstring of operators, each preceded by its operands
+    fn char_string(program: &[(&[i16], u16)]) -> Vec<u8> {
+        let mut w = Vec::new();
+        for &(operands, operator) in program {
+            for &v in operands {
+                write_uint8(&mut w, 28);
+                write_int16(&mut w, v);
+            }
+            if operator > 0xFF {
+                write_uint8(&mut w, 12);
+            }
+            write_uint8(&mut w, operator as u8);
+        }
+        w
+    }
+
+    /// A CFF INDEX holding `objects`
+    fn index(objects: &[Vec<u8>]) -> Index {
+        Index::from_bytes(&mut index_bytes(objects).as_slice()).unwrap()
+    }
+
+    fn index_bytes(objects: &[Vec<u8>]) -> Vec<u8> {
+        let mut w = Vec::new();
+        write_uint16(&mut w, objects.len() as u16);
+        write_uint8(&mut w, 1);
+        let mut offset = 1;
+        write_uint8(&mut w, offset);
+        for o in objects {
+            offset += o.len() as u8;
+            write_uint8(&mut w, offset);
+        }
+        objects.iter().for_each(|o| w.extend(o));
+        w
+    }
+
+    /// A DICT of operators, each preceded by its operands as 32-bit integers so offsets don't change its size
+    fn dict(entries: &[(&[i32], u16)]) -> Vec<u8> {
+        let mut w = Vec::new();
+        for &(operands, operator) in entries {
+            for &v in operands {
+                write_uint8(&mut w, 29);
+                write_int32(&mut w, v);
+            }
+            if operator > 0xFF {
+                write_uint8(&mut w, 12);
+            }
+            write_uint8(&mut w, operator as u8);
+        }
+        w
+    }
+
+    /// A CID-keyed font of three glyphs, glyphs 0 and 1 use Font DICT 0 and glyph 2 Font DICT 1, each glyph calls local subr 0 of its Font DICT
+    fn cid_keyed() -> Vec<u8> {
+        let char_strings = index_bytes(&vec![char_string(&[(&[-107], cs_op::CALLSUBR), (&[], cs_op::ENDCHAR)]); 3]);
+        let privates: Vec<Vec<u8>> = [10, 20]
+            .iter()
+            .map(|&d| {
+                let mut w = dict(&[(&[6], op::SUBRS)]);
+                w.extend(index_bytes(&[char_string(&[(&[d, d], cs_op::RMOVETO), (&[], cs_op::RETURN)])]));
+                w
+            })
+            .collect();
+        let charset = [0, 0, 100, 0, 200];
+        let fd_select = [3, 0, 2, 0, 0, 0, 0, 2, 1, 0, 3];
+
+        let top = |offsets: [i32; 4]| dict(&[(&[391, 392, 0], op::ROS), (&[offsets[0]], op::CHARSET), (&[offsets[1]], op::CHAR_STRINGS), (&[offsets[2]], op::FD_ARRAY), (&[offsets[3]], op::FD_SELECT)]);
+        let font_dicts = |offsets: [i32; 2]| index_bytes(&[dict(&[(&[6, offsets[0]], op::PRIVATE)]), dict(&[(&[6, offsets[1]], op::PRIVATE)])]);
+
+        let mut w = vec![1, 0, 4, 1];
+        w.extend(index_bytes(&[b"CID".to_vec()]));
+        let top_at = w.len();
+        w.extend(index_bytes(&[top([0; 4])]));
+        w.extend(index_bytes(&[b"Adobe".to_vec(), b"Identity".to_vec()]));
+        w.extend(index_bytes(&[]));
+        let charset_at = w.len();
+        w.extend(charset);
+        let fd_select_at = w.len();
+        w.extend(fd_select);
+        let char_strings_at = w.len();
+        w.extend(char_strings);
+        let fd_array_at = w.len();
+        let private_at = fd_array_at + font_dicts([0; 2]).len();
+        w.extend(font_dicts([private_at as i32, (private_at + privates[0].len()) as i32]));
+        privates.iter().for_each(|p| w.extend(p));
+
+        let top = index_bytes(&[top([charset_at, char_strings_at, fd_array_at, fd_select_at].map(|v| v as i32))]);
+        w[top_at..top_at + top.len()].copy_from_slice(&top);
+        w
+    }
+
+    #[test]
+    fn cid_keyed_font() {
+        let cff = CompactFontFormatTable::from_bytes(&cid_keyed()).unwrap();
+        assert!(cff.is_cid_keyed());
+        let ros = cff.cid_system_info().unwrap();
+        assert_eq!(("Adobe", "Identity", 0), (ros.registry(), ros.ordering(), ros.supplement()));
+        assert_eq!(Some(8720), cff.cid_count());
+        assert_eq!(vec![Some(0), Some(100), Some(200), None], (0..4).map(|g| cff.cid(g)).collect::<Vec<_>>());
+        assert_eq!(vec![Some(0), Some(0), Some(1), None], (0..4).map(|g| cff.font_dict_index(g)).collect::<Vec<_>>());
+        assert_eq!(Some("cid00100".to_string()), cff.glyph_name(1));
+
+        // the local subrs come from the glyph's own Private DICT
+        for (glyph_id, d) in [(1, 10.0), (2, 20.0)] {
+            let mut sink = Recorder::default();
+            cff.outline(glyph_id, &mut sink).unwrap();
+            assert_eq!(sink.0, [('M', d, d), ('Z', 0.0, 0.0)]);
+        }
+    }
+
+    #[test]
+    fn fd_select_must_reference_fd_array() {
+        let mut b = cid_keyed();
+        // the Font DICT of the second FDSelect range
+        let at = b.windows(11).position(|w| w == [3, 0, 2, 0, 0, 0, 0, 2, 1, 0, 3]).unwrap();
+        b[at + 8] = 2;
+        assert!(CompactFontFormatTable::from_bytes(&b).is_err());
+    }
+
+    /// One axis with a region up to its max and one down to its min, subtable 0 uses both and subtable 1 only the first
+    fn variation_store() -> ItemVariationStore {
+        let mut w = Vec::new();
+        write_uint16(&mut w, 1);
+        write_uint32(&mut w, 16);
+        write_uint16(&mut w, 2);
+        write_uint32(&mut w, 32);
+        write_uint32(&mut w, 42);
+        write_uint16(&mut w, 1);
+        write_uint16(&mut w, 2);
+        [0.0, 1.0, 1.0, -1.0, -1.0, 0.0].iter().for_each(|&v| write_f2dot14(&mut w, v));
+        [0, 0, 2, 0, 1].iter().for_each(|&v| write_uint16(&mut w, v));
+        [0, 0, 1, 0].iter().for_each(|&v| write_uint16(&mut w, v));
+        ItemVariationStore::from_bytes(&w).unwrap()
+    }
+
+    fn run(global_subrs: &Index, b: &[u8]) -> Result<Segments, Box<dyn Error>> {
+        let mut sink = Recorder::default();
+        let mut interpreter = CharStringInterpreter::new(global_subrs, None, (0.0, 0.0), &mut sink);
+        interpreter.execute(b, 0)?;
+        interpreter.finish();
+        Ok(sink.0)
+    }
+
+    fn run_cff2(b: &[u8], coords: &[F2Dot14]) -> Result<Segments, Box<dyn Error>> {
+        let (global_subrs, vstore) = (index(&[]), variation_store());
+        let mut sink = Recorder::default();
+        let mut interpreter = CharStringInterpreter::new(&global_subrs, None, (0.0, 0.0), &mut sink).with_variations(Some(&vstore), coords, 0);
+        interpreter.execute(b, 0)?;
+        interpreter.finish();
+        Ok(sink.0)
+    }
+
+    #[test]
+    fn arithmetic_keeps_lower_operands() {
+        // the width 10 and dx 20 sit below the operands of add
+        let b = char_string(&[(&[10, 20, 3, 4], cs_op::ADD), (&[], cs_op::RMOVETO), (&[], cs_op::ENDCHAR)]);
+        assert_eq!(run(&index(&[]), &b).unwrap(), [('M', 20.0, 7.0), ('Z', 0.0, 0.0)]);
+
+        let b = char_string(&[(&[7, 0], cs_op::PUT), (&[3, 9], cs_op::EXCH), (&[], cs_op::SUB), (&[0], cs_op::GET), (&[], cs_op::RMOVETO)]);
+        assert_eq!(run(&index(&[]), &b).unwrap(), [('M', 6.0, 7.0), ('Z', 0.0, 0.0)]);
+    }
+
+    #[test]
+    fn roll() {
+        let b = char_string(&[(&[0, 0], cs_op::RMOVETO), (&[1, 2, 3, 4, 4, 1], cs_op::ROLL), (&[], cs_op::RLINETO), (&[], cs_op::ENDCHAR)]);
+        assert_eq!(run(&index(&[]), &b).unwrap(), [('M', 0.0, 0.0), ('L', 4.0, 1.0), ('L', 6.0, 4.0), ('Z', 0.0, 0.0)]);
+
+        let b = char_string(&[(&[1, 2, 3, 1], cs_op::ROLL)]);
+        assert!(run(&index(&[]), &b).is_err());
+    }
+
+    #[test]
+    fn path_through_global_subr() {
+        let global_subrs = index(&[char_string(&[(&[], cs_op::RLINETO), (&[], cs_op::RETURN)])]);
+        let b = char_string(&[
+            (&[500, 100, 50], cs_op::RMOVETO),
+            (&[10, 0, 0, 20, -107], cs_op::CALLGSUBR),
+            (&[5, 5, 10, 0, 5, -5], cs_op::RRCURVETO),
+            (&[], cs_op::ENDCHAR),
+        ]);
+        assert_eq!(run(&global_subrs, &b).unwrap(), [('M', 100.0, 50.0), ('L', 110.0, 50.0), ('L', 110.0, 70.0), ('C', 130.0, 70.0), ('Z', 0.0, 0.0)]);
+    }
+
+    #[test]
+    fn blend() {
+        let b = char_string(&[(&[100, 200, 40, -20, 10, 10, 2], cs_op::BLEND), (&[], cs_op::RMOVETO)]);
+        assert_eq!(run_cff2(&b, &[]).unwrap(), [('M', 100.0, 200.0), ('Z', 0.0, 0.0)]);
+        assert_eq!(run_cff2(&b, &[0.5]).unwrap(), [('M', 120.0, 205.0), ('Z', 0.0, 0.0)]);
+        assert_eq!(run_cff2(&b, &[-1.0]).unwrap(), [('M', 80.0, 210.0), ('Z', 0.0, 0.0)]);
+
+        let b = char_string(&[(&[100, 200, 40, 2], cs_op::BLEND)]);
+        assert!(run_cff2(&b, &[0.5]).is_err());
+    }
+
+    #[test]
+    fn vsindex_selects_regions() {
+        // subtable 1 has one region, so blend reads one delta per value and leaves 100 in place
+        let b = char_string(&[(&[1], cs_op::VSINDEX), (&[100, 200, 40, 1], cs_op::BLEND), (&[], cs_op::RMOVETO)]);
+        assert_eq!(run_cff2(&b, &[0.5]).unwrap(), [('M', 100.0, 220.0), ('Z', 0.0, 0.0)]);
+
+        let b = char_string(&[(&[2], cs_op::VSINDEX), (&[100, 200, 40, 1], cs_op::BLEND)]);
+        assert!(run_cff2(&b, &[0.5]).is_err());
+    }
+}
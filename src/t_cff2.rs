@@ -0,0 +1,407 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::t_cff::*;
+use super::types::*;
+use super::utils::*;
+use super::variations::*;
+
+pub struct CompactFontFormat2Table {
+    /// Format major version (set to 2)
+    major_version: u8,
+    /// Format minor version (set to 0)
+    minor_version: u8,
+    /// Header size (bytes)
+    header_size: u8,
+    /// Length of Top DICT structure in bytes
+    top_dict_length: u16,
+    top_dict: Dict,
+    global_subr_index: Index,
+    char_strings: Index,
+    /// Font DICTs, each with its own Private DICT, CFF2 fonts always have at least one
+    fd_array: Vec<FontDict>,
+    /// Font DICT of each glyph, absent when all glyphs use the first Font DICT
+    fd_select: Option<FdSelect>,
+    variation_store: Option<ItemVariationStore>,
+}
+
+impl Debug for CompactFontFormat2Table {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompactFontFormat2Table")
+            .field("major_version", &self.major_version)
+            .field("minor_version", &self.minor_version)
+            .field("header_size", &self.header_size)
+            .field("top_dict_length", &self.top_dict_length)
+            .field("top_dict", &self.top_dict)
+            .field("global_subr_index", &self.global_subr_index)
+            .field("char_strings", &self.char_strings)
+            .field("fd_array", &self.fd_array)
+            .field("fd_select", &self.fd_select)
+            .field("variation_store", &self.variation_store)
+            .finish()
+    }
+}
+
+impl CompactFontFormat2Table {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let major_version = read_uint8(r)?;
+        let minor_version = read_uint8(r)?;
+        let header_size = read_uint8(r)?;
+        let top_dict_length = read_uint16(r)?;
+
+        if 2 != major_version {
+            return Err(format!("unsupported CFF2 version {}.{}", major_version, minor_version).into());
+        }
+
+        // the Top DICT follows the header directly, the Global Subr INDEX follows the Top DICT
+        let top_dict_start = header_size as usize;
+        let top_dict_end = top_dict_start + top_dict_length as usize;
+        let top_dict = Dict::from_bytes(o.get(top_dict_start..top_dict_end).ok_or("CFF2 Top DICT out of range")?)?;
+        let global_subr_index = Index::from_bytes_cff2(&mut &o[top_dict_end..])?;
+
+        let char_strings_offset = top_dict.get_offset(op::CHAR_STRINGS).ok_or("CFF2 Top DICT has no CharStrings")?;
+        let char_strings = Index::from_bytes_cff2(&mut o.get(char_strings_offset..).ok_or("CFF2 CharStrings offset out of range")?)?;
+
+        let fd_array_offset = top_dict.get_offset(op::FD_ARRAY).ok_or("CFF2 Top DICT has no FDArray")?;
+        let fd_array_index = Index::from_bytes_cff2(&mut o.get(fd_array_offset..).ok_or("CFF2 FDArray offset out of range")?)?;
+        let mut fd_array = Vec::with_capacity(fd_array_index.len());
+        for i in 0..fd_array_index.len() {
+            fd_array.push(FontDict::from_bytes_cff2(o, fd_array_index.get(i).ok_or("CFF2 FDArray truncated")?)?);
+        }
+        if fd_array.is_empty() {
+            return Err("CFF2 FDArray is empty".into());
+        }
+
+        let fd_select = match top_dict.get_offset(op::FD_SELECT) {
+            Some(offset) => Some(FdSelect::from_bytes(o.get(offset..).ok_or("CFF2 FDSelect offset out of range")?, char_strings.len())?),
+            None => None,
+        };
+        if let Some(fd) = fd_select.as_ref().and_then(|fs| fs.max_fd())
+            && fd as usize >= fd_array.len()
+        {
+            return Err(format!("CFF2 FDSelect references Font DICT {} of {}", fd, fd_array.len()).into());
+        }
+
+        // the VariationStore starts with its length, the ItemVariationStore follows
+        let variation_store = match top_dict.get_offset(op::VSTORE) {
+            Some(offset) => Some(ItemVariationStore::from_bytes(o.get(offset + 2..).ok_or("CFF2 VariationStore offset out of range")?)?),
+            None => None,
+        };
+
+        Ok(Self {
+            major_version,
+            minor_version,
+            header_size,
+            top_dict_length,
+            top_dict,
+            global_subr_index,
+            char_strings,
+            fd_array,
+            fd_select,
+            variation_store,
+        })
+    }
+
+    pub fn num_glyphs(&self) -> usize {
+        self.char_strings.len()
+    }
+
+    pub fn variation_store(&self) -> Option<&ItemVariationStore> {
+        self.variation_store.as_ref()
+    }
+
+    pub fn fd_array(&self) -> &[FontDict] {
+        &self.fd_array
+    }
+
+    /// Index of the Font DICT a glyph uses
+    pub fn font_dict_index(&self, glyph_id: GlyphId) -> Option<u16> {
+        match &self.fd_select {
+            Some(fd_select) => fd_select.get(glyph_id),
+            None if (glyph_id as usize) < self.num_glyphs() => Some(0),
+            None => None,
+        }
+    }
+
+    /// Draw a glyph instanced at normalized `coords`, an empty slice gives the default outline
+    pub fn outline(&self, glyph_id: GlyphId, coords: &[F2Dot14], sink: &mut impl OutlineSink) -> Result<(), Box<dyn Error>> {
+        let cs = self.char_strings.get(glyph_id as usize).ok_or_else(|| format!("glyph {} has no CFF2 charstring", glyph_id))?;
+        let private = self.font_dict_index(glyph_id).and_then(|fd| self.fd_array.get(fd as usize)?.private());
+
+        let local_subrs = private.map(|p| p.subrs());
+        let vsindex = private.map_or(0, |p| p.vsindex());
+
+        let mut interpreter = CharStringInterpreter::new(&self.global_subr_index, local_subrs, (0.0, 0.0), sink).with_variations(self.variation_store.as_ref(), coords, vsindex);
+        interpreter.execute(cs, 0)?;
+        interpreter.finish();
+        Ok(())
+    }
+
+    /// Encode a CFF2 table without variations, the charstrings and Private DICTs blended at normalized `coords`
+    ///
+    /// Subroutines are inlined since they may blend differently at each call site, hints are kept as they are
+    pub fn instance(&self, coords: &[F2Dot14]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut char_strings = Vec::with_capacity(self.num_glyphs());
+        for glyph_id in 0..self.num_glyphs() {
+            let cs = self.char_strings.get(glyph_id).ok_or_else(|| format!("glyph {} has no CFF2 charstring", glyph_id))?;
+            let private = self.font_dict_index(glyph_id as GlyphId).and_then(|fd| self.fd_array.get(fd as usize)?.private());
+            let mut instancer = CharStringInstancer {
+                global_subrs: &self.global_subr_index,
+                local_subrs: private.map(|p| p.subrs()),
+                vstore: self.variation_store.as_ref(),
+                coords,
+                vsindex: private.map_or(0, |p| p.vsindex()),
+                scalars: None,
+                stack: Vec::new(),
+                num_stems: 0,
+                out: Vec::with_capacity(cs.len()),
+            };
+            instancer.rewrite(cs, 0)?;
+            char_strings.push(instancer.out);
+        }
+
+        let mut privates = Vec::with_capacity(self.fd_array.len());
+        for fd in &self.fd_array {
+            let mut b = Vec::new();
+            if let Some(private) = fd.private() {
+                self.instance_private_dict(private, coords)?.write(&mut b, &[]);
+            }
+            privates.push(b);
+        }
+
+        // FDSelect as ranges of glyphs sharing a Font DICT
+        let mut fd_select = Vec::new();
+        if self.fd_select.is_some() {
+            let mut ranges: Vec<(u32, u16)> = Vec::new();
+            for glyph_id in 0..self.num_glyphs() {
+                let fd = self.font_dict_index(glyph_id as GlyphId).unwrap_or(0);
+                if ranges.last().is_none_or(|&(_, last)| last != fd) {
+                    ranges.push((glyph_id as u32, fd));
+                }
+            }
+            if self.fd_array.len() <= 0x100 && self.num_glyphs() <= 0xFFFF {
+                write_uint8(&mut fd_select, 3);
+                write_uint16(&mut fd_select, ranges.len() as u16);
+                ranges.iter().for_each(|&(first, fd)| {
+                    write_uint16(&mut fd_select, first as u16);
+                    write_uint8(&mut fd_select, fd as u8);
+                });
+                write_uint16(&mut fd_select, self.num_glyphs() as u16);
+            } else {
+                write_uint8(&mut fd_select, 4);
+                write_uint32(&mut fd_select, ranges.len() as u32);
+                ranges.iter().for_each(|&(first, fd)| {
+                    write_uint32(&mut fd_select, first);
+                    write_uint16(&mut fd_select, fd);
+                });
+                write_uint32(&mut fd_select, self.num_glyphs() as u32);
+            }
+        }
+
+        // offsets take a fixed five bytes, so the layout can be computed with placeholders and written once the offsets are known
+        let top_dict = |char_strings_offset: usize, fd_array_offset: usize, fd_select_offset: usize| {
+            let mut entries: Vec<_> = self.top_dict.entries().iter().filter(|(op, _)| ![op::CHAR_STRINGS, op::FD_ARRAY, op::FD_SELECT, op::VSTORE].contains(op)).cloned().collect();
+            entries.push((op::CHAR_STRINGS, vec![char_strings_offset as f64]));
+            entries.push((op::FD_ARRAY, vec![fd_array_offset as f64]));
+            if !fd_select.is_empty() {
+                entries.push((op::FD_SELECT, vec![fd_select_offset as f64]));
+            }
+            let mut b = Vec::new();
+            Dict::from_entries(entries).write(&mut b, &[op::CHAR_STRINGS, op::FD_ARRAY, op::FD_SELECT]);
+            b
+        };
+        let font_dicts = |private_offsets: &[usize]| -> Vec<Vec<u8>> {
+            self.fd_array
+                .iter()
+                .zip(privates.iter().zip(private_offsets))
+                .map(|(fd, (private, &offset))| {
+                    let mut entries: Vec<_> = fd.dict().entries().iter().filter(|(op, _)| op::PRIVATE != *op).cloned().collect();
+                    entries.push((op::PRIVATE, vec![private.len() as f64, offset as f64]));
+                    let mut b = Vec::new();
+                    Dict::from_entries(entries).write(&mut b, &[op::PRIVATE]);
+                    b
+                })
+                .collect()
+        };
+
+        let mut char_strings_index = Vec::new();
+        Index::write_cff2(&mut char_strings_index, &char_strings);
+        let mut fd_array_index = Vec::new();
+        Index::write_cff2(&mut fd_array_index, &font_dicts(&vec![0; privates.len()]));
+
+        let header_size = 5;
+        let top_dict_length = top_dict(0, 0, 0).len();
+        let global_subrs_offset = header_size + top_dict_length;
+        let char_strings_offset = global_subrs_offset + 4;
+        let fd_select_offset = char_strings_offset + char_strings_index.len();
+        let fd_array_offset = fd_select_offset + fd_select.len();
+        let mut private_offsets = Vec::with_capacity(privates.len());
+        let mut offset = fd_array_offset + fd_array_index.len();
+        for private in &privates {
+            private_offsets.push(offset);
+            offset += private.len();
+        }
+        fd_array_index.clear();
+        Index::write_cff2(&mut fd_array_index, &font_dicts(&private_offsets));
+
+        let mut w = Vec::with_capacity(offset);
+        w.extend([2, 0, header_size as u8]);
+        write_uint16(&mut w, top_dict_length as u16);
+        w.extend(top_dict(char_strings_offset, fd_array_offset, fd_select_offset));
+        Index::write_cff2(&mut w, &[]);
+        w.extend(char_strings_index);
+        w.extend(fd_select);
+        w.extend(fd_array_index);
+        privates.iter().for_each(|p| w.extend(p));
+        Ok(w)
+    }
+
+    /// The Private DICT with its blends resolved, without vsindex and with its subroutines inlined into the charstrings
+    fn instance_private_dict(&self, private: &PrivateDict, coords: &[F2Dot14]) -> Result<Dict, Box<dyn Error>> {
+        let scalars = match &self.variation_store {
+            Some(vstore) => vstore.region_scalars(private.vsindex(), coords).ok_or_else(|| format!("CFF2 vsindex {} out of range", private.vsindex()))?,
+            None => Vec::new(),
+        };
+
+        // the operands of a blend stay on the stack for the operator that follows
+        let mut entries = Vec::new();
+        let mut stack: Vec<f64> = Vec::new();
+        for (op, operands) in private.dict().entries() {
+            stack.extend(operands);
+            match *op {
+                op::BLEND => blend(&mut stack, &scalars)?,
+                op::VSINDEX | op::SUBRS => stack.clear(),
+                op => entries.push((op, std::mem::take(&mut stack))),
+            }
+        }
+        Ok(Dict::from_entries(entries))
+    }
+}
+
+/// Replace the n default values and n * region count deltas on top of the stack with the blended values, rounded like instancers round them
+fn blend(stack: &mut Vec<f64>, scalars: &[f32]) -> Result<(), Box<dyn Error>> {
+    let n = stack.pop().ok_or("CFF2 blend stack underflow")?.max(0.0) as usize;
+    let k = scalars.len();
+    let base = stack.len().checked_sub(n * (k + 1)).ok_or("CFF2 blend stack underflow")?;
+    for i in 0..n {
+        let deltas = &stack[base + n + i * k..base + n + (i + 1) * k];
+        let delta: f64 = deltas.iter().zip(scalars).map(|(&d, &s)| d * s as f64).sum();
+        if 0.0 != delta {
+            stack[base + i] = ot_round((stack[base + i] + delta) as f32) as f64;
+        }
+    }
+    stack.truncate(base + n);
+    Ok(())
+}
+
+/// Rewrites a CFF2 charstring with its blends resolved and its subroutine calls inlined
+struct CharStringInstancer<'a> {
+    global_subrs: &'a Index,
+    local_subrs: Option<&'a Index>,
+    vstore: Option<&'a ItemVariationStore>,
+    coords: &'a [F2Dot14],
+    vsindex: u16,
+    /// Region scalars for the current vsindex, computed at the first blend
+    scalars: Option<Vec<f32>>,
+    stack: Vec<f64>,
+    /// Stems declared so far, they give the length of hintmask data
+    num_stems: usize,
+    out: Vec<u8>,
+}
+
+impl CharStringInstancer<'_> {
+    fn rewrite(&mut self, mut b: &[u8], depth: usize) -> Result<(), Box<dyn Error>> {
+        if depth > MAX_SUBR_DEPTH {
+            return Err("CFF subroutines nested too deep".into());
+        }
+
+        let r = &mut b;
+        while !r.is_empty() {
+            let b0 = read_uint8(r)?;
+            let operator = match b0 {
+                12 => 12 << 8 | read_uint8(r)? as u16,
+                0..=31 if 28 != b0 => b0 as u16,
+                _ => {
+                    let v = match b0 {
+                        28 => read_int16(r)? as f64,
+                        32..=246 => b0 as f64 - 139.0,
+                        247..=250 => (b0 as f64 - 247.0) * 256.0 + read_uint8(r)? as f64 + 108.0,
+                        251..=254 => -(b0 as f64 - 251.0) * 256.0 - read_uint8(r)? as f64 - 108.0,
+                        _ => read_int32(r)? as f64 / 65536.0,
+                    };
+                    self.stack.push(v);
+                    continue;
+                }
+            };
+
+            match operator {
+                cs_op::CALLSUBR | cs_op::CALLGSUBR => {
+                    let subrs = if cs_op::CALLSUBR == operator { self.local_subrs.ok_or("CFF callsubr without local subrs")? } else { self.global_subrs };
+                    let index = self.stack.pop().ok_or("CFF subroutine call without a number")? as i32 + subr_bias(subrs);
+                    let subr = subrs.get(index as usize).ok_or_else(|| format!("CFF subroutine {} out of range", index))?;
+                    self.rewrite(subr, depth + 1)?;
+                }
+                cs_op::RETURN => return Ok(()),
+                cs_op::VSINDEX => {
+                    self.vsindex = self.stack.pop().ok_or("CFF2 vsindex without an index")? as u16;
+                    self.scalars = None;
+                }
+                cs_op::BLEND => {
+                    if self.scalars.is_none() {
+                        self.scalars = Some(match self.vstore {
+                            Some(vstore) => vstore.region_scalars(self.vsindex, self.coords).ok_or_else(|| format!("CFF2 vsindex {} out of range", self.vsindex))?,
+                            None => Vec::new(),
+                        });
+                    }
+                    blend(&mut self.stack, self.scalars.as_deref().unwrap_or_default())?;
+                }
+                cs_op::HSTEM | cs_op::VSTEM | cs_op::HSTEMHM | cs_op::VSTEMHM => {
+                    self.num_stems += self.stack.len() / 2;
+                    self.flush(operator);
+                }
+                cs_op::HINTMASK | cs_op::CNTRMASK => {
+                    // arguments left on the stack are an implied vstem
+                    self.num_stems += self.stack.len() / 2;
+                    self.flush(operator);
+                    let mask_len = self.num_stems.div_ceil(8);
+                    self.out.extend(r.get(..mask_len).ok_or("CFF hintmask truncated")?);
+                    *r = &r[mask_len..];
+                }
+                _ => self.flush(operator),
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the operands on the stack followed by `operator`
+    fn flush(&mut self, operator: u16) {
+        for v in self.stack.drain(..) {
+            write_char_string_number(&mut self.out, v);
+        }
+        if operator > 0xFF {
+            write_uint8(&mut self.out, 12);
+        }
+        write_uint8(&mut self.out, operator as u8);
+    }
+}
+
+/// Integers in their shortest form, anything else as 16.16 fixed point
+fn write_char_string_number(w: &mut Vec<u8>, v: f64) {
+    if v.fract() != 0.0 || !(-32768.0..=32767.0).contains(&v) {
+        write_uint8(w, 255);
+        write_int32(w, (v * 65536.0).round() as i32);
+        return;
+    }
+    match v as i32 {
+        i @ -107..=107 => write_uint8(w, (i + 139) as u8),
+        i @ 108..=1131 => w.extend([((i - 108) >> 8) as u8 + 247, (i - 108) as u8]),
+        i @ -1131..=-108 => w.extend([((-i - 108) >> 8) as u8 + 251, (-i - 108) as u8]),
+        i => {
+            write_uint8(w, 28);
+            write_int16(w, i as i16);
+        }
+    }
+}
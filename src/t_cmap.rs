@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::Debug;
 
@@ -8,6 +9,8 @@ pub struct CharacterToGlyphIndexMappingTable {
     version: u16,
     num_tables: u16,
     encoding_records: Vec<EncodingRecord>,
+    /// Index of the encoding record used to map characters, picked once at load time
+    preferred_record: Option<usize>,
 }
 
 impl Debug for CharacterToGlyphIndexMappingTable {
@@ -16,6 +19,7 @@ impl Debug for CharacterToGlyphIndexMappingTable {
             .field("version", &self.version)
             .field("num_tables", &self.num_tables)
             .field("encoding_records", &self.encoding_records)
+            .field("preferred_record", &self.preferred_record)
             .finish()
     }
 }
@@ -35,7 +39,127 @@ impl CharacterToGlyphIndexMappingTable {
             encoding_records.push(er);
         }
 
-        Ok(Self { version, num_tables, encoding_records })
+        let preferred_record = Self::select_record(&encoding_records);
+
+        Ok(Self {
+            version,
+            num_tables,
+            encoding_records,
+            preferred_record,
+        })
+    }
+
+    /// Pick the best subtable for character lookup: (3,10) > (0,4) > (0,6) > (3,1) > (0,3) > other Unicode > (3,0) symbol > (1,0) Mac Roman
+    fn select_record(encoding_records: &[EncodingRecord]) -> Option<usize> {
+        encoding_records
+            .iter()
+            .enumerate()
+            .filter(|(_, er)| er.sub_table.as_ref().is_some_and(|st| !matches!(st, SubTable::Format14(_) | SubTable::Unknown(_))))
+            .filter_map(|(i, er)| {
+                let priority = match (er.platform_id, er.encoding_id) {
+                    (3, 10) => 0,
+                    (0, 4) => 1,
+                    (0, 6) => 2,
+                    (3, 1) => 3,
+                    (0, 3) => 4,
+                    (0, 0..=2) => 5,
+                    (3, 0) => 6,
+                    (1, 0) => 7,
+                    _ => return None,
+                };
+                Some((priority, i))
+            })
+            .min()
+            .map(|(_, i)| i)
+    }
+
+    pub fn encoding_records(&self) -> &[EncodingRecord] {
+        &self.encoding_records
+    }
+
+    /// The encoding record picked for character lookup
+    pub fn preferred_record(&self) -> Option<&EncodingRecord> {
+        self.encoding_records.get(self.preferred_record?)
+    }
+
+    /// Map a Unicode code point through the preferred subtable
+    pub fn lookup(&self, code_point: u32) -> Option<GlyphId> {
+        let er = self.preferred_record()?;
+        let st = er.sub_table.as_ref()?;
+
+        match (er.platform_id, er.encoding_id) {
+            // symbol fonts put their characters in the private use area at U+F020..=U+F0FF
+            (3, 0) => st.lookup(code_point).or_else(|| if code_point <= 0xFF { st.lookup(0xF000 + code_point) } else { None }),
+            (1, 0) => st.lookup(unicode_to_mac_roman(code_point)? as u32),
+            _ => st.lookup(code_point),
+        }
+    }
+
+    /// Look up a Unicode variation sequence in the (0, 5) subtable
+    pub fn lookup_variation(&self, code_point: u32, variation_selector: u32) -> Option<VariationGlyph> {
+        self.encoding_records.iter().filter_map(|er| er.sub_table.as_ref()).find_map(|st| st.lookup_variation(code_point, variation_selector))
+    }
+
+    /// Every (Unicode code point, glyph) pair of the preferred subtable, Mac Roman codes are converted to Unicode
+    pub fn mappings(&self) -> Box<dyn Iterator<Item = (u32, GlyphId)> + '_> {
+        let Some(er) = self.preferred_record() else {
+            return Box::new(std::iter::empty());
+        };
+        let Some(st) = er.sub_table.as_ref() else {
+            return Box::new(std::iter::empty());
+        };
+
+        match (er.platform_id, er.encoding_id) {
+            (1, 0) => Box::new(st.mappings().filter_map(|(c, g)| Some((mac_roman_to_unicode(u8::try_from(c).ok()?) as u32, g)))),
+            _ => st.mappings(),
+        }
+    }
+
+    /// Every glyph ID a subtable maps to must be below maxp numGlyphs
+    pub fn check_glyph_ids(&self, num_glyphs: u16) -> Result<(), Box<dyn Error>> {
+        for er in &self.encoding_records {
+            let Some(st) = er.sub_table.as_ref() else {
+                continue;
+            };
+
+            // 32-bit groups are checked on their raw glyph IDs, their mappings stop at the first ID past 16 bits
+            let bad = match st {
+                SubTable::Format8(st) => sequential_out_of_range(&st.groups, num_glyphs),
+                SubTable::Format12(st) => sequential_out_of_range(&st.groups, num_glyphs),
+                SubTable::Format13(st) => st.groups.iter().find(|g| g.glyph_id >= num_glyphs as u32).map(|g| (g.start_char_code, g.glyph_id)),
+                SubTable::Format14(st) => st.mappings().map(|(c, _, g)| (c, g as u32)).find(|&(_, g)| g >= num_glyphs as u32),
+                _ => st.mappings().map(|(c, g)| (c, g as u32)).find(|&(_, g)| g >= num_glyphs as u32),
+            };
+
+            if let Some((c, g)) = bad {
+                return Err(format!("cmap ({}, {}) maps 0x{:04x} to glyph {}, but the font only has {} glyphs", er.platform_id, er.encoding_id, c, g, num_glyphs).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Which code points reach each glyph, through the preferred subtable and the non-default variation sequences (keyed by their base character)
+    pub fn reverse_map(&self) -> HashMap<GlyphId, Vec<u32>> {
+        let mut m: HashMap<GlyphId, Vec<u32>> = HashMap::new();
+
+        for (c, g) in self.mappings() {
+            m.entry(g).or_default().push(c);
+        }
+
+        for st in self.encoding_records.iter().filter_map(|er| er.sub_table.as_ref()) {
+            if let SubTable::Format14(st) = st {
+                for (c, _, g) in st.mappings() {
+                    m.entry(g).or_default().push(c);
+                }
+            }
+        }
+
+        for cs in m.values_mut() {
+            cs.sort_unstable();
+            cs.dedup();
+        }
+
+        m
     }
 }
 
@@ -50,8 +174,8 @@ pub struct EncodingRecord {
 impl Debug for EncodingRecord {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("EncodingRecord")
-            .field_with("platform_id", |f| write!(f, "{}({})", get_platform_desc(self.platform_id), &self.platform_id))
-            .field_with("encoding_id", |f| write!(f, "{}({})", get_encoding_desc(self.platform_id, self.encoding_id), &self.encoding_id))
+            .field_with("platform_id", |f| write!(f, "{}({})", get_platform_desc(self.platform_id), self.platform_id))
+            .field_with("encoding_id", |f| write!(f, "{}({})", get_encoding_desc(self.platform_id, self.encoding_id), self.encoding_id))
             .field("subtable_offset", &self.subtable_offset)
             .field("sub_table", &self.sub_table)
             .finish()
@@ -71,25 +195,122 @@ impl EncodingRecord {
             sub_table: None,
         })
     }
+
+    pub fn platform_id(&self) -> u16 {
+        self.platform_id
+    }
+
+    pub fn encoding_id(&self) -> u16 {
+        self.encoding_id
+    }
+
+    pub fn sub_table(&self) -> Option<&SubTable> {
+        self.sub_table.as_ref()
+    }
 }
 
-pub struct SubTable {
-    format: u16,
+pub enum SubTable {
+    Format0(SubTable0),
+    Format2(SubTable2),
+    Format4(SubTable4),
+    Format6(SubTable6),
+    Format8(SubTable8),
+    Format10(SubTable10),
+    Format12(SubTable12),
+    Format13(SubTable13),
+    Format14(SubTable14),
+    Unknown(u16),
 }
 
 impl Debug for SubTable {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("SubTable").field_with("format", |f| write!(f, "{}({})", Self::get_format_desc(self.format), &self.format)).finish()
+        match self {
+            Self::Format0(st) => st.fmt(f),
+            Self::Format2(st) => st.fmt(f),
+            Self::Format4(st) => st.fmt(f),
+            Self::Format6(st) => st.fmt(f),
+            Self::Format8(st) => st.fmt(f),
+            Self::Format10(st) => st.fmt(f),
+            Self::Format12(st) => st.fmt(f),
+            Self::Format13(st) => st.fmt(f),
+            Self::Format14(st) => st.fmt(f),
+            Self::Unknown(format) => f.debug_struct("SubTable").field_with("format", |f| write!(f, "{}({})", Self::get_format_desc(*format), format)).finish(),
+        }
     }
 }
 
 impl SubTable {
     pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
         let r = &mut b;
 
         let format = read_uint16(r)?;
 
-        Ok(Self { format })
+        Ok(match format {
+            0 => Self::Format0(SubTable0::from_bytes(o)?),
+            2 => Self::Format2(SubTable2::from_bytes(o)?),
+            4 => Self::Format4(SubTable4::from_bytes(o)?),
+            6 => Self::Format6(SubTable6::from_bytes(o)?),
+            8 => Self::Format8(SubTable8::from_bytes(o)?),
+            10 => Self::Format10(SubTable10::from_bytes(o)?),
+            12 => Self::Format12(SubTable12::from_bytes(o)?),
+            13 => Self::Format13(SubTable13::from_bytes(o)?),
+            14 => Self::Format14(SubTable14::from_bytes(o)?),
+            _ => Self::Unknown(format),
+        })
+    }
+
+    pub fn format(&self) -> u16 {
+        match self {
+            Self::Format0(_) => 0,
+            Self::Format2(_) => 2,
+            Self::Format4(_) => 4,
+            Self::Format6(_) => 6,
+            Self::Format8(_) => 8,
+            Self::Format10(_) => 10,
+            Self::Format12(_) => 12,
+            Self::Format13(_) => 13,
+            Self::Format14(_) => 14,
+            Self::Unknown(format) => *format,
+        }
+    }
+
+    /// Map a character code to a glyph, `None` for unmapped codes and for subtables that don't map single characters
+    pub fn lookup(&self, code_point: u32) -> Option<GlyphId> {
+        match self {
+            Self::Format0(st) => st.lookup(code_point),
+            Self::Format2(st) => st.lookup(code_point),
+            Self::Format4(st) => st.lookup(code_point),
+            Self::Format6(st) => st.lookup(code_point),
+            Self::Format8(st) => st.lookup(code_point),
+            Self::Format10(st) => st.lookup(code_point),
+            Self::Format12(st) => st.lookup(code_point),
+            Self::Format13(st) => st.lookup(code_point),
+            Self::Format14(_) | Self::Unknown(_) => None,
+        }
+    }
+
+    /// Every (character code, glyph) pair the subtable maps, in subtable order, format 14 subtables yield nothing
+    pub fn mappings(&self) -> Box<dyn Iterator<Item = (u32, GlyphId)> + '_> {
+        match self {
+            Self::Format0(st) => Box::new(st.mappings()),
+            Self::Format2(st) => Box::new(st.mappings()),
+            Self::Format4(st) => Box::new(st.mappings()),
+            Self::Format6(st) => Box::new(st.mappings()),
+            Self::Format8(st) => Box::new(st.mappings()),
+            Self::Format10(st) => Box::new(st.mappings()),
+            Self::Format12(st) => Box::new(st.mappings()),
+            Self::Format13(st) => Box::new(st.mappings()),
+            Self::Format14(_) | Self::Unknown(_) => Box::new(std::iter::empty()),
+        }
+    }
+
+    /// Look up a variation sequence, only format 14 subtables carry them
+    pub fn lookup_variation(&self, code_point: u32, variation_selector: u32) -> Option<VariationGlyph> {
+        match self {
+            Self::Format14(st) => st.lookup(code_point, variation_selector),
+            _ => None,
+        }
     }
 
     pub fn get_format_desc(format: u16) -> &'static str {
@@ -108,8 +329,1181 @@ impl SubTable {
     }
 }
 
+/// Format 0: Byte encoding table
+pub struct SubTable0 {
+    format: u16,
+    /// This is the length in bytes of the subtable
+    length: u16,
+    language: u16,
+    /// An array that maps character codes to glyph index values
+    glyph_id_array: Vec<u8>,
+}
+
+impl Debug for SubTable0 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubTable0")
+            .field_with("format", |f| write!(f, "{}({})", SubTable::get_format_desc(self.format), self.format))
+            .field("length", &self.length)
+            .field("language", &self.language)
+            .field_with("glyph_id_array", |f| write!(f, "[u8; {}]", self.glyph_id_array.len()))
+            .finish()
+    }
+}
+
+impl SubTable0 {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        let length = read_uint16(r)?;
+        let language = read_uint16(r)?;
+        let glyph_id_array = read_array(r, 256, read_uint8)?;
+
+        Ok(Self { format, length, language, glyph_id_array })
+    }
+
+    pub fn lookup(&self, code_point: u32) -> Option<GlyphId> {
+        match *self.glyph_id_array.get(code_point as usize)? {
+            0 => None,
+            g => Some(g as GlyphId),
+        }
+    }
+
+    pub fn mappings(&self) -> impl Iterator<Item = (u32, GlyphId)> + '_ {
+        (0..self.glyph_id_array.len() as u32).filter_map(|c| Some((c, self.lookup(c)?)))
+    }
+}
+
+/// Format 2: High-byte mapping through table
+pub struct SubTable2 {
+    format: u16,
+    /// This is the length in bytes of the subtable
+    length: u16,
+    language: u16,
+    /// Array that maps high bytes to subHeaders: value is subHeader index × 8
+    sub_header_keys: Vec<u16>,
+    /// Variable-length array of SubHeader records
+    sub_headers: Vec<SubHeader>,
+    /// Variable-length array containing subarrays used for mapping the low byte of 2-byte characters
+    glyph_id_array: Vec<u16>,
+}
+
+impl Debug for SubTable2 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubTable2")
+            .field_with("format", |f| write!(f, "{}({})", SubTable::get_format_desc(self.format), self.format))
+            .field("length", &self.length)
+            .field("language", &self.language)
+            .field_with("sub_header_keys", |f| write!(f, "[u16; {}]", self.sub_header_keys.len()))
+            .field("sub_headers", &self.sub_headers)
+            .field_with("glyph_id_array", |f| write!(f, "[u16; {}]", self.glyph_id_array.len()))
+            .finish()
+    }
+}
+
+impl SubTable2 {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        let length = read_uint16(r)?;
+        let language = read_uint16(r)?;
+        let sub_header_keys = read_array(r, 256, read_uint16)?;
+
+        let num_sub_headers = sub_header_keys.iter().map(|&k| k / 8).max().unwrap_or(0) as usize + 1;
+        let sub_headers = read_array(r, num_sub_headers, SubHeader::from_bytes)?;
+
+        let glyph_id_count = (length as usize).saturating_sub(6 + 512 + num_sub_headers * 8).min(r.len()) / 2;
+        let glyph_id_array = read_array(r, glyph_id_count, read_uint16)?;
+
+        Ok(Self {
+            format,
+            length,
+            language,
+            sub_header_keys,
+            sub_headers,
+            glyph_id_array,
+        })
+    }
+
+    /// `code_point` is a single byte code, or a two byte code with the high byte first
+    pub fn lookup(&self, code_point: u32) -> Option<GlyphId> {
+        let (k, low) = if code_point < 0x100 {
+            // subHeader 0 is reserved for single byte codes, any other key marks a high byte that needs a second byte
+            if 0 != self.sub_header_keys[code_point as usize] {
+                return None;
+            }
+            (0, code_point as u16)
+        } else if code_point < 0x10000 {
+            match self.sub_header_keys[(code_point >> 8) as usize] / 8 {
+                0 => return None,
+                k => (k as usize, (code_point & 0xFF) as u16),
+            }
+        } else {
+            return None;
+        };
+
+        let sh = &self.sub_headers[k];
+        if low < sh.first_code || low - sh.first_code >= sh.entry_count {
+            return None;
+        }
+
+        // idRangeOffset counts bytes from its own field in the subHeader, turn that into an index into glyphIdArray
+        let from_array = (k * 8 + 6 + sh.id_range_offset as usize).checked_sub(self.sub_headers.len() * 8)?;
+        let index = from_array / 2 + (low - sh.first_code) as usize;
+        match *self.glyph_id_array.get(index)? {
+            0 => None,
+            g => match g.wrapping_add(sh.id_delta as u16) {
+                0 => None,
+                g => Some(g),
+            },
+        }
+    }
+
+    /// Single byte codes first, then the two byte codes of each high byte
+    pub fn mappings(&self) -> impl Iterator<Item = (u32, GlyphId)> + '_ {
+        let single = (0..0x100).filter(|&c| 0 == self.sub_header_keys[c as usize]);
+        let double = (0..0x100u32).filter(|&high| 0 != self.sub_header_keys[high as usize] / 8).flat_map(|high| {
+            let sh = &self.sub_headers[self.sub_header_keys[high as usize] as usize / 8];
+            let first = sh.first_code as u32;
+            (first..(first + sh.entry_count as u32).min(0x100)).map(move |low| high << 8 | low)
+        });
+        single.chain(double).filter_map(|c| Some((c, self.lookup(c)?)))
+    }
+}
+
+pub struct SubHeader {
+    /// First valid low byte for this SubHeader
+    first_code: u16,
+    /// Number of valid low bytes for this SubHeader
+    entry_count: u16,
+    id_delta: i16,
+    id_range_offset: u16,
+}
+
+impl Debug for SubHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubHeader")
+            .field("first_code", &self.first_code)
+            .field("entry_count", &self.entry_count)
+            .field("id_delta", &self.id_delta)
+            .field("id_range_offset", &self.id_range_offset)
+            .finish()
+    }
+}
+
+impl SubHeader {
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let first_code = read_uint16(r)?;
+        let entry_count = read_uint16(r)?;
+        let id_delta = read_int16(r)?;
+        let id_range_offset = read_uint16(r)?;
+
+        Ok(Self {
+            first_code,
+            entry_count,
+            id_delta,
+            id_range_offset,
+        })
+    }
+}
+
+/// Format 4: Segment mapping to delta values
 pub struct SubTable4 {
+    format: u16,
+    /// This is the length in bytes of the subtable
     length: u16,
     language: u16,
-    
+    /// 2 × segCount
+    seg_count_x2: u16,
+    search_range: u16,
+    entry_selector: u16,
+    range_shift: u16,
+    /// End characterCode for each segment, last=0xFFFF
+    end_code: Vec<u16>,
+    reserved_pad: u16,
+    /// Start character code for each segment
+    start_code: Vec<u16>,
+    /// Delta for all character codes in segment
+    id_delta: Vec<i16>,
+    /// Offsets into glyphIdArray or 0
+    id_range_offset: Vec<u16>,
+    glyph_id_array: Vec<u16>,
+}
+
+impl Debug for SubTable4 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubTable4")
+            .field_with("format", |f| write!(f, "{}({})", SubTable::get_format_desc(self.format), self.format))
+            .field("length", &self.length)
+            .field("language", &self.language)
+            .field("seg_count_x2", &self.seg_count_x2)
+            .field("search_range", &self.search_range)
+            .field("entry_selector", &self.entry_selector)
+            .field("range_shift", &self.range_shift)
+            .field_with("end_code", |f| write!(f, "[u16; {}]", self.end_code.len()))
+            .field("reserved_pad", &self.reserved_pad)
+            .field_with("start_code", |f| write!(f, "[u16; {}]", self.start_code.len()))
+            .field_with("id_delta", |f| write!(f, "[i16; {}]", self.id_delta.len()))
+            .field_with("id_range_offset", |f| write!(f, "[u16; {}]", self.id_range_offset.len()))
+            .field_with("glyph_id_array", |f| write!(f, "[u16; {}]", self.glyph_id_array.len()))
+            .finish()
+    }
+}
+
+impl SubTable4 {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        let length = read_uint16(r)?;
+        let language = read_uint16(r)?;
+        let seg_count_x2 = read_uint16(r)?;
+        let search_range = read_uint16(r)?;
+        let entry_selector = read_uint16(r)?;
+        let range_shift = read_uint16(r)?;
+
+        let seg_count = (seg_count_x2 / 2) as usize;
+        let end_code = read_array(r, seg_count, read_uint16)?;
+        let reserved_pad = read_uint16(r)?;
+        let start_code = read_array(r, seg_count, read_uint16)?;
+        let id_delta = read_array(r, seg_count, read_int16)?;
+        let id_range_offset = read_array(r, seg_count, read_uint16)?;
+
+        // glyphIdArray fills the rest of the subtable, but never read past the data we actually have
+        let glyph_id_count = (length as usize).saturating_sub(16 + seg_count * 8).min(r.len()) / 2;
+        let glyph_id_array = read_array(r, glyph_id_count, read_uint16)?;
+
+        Ok(Self {
+            format,
+            length,
+            language,
+            seg_count_x2,
+            search_range,
+            entry_selector,
+            range_shift,
+            end_code,
+            reserved_pad,
+            start_code,
+            id_delta,
+            id_range_offset,
+            glyph_id_array,
+        })
+    }
+
+    pub fn lookup(&self, code_point: u32) -> Option<GlyphId> {
+        let c = u16::try_from(code_point).ok()?;
+
+        let i = self.end_code.partition_point(|&e| e < c);
+        let start = *self.start_code.get(i)?;
+        let end = self.end_code[i];
+        // the final start = end = 0xFFFF segment only terminates the search
+        if c < start || (0xFFFF == start && 0xFFFF == end) {
+            return None;
+        }
+
+        let delta = self.id_delta[i] as u16;
+        let range_offset = self.id_range_offset[i];
+
+        let glyph_id = if 0 == range_offset {
+            c.wrapping_add(delta)
+        } else {
+            // idRangeOffset counts bytes from its own slot in idRangeOffset[], which glyphIdArray directly follows
+            let index = (range_offset / 2) as usize + (c - start) as usize;
+            let index = index.checked_sub(self.id_range_offset.len() - i)?;
+            match *self.glyph_id_array.get(index)? {
+                0 => return None,
+                g => g.wrapping_add(delta),
+            }
+        };
+
+        if 0 == glyph_id { None } else { Some(glyph_id) }
+    }
+
+    pub fn mappings(&self) -> impl Iterator<Item = (u32, GlyphId)> + '_ {
+        self.start_code.iter().zip(&self.end_code).flat_map(|(&start, &end)| start as u32..=end as u32).filter_map(|c| Some((c, self.lookup(c)?)))
+    }
+}
+
+/// Format 6: Trimmed table mapping
+pub struct SubTable6 {
+    format: u16,
+    /// This is the length in bytes of the subtable
+    length: u16,
+    language: u16,
+    /// First character code of subrange
+    first_code: u16,
+    /// Number of character codes in subrange
+    entry_count: u16,
+    /// Array of glyph index values for character codes in the range
+    glyph_id_array: Vec<u16>,
+}
+
+impl Debug for SubTable6 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubTable6")
+            .field_with("format", |f| write!(f, "{}({})", SubTable::get_format_desc(self.format), self.format))
+            .field("length", &self.length)
+            .field("language", &self.language)
+            .field("first_code", &self.first_code)
+            .field("entry_count", &self.entry_count)
+            .field_with("glyph_id_array", |f| write!(f, "[u16; {}]", self.glyph_id_array.len()))
+            .finish()
+    }
+}
+
+impl SubTable6 {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        let length = read_uint16(r)?;
+        let language = read_uint16(r)?;
+        let first_code = read_uint16(r)?;
+        let entry_count = read_uint16(r)?;
+        let glyph_id_array = read_array(r, entry_count as usize, read_uint16)?;
+
+        Ok(Self {
+            format,
+            length,
+            language,
+            first_code,
+            entry_count,
+            glyph_id_array,
+        })
+    }
+
+    pub fn lookup(&self, code_point: u32) -> Option<GlyphId> {
+        match *self.glyph_id_array.get(code_point.checked_sub(self.first_code as u32)? as usize)? {
+            0 => None,
+            g => Some(g),
+        }
+    }
+
+    pub fn mappings(&self) -> impl Iterator<Item = (u32, GlyphId)> + '_ {
+        self.glyph_id_array.iter().zip(self.first_code as u32..).filter(|&(&g, _)| 0 != g).map(|(&g, c)| (c, g))
+    }
+}
+
+/// Format 8: mixed 16-bit and 32-bit coverage
+pub struct SubTable8 {
+    format: u16,
+    reserved: u16,
+    /// Byte length of this subtable (including the header)
+    length: u32,
+    language: u32,
+    /// Tightly packed array of bits (8K bytes total) indicating whether the particular 16-bit (index) value is the start of a 32-bit character code
+    is32: Vec<u8>,
+    /// Number of groupings which follow
+    num_groups: u32,
+    groups: Vec<SequentialMapGroup>,
+}
+
+impl Debug for SubTable8 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubTable8")
+            .field_with("format", |f| write!(f, "{}({})", SubTable::get_format_desc(self.format), self.format))
+            .field("reserved", &self.reserved)
+            .field("length", &self.length)
+            .field("language", &self.language)
+            .field_with("is32", |f| write!(f, "[u8; {}]", self.is32.len()))
+            .field("num_groups", &self.num_groups)
+            .field_with("groups", |f| write!(f, "[SequentialMapGroup; {}]", self.groups.len()))
+            .finish()
+    }
+}
+
+impl SubTable8 {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        let reserved = read_uint16(r)?;
+        let length = read_uint32(r)?;
+        let language = read_uint32(r)?;
+        let is32 = read_array(r, 8192, read_uint8)?;
+        let num_groups = read_uint32(r)?;
+        let groups = read_array(r, num_groups as usize, SequentialMapGroup::from_bytes)?;
+
+        validate_groups(groups.iter().map(|g| (g.start_char_code, g.end_char_code)))?;
+
+        Ok(Self {
+            format,
+            reserved,
+            length,
+            language,
+            is32,
+            num_groups,
+            groups,
+        })
+    }
+
+    /// Whether the 16-bit value `code` is the high half of a 32-bit character code
+    pub fn is_32(&self, code: u16) -> bool {
+        0 != self.is32[(code / 8) as usize] & (0x80 >> (code % 8))
+    }
+
+    /// `code_point` is the packed character code, a lone 16-bit code or both 16-bit halves of a 32-bit code
+    pub fn lookup(&self, code_point: u32) -> Option<GlyphId> {
+        let g = &self.groups[find_group(&self.groups, code_point, |g| (g.start_char_code, g.end_char_code))?];
+        GlyphId::try_from(g.start_glyph_id.checked_add(code_point - g.start_char_code)?).ok().filter(|&gid| 0 != gid)
+    }
+
+    pub fn mappings(&self) -> impl Iterator<Item = (u32, GlyphId)> + '_ {
+        sequential_mappings(&self.groups)
+    }
+}
+
+/// Format 10: Trimmed array
+pub struct SubTable10 {
+    format: u16,
+    reserved: u16,
+    /// Byte length of this subtable (including the header)
+    length: u32,
+    language: u32,
+    /// First character code covered
+    start_char_code: u32,
+    /// Number of character codes covered
+    num_chars: u32,
+    /// Array of glyph indices for the character codes covered
+    glyph_id_array: Vec<u16>,
+}
+
+impl Debug for SubTable10 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubTable10")
+            .field_with("format", |f| write!(f, "{}({})", SubTable::get_format_desc(self.format), self.format))
+            .field("reserved", &self.reserved)
+            .field("length", &self.length)
+            .field("language", &self.language)
+            .field("start_char_code", &self.start_char_code)
+            .field("num_chars", &self.num_chars)
+            .field_with("glyph_id_array", |f| write!(f, "[u16; {}]", self.glyph_id_array.len()))
+            .finish()
+    }
+}
+
+impl SubTable10 {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        let reserved = read_uint16(r)?;
+        let length = read_uint32(r)?;
+        let language = read_uint32(r)?;
+        let start_char_code = read_uint32(r)?;
+        let num_chars = read_uint32(r)?;
+        let glyph_id_array = read_array(r, num_chars as usize, read_uint16)?;
+
+        Ok(Self {
+            format,
+            reserved,
+            length,
+            language,
+            start_char_code,
+            num_chars,
+            glyph_id_array,
+        })
+    }
+
+    pub fn lookup(&self, code_point: u32) -> Option<GlyphId> {
+        match *self.glyph_id_array.get(code_point.checked_sub(self.start_char_code)? as usize)? {
+            0 => None,
+            g => Some(g),
+        }
+    }
+
+    pub fn mappings(&self) -> impl Iterator<Item = (u32, GlyphId)> + '_ {
+        self.glyph_id_array.iter().zip(self.start_char_code..).filter(|&(&g, _)| 0 != g).map(|(&g, c)| (c, g))
+    }
+}
+
+/// Format 12: Segmented coverage
+pub struct SubTable12 {
+    format: u16,
+    reserved: u16,
+    /// Byte length of this subtable (including the header)
+    length: u32,
+    language: u32,
+    /// Number of groupings which follow
+    num_groups: u32,
+    groups: Vec<SequentialMapGroup>,
+}
+
+impl Debug for SubTable12 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubTable12")
+            .field_with("format", |f| write!(f, "{}({})", SubTable::get_format_desc(self.format), self.format))
+            .field("reserved", &self.reserved)
+            .field("length", &self.length)
+            .field("language", &self.language)
+            .field("num_groups", &self.num_groups)
+            .field_with("groups", |f| write!(f, "[SequentialMapGroup; {}]", self.groups.len()))
+            .finish()
+    }
+}
+
+impl SubTable12 {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        let reserved = read_uint16(r)?;
+        let length = read_uint32(r)?;
+        let language = read_uint32(r)?;
+        let num_groups = read_uint32(r)?;
+        let groups = read_array(r, num_groups as usize, SequentialMapGroup::from_bytes)?;
+
+        validate_groups(groups.iter().map(|g| (g.start_char_code, g.end_char_code)))?;
+
+        Ok(Self {
+            format,
+            reserved,
+            length,
+            language,
+            num_groups,
+            groups,
+        })
+    }
+
+    pub fn lookup(&self, code_point: u32) -> Option<GlyphId> {
+        let g = &self.groups[find_group(&self.groups, code_point, |g| (g.start_char_code, g.end_char_code))?];
+        GlyphId::try_from(g.start_glyph_id.checked_add(code_point - g.start_char_code)?).ok().filter(|&gid| 0 != gid)
+    }
+
+    pub fn mappings(&self) -> impl Iterator<Item = (u32, GlyphId)> + '_ {
+        sequential_mappings(&self.groups)
+    }
+}
+
+pub struct SequentialMapGroup {
+    /// First character code in this group
+    start_char_code: u32,
+    /// Last character code in this group
+    end_char_code: u32,
+    /// Glyph index corresponding to the starting character code
+    start_glyph_id: u32,
+}
+
+impl Debug for SequentialMapGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SequentialMapGroup")
+            .field("start_char_code", &self.start_char_code)
+            .field("end_char_code", &self.end_char_code)
+            .field("start_glyph_id", &self.start_glyph_id)
+            .finish()
+    }
+}
+
+impl SequentialMapGroup {
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let start_char_code = read_uint32(r)?;
+        let end_char_code = read_uint32(r)?;
+        let start_glyph_id = read_uint32(r)?;
+
+        Ok(Self { start_char_code, end_char_code, start_glyph_id })
+    }
+}
+
+/// Format 13: Many-to-one range mappings
+pub struct SubTable13 {
+    format: u16,
+    reserved: u16,
+    /// Byte length of this subtable (including the header)
+    length: u32,
+    language: u32,
+    /// Number of groupings which follow
+    num_groups: u32,
+    groups: Vec<ConstantMapGroup>,
+}
+
+impl Debug for SubTable13 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubTable13")
+            .field_with("format", |f| write!(f, "{}({})", SubTable::get_format_desc(self.format), self.format))
+            .field("reserved", &self.reserved)
+            .field("length", &self.length)
+            .field("language", &self.language)
+            .field("num_groups", &self.num_groups)
+            .field_with("groups", |f| write!(f, "[ConstantMapGroup; {}]", self.groups.len()))
+            .finish()
+    }
+}
+
+impl SubTable13 {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        let reserved = read_uint16(r)?;
+        let length = read_uint32(r)?;
+        let language = read_uint32(r)?;
+        let num_groups = read_uint32(r)?;
+        let groups = read_array(r, num_groups as usize, ConstantMapGroup::from_bytes)?;
+
+        validate_groups(groups.iter().map(|g| (g.start_char_code, g.end_char_code)))?;
+
+        Ok(Self {
+            format,
+            reserved,
+            length,
+            language,
+            num_groups,
+            groups,
+        })
+    }
+
+    pub fn lookup(&self, code_point: u32) -> Option<GlyphId> {
+        let g = &self.groups[find_group(&self.groups, code_point, |g| (g.start_char_code, g.end_char_code))?];
+        GlyphId::try_from(g.glyph_id).ok().filter(|&gid| 0 != gid)
+    }
+
+    pub fn mappings(&self) -> impl Iterator<Item = (u32, GlyphId)> + '_ {
+        self.groups
+            .iter()
+            .filter_map(|g| Some((g, GlyphId::try_from(g.glyph_id).ok().filter(|&gid| 0 != gid)?)))
+            .flat_map(|(g, gid)| (g.start_char_code..=g.end_char_code.min(MAX_CODE_POINT)).map(move |c| (c, gid)))
+    }
+}
+
+pub struct ConstantMapGroup {
+    /// First character code in this group
+    start_char_code: u32,
+    /// Last character code in this group
+    end_char_code: u32,
+    /// Glyph index to be used for all the characters in the group's range
+    glyph_id: u32,
+}
+
+impl Debug for ConstantMapGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConstantMapGroup")
+            .field("start_char_code", &self.start_char_code)
+            .field("end_char_code", &self.end_char_code)
+            .field("glyph_id", &self.glyph_id)
+            .finish()
+    }
+}
+
+impl ConstantMapGroup {
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let start_char_code = read_uint32(r)?;
+        let end_char_code = read_uint32(r)?;
+        let glyph_id = read_uint32(r)?;
+
+        Ok(Self { start_char_code, end_char_code, glyph_id })
+    }
+}
+
+/// Groups must be sorted by increasing startCharCode and must not overlap, otherwise binary search is meaningless
+fn validate_groups<I: Iterator<Item = (u32, u32)>>(groups: I) -> Result<(), Box<dyn Error>> {
+    let mut prev_end: Option<u32> = None;
+    for (start, end) in groups {
+        if start > end {
+            return Err(format!("cmap group 0x{:x}..0x{:x} ends before it starts", start, end).into());
+        }
+        if let Some(prev_end) = prev_end
+            && start <= prev_end
+        {
+            return Err(format!("cmap group starting at 0x{:x} is unsorted or overlaps the previous group", start).into());
+        }
+        prev_end = Some(end);
+    }
+    Ok(())
+}
+
+/// Highest Unicode code point, also caps groups in malformed subtables that claim to reach 0xFFFFFFFF
+const MAX_CODE_POINT: u32 = 0x10FFFF;
+
+/// A group ends at its first glyph ID past 16 bits, `check_glyph_ids` reports those through `sequential_out_of_range`
+fn sequential_mappings(groups: &[SequentialMapGroup]) -> impl Iterator<Item = (u32, GlyphId)> + '_ {
+    groups.iter().flat_map(|g| {
+        (g.start_char_code..=g.end_char_code.min(MAX_CODE_POINT)).map_while(move |c| Some((c, GlyphId::try_from(g.start_glyph_id.checked_add(c - g.start_char_code)?).ok()?))).filter(|&(_, gid)| 0 != gid)
+    })
+}
+
+/// The first (char code, glyph ID) of the groups reaching `num_glyphs`
+fn sequential_out_of_range(groups: &[SequentialMapGroup], num_glyphs: u16) -> Option<(u32, u32)> {
+    groups.iter().filter(|g| g.start_char_code <= MAX_CODE_POINT).find_map(|g| {
+        let last = g.start_glyph_id as u64 + (g.end_char_code.min(MAX_CODE_POINT) - g.start_char_code) as u64;
+        if last < num_glyphs as u64 {
+            return None;
+        }
+        let skip = (num_glyphs as u32).saturating_sub(g.start_glyph_id);
+        Some((g.start_char_code + skip, g.start_glyph_id + skip))
+    })
+}
+
+/// Binary search for the group covering `code_point`, `range` gives the (start, end) char codes of a group
+fn find_group<T>(groups: &[T], code_point: u32, range: fn(&T) -> (u32, u32)) -> Option<usize> {
+    let i = groups.partition_point(|g| range(g).1 < code_point);
+    let (start, _) = range(groups.get(i)?);
+    if start <= code_point { Some(i) } else { None }
+}
+
+/// Format 14: Unicode Variation Sequences
+pub struct SubTable14 {
+    format: u16,
+    /// Byte length of this subtable (including this header)
+    length: u32,
+    /// Number of variation Selector Records
+    num_var_selector_records: u32,
+    /// Array of VariationSelector records, sorted by increasing varSelector
+    var_selector: Vec<VariationSelector>,
+}
+
+impl Debug for SubTable14 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubTable14")
+            .field_with("format", |f| write!(f, "{}({})", SubTable::get_format_desc(self.format), self.format))
+            .field("length", &self.length)
+            .field("num_var_selector_records", &self.num_var_selector_records)
+            .field("var_selector", &self.var_selector)
+            .finish()
+    }
+}
+
+impl SubTable14 {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        let length = read_uint32(r)?;
+        let num_var_selector_records = read_uint32(r)?;
+        let mut var_selector = Vec::with_capacity(num_var_selector_records.min(u16::MAX as u32) as usize);
+
+        for _ in 0..num_var_selector_records {
+            let mut vs = VariationSelector::from_bytes(r)?;
+            if 0 != vs.default_uvs_offset {
+                vs.default_uvs = Some(DefaultUVS::from_bytes(o.get(vs.default_uvs_offset as usize..).ok_or("DefaultUVS offset out of range")?)?);
+            }
+            if 0 != vs.non_default_uvs_offset {
+                vs.non_default_uvs = Some(NonDefaultUVS::from_bytes(o.get(vs.non_default_uvs_offset as usize..).ok_or("NonDefaultUVS offset out of range")?)?);
+            }
+            var_selector.push(vs);
+        }
+
+        Ok(Self {
+            format,
+            length,
+            num_var_selector_records,
+            var_selector,
+        })
+    }
+
+    /// `None` when the font doesn't support the variation sequence
+    pub fn lookup(&self, code_point: u32, variation_selector: u32) -> Option<VariationGlyph> {
+        let i = self.var_selector.binary_search_by_key(&variation_selector, |vs| vs.var_selector).ok()?;
+        let vs = &self.var_selector[i];
+
+        if let Some(d) = &vs.default_uvs
+            && d.contains(code_point)
+        {
+            return Some(VariationGlyph::Default);
+        }
+
+        vs.non_default_uvs.as_ref()?.lookup(code_point).map(VariationGlyph::Glyph)
+    }
+
+    /// Every (base character, variation selector, glyph) the non-default UVS tables map, default UVS entries carry no glyph of their own
+    pub fn mappings(&self) -> impl Iterator<Item = (u32, u32, GlyphId)> + '_ {
+        self.var_selector.iter().filter_map(|vs| Some((vs.var_selector, vs.non_default_uvs.as_ref()?))).flat_map(|(selector, nd)| nd.uvs_mappings.iter().map(move |m| (m.unicode_value, selector, m.glyph_id)))
+    }
+}
+
+/// Result of looking up a variation sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariationGlyph {
+    /// The sequence uses the glyph the default cmap gives the base character
+    Default,
+    /// The sequence maps to this glyph
+    Glyph(GlyphId),
+}
+
+pub struct VariationSelector {
+    /// Variation selector
+    var_selector: u32,
+    /// Offset from the start of the format 14 subtable to Default UVS Table. May be 0.
+    default_uvs_offset: Offset32,
+    /// Offset from the start of the format 14 subtable to Non-Default UVS Table. May be 0.
+    non_default_uvs_offset: Offset32,
+    default_uvs: Option<DefaultUVS>,
+    non_default_uvs: Option<NonDefaultUVS>,
+}
+
+impl Debug for VariationSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VariationSelector")
+            .field_with("var_selector", |f| write!(f, "U+{:04X}", self.var_selector))
+            .field("default_uvs_offset", &self.default_uvs_offset)
+            .field("non_default_uvs_offset", &self.non_default_uvs_offset)
+            .field("default_uvs", &self.default_uvs)
+            .field("non_default_uvs", &self.non_default_uvs)
+            .finish()
+    }
+}
+
+impl VariationSelector {
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let var_selector = read_uint24(r)?;
+        let default_uvs_offset = read_offset32(r)?;
+        let non_default_uvs_offset = read_offset32(r)?;
+
+        Ok(Self {
+            var_selector,
+            default_uvs_offset,
+            non_default_uvs_offset,
+            default_uvs: None,
+            non_default_uvs: None,
+        })
+    }
+}
+
+/// Default UVS table, ranges of base characters whose variation sequence uses the default glyph
+pub struct DefaultUVS {
+    /// Number of Unicode character ranges.
+    num_unicode_value_ranges: u32,
+    /// Array of UnicodeRange records.
+    ranges: Vec<UnicodeRange>,
+}
+
+impl Debug for DefaultUVS {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DefaultUVS")
+            .field("num_unicode_value_ranges", &self.num_unicode_value_ranges)
+            .field_with("ranges", |f| write!(f, "[UnicodeRange; {}]", self.ranges.len()))
+            .finish()
+    }
+}
+
+impl DefaultUVS {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let num_unicode_value_ranges = read_uint32(r)?;
+        let ranges = read_array(r, num_unicode_value_ranges as usize, UnicodeRange::from_bytes)?;
+
+        Ok(Self { num_unicode_value_ranges, ranges })
+    }
+
+    pub fn contains(&self, code_point: u32) -> bool {
+        let i = self.ranges.partition_point(|ur| ur.start_unicode_value + (ur.additional_count as u32) < code_point);
+        self.ranges.get(i).is_some_and(|ur| ur.start_unicode_value <= code_point && code_point <= ur.start_unicode_value + ur.additional_count as u32)
+    }
+}
+
+pub struct UnicodeRange {
+    /// First value in this range
+    start_unicode_value: u32,
+    /// Number of additional values in this range
+    additional_count: u8,
+}
+
+impl Debug for UnicodeRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UnicodeRange").field("start_unicode_value", &self.start_unicode_value).field("additional_count", &self.additional_count).finish()
+    }
+}
+
+impl UnicodeRange {
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let start_unicode_value = read_uint24(r)?;
+        let additional_count = read_uint8(r)?;
+
+        Ok(Self { start_unicode_value, additional_count })
+    }
+}
+
+/// Non-Default UVS table, base characters whose variation sequence maps to a specific glyph
+pub struct NonDefaultUVS {
+    /// Number of UVS Mappings that follow
+    num_uvs_mappings: u32,
+    /// Array of UVSMapping records.
+    uvs_mappings: Vec<UVSMapping>,
+}
+
+impl Debug for NonDefaultUVS {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NonDefaultUVS")
+            .field("num_uvs_mappings", &self.num_uvs_mappings)
+            .field_with("uvs_mappings", |f| write!(f, "[UVSMapping; {}]", self.uvs_mappings.len()))
+            .finish()
+    }
+}
+
+impl NonDefaultUVS {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let num_uvs_mappings = read_uint32(r)?;
+        let uvs_mappings = read_array(r, num_uvs_mappings as usize, UVSMapping::from_bytes)?;
+
+        Ok(Self { num_uvs_mappings, uvs_mappings })
+    }
+
+    pub fn lookup(&self, code_point: u32) -> Option<GlyphId> {
+        let i = self.uvs_mappings.binary_search_by_key(&code_point, |m| m.unicode_value).ok()?;
+        Some(self.uvs_mappings[i].glyph_id)
+    }
+}
+
+pub struct UVSMapping {
+    /// Base Unicode value of the UVS
+    unicode_value: u32,
+    /// Glyph ID of the UVS
+    glyph_id: GlyphId,
+}
+
+impl Debug for UVSMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UVSMapping").field("unicode_value", &self.unicode_value).field("glyph_id", &self.glyph_id).finish()
+    }
+}
+
+impl UVSMapping {
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let unicode_value = read_uint24(r)?;
+        let glyph_id = read_uint16(r)?;
+
+        Ok(Self { unicode_value, glyph_id })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Format 4 with a delta segment 'A'..='C' -> 1..=3, a glyphIdArray segment 'a'..='b' -> 10, unmapped, and the final 0xFFFF segment
+    fn format4() -> Vec<u8> {
+        let mut w = Vec::new();
+        for v in [4, 0, 0, 6, 4, 1, 2] {
+            write_uint16(&mut w, v);
+        }
+        for end_code in [0x43, 0x62, 0xFFFF] {
+            write_uint16(&mut w, end_code);
+        }
+        write_uint16(&mut w, 0);
+        for start_code in [0x41, 0x61, 0xFFFF] {
+            write_uint16(&mut w, start_code);
+        }
+        for id_delta in [1 - 0x41, 0, 1] {
+            write_int16(&mut w, id_delta);
+        }
+        for id_range_offset in [0, 4, 0] {
+            write_uint16(&mut w, id_range_offset);
+        }
+        for glyph_id in [10, 0] {
+            write_uint16(&mut w, glyph_id);
+        }
+        let length = w.len() as u16;
+        w[2..4].copy_from_slice(&length.to_be_bytes());
+        w
+    }
+
+    #[test]
+    fn format4_lookup() {
+        let st = SubTable::from_bytes(&format4()).unwrap();
+        assert_eq!(4, st.format());
+        assert_eq!(Some(1), st.lookup(0x41));
+        assert_eq!(Some(3), st.lookup(0x43));
+        assert_eq!(None, st.lookup(0x44));
+        assert_eq!(Some(10), st.lookup(0x61));
+        assert_eq!(None, st.lookup(0x62));
+        assert_eq!(None, st.lookup(0xFFFF));
+        assert_eq!(None, st.lookup(0x10041));
+    }
+
+    #[test]
+    fn format4_mappings() {
+        let st = SubTable::from_bytes(&format4()).unwrap();
+        assert_eq!(vec![(0x41, 1), (0x42, 2), (0x43, 3), (0x61, 10)], st.mappings().collect::<Vec<_>>());
+    }
+
+    /// Format 12 or 13 with (startCharCode, endCharCode, glyph) groups
+    fn segmented(format: u16, groups: &[(u32, u32, u32)]) -> Vec<u8> {
+        let mut w = Vec::new();
+        write_uint16(&mut w, format);
+        write_uint16(&mut w, 0);
+        write_uint32(&mut w, 16 + 12 * groups.len() as u32);
+        write_uint32(&mut w, 0);
+        write_uint32(&mut w, groups.len() as u32);
+        for &(start, end, glyph_id) in groups {
+            write_uint32(&mut w, start);
+            write_uint32(&mut w, end);
+            write_uint32(&mut w, glyph_id);
+        }
+        w
+    }
+
+    #[test]
+    fn format12_lookup() {
+        let st = SubTable::from_bytes(&segmented(12, &[(0x41, 0x43, 1), (0x1F600, 0x1F601, 0xFFFF)])).unwrap();
+        assert_eq!(Some(1), st.lookup(0x41));
+        assert_eq!(Some(3), st.lookup(0x43));
+        assert_eq!(None, st.lookup(0x44));
+        assert_eq!(Some(0xFFFF), st.lookup(0x1F600));
+        // the second code of the group would need glyph 0x10000
+        assert_eq!(None, st.lookup(0x1F601));
+        assert_eq!(vec![(0x41, 1), (0x42, 2), (0x43, 3), (0x1F600, 0xFFFF)], st.mappings().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn format13_lookup() {
+        let st = SubTable::from_bytes(&segmented(13, &[(0x20, 0x7E, 5), (0x100, 0x101, 0)])).unwrap();
+        assert_eq!(Some(5), st.lookup(0x20));
+        assert_eq!(Some(5), st.lookup(0x7E));
+        assert_eq!(None, st.lookup(0x100));
+        assert_eq!(0x7E - 0x20 + 1, st.mappings().count());
+    }
+
+    #[test]
+    fn glyph_ids_past_num_glyphs() {
+        assert!(cmap(&[(3, 10, &segmented(12, &[(0x41, 0x43, 1)]))]).check_glyph_ids(4).is_ok());
+        let err = cmap(&[(3, 10, &segmented(12, &[(0x41, 0x43, 1)]))]).check_glyph_ids(3).unwrap_err();
+        assert_eq!("cmap (3, 10) maps 0x0043 to glyph 3, but the font only has 3 glyphs", err.to_string());
+        // glyph IDs past 16 bits are out of range too, not dropped from the mappings unnoticed
+        let err = cmap(&[(3, 10, &segmented(12, &[(0x1F600, 0x1F601, 0xFFFF)]))]).check_glyph_ids(10).unwrap_err();
+        assert_eq!("cmap (3, 10) maps 0x1f600 to glyph 65535, but the font only has 10 glyphs", err.to_string());
+        let err = cmap(&[(3, 10, &segmented(13, &[(0x1F600, 0x1F601, 0x10000)]))]).check_glyph_ids(u16::MAX).unwrap_err();
+        assert_eq!("cmap (3, 10) maps 0x1f600 to glyph 65536, but the font only has 65535 glyphs", err.to_string());
+        let err = cmap(&[(3, 10, &segmented(12, &[(0x1F600, 0x1F600, 0x10000)]))]).check_glyph_ids(u16::MAX).unwrap_err();
+        assert_eq!("cmap (3, 10) maps 0x1f600 to glyph 65536, but the font only has 65535 glyphs", err.to_string());
+    }
+
+    #[test]
+    fn segmented_groups_must_be_sorted() {
+        assert!(SubTable::from_bytes(&segmented(12, &[(0x50, 0x60, 1), (0x41, 0x43, 1)])).is_err());
+        assert!(SubTable::from_bytes(&segmented(13, &[(0x41, 0x50, 1), (0x50, 0x60, 2)])).is_err());
+        assert!(SubTable::from_bytes(&segmented(12, &[(0x43, 0x41, 1)])).is_err());
+    }
+
+    /// Format 14 with one selector U+FE00: default UVS for U+4E00..=U+4E02, non-default U+4E08 -> 42
+    fn format14(default_uvs_offset: u32) -> Vec<u8> {
+        let mut w = Vec::new();
+        write_uint16(&mut w, 14);
+        write_uint32(&mut w, 0);
+        write_uint32(&mut w, 1);
+        w.extend(&0xFE00u32.to_be_bytes()[1..]);
+        write_uint32(&mut w, default_uvs_offset);
+        write_uint32(&mut w, 29);
+        // DefaultUVS at 21
+        write_uint32(&mut w, 1);
+        w.extend(&0x4E00u32.to_be_bytes()[1..]);
+        write_uint8(&mut w, 2);
+        // NonDefaultUVS at 29
+        write_uint32(&mut w, 1);
+        w.extend(&0x4E08u32.to_be_bytes()[1..]);
+        write_uint16(&mut w, 42);
+        let length = w.len() as u32;
+        w[2..6].copy_from_slice(&length.to_be_bytes());
+        w
+    }
+
+    #[test]
+    fn format14_lookup() {
+        let st = SubTable::from_bytes(&format14(21)).unwrap();
+        assert_eq!(Some(VariationGlyph::Default), st.lookup_variation(0x4E00, 0xFE00));
+        assert_eq!(Some(VariationGlyph::Default), st.lookup_variation(0x4E02, 0xFE00));
+        assert_eq!(None, st.lookup_variation(0x4E03, 0xFE00));
+        assert_eq!(Some(VariationGlyph::Glyph(42)), st.lookup_variation(0x4E08, 0xFE00));
+        assert_eq!(None, st.lookup_variation(0x4E08, 0xFE01));
+    }
+
+    #[test]
+    fn format14_offset_out_of_range() {
+        assert!(SubTable::from_bytes(&format14(0x1000)).is_err());
+    }
+
+    /// A cmap with (platformID, encodingID, subtable) records, the subtables follow the records in order
+    fn cmap(records: &[(u16, u16, &[u8])]) -> CharacterToGlyphIndexMappingTable {
+        let mut w = Vec::new();
+        write_uint16(&mut w, 0);
+        write_uint16(&mut w, records.len() as u16);
+        let mut offset = 4 + 8 * records.len() as u32;
+        for &(platform_id, encoding_id, sub_table) in records {
+            write_uint16(&mut w, platform_id);
+            write_uint16(&mut w, encoding_id);
+            write_uint32(&mut w, offset);
+            offset += sub_table.len() as u32;
+        }
+        for &(_, _, sub_table) in records {
+            w.extend(sub_table);
+        }
+        CharacterToGlyphIndexMappingTable::from_bytes(&w).unwrap()
+    }
+
+    #[test]
+    fn reverse_map() {
+        let cmap = cmap(&[(0, 5, &format14(21)), (3, 1, &format4())]);
+        assert_eq!(vec![(0x41, 1), (0x42, 2), (0x43, 3), (0x61, 10)], cmap.mappings().collect::<Vec<_>>());
+        let m = cmap.reverse_map();
+        assert_eq!(5, m.len());
+        assert_eq!(Some(&vec![0x41]), m.get(&1));
+        assert_eq!(Some(&vec![0x61]), m.get(&10));
+        // non-default variation sequences are keyed by their base character
+        assert_eq!(Some(&vec![0x4E08]), m.get(&42));
+    }
+
+    #[test]
+    fn format0_lookup() {
+        let mut w = Vec::new();
+        for v in [0, 262, 0] {
+            write_uint16(&mut w, v);
+        }
+        let mut glyph_ids = [0u8; 256];
+        glyph_ids[0x41] = 7;
+        w.extend(glyph_ids);
+        let st = SubTable::from_bytes(&w).unwrap();
+        assert_eq!(Some(7), st.lookup(0x41));
+        assert_eq!(None, st.lookup(0x42));
+        assert_eq!(None, st.lookup(0x141));
+    }
+
+    /// Format 2 with single byte codes 'A'..='B' -> 5, 6 and the two byte codes 0x8140..=0x8141 -> 101, 102 through idDelta
+    #[test]
+    fn format2_lookup() {
+        let mut w = Vec::new();
+        for v in [2, 6 + 512 + 16 + 8, 0] {
+            write_uint16(&mut w, v);
+        }
+        for high in 0..256 {
+            write_uint16(&mut w, if 0x81 == high { 8 } else { 0 });
+        }
+        // idRangeOffset counts from the field itself, which sits 6 bytes into each 8 byte subHeader
+        for (first_code, entry_count, id_delta, id_range_offset) in [(0x41, 2, 0, 10), (0x40, 2, 100, 6)] {
+            write_uint16(&mut w, first_code);
+            write_uint16(&mut w, entry_count);
+            write_int16(&mut w, id_delta);
+            write_uint16(&mut w, id_range_offset);
+        }
+        for glyph_id in [5, 6, 1, 2] {
+            write_uint16(&mut w, glyph_id);
+        }
+
+        let st = SubTable::from_bytes(&w).unwrap();
+        assert_eq!(Some(5), st.lookup(0x41));
+        assert_eq!(Some(6), st.lookup(0x42));
+        assert_eq!(None, st.lookup(0x43));
+        // 0x81 starts a two byte code
+        assert_eq!(None, st.lookup(0x81));
+        assert_eq!(Some(101), st.lookup(0x8140));
+        assert_eq!(Some(102), st.lookup(0x8141));
+        assert_eq!(None, st.lookup(0x8142));
+        assert_eq!(None, st.lookup(0x4141));
+        assert_eq!(vec![(0x41, 5), (0x42, 6), (0x8140, 101), (0x8141, 102)], st.mappings().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn format6_lookup() {
+        let mut w = Vec::new();
+        for v in [6, 14, 0, 0x30, 2, 9, 0] {
+            write_uint16(&mut w, v);
+        }
+        let st = SubTable::from_bytes(&w).unwrap();
+        assert_eq!(Some(9), st.lookup(0x30));
+        assert_eq!(None, st.lookup(0x31));
+        assert_eq!(None, st.lookup(0x32));
+        assert_eq!(None, st.lookup(0x2F));
+    }
 }
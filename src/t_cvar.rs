@@ -0,0 +1,77 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::t_gvar::*;
+use super::types::*;
+use super::utils::*;
+
+/// Variations of the control values in cvt, tuples of single deltas, one per cvt entry
+pub struct ControlValueVariationsTable {
+    /// Major version number of the CVT variations table — set to 1
+    major_version: u16,
+    /// Minor version number of the CVT variations table — set to 0
+    minor_version: u16,
+    tuple_variations: Vec<TupleVariation>,
+    /// Number of FWORD entries in cvt, the length of tuples that apply to all entries
+    cvt_count: usize,
+}
+
+impl Debug for ControlValueVariationsTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ControlValueVariationsTable")
+            .field("major_version", &self.major_version)
+            .field("minor_version", &self.minor_version)
+            .field("tuple_variations", &self.tuple_variations)
+            .field("cvt_count", &self.cvt_count)
+            .finish()
+    }
+}
+
+impl ControlValueVariationsTable {
+    /// `axis_count` comes from fvar, `cvt_count` from the length of cvt
+    pub fn from_bytes(mut b: &[u8], axis_count: u16, cvt_count: usize) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let major_version = read_uint16(r)?;
+        let minor_version = read_uint16(r)?;
+        if 1 != major_version {
+            return Err(format!("unsupported cvar version {}.{}", major_version, minor_version).into());
+        }
+        // cvar has no shared tuples and counts its data offset from the start of the table
+        let tuple_variations = read_tuple_variations(o, r, axis_count, &[], cvt_count, 1)?;
+
+        Ok(Self {
+            major_version,
+            minor_version,
+            tuple_variations,
+            cvt_count,
+        })
+    }
+
+    pub fn tuple_variations(&self) -> &[TupleVariation] {
+        &self.tuple_variations
+    }
+
+    /// Accumulated delta of each cvt entry at normalized `coords`, untouched entries don't move
+    pub fn deltas(&self, coords: &[F2Dot14]) -> Vec<f32> {
+        let mut deltas = vec![0.0; self.cvt_count];
+        for tv in &self.tuple_variations {
+            let scalar = tv.scalar(coords);
+            if 0.0 == scalar {
+                continue;
+            }
+            match tv.point_numbers() {
+                None => deltas.iter_mut().zip(tv.x_deltas()).for_each(|(d, &x)| *d += x as f32 * scalar),
+                Some(point_numbers) => {
+                    for (&p, &x) in point_numbers.iter().zip(tv.x_deltas()) {
+                        if let Some(d) = deltas.get_mut(p as usize) {
+                            *d += x as f32 * scalar;
+                        }
+                    }
+                }
+            }
+        }
+        deltas
+    }
+}
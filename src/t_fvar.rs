@@ -0,0 +1,229 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::types::*;
+use super::utils::*;
+
+/// Axis flag, the axis should not be exposed directly in user interfaces
+const HIDDEN_AXIS: u16 = 0x0001;
+
+/// Variation axes and named instances of a variable font
+pub struct FontVariationsTable {
+    /// Major version number of the font variations table — set to 1
+    major_version: u16,
+    /// Minor version number of the font variations table — set to 0
+    minor_version: u16,
+    axes: Vec<VariationAxisRecord>,
+    instances: Vec<InstanceRecord>,
+}
+
+impl Debug for FontVariationsTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FontVariationsTable")
+            .field("major_version", &self.major_version)
+            .field("minor_version", &self.minor_version)
+            .field("axes", &self.axes)
+            .field("instances", &self.instances)
+            .finish()
+    }
+}
+
+impl FontVariationsTable {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let major_version = read_uint16(r)?;
+        let minor_version = read_uint16(r)?;
+        if 1 != major_version {
+            return Err(format!("unsupported fvar version {}.{}", major_version, minor_version).into());
+        }
+        let axes_array_offset = read_offset16(r)?;
+        let _reserved = read_uint16(r)?;
+        let axis_count = read_uint16(r)?;
+        let axis_size = read_uint16(r)?;
+        let instance_count = read_uint16(r)?;
+        let instance_size = read_uint16(r)?;
+
+        // record sizes allow for future growth, records are read at their stride and trailing fields ignored
+        if axis_size < 20 {
+            return Err(format!("fvar axisSize {} is too small", axis_size).into());
+        }
+        let coords_size = axis_count as usize * 4;
+        if (instance_size as usize) < coords_size + 4 {
+            return Err(format!("fvar instanceSize {} is too small for {} axes", instance_size, axis_count).into());
+        }
+
+        let mut start = axes_array_offset as usize;
+        let mut axes = Vec::with_capacity(axis_count as usize);
+        for _ in 0..axis_count {
+            axes.push(VariationAxisRecord::from_bytes(&mut o.get(start..).ok_or("fvar axis record out of range")?)?);
+            start += axis_size as usize;
+        }
+
+        let has_post_script_name_id = instance_size as usize >= coords_size + 6;
+        let mut instances = Vec::with_capacity(instance_count as usize);
+        for _ in 0..instance_count {
+            let s = &mut o.get(start..).ok_or("fvar instance record out of range")?;
+            instances.push(InstanceRecord::from_bytes(s, axis_count, has_post_script_name_id)?);
+            start += instance_size as usize;
+        }
+
+        Ok(Self {
+            major_version,
+            minor_version,
+            axes,
+            instances,
+        })
+    }
+
+    pub fn axes(&self) -> &[VariationAxisRecord] {
+        &self.axes
+    }
+
+    pub fn instances(&self) -> &[InstanceRecord] {
+        &self.instances
+    }
+
+    /// Normalized coordinate of a user value on axis `axis_index`, in -1.0..=1.0 at 16.16 precision and before any avar mapping
+    pub fn normalize(&self, axis_index: usize, value: f32) -> Option<F2Dot14> {
+        let axis = self.axes.get(axis_index)?;
+        // computed in 16.16 as the spec does, quotients rounded to nearest before the sign is applied
+        let to_fixed = |v: f32| (v as f64 * 65536.0).round() as i64;
+        let div = |a: i64, b: i64| if 0 == b { 0 } else { ((a << 16) + b / 2) / b };
+        let (min, default) = (to_fixed(axis.min_value), to_fixed(axis.default_value));
+        let max = to_fixed(axis.max_value).max(min);
+        let value = to_fixed(value).clamp(min, max);
+        let n = match value.cmp(&default) {
+            Ordering::Less => -div(default - value, default - min),
+            Ordering::Greater => div(value - default, max - default),
+            Ordering::Equal => 0,
+        };
+        let n = n.clamp(-0x10000, 0x10000) as f32 / 65536.0;
+        Some(n)
+    }
+}
+
+pub struct VariationAxisRecord {
+    /// Tag identifying the design variation for the axis
+    axis_tag: Tag,
+    /// The minimum coordinate value for the axis
+    min_value: Fixed,
+    /// The default coordinate value for the axis
+    default_value: Fixed,
+    /// The maximum coordinate value for the axis
+    max_value: Fixed,
+    /// Axis qualifiers
+    flags: u16,
+    /// The name ID for entries in the 'name' table that provide a display name for this axis
+    axis_name_id: u16,
+}
+
+impl Debug for VariationAxisRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VariationAxisRecord")
+            .field("axis_tag", &self.axis_tag)
+            .field("min_value", &self.min_value)
+            .field("default_value", &self.default_value)
+            .field("max_value", &self.max_value)
+            .field_with("flags", |f| write!(f, "0x{:04x}", &self.flags))
+            .field("axis_name_id", &self.axis_name_id)
+            .finish()
+    }
+}
+
+impl VariationAxisRecord {
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let axis_tag = read_tag(r)?;
+        let min_value = read_fixed(r)?;
+        let default_value = read_fixed(r)?;
+        let max_value = read_fixed(r)?;
+        let flags = read_uint16(r)?;
+        let axis_name_id = read_uint16(r)?;
+
+        Ok(Self {
+            axis_tag,
+            min_value,
+            default_value,
+            max_value,
+            flags,
+            axis_name_id,
+        })
+    }
+
+    pub fn axis_tag(&self) -> Tag {
+        self.axis_tag
+    }
+
+    pub fn min_value(&self) -> Fixed {
+        self.min_value
+    }
+
+    pub fn default_value(&self) -> Fixed {
+        self.default_value
+    }
+
+    pub fn max_value(&self) -> Fixed {
+        self.max_value
+    }
+
+    pub fn axis_name_id(&self) -> u16 {
+        self.axis_name_id
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        0 != self.flags & HIDDEN_AXIS
+    }
+}
+
+pub struct InstanceRecord {
+    /// The name ID for entries in the 'name' table that provide subfamily names for this instance
+    subfamily_name_id: u16,
+    /// Reserved for future use — set to 0
+    flags: u16,
+    /// The user coordinates of the instance, one per axis
+    coordinates: Vec<Fixed>,
+    /// The name ID for entries in the 'name' table that provide PostScript names for this instance, only present when instanceSize allows it
+    post_script_name_id: Option<u16>,
+}
+
+impl Debug for InstanceRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InstanceRecord")
+            .field("subfamily_name_id", &self.subfamily_name_id)
+            .field("flags", &self.flags)
+            .field("coordinates", &self.coordinates)
+            .field("post_script_name_id", &self.post_script_name_id)
+            .finish()
+    }
+}
+
+impl InstanceRecord {
+    pub fn from_bytes(r: &mut &[u8], axis_count: u16, has_post_script_name_id: bool) -> Result<Self, Box<dyn Error>> {
+        let subfamily_name_id = read_uint16(r)?;
+        let flags = read_uint16(r)?;
+        let coordinates = read_array(r, axis_count as usize, read_fixed)?;
+        // 0xFFFF means the instance has no PostScript name
+        let post_script_name_id = if has_post_script_name_id { Some(read_uint16(r)?).filter(|&id| 0xFFFF != id) } else { None };
+
+        Ok(Self {
+            subfamily_name_id,
+            flags,
+            coordinates,
+            post_script_name_id,
+        })
+    }
+
+    pub fn subfamily_name_id(&self) -> u16 {
+        self.subfamily_name_id
+    }
+
+    pub fn coordinates(&self) -> &[Fixed] {
+        &self.coordinates
+    }
+
+    pub fn post_script_name_id(&self) -> Option<u16> {
+        self.post_script_name_id
+    }
+}
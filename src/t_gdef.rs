@@ -0,0 +1,419 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::layout::*;
+use super::types::*;
+use super::utils::*;
+use super::variations::*;
+
+/// Glyph classes of the glyph class definition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlyphClass {
+    /// Single character, spacing glyph
+    Base,
+    /// Multiple character, spacing glyph
+    Ligature,
+    /// Non-spacing combining glyph
+    Mark,
+    /// Part of single character, spacing glyph
+    Component,
+}
+
+impl GlyphClass {
+    fn from_class(class: u16) -> Option<Self> {
+        match class {
+            1 => Some(Self::Base),
+            2 => Some(Self::Ligature),
+            3 => Some(Self::Mark),
+            4 => Some(Self::Component),
+            _ => None,
+        }
+    }
+}
+
+pub struct GlyphDefinitionTable {
+    /// Major version of the GDEF table, = 1
+    major_version: u16,
+    /// Minor version of the GDEF table, = 0, 2 or 3
+    minor_version: u16,
+    glyph_class_def: Option<ClassDef>,
+    attach_list: Option<AttachList>,
+    lig_caret_list: Option<LigCaretList>,
+    mark_attach_class_def: Option<ClassDef>,
+    /// Version 1.2 and later, one coverage per mark glyph set
+    mark_glyph_sets: Vec<Coverage>,
+    /// Version 1.3 only, deltas for the VariationIndex tables of GDEF and GPOS
+    item_var_store: Option<ItemVariationStore>,
+}
+
+impl Debug for GlyphDefinitionTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GlyphDefinitionTable")
+            .field("major_version", &self.major_version)
+            .field("minor_version", &self.minor_version)
+            .field("glyph_class_def", &self.glyph_class_def)
+            .field("attach_list", &self.attach_list)
+            .field("lig_caret_list", &self.lig_caret_list)
+            .field("mark_attach_class_def", &self.mark_attach_class_def)
+            .field("mark_glyph_sets", &self.mark_glyph_sets)
+            .field("item_var_store", &self.item_var_store)
+            .finish()
+    }
+}
+
+impl GlyphDefinitionTable {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let major_version = read_uint16(r)?;
+        let minor_version = read_uint16(r)?;
+        if 1 != major_version {
+            return Err(format!("unsupported GDEF version {}.{}", major_version, minor_version).into());
+        }
+
+        let glyph_class_def_offset = read_offset16(r)?;
+        let attach_list_offset = read_offset16(r)?;
+        let lig_caret_list_offset = read_offset16(r)?;
+        let mark_attach_class_def_offset = read_offset16(r)?;
+        let mark_glyph_sets_def_offset = if 2 <= minor_version { read_offset16(r)? } else { 0 };
+        let item_var_store_offset = if 3 <= minor_version { read_offset32(r)? } else { 0 };
+
+        let class_def = |offset: Offset16| match offset {
+            0 => Ok(None),
+            offset => ClassDef::at(o, offset).map(Some),
+        };
+        let glyph_class_def = class_def(glyph_class_def_offset)?;
+        let mark_attach_class_def = class_def(mark_attach_class_def_offset)?;
+        let attach_list = match attach_list_offset {
+            0 => None,
+            offset => Some(AttachList::from_bytes(o.get(offset as usize..).ok_or("GDEF AttachList offset out of range")?)?),
+        };
+        let lig_caret_list = match lig_caret_list_offset {
+            0 => None,
+            offset => Some(LigCaretList::from_bytes(o.get(offset as usize..).ok_or("GDEF LigCaretList offset out of range")?)?),
+        };
+
+        let mut mark_glyph_sets = Vec::new();
+        if 0 != mark_glyph_sets_def_offset {
+            let mut s = o.get(mark_glyph_sets_def_offset as usize..).ok_or("GDEF MarkGlyphSetsDef offset out of range")?;
+            let m = s;
+            let format = read_uint16(&mut s)?;
+            if 1 != format {
+                return Err(format!("unknown mark glyph sets format {}", format).into());
+            }
+            let mark_glyph_set_count = read_uint16(&mut s)?;
+            for offset in read_array(&mut s, mark_glyph_set_count as usize, read_offset32)? {
+                mark_glyph_sets.push(Coverage::from_bytes(m.get(offset as usize..).ok_or("mark glyph set offset out of range")?)?);
+            }
+        }
+
+        let item_var_store = match item_var_store_offset {
+            0 => None,
+            offset => Some(ItemVariationStore::from_bytes(o.get(offset as usize..).ok_or("GDEF ItemVariationStore offset out of range")?)?),
+        };
+
+        Ok(Self {
+            major_version,
+            minor_version,
+            glyph_class_def,
+            attach_list,
+            lig_caret_list,
+            mark_attach_class_def,
+            mark_glyph_sets,
+            item_var_store,
+        })
+    }
+
+    /// Class of a glyph, `None` when the font leaves it unclassified
+    pub fn glyph_class(&self, glyph_id: GlyphId) -> Option<GlyphClass> {
+        GlyphClass::from_class(self.glyph_class_def.as_ref()?.class(glyph_id))
+    }
+
+    /// Mark attachment class of a glyph, 0 when it has none
+    pub fn mark_attach_class(&self, glyph_id: GlyphId) -> u16 {
+        self.mark_attach_class_def.as_ref().map_or(0, |cd| cd.class(glyph_id))
+    }
+
+    pub fn mark_glyph_sets(&self) -> &[Coverage] {
+        &self.mark_glyph_sets
+    }
+
+    /// Whether `glyph_id` is in the mark glyph set `set_index`
+    pub fn is_in_mark_glyph_set(&self, set_index: u16, glyph_id: GlyphId) -> bool {
+        self.mark_glyph_sets.get(set_index as usize).is_some_and(|c| c.contains(glyph_id))
+    }
+
+    /// Contour points of a glyph used as attachment points
+    pub fn attach_points(&self, glyph_id: GlyphId) -> Option<&[u16]> {
+        self.attach_list.as_ref()?.attach_points(glyph_id)
+    }
+
+    /// Caret positions between the components of a ligature, in writing direction
+    pub fn ligature_carets(&self, glyph_id: GlyphId) -> Option<&[CaretValue]> {
+        self.lig_caret_list.as_ref()?.carets(glyph_id)
+    }
+
+    pub fn item_var_store(&self) -> Option<&ItemVariationStore> {
+        self.item_var_store.as_ref()
+    }
+
+    /// The GDEF bytes `b` of this table with the caret deltas at normalized `coords` folded in and the ItemVariationStore dropped
+    pub fn instance(&self, b: &[u8], coords: &[F2Dot14]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut v = b.to_vec();
+        if self.minor_version < 3 {
+            return Ok(v);
+        }
+
+        let mut folder = VariationIndexFolder::new(self.item_var_store.as_ref(), coords);
+        let lig_caret_list = get_uint16(&v, 8)? as usize;
+        if 0 != lig_caret_list {
+            for k in 0..get_uint16(&v, lig_caret_list + 2)? as usize {
+                let lig_glyph = lig_caret_list + get_uint16(&v, lig_caret_list + 4 + 2 * k)? as usize;
+                if !folder.first_visit(lig_glyph) {
+                    continue;
+                }
+                for m in 0..get_uint16(&v, lig_glyph)? as usize {
+                    let caret_value = lig_glyph + get_uint16(&v, lig_glyph + 2 + 2 * m)? as usize;
+                    if folder.first_visit(caret_value) && 3 == get_uint16(&v, caret_value)? {
+                        folder.fold(&mut v, caret_value, caret_value + 4, Some(caret_value + 2))?;
+                    }
+                }
+            }
+        }
+        put_uint32(&mut v, 14, 0)?;
+        Ok(v)
+    }
+
+    /// Whether a lookup with `lookup_flag` and `mark_filtering_set` passes over the glyph rather than acting on it
+    pub fn skips(&self, lookup_flag: u16, mark_filtering_set: Option<u16>, glyph_id: GlyphId) -> bool {
+        match self.glyph_class(glyph_id) {
+            Some(GlyphClass::Base) => 0 != lookup_flag & IGNORE_BASE_GLYPHS,
+            Some(GlyphClass::Ligature) => 0 != lookup_flag & IGNORE_LIGATURES,
+            Some(GlyphClass::Mark) => {
+                if 0 != lookup_flag & IGNORE_MARKS {
+                    return true;
+                }
+                if let Some(set_index) = mark_filtering_set {
+                    return !self.is_in_mark_glyph_set(set_index, glyph_id);
+                }
+                let mark_attachment_type = (lookup_flag & MARK_ATTACHMENT_CLASS_FILTER) >> 8;
+                0 != mark_attachment_type && mark_attachment_type != self.mark_attach_class(glyph_id)
+            }
+            Some(GlyphClass::Component) | None => false,
+        }
+    }
+}
+
+/// Contour points of glyphs used as attachment points, for hinted rendering
+pub struct AttachList {
+    coverage: Coverage,
+    /// Point indices of each covered glyph, in increasing order
+    attach_points: Vec<Vec<u16>>,
+}
+
+impl Debug for AttachList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AttachList").field("coverage", &self.coverage).field("attach_points", &self.attach_points).finish()
+    }
+}
+
+impl AttachList {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let coverage = Coverage::at(o, read_offset16(r)?)?;
+        let glyph_count = read_uint16(r)?;
+        let mut attach_points = Vec::with_capacity(glyph_count as usize);
+        for offset in read_array(r, glyph_count as usize, read_offset16)? {
+            let mut s = o.get(offset as usize..).ok_or("AttachPoint offset out of range")?;
+            let point_count = read_uint16(&mut s)?;
+            attach_points.push(read_array(&mut s, point_count as usize, read_uint16)?);
+        }
+
+        Ok(Self { coverage, attach_points })
+    }
+
+    pub fn attach_points(&self, glyph_id: GlyphId) -> Option<&[u16]> {
+        self.attach_points.get(self.coverage.index(glyph_id)? as usize).map(|v| v.as_slice())
+    }
+}
+
+/// Caret positions of ligature glyphs
+pub struct LigCaretList {
+    coverage: Coverage,
+    lig_glyphs: Vec<Vec<CaretValue>>,
+}
+
+impl Debug for LigCaretList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LigCaretList").field("coverage", &self.coverage).field("lig_glyphs", &self.lig_glyphs).finish()
+    }
+}
+
+impl LigCaretList {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let coverage = Coverage::at(o, read_offset16(r)?)?;
+        let lig_glyph_count = read_uint16(r)?;
+        let mut lig_glyphs = Vec::with_capacity(lig_glyph_count as usize);
+        for offset in read_array(r, lig_glyph_count as usize, read_offset16)? {
+            let mut s = o.get(offset as usize..).ok_or("LigGlyph offset out of range")?;
+            let l = s;
+            let caret_count = read_uint16(&mut s)?;
+            let mut carets = Vec::with_capacity(caret_count as usize);
+            for caret_offset in read_array(&mut s, caret_count as usize, read_offset16)? {
+                carets.push(CaretValue::from_bytes(l.get(caret_offset as usize..).ok_or("CaretValue offset out of range")?)?);
+            }
+            lig_glyphs.push(carets);
+        }
+
+        Ok(Self { coverage, lig_glyphs })
+    }
+
+    pub fn carets(&self, glyph_id: GlyphId) -> Option<&[CaretValue]> {
+        self.lig_glyphs.get(self.coverage.index(glyph_id)? as usize).map(|v| v.as_slice())
+    }
+}
+
+/// A caret position inside a ligature
+pub enum CaretValue {
+    /// X or Y value, in design units
+    Coordinate(i16),
+    /// Contour point index on glyph
+    ContourPoint(u16),
+    /// X or Y value adjusted by a Device or VariationIndex table
+    DeviceCoordinate { coordinate: i16, device: Option<Device> },
+}
+
+impl Debug for CaretValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Coordinate(coordinate) => f.debug_tuple("Coordinate").field(coordinate).finish(),
+            Self::ContourPoint(point) => f.debug_tuple("ContourPoint").field(point).finish(),
+            Self::DeviceCoordinate { coordinate, device } => f.debug_struct("DeviceCoordinate").field("coordinate", coordinate).field("device", device).finish(),
+        }
+    }
+}
+
+impl CaretValue {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        match format {
+            1 => Ok(Self::Coordinate(read_int16(r)?)),
+            2 => Ok(Self::ContourPoint(read_uint16(r)?)),
+            3 => {
+                let coordinate = read_int16(r)?;
+                let device = Device::at(o, read_offset16(r)?)?;
+                Ok(Self::DeviceCoordinate { coordinate, device })
+            }
+            _ => Err(format!("unknown caret value format {}", format).into()),
+        }
+    }
+
+    /// Caret coordinate at normalized `coords`, `None` for a contour point which needs the glyph outline
+    pub fn coordinate(&self, var_store: Option<&ItemVariationStore>, coords: &[F2Dot14]) -> Option<f32> {
+        match self {
+            Self::Coordinate(coordinate) => Some(*coordinate as f32),
+            Self::ContourPoint(_) => None,
+            Self::DeviceCoordinate { coordinate, device } => Some(*coordinate as f32 + device.as_ref().map_or(0.0, |d| d.variation_delta(var_store, coords))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::builders::*;
+
+    /// GDEF 1.3 with glyph 1 a base, 2 and 3 marks of attachment classes 1 and 2, 3 alone in mark glyph set 0,
+    /// and ligature 10 with carets of formats 1, 2 and 3, the last one moving by 50 units at the max of the single axis
+    fn gdef() -> Vec<u8> {
+        let glyph_class_def = class_def(&[(1, 1, 1), (2, 3, 3), (10, 10, 2)]);
+        let mark_attach_class_def = class_def(&[(2, 2, 1), (3, 3, 2)]);
+        let mut mark_glyph_sets = Vec::new();
+        [1, 1].iter().for_each(|&v| write_uint16(&mut mark_glyph_sets, v));
+        write_uint32(&mut mark_glyph_sets, 8);
+        mark_glyph_sets.extend(coverage(&[3]));
+        let mut lig_caret_list = Vec::new();
+        [6, 1, 12].iter().for_each(|&v| write_uint16(&mut lig_caret_list, v));
+        lig_caret_list.extend(coverage(&[10]));
+        // LigGlyph, then a caret of each format, the last one pointing at a VariationIndex table
+        [3, 8, 12, 16, 1, 300, 2, 5, 3, 600, 6, 0, 0, 0x8000].iter().for_each(|&v| write_uint16(&mut lig_caret_list, v));
+        let mut item_var_store = Vec::new();
+        write_uint16(&mut item_var_store, 1);
+        write_uint32(&mut item_var_store, 12);
+        write_uint16(&mut item_var_store, 1);
+        write_uint32(&mut item_var_store, 22);
+        [1, 1].iter().for_each(|&v| write_uint16(&mut item_var_store, v));
+        [0.0, 1.0, 1.0].iter().for_each(|&v| write_f2dot14(&mut item_var_store, v));
+        [1, 0, 1, 0].iter().for_each(|&v| write_uint16(&mut item_var_store, v));
+        write_int8(&mut item_var_store, 50);
+
+        let mut w = Vec::new();
+        [1, 3].iter().for_each(|&v| write_uint16(&mut w, v));
+        let tables = [glyph_class_def, vec![], lig_caret_list, mark_attach_class_def, mark_glyph_sets, item_var_store];
+        let mut offset = 18;
+        for (k, table) in tables.iter().enumerate() {
+            let table_offset = if table.is_empty() { 0 } else { offset };
+            if 5 == k { write_uint32(&mut w, table_offset as u32) } else { write_uint16(&mut w, table_offset as u16) }
+            offset += table.len();
+        }
+        tables.iter().for_each(|t| w.extend(t));
+        w
+    }
+
+    #[test]
+    fn glyph_classes() {
+        let gdef = GlyphDefinitionTable::from_bytes(&gdef()).unwrap();
+        assert_eq!(vec![None, Some(GlyphClass::Base), Some(GlyphClass::Mark), Some(GlyphClass::Mark)], (0..4).map(|g| gdef.glyph_class(g)).collect::<Vec<_>>());
+        assert_eq!(Some(GlyphClass::Ligature), gdef.glyph_class(10));
+        assert_eq!((0, 1, 2), (gdef.mark_attach_class(1), gdef.mark_attach_class(2), gdef.mark_attach_class(3)));
+        assert!(gdef.is_in_mark_glyph_set(0, 3) && !gdef.is_in_mark_glyph_set(0, 2) && !gdef.is_in_mark_glyph_set(1, 3));
+        assert_eq!(None, gdef.attach_points(1));
+    }
+
+    #[test]
+    fn lookup_flag_skips() {
+        let gdef = GlyphDefinitionTable::from_bytes(&gdef()).unwrap();
+        let skipped = |lookup_flag: u16, mark_filtering_set: Option<u16>| [1, 2, 3, 7, 10].into_iter().filter(|&g| gdef.skips(lookup_flag, mark_filtering_set, g)).collect::<Vec<_>>();
+        assert_eq!(skipped(0, None), []);
+        assert_eq!(skipped(IGNORE_BASE_GLYPHS, None), [1]);
+        assert_eq!(skipped(IGNORE_LIGATURES, None), [10]);
+        assert_eq!(skipped(IGNORE_MARKS, None), [2, 3]);
+        // marks of another attachment class are skipped
+        assert_eq!(skipped(1 << 8, None), [3]);
+        // so are marks outside the filtering set, which wins over the attachment class
+        assert_eq!(skipped(USE_MARK_FILTERING_SET | 1 << 8, Some(0)), [2]);
+    }
+
+    #[test]
+    fn ligature_carets() {
+        let gdef = GlyphDefinitionTable::from_bytes(&gdef()).unwrap();
+        let carets = gdef.ligature_carets(10).unwrap();
+        assert_eq!(3, carets.len());
+        assert!(matches!(carets[1], CaretValue::ContourPoint(5)));
+        assert_eq!(vec![Some(300.0), None, Some(600.0)], carets.iter().map(|c| c.coordinate(gdef.item_var_store(), &[])).collect::<Vec<_>>());
+        assert_eq!(Some(625.0), carets[2].coordinate(gdef.item_var_store(), &[0.5]));
+        assert!(gdef.ligature_carets(1).is_none());
+    }
+
+    #[test]
+    fn instance_folds_caret_deltas() {
+        let b = gdef();
+        let gdef = GlyphDefinitionTable::from_bytes(&b).unwrap();
+        let instance = gdef.instance(&b, &[0.5]).unwrap();
+        assert_eq!(0, get_uint32(&instance, 14).unwrap());
+        let instance = GlyphDefinitionTable::from_bytes(&instance).unwrap();
+        assert!(instance.item_var_store().is_none());
+        let carets = instance.ligature_carets(10).unwrap();
+        assert_eq!(vec![Some(300.0), None, Some(625.0)], carets.iter().map(|c| c.coordinate(None, &[])).collect::<Vec<_>>());
+        assert_eq!(Some(625.0), carets[2].coordinate(None, &[1.0]));
+    }
+}
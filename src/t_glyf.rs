@@ -0,0 +1,865 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::t_gvar::*;
+use super::t_loca::*;
+use super::types::*;
+use super::utils::*;
+
+/// Composite glyphs nest at most this deep
+const MAX_COMPONENT_DEPTH: usize = 32;
+
+pub struct GlyphDataTable {
+    /// Raw glyph data, glyphs are decoded on demand through the offsets in loca
+    data: Vec<u8>,
+}
+
+impl Debug for GlyphDataTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GlyphDataTable").field_with("data", |f| write!(f, "[u8; {}]", self.data.len())).finish()
+    }
+}
+
+impl GlyphDataTable {
+    pub fn from_bytes(b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        Ok(Self { data: b.to_vec() })
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Decode one glyph, `None` for glyphs without outline such as the space
+    pub fn glyph(&self, loca: &IndexToLocationTable, glyph_id: GlyphId) -> Result<Option<Glyph>, Box<dyn Error>> {
+        let range = loca.glyph_range(glyph_id).ok_or_else(|| format!("glyph {} is not in loca", glyph_id))?;
+        if range.is_empty() {
+            return Ok(None);
+        }
+        let b = self.data.get(range).ok_or_else(|| format!("glyph {} lies outside glyf", glyph_id))?;
+        Ok(Some(Glyph::from_bytes(b)?))
+    }
+
+    /// Resolve a glyph, components included, into points and contour end points
+    pub fn outline_points(&self, loca: &IndexToLocationTable, glyph_id: GlyphId) -> Result<OutlinePoints, Box<dyn Error>> {
+        let mut op = OutlinePoints { points: Vec::new(), end_pts: Vec::new() };
+        self.append_points(loca, glyph_id, &mut Vec::new(), &mut op)?;
+        Ok(op)
+    }
+
+    /// `path` holds the composites being resolved, from the outermost one down
+    fn append_points(&self, loca: &IndexToLocationTable, glyph_id: GlyphId, path: &mut Vec<GlyphId>, op: &mut OutlinePoints) -> Result<(), Box<dyn Error>> {
+        enter_component(path, glyph_id)?;
+
+        match self.glyph(loca, glyph_id)? {
+            None => {}
+            Some(Glyph::Simple(sg)) => {
+                let base = op.points.len();
+                op.points.extend(sg.points.iter().map(|p| Point { x: p.x as f32, y: p.y as f32, on_curve: p.on_curve }));
+                op.end_pts.extend(sg.end_pts_of_contours.iter().map(|&e| base + e as usize));
+            }
+            Some(Glyph::Composite(cg)) => {
+                for c in &cg.components {
+                    let mut child = OutlinePoints { points: Vec::new(), end_pts: Vec::new() };
+                    self.append_points(loca, c.glyph_index, path, &mut child)?;
+                    c.apply(&op.points, &mut child.points)?;
+
+                    let base = op.points.len();
+                    op.points.extend(child.points);
+                    op.end_pts.extend(child.end_pts.iter().map(|&e| base + e));
+                }
+            }
+        }
+        path.pop();
+        Ok(())
+    }
+
+    /// Resolve a glyph at normalized `coords` into points and phantom points, with the gvar deltas of the glyph and of every component applied
+    pub fn outline_points_at(
+        &self,
+        loca: &IndexToLocationTable,
+        glyph_id: GlyphId,
+        gvar: Option<&GlyphVariationsTable>,
+        coords: &[F2Dot14],
+        metrics: &impl Fn(GlyphId) -> PhantomMetrics,
+    ) -> Result<GlyphOutline, Box<dyn Error>> {
+        let vc = VariationContext { loca, gvar, coords, metrics };
+        let mut outline = OutlinePoints { points: Vec::new(), end_pts: Vec::new() };
+        let phantom_points = self.append_points_at(&vc, glyph_id, &mut Vec::new(), &mut outline)?;
+        Ok(GlyphOutline { outline, phantom_points })
+    }
+
+    /// Bake the gvar deltas at normalized `coords` into every glyph the way a static instance stores them, points and component offsets rounded to whole units and composite bounding boxes recomputed from the instanced components
+    pub fn instance(&self, loca: &IndexToLocationTable, gvar: &GlyphVariationsTable, coords: &[F2Dot14], metrics: &impl Fn(GlyphId) -> PhantomMetrics) -> Result<GlyfInstance, Box<dyn Error>> {
+        let vc = VariationContext { loca, gvar: Some(gvar), coords, metrics };
+        let num_glyphs = loca.num_glyphs();
+        let mut data = Vec::with_capacity(self.data.len());
+        let mut offsets = Vec::with_capacity(num_glyphs + 1);
+        let mut phantom_points = Vec::with_capacity(num_glyphs);
+        let mut composites = Vec::new();
+
+        for glyph_id in 0..num_glyphs as GlyphId {
+            offsets.push(data.len() as Offset32);
+            let glyph = self.glyph(loca, glyph_id)?;
+            let unvaried = self::phantom_points(glyph.as_ref().map_or((0, 0, 0, 0), |g| g.header().bbox()), metrics(glyph_id));
+
+            // a simple glyph varies its points, a composite the offset of each component, both followed by the four phantom points
+            let mut points: Vec<_> = match &glyph {
+                None => Vec::new(),
+                Some(Glyph::Simple(sg)) => sg.points.iter().map(|p| Point { x: p.x as f32, y: p.y as f32, on_curve: p.on_curve }).collect(),
+                Some(Glyph::Composite(cg)) => cg.components.iter().map(|c| c.offset()).collect(),
+            };
+            points.extend(unvaried);
+            let end_pts: Vec<_> = match &glyph {
+                Some(Glyph::Simple(sg)) => sg.end_pts_of_contours.iter().map(|&e| e as usize).collect(),
+                _ => Vec::new(),
+            };
+            vc.apply_deltas(glyph_id, &mut points, &end_pts)?;
+            for p in points.iter_mut() {
+                (p.x, p.y) = (ot_round(p.x) as f32, ot_round(p.y) as f32);
+            }
+
+            let pp = points.split_off(points.len() - 4);
+            phantom_points.push(pp.try_into().unwrap_or(unvaried));
+            match &glyph {
+                None => {}
+                Some(Glyph::Simple(sg)) => sg.encode(&points, &mut data),
+                Some(Glyph::Composite(cg)) => {
+                    cg.encode(&points, &mut data);
+                    composites.push(glyph_id);
+                }
+            }
+            data.resize(data.len().next_multiple_of(4), 0);
+        }
+        offsets.push(data.len() as Offset32);
+
+        let loca = IndexToLocationTable::from_offsets(offsets);
+        let mut glyf = Self { data };
+        for glyph_id in composites {
+            let (x_min, y_min, x_max, y_max) = bounds(&glyf.outline_points(&loca, glyph_id)?.points);
+            let start = loca.glyph_range(glyph_id).map_or(0, |r| r.start);
+            put_int16(&mut glyf.data, start + 2, x_min)?;
+            put_int16(&mut glyf.data, start + 4, y_min)?;
+            put_int16(&mut glyf.data, start + 6, x_max)?;
+            put_int16(&mut glyf.data, start + 8, y_max)?;
+        }
+
+        Ok(GlyfInstance { glyf, loca, phantom_points })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the phantom points of the glyph, which a composite takes from the component that sets USE_MY_METRICS
+    fn append_points_at<F: Fn(GlyphId) -> PhantomMetrics>(&self, vc: &VariationContext<F>, glyph_id: GlyphId, path: &mut Vec<GlyphId>, op: &mut OutlinePoints) -> Result<[Point; 4], Box<dyn Error>> {
+        enter_component(path, glyph_id)?;
+
+        let glyph = self.glyph(vc.loca, glyph_id)?;
+        let phantom_points = phantom_points(glyph.as_ref().map_or((0, 0, 0, 0), |g| g.header().bbox()), (vc.metrics)(glyph_id));
+
+        let result = match glyph {
+            None => {
+                let mut phantom_points = phantom_points;
+                vc.apply_deltas(glyph_id, &mut phantom_points, &[])?;
+                Ok(phantom_points)
+            }
+            Some(Glyph::Simple(sg)) => {
+                let mut points: Vec<_> = sg.points.iter().map(|p| Point { x: p.x as f32, y: p.y as f32, on_curve: p.on_curve }).collect();
+                let end_pts: Vec<_> = sg.end_pts_of_contours.iter().map(|&e| e as usize).collect();
+                points.extend(phantom_points);
+                vc.apply_deltas(glyph_id, &mut points, &end_pts)?;
+
+                let phantom_points = points.split_off(points.len() - 4);
+                let base = op.points.len();
+                op.points.extend(points);
+                op.end_pts.extend(end_pts.iter().map(|&e| base + e));
+                Ok(phantom_points.try_into().unwrap_or_default())
+            }
+            Some(Glyph::Composite(cg)) => {
+                // a composite varies the offset of each component, one point per component
+                let mut points: Vec<_> = cg.components.iter().map(|c| c.offset()).collect();
+                points.extend(phantom_points);
+                vc.apply_deltas(glyph_id, &mut points, &[])?;
+
+                let mut phantom_points = points[points.len() - 4..].try_into().unwrap_or(phantom_points);
+                for (c, offset) in cg.components.iter().zip(&points) {
+                    let mut child = OutlinePoints { points: Vec::new(), end_pts: Vec::new() };
+                    let child_phantom_points = self.append_points_at(vc, c.glyph_index, path, &mut child)?;
+                    // offsets stay whole font units, the deltas round half up as in FreeType
+                    let unvaried = c.offset();
+                    let offset_delta = ((offset.x - unvaried.x + 0.5).floor(), (offset.y - unvaried.y + 0.5).floor());
+                    c.apply_at(&op.points, &mut child.points, offset_delta)?;
+                    if c.use_my_metrics() {
+                        phantom_points = child_phantom_points;
+                    }
+
+                    let base = op.points.len();
+                    op.points.extend(child.points);
+                    op.end_pts.extend(child.end_pts.iter().map(|&e| base + e));
+                }
+                Ok(phantom_points)
+            }
+        };
+        path.pop();
+        result
+    }
+}
+
+/// Push a composite onto the resolution `path`, failing when it is already on it or nesting gets too deep
+fn enter_component(path: &mut Vec<GlyphId>, glyph_id: GlyphId) -> Result<(), Box<dyn Error>> {
+    if path.contains(&glyph_id) {
+        return Err(format!("composite glyph {} references itself", glyph_id).into());
+    }
+    if path.len() > MAX_COMPONENT_DEPTH {
+        return Err(format!("composite glyph nesting too deep at glyph {}", glyph_id).into());
+    }
+    path.push(glyph_id);
+    Ok(())
+}
+
+struct VariationContext<'a, F> {
+    loca: &'a IndexToLocationTable,
+    gvar: Option<&'a GlyphVariationsTable>,
+    coords: &'a [F2Dot14],
+    metrics: &'a F,
+}
+
+impl<F> VariationContext<'_, F> {
+    fn apply_deltas(&self, glyph_id: GlyphId, points: &mut [Point], end_pts: &[usize]) -> Result<(), Box<dyn Error>> {
+        match self.gvar {
+            Some(gvar) => gvar.apply_deltas(glyph_id, self.coords, points, end_pts),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Integer bounding box (x_min, y_min, x_max, y_max) of points, all zero without points
+fn bounds(points: &[Point]) -> (i16, i16, i16, i16) {
+    if points.is_empty() {
+        return (0, 0, 0, 0);
+    }
+    let (mut x_min, mut y_min, mut x_max, mut y_max) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+    for p in points {
+        (x_min, y_min, x_max, y_max) = (x_min.min(p.x), y_min.min(p.y), x_max.max(p.x), y_max.max(p.y));
+    }
+    let round = |v: f32| ot_round(v).clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+    (round(x_min), round(y_min), round(x_max), round(y_max))
+}
+
+/// Horizontal phantom points sit on the baseline at the left and right of the advance, vertical ones on the top and bottom of the vertical advance
+fn phantom_points(bbox: (i16, i16, i16, i16), metrics: PhantomMetrics) -> [Point; 4] {
+    let left = bbox.0 as f32 - metrics.left_side_bearing as f32;
+    let top = bbox.3 as f32 + metrics.top_side_bearing as f32;
+    [
+        Point { x: left, y: 0.0, on_curve: true },
+        Point { x: left + metrics.advance_width as f32, y: 0.0, on_curve: true },
+        Point { x: 0.0, y: top, on_curve: true },
+        Point { x: 0.0, y: top - metrics.advance_height as f32, on_curve: true },
+    ]
+}
+
+/// Unvaried metrics of a glyph in font design units, they place its phantom points
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PhantomMetrics {
+    pub advance_width: u16,
+    pub left_side_bearing: i16,
+    pub advance_height: u16,
+    pub top_side_bearing: i16,
+}
+
+/// A resolved glyph with its phantom points: the left and right of the horizontal advance, then the top and bottom of the vertical one
+pub struct GlyphOutline {
+    pub outline: OutlinePoints,
+    pub phantom_points: [Point; 4],
+}
+
+impl GlyphOutline {
+    pub fn advance_width(&self) -> f32 {
+        self.phantom_points[1].x - self.phantom_points[0].x
+    }
+
+    pub fn advance_height(&self) -> f32 {
+        self.phantom_points[2].y - self.phantom_points[3].y
+    }
+}
+
+/// The glyf and loca of a static instance, with the varied phantom points of each glyph for the metrics tables
+pub struct GlyfInstance {
+    pub glyf: GlyphDataTable,
+    pub loca: IndexToLocationTable,
+    pub phantom_points: Vec<[Point; 4]>,
+}
+
+/// Glyph points in font design units, with the index of the last point of each contour
+pub struct OutlinePoints {
+    pub points: Vec<Point>,
+    pub end_pts: Vec<usize>,
+}
+
+impl OutlinePoints {
+    /// Emit the contours as quadratic curves, off-curve points in a row get their implied on-curve midpoint
+    pub fn emit(&self, sink: &mut impl OutlineSink) {
+        let mut start = 0;
+        for &end in &self.end_pts {
+            if end < start || end >= self.points.len() {
+                break;
+            }
+            emit_contour(&self.points[start..=end], sink);
+            start = end + 1;
+        }
+    }
+}
+
+fn emit_contour(pts: &[Point], sink: &mut impl OutlineSink) {
+    let first = pts[0];
+    let last = pts[pts.len() - 1];
+
+    // start on an on-curve point, or on the midpoint of the first and last points when both are off-curve
+    let (start, rest) = if first.on_curve {
+        ((first.x, first.y), &pts[1..])
+    } else if last.on_curve {
+        ((last.x, last.y), &pts[..pts.len() - 1])
+    } else {
+        (((first.x + last.x) / 2.0, (first.y + last.y) / 2.0), pts)
+    };
+
+    sink.move_to(start.0, start.1);
+    let mut control: Option<Point> = None;
+    for &p in rest {
+        match (p.on_curve, control) {
+            (true, Some(c)) => sink.quad_to(c.x, c.y, p.x, p.y),
+            (true, None) => sink.line_to(p.x, p.y),
+            (false, Some(c)) => sink.quad_to(c.x, c.y, (c.x + p.x) / 2.0, (c.y + p.y) / 2.0),
+            (false, None) => {}
+        }
+        control = if p.on_curve { None } else { Some(p) };
+    }
+    if let Some(c) = control {
+        sink.quad_to(c.x, c.y, start.0, start.1);
+    }
+    sink.close();
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+    pub on_curve: bool,
+}
+
+pub enum Glyph {
+    Simple(SimpleGlyph),
+    Composite(CompositeGlyph),
+}
+
+impl Debug for Glyph {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Simple(g) => g.fmt(f),
+            Self::Composite(g) => g.fmt(f),
+        }
+    }
+}
+
+impl Glyph {
+    pub fn from_bytes(b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let mut r = b;
+        let header = GlyphHeader::from_bytes(&mut r)?;
+
+        if header.number_of_contours >= 0 {
+            Ok(Self::Simple(SimpleGlyph::from_bytes(header, r)?))
+        } else {
+            Ok(Self::Composite(CompositeGlyph::from_bytes(header, r)?))
+        }
+    }
+
+    pub fn header(&self) -> &GlyphHeader {
+        match self {
+            Self::Simple(g) => &g.header,
+            Self::Composite(g) => &g.header,
+        }
+    }
+}
+
+pub struct GlyphHeader {
+    /// If the number of contours is greater than or equal to zero, this is a simple glyph. If negative, this is a composite glyph — the value -1 should be used for composite glyphs.
+    number_of_contours: i16,
+    /// Minimum x for coordinate data
+    x_min: i16,
+    /// Minimum y for coordinate data
+    y_min: i16,
+    /// Maximum x for coordinate data
+    x_max: i16,
+    /// Maximum y for coordinate data
+    y_max: i16,
+}
+
+impl Debug for GlyphHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GlyphHeader")
+            .field("number_of_contours", &self.number_of_contours)
+            .field("x_min", &self.x_min)
+            .field("y_min", &self.y_min)
+            .field("x_max", &self.x_max)
+            .field("y_max", &self.y_max)
+            .finish()
+    }
+}
+
+impl GlyphHeader {
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let number_of_contours = read_int16(r)?;
+        let x_min = read_int16(r)?;
+        let y_min = read_int16(r)?;
+        let x_max = read_int16(r)?;
+        let y_max = read_int16(r)?;
+
+        Ok(Self {
+            number_of_contours,
+            x_min,
+            y_min,
+            x_max,
+            y_max,
+        })
+    }
+
+    /// (x_min, y_min, x_max, y_max)
+    pub fn bbox(&self) -> (i16, i16, i16, i16) {
+        (self.x_min, self.y_min, self.x_max, self.y_max)
+    }
+}
+
+const ON_CURVE_POINT: u8 = 0x01;
+const X_SHORT_VECTOR: u8 = 0x02;
+const Y_SHORT_VECTOR: u8 = 0x04;
+const REPEAT_FLAG: u8 = 0x08;
+const X_IS_SAME_OR_POSITIVE_X_SHORT_VECTOR: u8 = 0x10;
+const Y_IS_SAME_OR_POSITIVE_Y_SHORT_VECTOR: u8 = 0x20;
+const OVERLAP_SIMPLE: u8 = 0x40;
+
+pub struct SimpleGlyph {
+    header: GlyphHeader,
+    /// Array of point indices for the last point of each contour, in increasing numeric order
+    end_pts_of_contours: Vec<u16>,
+    /// Array of instruction byte code for the glyph
+    instructions: Vec<u8>,
+    /// Flags and coordinates decoded into absolute points
+    points: Vec<GlyphPoint>,
+}
+
+impl Debug for SimpleGlyph {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SimpleGlyph")
+            .field("header", &self.header)
+            .field("end_pts_of_contours", &self.end_pts_of_contours)
+            .field_with("instructions", |f| write!(f, "[u8; {}]", self.instructions.len()))
+            .field("points", &self.points)
+            .finish()
+    }
+}
+
+impl SimpleGlyph {
+    pub fn from_bytes(header: GlyphHeader, mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let end_pts_of_contours = read_array(r, header.number_of_contours as usize, read_uint16)?;
+        if end_pts_of_contours.windows(2).any(|w| w[0] >= w[1]) {
+            return Err("glyph contour end points must increase".into());
+        }
+
+        let instruction_length = read_uint16(r)?;
+        let instructions = read_array(r, instruction_length as usize, read_uint8)?;
+
+        let num_points = end_pts_of_contours.last().map_or(0, |&e| e as usize + 1);
+
+        let mut flags = Vec::with_capacity(num_points);
+        while flags.len() < num_points {
+            let flag = read_uint8(r)?;
+            let repeat = if 0 != flag & REPEAT_FLAG { read_uint8(r)? as usize } else { 0 };
+            for _ in 0..=repeat {
+                flags.push(flag);
+            }
+        }
+        flags.truncate(num_points);
+
+        let xs = read_coordinates(r, &flags, X_SHORT_VECTOR, X_IS_SAME_OR_POSITIVE_X_SHORT_VECTOR)?;
+        let ys = read_coordinates(r, &flags, Y_SHORT_VECTOR, Y_IS_SAME_OR_POSITIVE_Y_SHORT_VECTOR)?;
+
+        let points = flags.iter().zip(xs.into_iter().zip(ys)).map(|(&flag, (x, y))| GlyphPoint { x, y, on_curve: 0 != flag & ON_CURVE_POINT }).collect();
+
+        Ok(Self {
+            header,
+            end_pts_of_contours,
+            instructions,
+            points,
+        })
+    }
+
+    pub fn end_pts_of_contours(&self) -> &[u16] {
+        &self.end_pts_of_contours
+    }
+
+    pub fn instructions(&self) -> &[u8] {
+        &self.instructions
+    }
+
+    pub fn points(&self) -> &[GlyphPoint] {
+        &self.points
+    }
+
+    /// Encode the glyph with its points moved to `points`, whole units in the same order, flagged as possibly overlapping the way instancers flag static instances
+    fn encode(&self, points: &[Point], w: &mut Vec<u8>) {
+        let coords: Vec<_> = points.iter().map(|p| (p.x as i16, p.y as i16)).collect();
+        let (x_min, y_min, x_max, y_max) = bounds(points);
+        write_int16(w, self.header.number_of_contours);
+        write_int16(w, x_min);
+        write_int16(w, y_min);
+        write_int16(w, x_max);
+        write_int16(w, y_max);
+        self.end_pts_of_contours.iter().for_each(|&e| write_uint16(w, e));
+        write_uint16(w, self.instructions.len() as u16);
+        w.extend(&self.instructions);
+
+        let mut flags = Vec::with_capacity(coords.len());
+        let (mut xs, mut ys) = (Vec::new(), Vec::new());
+        let mut previous = (0i16, 0i16);
+        for (i, (&(x, y), p)) in coords.iter().zip(points).enumerate() {
+            let mut flag = if p.on_curve { ON_CURVE_POINT } else { 0 };
+            if 0 == i {
+                flag |= OVERLAP_SIMPLE;
+            }
+            flag |= write_coordinate(&mut xs, x.wrapping_sub(previous.0), X_SHORT_VECTOR, X_IS_SAME_OR_POSITIVE_X_SHORT_VECTOR);
+            flag |= write_coordinate(&mut ys, y.wrapping_sub(previous.1), Y_SHORT_VECTOR, Y_IS_SAME_OR_POSITIVE_Y_SHORT_VECTOR);
+            flags.push(flag);
+            previous = (x, y);
+        }
+
+        // runs of equal flags collapse into the flag and a repeat count
+        let mut i = 0;
+        while i < flags.len() {
+            let run = flags[i..].iter().take(256).take_while(|&&f| f == flags[i]).count();
+            if run > 1 {
+                write_uint8(w, flags[i] | REPEAT_FLAG);
+                write_uint8(w, (run - 1) as u8);
+            } else {
+                write_uint8(w, flags[i]);
+            }
+            i += run;
+        }
+        w.extend(xs);
+        w.extend(ys);
+    }
+}
+
+/// Write one coordinate delta in its shortest form, returns the flags describing that form
+fn write_coordinate(w: &mut Vec<u8>, delta: i16, short_flag: u8, same_or_positive_flag: u8) -> u8 {
+    if 0 == delta {
+        same_or_positive_flag
+    } else if (-255..=255).contains(&delta) {
+        write_uint8(w, delta.unsigned_abs() as u8);
+        if delta > 0 { short_flag | same_or_positive_flag } else { short_flag }
+    } else {
+        write_int16(w, delta);
+        0
+    }
+}
+
+/// Coordinates are stored as deltas from the previous point, the first relative to (0, 0)
+fn read_coordinates(r: &mut &[u8], flags: &[u8], short_flag: u8, same_or_positive_flag: u8) -> Result<Vec<i16>, Box<dyn Error>> {
+    let mut v = Vec::with_capacity(flags.len());
+    let mut c: i16 = 0;
+    for &flag in flags {
+        let delta = if 0 != flag & short_flag {
+            let d = read_uint8(r)? as i16;
+            if 0 != flag & same_or_positive_flag { d } else { -d }
+        } else if 0 != flag & same_or_positive_flag {
+            0
+        } else {
+            read_int16(r)?
+        };
+        c = c.wrapping_add(delta);
+        v.push(c);
+    }
+    Ok(v)
+}
+
+/// A point of a simple glyph in font design units
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlyphPoint {
+    pub x: i16,
+    pub y: i16,
+    pub on_curve: bool,
+}
+
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+const USE_MY_METRICS: u16 = 0x0200;
+const SCALED_COMPONENT_OFFSET: u16 = 0x0800;
+const OVERLAP_COMPOUND: u16 = 0x0400;
+const UNSCALED_COMPONENT_OFFSET: u16 = 0x1000;
+
+pub struct CompositeGlyph {
+    header: GlyphHeader,
+    components: Vec<Component>,
+    /// Instructions for the composite character, present when a component sets WE_HAVE_INSTRUCTIONS
+    instructions: Vec<u8>,
+}
+
+impl Debug for CompositeGlyph {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompositeGlyph")
+            .field("header", &self.header)
+            .field("components", &self.components)
+            .field_with("instructions", |f| write!(f, "[u8; {}]", self.instructions.len()))
+            .finish()
+    }
+}
+
+impl CompositeGlyph {
+    pub fn from_bytes(header: GlyphHeader, mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let mut components = Vec::new();
+        loop {
+            let c = Component::from_bytes(r)?;
+            let more = 0 != c.flags & MORE_COMPONENTS;
+            components.push(c);
+            if !more {
+                break;
+            }
+        }
+
+        let instructions = if components.iter().any(|c| 0 != c.flags & WE_HAVE_INSTRUCTIONS) {
+            let instruction_length = read_uint16(r)?;
+            read_array(r, instruction_length as usize, read_uint8)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self { header, components, instructions })
+    }
+
+    pub fn components(&self) -> &[Component] {
+        &self.components
+    }
+
+    /// Encode the glyph with the component offsets moved to `offsets`, one per component, and a zero bounding box for the caller to fill in
+    fn encode(&self, offsets: &[Point], w: &mut Vec<u8>) {
+        write_int16(w, self.header.number_of_contours);
+        w.extend([0; 8]);
+        for (i, (c, offset)) in self.components.iter().zip(offsets).enumerate() {
+            let xy = 0 != c.flags & ARGS_ARE_XY_VALUES;
+            let (argument1, argument2) = if xy { (offset.x as i32, offset.y as i32) } else { (c.argument1, c.argument2) };
+            let words = if xy { [argument1, argument2].iter().any(|a| !(-128..=127).contains(a)) } else { argument1 > 255 || argument2 > 255 };
+
+            let mut flags = c.flags & !(ARG_1_AND_2_ARE_WORDS | MORE_COMPONENTS | OVERLAP_COMPOUND);
+            if words {
+                flags |= ARG_1_AND_2_ARE_WORDS;
+            }
+            if i + 1 < self.components.len() {
+                flags |= MORE_COMPONENTS;
+            }
+            if 0 == i {
+                flags |= OVERLAP_COMPOUND;
+            }
+            write_uint16(w, flags);
+            write_uint16(w, c.glyph_index);
+            match (words, xy) {
+                (true, true) => [argument1, argument2].iter().for_each(|&a| write_int16(w, a as i16)),
+                (true, false) => [argument1, argument2].iter().for_each(|&a| write_uint16(w, a as u16)),
+                (false, true) => [argument1, argument2].iter().for_each(|&a| write_int8(w, a as i8)),
+                (false, false) => [argument1, argument2].iter().for_each(|&a| write_uint8(w, a as u8)),
+            }
+
+            let [xx, yx, xy, yy] = c.transform;
+            if 0 != flags & WE_HAVE_A_SCALE {
+                write_f2dot14(w, xx);
+            } else if 0 != flags & WE_HAVE_AN_X_AND_Y_SCALE {
+                write_f2dot14(w, xx);
+                write_f2dot14(w, yy);
+            } else if 0 != flags & WE_HAVE_A_TWO_BY_TWO {
+                [xx, yx, xy, yy].iter().for_each(|&v| write_f2dot14(w, v));
+            }
+        }
+
+        if self.components.iter().any(|c| 0 != c.flags & WE_HAVE_INSTRUCTIONS) {
+            write_uint16(w, self.instructions.len() as u16);
+            w.extend(&self.instructions);
+        }
+    }
+}
+
+pub struct Component {
+    /// Component flags
+    flags: u16,
+    /// Glyph ID of the component
+    glyph_index: GlyphId,
+    /// x-offset, or the point number in the parent glyph when ARGS_ARE_XY_VALUES is clear
+    argument1: i32,
+    /// y-offset, or the point number in the component when ARGS_ARE_XY_VALUES is clear
+    argument2: i32,
+    /// 2x2 transform [xx, yx, xy, yy], the identity when the component has no scale
+    transform: [F2Dot14; 4],
+}
+
+impl Debug for Component {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Component")
+            .field_with("flags", |f| write!(f, "0x{:04x}", self.flags))
+            .field("glyph_index", &self.glyph_index)
+            .field("argument1", &self.argument1)
+            .field("argument2", &self.argument2)
+            .field("transform", &self.transform)
+            .finish()
+    }
+}
+
+impl Component {
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let flags = read_uint16(r)?;
+        let glyph_index = read_uint16(r)?;
+
+        // offsets are signed, point numbers unsigned
+        let xy = 0 != flags & ARGS_ARE_XY_VALUES;
+        let (argument1, argument2) = match (0 != flags & ARG_1_AND_2_ARE_WORDS, xy) {
+            (true, true) => (read_int16(r)? as i32, read_int16(r)? as i32),
+            (true, false) => (read_uint16(r)? as i32, read_uint16(r)? as i32),
+            (false, true) => (read_int8(r)? as i32, read_int8(r)? as i32),
+            (false, false) => (read_uint8(r)? as i32, read_uint8(r)? as i32),
+        };
+
+        let transform = if 0 != flags & WE_HAVE_A_SCALE {
+            let scale = read_f2dot14(r)?;
+            [scale, 0.0, 0.0, scale]
+        } else if 0 != flags & WE_HAVE_AN_X_AND_Y_SCALE {
+            let x_scale = read_f2dot14(r)?;
+            let y_scale = read_f2dot14(r)?;
+            [x_scale, 0.0, 0.0, y_scale]
+        } else if 0 != flags & WE_HAVE_A_TWO_BY_TWO {
+            [read_f2dot14(r)?, read_f2dot14(r)?, read_f2dot14(r)?, read_f2dot14(r)?]
+        } else {
+            [1.0, 0.0, 0.0, 1.0]
+        };
+
+        Ok(Self {
+            flags,
+            glyph_index,
+            argument1,
+            argument2,
+            transform,
+        })
+    }
+
+    pub fn glyph_index(&self) -> GlyphId {
+        self.glyph_index
+    }
+
+    pub fn use_my_metrics(&self) -> bool {
+        0 != self.flags & USE_MY_METRICS
+    }
+
+    /// The x and y offset as a point, (0, 0) for components placed by point matching
+    pub fn offset(&self) -> Point {
+        if 0 != self.flags & ARGS_ARE_XY_VALUES {
+            Point { x: self.argument1 as f32, y: self.argument2 as f32, on_curve: true }
+        } else {
+            Point { x: 0.0, y: 0.0, on_curve: true }
+        }
+    }
+
+    /// Transform and position the component's points, `parent` holds the points of the composite placed so far
+    pub fn apply(&self, parent: &[Point], points: &mut [Point]) -> Result<(), Box<dyn Error>> {
+        self.apply_at(parent, points, (0.0, 0.0))
+    }
+
+    /// As `apply`, with a gvar delta added to the x and y offset before it is scaled
+    pub fn apply_at(&self, parent: &[Point], points: &mut [Point], offset_delta: (f32, f32)) -> Result<(), Box<dyn Error>> {
+        let [xx, yx, xy, yy] = self.transform;
+        for p in points.iter_mut() {
+            (p.x, p.y) = (xx * p.x + xy * p.y, yx * p.x + yy * p.y);
+        }
+
+        let (dx, dy) = if 0 != self.flags & ARGS_ARE_XY_VALUES {
+            let (dx, dy) = (self.argument1 as f32 + offset_delta.0, self.argument2 as f32 + offset_delta.1);
+            // offsets are unscaled unless the font explicitly asks for the Apple behaviour
+            if 0 != self.flags & SCALED_COMPONENT_OFFSET && 0 == self.flags & UNSCALED_COMPONENT_OFFSET { (xx * dx + xy * dy, yx * dx + yy * dy) } else { (dx, dy) }
+        } else {
+            // point matching: move the component so its point argument2 lands on the parent's point argument1
+            let pp = parent.get(self.argument1 as usize).ok_or("component anchor point not in the parent glyph")?;
+            let cp = points.get(self.argument2 as usize).ok_or("component anchor point not in the component glyph")?;
+            (pp.x - cp.x, pp.y - cp.y)
+        };
+
+        for p in points.iter_mut() {
+            p.x += dx;
+            p.y += dy;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A triangle with its corners at (0, 0), (100, 0) and (0, 100)
+    fn triangle() -> Vec<u8> {
+        let mut w = Vec::new();
+        [1, 0, 0, 100, 100, 2, 0].iter().for_each(|&v| write_int16(&mut w, v));
+        w.extend([ON_CURVE_POINT; 3]);
+        [0, 100, -100, 0, 0, 100].iter().for_each(|&v| write_int16(&mut w, v));
+        w
+    }
+
+    /// A composite of `components`, each a glyph id moved by an x offset
+    fn composite(components: &[(GlyphId, i16)]) -> Vec<u8> {
+        let mut w = Vec::new();
+        [-1, 0, 0, 0, 0].iter().for_each(|&v| write_int16(&mut w, v));
+        for (i, &(glyph_index, dx)) in components.iter().enumerate() {
+            let more = if i + 1 < components.len() { MORE_COMPONENTS } else { 0 };
+            write_uint16(&mut w, ARG_1_AND_2_ARE_WORDS | ARGS_ARE_XY_VALUES | more);
+            write_uint16(&mut w, glyph_index);
+            write_int16(&mut w, dx);
+            write_int16(&mut w, 0);
+        }
+        w
+    }
+
+    fn glyf(glyphs: &[Vec<u8>]) -> (GlyphDataTable, IndexToLocationTable) {
+        let mut data = Vec::new();
+        let mut loca = Vec::new();
+        write_uint32(&mut loca, 0);
+        for g in glyphs {
+            data.extend(g);
+            write_uint32(&mut loca, data.len() as Offset32);
+        }
+        (GlyphDataTable { data }, IndexToLocationTable::from_bytes(&loca, 1, glyphs.len() as u16).unwrap())
+    }
+
+    #[test]
+    fn resolves_repeated_components() {
+        let (glyf, loca) = glyf(&[triangle(), composite(&[(0, 0), (0, 200)]), composite(&[(1, 0), (1, 500)])]);
+        let op = glyf.outline_points(&loca, 2).unwrap();
+        assert_eq!(op.end_pts, [2, 5, 8, 11]);
+        assert_eq!(op.points.iter().map(|p| p.x).collect::<Vec<_>>(), [0.0, 100.0, 0.0, 200.0, 300.0, 200.0, 500.0, 600.0, 500.0, 700.0, 800.0, 700.0]);
+    }
+
+    #[test]
+    fn rejects_component_cycles() {
+        let (glyf, loca) = glyf(&[triangle(), composite(&[(0, 0), (1, 0)]), composite(&[(3, 0)]), composite(&[(0, 0), (2, 0)])]);
+        assert!(glyf.outline_points(&loca, 1).is_err());
+        assert!(glyf.outline_points(&loca, 2).is_err());
+        assert!(glyf.outline_points(&loca, 3).is_err());
+        let metrics = |_| PhantomMetrics::default();
+        assert!(glyf.outline_points_at(&loca, 3, None, &[], &metrics).is_err());
+    }
+}
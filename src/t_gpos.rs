@@ -0,0 +1,1354 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::layout::*;
+use super::t_gdef::*;
+use super::types::*;
+use super::utils::*;
+use super::variations::*;
+
+pub struct GlyphPositioningTable {
+    /// Major version of the GPOS table, = 1
+    major_version: u16,
+    /// Minor version of the GPOS table, = 0 or 1
+    minor_version: u16,
+    script_list: ScriptList,
+    feature_list: FeatureList,
+    lookup_list: LookupList<PositioningSubtable>,
+    /// Version 1.1 only
+    feature_variations: Option<FeatureVariations>,
+}
+
+impl Debug for GlyphPositioningTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GlyphPositioningTable")
+            .field("major_version", &self.major_version)
+            .field("minor_version", &self.minor_version)
+            .field("script_list", &self.script_list)
+            .field("feature_list", &self.feature_list)
+            .field("lookup_list", &self.lookup_list)
+            .field("feature_variations", &self.feature_variations)
+            .finish()
+    }
+}
+
+impl GlyphPositioningTable {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let major_version = read_uint16(r)?;
+        let minor_version = read_uint16(r)?;
+        if 1 != major_version {
+            return Err(format!("unsupported GPOS version {}.{}", major_version, minor_version).into());
+        }
+
+        let script_list_offset = read_offset16(r)?;
+        let feature_list_offset = read_offset16(r)?;
+        let lookup_list_offset = read_offset16(r)?;
+        let feature_variations_offset = if 1 <= minor_version { read_offset32(r)? } else { 0 };
+
+        let script_list = ScriptList::from_bytes(o.get(script_list_offset as usize..).ok_or("GPOS ScriptList offset out of range")?)?;
+        let feature_list = FeatureList::from_bytes(o.get(feature_list_offset as usize..).ok_or("GPOS FeatureList offset out of range")?)?;
+        let lookup_list = LookupList::from_bytes(o.get(lookup_list_offset as usize..).ok_or("GPOS LookupList offset out of range")?)?;
+        let feature_variations = match feature_variations_offset {
+            0 => None,
+            offset => Some(FeatureVariations::from_bytes(o.get(offset as usize..).ok_or("GPOS FeatureVariations offset out of range")?)?),
+        };
+
+        Ok(Self {
+            major_version,
+            minor_version,
+            script_list,
+            feature_list,
+            lookup_list,
+            feature_variations,
+        })
+    }
+
+    pub fn script_list(&self) -> &ScriptList {
+        &self.script_list
+    }
+
+    pub fn feature_list(&self) -> &FeatureList {
+        &self.feature_list
+    }
+
+    pub fn lookup_list(&self) -> &LookupList<PositioningSubtable> {
+        &self.lookup_list
+    }
+
+    pub fn feature_variations(&self) -> Option<&FeatureVariations> {
+        self.feature_variations.as_ref()
+    }
+
+    /// Lookups `features` select under a script and language, for `position`
+    pub fn lookups(&self, script: Tag, language: Option<Tag>, features: &[Tag], coords: &[F2Dot14]) -> Vec<u16> {
+        select_lookups(&self.script_list, &self.feature_list, self.feature_variations.as_ref(), script, language, features, coords)
+    }
+
+    /// Apply `lookups` to a buffer in logical order, `positions` starts with the glyph advances and ends in buffer order too,
+    /// so a right-to-left run is drawn from its last glyph. GDEF supplies the glyph classes lookup flags test and the variation deltas
+    pub fn position(&self, glyphs: &[GlyphInfo], positions: &mut [GlyphPosition], lookups: &[u16], coords: &[F2Dot14], right_to_left: bool, gdef: Option<&GlyphDefinitionTable>) {
+        if glyphs.len() != positions.len() {
+            return;
+        }
+        for p in positions.iter_mut() {
+            p.attach_chain = 0;
+            p.attach_type = AttachType::None;
+        }
+
+        let mut ctx = PositionContext {
+            glyphs,
+            positions,
+            coords,
+            gdef,
+            var_store: gdef.and_then(|gdef| gdef.item_var_store()),
+            right_to_left,
+        };
+        for &lookup_index in lookups {
+            self.apply_lookup(&mut ctx, lookup_index);
+        }
+
+        for i in 0..ctx.positions.len() {
+            propagate_attachment(ctx.positions, i, right_to_left, MAX_NESTING_LEVEL);
+        }
+    }
+
+    /// Apply one lookup across the whole buffer
+    fn apply_lookup(&self, ctx: &mut PositionContext, lookup_index: u16) {
+        let Some(lookup) = self.lookup_list.get(lookup_index) else {
+            return;
+        };
+        let gdef = ctx.gdef;
+        let skip = |g: &GlyphInfo| lookup_skips(gdef, lookup, g);
+
+        let mut i = 0;
+        while i < ctx.glyphs.len() {
+            if skip(&ctx.glyphs[i]) {
+                i += 1;
+                continue;
+            }
+            i = match self.apply_at(ctx, lookup_index, i, 0) {
+                Some(next) => next.max(i + 1),
+                None => i + 1,
+            };
+        }
+    }
+
+    /// Apply the first subtable of a lookup that matches at `i`, giving the position to continue from
+    fn apply_at(&self, ctx: &mut PositionContext, lookup_index: u16, i: usize, depth: usize) -> Option<usize> {
+        if depth > MAX_NESTING_LEVEL {
+            return None;
+        }
+        let lookup = self.lookup_list.get(lookup_index)?;
+        let gdef = ctx.gdef;
+        let skip = |g: &GlyphInfo| lookup_skips(gdef, lookup, g);
+        let is_mark = |g: &GlyphInfo| gdef.is_some_and(|gdef| Some(GlyphClass::Mark) == gdef.glyph_class(g.glyph_id));
+        // mark-to-mark looks for the previous mark, so only the mark filtering of the lookup applies
+        let skip_other_marks = |g: &GlyphInfo| {
+            gdef.is_some_and(|gdef| gdef.skips(lookup.lookup_flag() & !(IGNORE_BASE_GLYPHS | IGNORE_LIGATURES | IGNORE_MARKS), lookup.mark_filtering_set(), g.glyph_id))
+        };
+
+        let glyph_id = ctx.glyphs.get(i)?.glyph_id;
+        for subtable in lookup.subtables() {
+            let next = match subtable {
+                PositioningSubtable::Single(st) => st.value_record(glyph_id).map(|vr| {
+                    vr.apply(&mut ctx.positions[i], ctx.var_store, ctx.coords);
+                    i + 1
+                }),
+                PositioningSubtable::Pair(st) => st.apply(ctx, i, &skip),
+                PositioningSubtable::Cursive(st) => st.apply(ctx, i, lookup.lookup_flag(), &skip),
+                PositioningSubtable::MarkToBase(st) => st.apply(ctx, i, &is_mark),
+                PositioningSubtable::MarkToLigature(st) => st.apply(ctx, i, &is_mark),
+                PositioningSubtable::MarkToMark(st) => st.apply(ctx, i, &skip_other_marks, &is_mark),
+                PositioningSubtable::Context(st) => st.matches(ctx.glyphs, i, &skip).map(|(positions, records)| self.apply_records(ctx, positions, records, depth)),
+                PositioningSubtable::ChainedContext(st) => st.matches(ctx.glyphs, i, &skip).map(|(positions, records)| self.apply_records(ctx, positions, records, depth)),
+            };
+            if next.is_some() {
+                return next;
+            }
+        }
+        None
+    }
+
+    /// Apply the nested lookups of a matched context, positioning never changes the buffer length
+    fn apply_records(&self, ctx: &mut PositionContext, positions: Vec<usize>, records: &[SequenceLookupRecord], depth: usize) -> usize {
+        for record in records {
+            if let Some(&pos) = positions.get(record.sequence_index as usize) {
+                self.apply_at(ctx, record.lookup_list_index, pos, depth + 1);
+            }
+        }
+        positions.last().map_or(0, |&p| p + 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum AttachType {
+    #[default]
+    None,
+    Mark,
+    Cursive,
+}
+
+/// Placement of a glyph in font design units
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GlyphPosition {
+    /// How far the pen moves after the glyph
+    pub x_advance: i32,
+    pub y_advance: i32,
+    /// Displacement of the glyph from the pen, which doesn't move the pen
+    pub x_offset: i32,
+    pub y_offset: i32,
+    /// Relative index of the glyph this one is attached to, 0 for none
+    attach_chain: isize,
+    attach_type: AttachType,
+}
+
+impl GlyphPosition {
+    pub fn new(x_advance: i32, y_advance: i32) -> Self {
+        Self {
+            x_advance,
+            y_advance,
+            ..Default::default()
+        }
+    }
+}
+
+/// Make the offsets of an attached glyph relative to the pen position instead of to the glyph it attaches to
+fn propagate_attachment(positions: &mut [GlyphPosition], i: usize, right_to_left: bool, depth: usize) {
+    let chain = positions[i].attach_chain;
+    if 0 == chain {
+        return;
+    }
+    positions[i].attach_chain = 0;
+    let Some(j) = i.checked_add_signed(chain).filter(|&j| j < positions.len()) else {
+        return;
+    };
+    if 0 == depth {
+        return;
+    }
+    propagate_attachment(positions, j, right_to_left, depth - 1);
+
+    match positions[i].attach_type {
+        AttachType::Cursive => positions[i].y_offset += positions[j].y_offset,
+        AttachType::Mark => {
+            positions[i].x_offset += positions[j].x_offset;
+            positions[i].y_offset += positions[j].y_offset;
+            // the pen has moved by the advances between the base and the mark, in the direction the run is drawn
+            let (range, sign) = if right_to_left { (j + 1..i + 1, 1) } else { (j..i, -1) };
+            for k in range {
+                positions[i].x_offset += sign * positions[k].x_advance;
+                positions[i].y_offset += sign * positions[k].y_advance;
+            }
+        }
+        AttachType::None => {}
+    }
+}
+
+/// State of one positioning run
+struct PositionContext<'a> {
+    glyphs: &'a [GlyphInfo],
+    positions: &'a mut [GlyphPosition],
+    coords: &'a [F2Dot14],
+    gdef: Option<&'a GlyphDefinitionTable>,
+    /// Deltas for VariationIndex tables, from GDEF
+    var_store: Option<&'a ItemVariationStore>,
+    right_to_left: bool,
+}
+
+const SINGLE: u16 = 1;
+const PAIR: u16 = 2;
+const CURSIVE: u16 = 3;
+const MARK_TO_BASE: u16 = 4;
+const MARK_TO_LIGATURE: u16 = 5;
+const MARK_TO_MARK: u16 = 6;
+const CONTEXT: u16 = 7;
+const CHAINING_CONTEXT: u16 = 8;
+const EXTENSION_POSITIONING: u16 = 9;
+
+pub enum PositioningSubtable {
+    Single(SinglePos),
+    Pair(PairPos),
+    Cursive(CursivePos),
+    MarkToBase(MarkBasePos),
+    MarkToLigature(MarkLigPos),
+    MarkToMark(MarkMarkPos),
+    Context(SequenceContext),
+    ChainedContext(ChainedSequenceContext),
+}
+
+impl Debug for PositioningSubtable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Single(st) => st.fmt(f),
+            Self::Pair(st) => st.fmt(f),
+            Self::Cursive(st) => st.fmt(f),
+            Self::MarkToBase(st) => st.fmt(f),
+            Self::MarkToLigature(st) => st.fmt(f),
+            Self::MarkToMark(st) => st.fmt(f),
+            Self::Context(st) => f.debug_tuple("Context").field(st).finish(),
+            Self::ChainedContext(st) => f.debug_tuple("ChainedContext").field(st).finish(),
+        }
+    }
+}
+
+impl LookupSubtable for PositioningSubtable {
+    const EXTENSION_LOOKUP_TYPE: u16 = EXTENSION_POSITIONING;
+
+    fn from_bytes(b: &[u8], lookup_type: u16) -> Result<Self, Box<dyn Error>> {
+        match lookup_type {
+            SINGLE => Ok(Self::Single(SinglePos::from_bytes(b)?)),
+            PAIR => Ok(Self::Pair(PairPos::from_bytes(b)?)),
+            CURSIVE => Ok(Self::Cursive(CursivePos::from_bytes(b)?)),
+            MARK_TO_BASE => Ok(Self::MarkToBase(MarkBasePos::from_bytes(b)?)),
+            MARK_TO_LIGATURE => Ok(Self::MarkToLigature(MarkLigPos::from_bytes(b)?)),
+            MARK_TO_MARK => Ok(Self::MarkToMark(MarkMarkPos::from_bytes(b)?)),
+            CONTEXT => Ok(Self::Context(SequenceContext::from_bytes(b)?)),
+            CHAINING_CONTEXT => Ok(Self::ChainedContext(ChainedSequenceContext::from_bytes(b)?)),
+            _ => Err(format!("unknown GPOS lookup type {}", lookup_type).into()),
+        }
+    }
+}
+
+const X_PLACEMENT: u16 = 0x0001;
+const Y_PLACEMENT: u16 = 0x0002;
+const X_ADVANCE: u16 = 0x0004;
+const Y_ADVANCE: u16 = 0x0008;
+const X_PLACEMENT_DEVICE: u16 = 0x0010;
+const Y_PLACEMENT_DEVICE: u16 = 0x0020;
+const X_ADVANCE_DEVICE: u16 = 0x0040;
+const Y_ADVANCE_DEVICE: u16 = 0x0080;
+
+/// Adjustments of a glyph's placement and advance, fields the ValueFormat leaves out are 0
+#[derive(Default)]
+pub struct ValueRecord {
+    x_placement: i16,
+    y_placement: i16,
+    x_advance: i16,
+    y_advance: i16,
+    /// Device or VariationIndex tables for x_placement, y_placement, x_advance and y_advance,
+    /// boxed as most records have none and class pair tables hold many records
+    devices: Option<Box<[Option<Device>; 4]>>,
+}
+
+impl Debug for ValueRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ValueRecord")
+            .field("x_placement", &self.x_placement)
+            .field("y_placement", &self.y_placement)
+            .field("x_advance", &self.x_advance)
+            .field("y_advance", &self.y_advance)
+            .field("devices", &self.devices)
+            .finish()
+    }
+}
+
+impl ValueRecord {
+    /// Read the fields `value_format` selects, device offsets are from the start of the subtable `o`
+    pub fn from_bytes(r: &mut &[u8], o: &[u8], value_format: u16) -> Result<Self, Box<dyn Error>> {
+        let mut vr = Self::default();
+        if 0 != value_format & X_PLACEMENT {
+            vr.x_placement = read_int16(r)?;
+        }
+        if 0 != value_format & Y_PLACEMENT {
+            vr.y_placement = read_int16(r)?;
+        }
+        if 0 != value_format & X_ADVANCE {
+            vr.x_advance = read_int16(r)?;
+        }
+        if 0 != value_format & Y_ADVANCE {
+            vr.y_advance = read_int16(r)?;
+        }
+
+        let mut devices: [Option<Device>; 4] = Default::default();
+        for (i, flag) in [X_PLACEMENT_DEVICE, Y_PLACEMENT_DEVICE, X_ADVANCE_DEVICE, Y_ADVANCE_DEVICE].into_iter().enumerate() {
+            if 0 != value_format & flag {
+                devices[i] = Device::at(o, read_offset16(r)?)?;
+            }
+        }
+        if devices.iter().any(Option::is_some) {
+            vr.devices = Some(Box::new(devices));
+        }
+
+        Ok(vr)
+    }
+
+    pub fn x_placement(&self) -> i16 {
+        self.x_placement
+    }
+
+    pub fn y_placement(&self) -> i16 {
+        self.y_placement
+    }
+
+    pub fn x_advance(&self) -> i16 {
+        self.x_advance
+    }
+
+    pub fn y_advance(&self) -> i16 {
+        self.y_advance
+    }
+
+    /// Add the adjustments, with their variation deltas at `coords`, to a glyph position
+    pub fn apply(&self, position: &mut GlyphPosition, var_store: Option<&ItemVariationStore>, coords: &[F2Dot14]) {
+        let delta = |k: usize| match &self.devices {
+            Some(devices) => devices[k].as_ref().map_or(0.0, |d| d.variation_delta(var_store, coords)),
+            None => 0.0,
+        };
+        position.x_offset += (self.x_placement as f32 + delta(0)).round() as i32;
+        position.y_offset += (self.y_placement as f32 + delta(1)).round() as i32;
+        position.x_advance += (self.x_advance as f32 + delta(2)).round() as i32;
+        position.y_advance += (self.y_advance as f32 + delta(3)).round() as i32;
+    }
+}
+
+/// A point glyphs attach at, in design units
+pub struct Anchor {
+    x_coordinate: i16,
+    y_coordinate: i16,
+    /// Index to glyph contour point, format 2 only
+    anchor_point: Option<u16>,
+    /// Format 3 only
+    x_device: Option<Device>,
+    y_device: Option<Device>,
+}
+
+impl Debug for Anchor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Anchor")
+            .field("x_coordinate", &self.x_coordinate)
+            .field("y_coordinate", &self.y_coordinate)
+            .field("anchor_point", &self.anchor_point)
+            .field("x_device", &self.x_device)
+            .field("y_device", &self.y_device)
+            .finish()
+    }
+}
+
+impl Anchor {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        let x_coordinate = read_int16(r)?;
+        let y_coordinate = read_int16(r)?;
+        let mut anchor = Self {
+            x_coordinate,
+            y_coordinate,
+            anchor_point: None,
+            x_device: None,
+            y_device: None,
+        };
+        match format {
+            1 => {}
+            2 => anchor.anchor_point = Some(read_uint16(r)?),
+            3 => {
+                anchor.x_device = Device::at(o, read_offset16(r)?)?;
+                anchor.y_device = Device::at(o, read_offset16(r)?)?;
+            }
+            _ => return Err(format!("unknown anchor format {}", format).into()),
+        }
+        Ok(anchor)
+    }
+
+    /// Read an optional anchor at `offset` from the start of the table `o`
+    pub fn at(o: &[u8], offset: Offset16) -> Result<Option<Self>, Box<dyn Error>> {
+        match offset {
+            0 => Ok(None),
+            offset => Ok(Some(Self::from_bytes(o.get(offset as usize..).ok_or("Anchor offset out of range")?)?)),
+        }
+    }
+
+    pub fn anchor_point(&self) -> Option<u16> {
+        self.anchor_point
+    }
+
+    /// Coordinates at normalized `coords`, a format 2 contour point is only a hint for hinted rendering so the design coordinates are used
+    pub fn position(&self, var_store: Option<&ItemVariationStore>, coords: &[F2Dot14]) -> (f32, f32) {
+        let x = self.x_coordinate as f32 + self.x_device.as_ref().map_or(0.0, |d| d.variation_delta(var_store, coords));
+        let y = self.y_coordinate as f32 + self.y_device.as_ref().map_or(0.0, |d| d.variation_delta(var_store, coords));
+        (x, y)
+    }
+}
+
+/// Adjust the position of a single glyph
+pub enum SinglePos {
+    /// One value for every covered glyph
+    Format1 { coverage: Coverage, value_record: ValueRecord },
+    /// Values ordered by coverage index
+    Format2 { coverage: Coverage, value_records: Vec<ValueRecord> },
+}
+
+impl Debug for SinglePos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Format1 { coverage, value_record } => f.debug_struct("SinglePosFormat1").field("coverage", coverage).field("value_record", value_record).finish(),
+            Self::Format2 { coverage, value_records } => f.debug_struct("SinglePosFormat2").field("coverage", coverage).field("value_records", value_records).finish(),
+        }
+    }
+}
+
+impl SinglePos {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        let coverage = Coverage::at(o, read_offset16(r)?)?;
+        let value_format = read_uint16(r)?;
+        match format {
+            1 => Ok(Self::Format1 {
+                coverage,
+                value_record: ValueRecord::from_bytes(r, o, value_format)?,
+            }),
+            2 => {
+                let value_count = read_uint16(r)?;
+                let mut value_records = Vec::with_capacity(value_count as usize);
+                for _ in 0..value_count {
+                    value_records.push(ValueRecord::from_bytes(r, o, value_format)?);
+                }
+                Ok(Self::Format2 { coverage, value_records })
+            }
+            _ => Err(format!("unknown single positioning format {}", format).into()),
+        }
+    }
+
+    pub fn value_record(&self, glyph_id: GlyphId) -> Option<&ValueRecord> {
+        match self {
+            Self::Format1 { coverage, value_record } => coverage.index(glyph_id).map(|_| value_record),
+            Self::Format2 { coverage, value_records } => value_records.get(coverage.index(glyph_id)? as usize),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PairValueRecord {
+    /// Glyph ID of second glyph in the pair
+    second_glyph: GlyphId,
+    value_record1: ValueRecord,
+    value_record2: ValueRecord,
+}
+
+/// Adjust the positions of a pair of glyphs, typically kerning
+pub enum PairPos {
+    /// Pairs of individual glyphs, the pair sets are ordered by the coverage index of the first glyph and sorted by the second
+    Format1 {
+        coverage: Coverage,
+        value_format2: u16,
+        pair_sets: Vec<Vec<PairValueRecord>>,
+    },
+    /// Pairs of glyph classes, the values are indexed by class1 then class2
+    Format2 {
+        coverage: Coverage,
+        value_format2: u16,
+        class_def1: ClassDef,
+        class_def2: ClassDef,
+        class2_count: u16,
+        class_records: Vec<(ValueRecord, ValueRecord)>,
+    },
+}
+
+impl Debug for PairPos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Format1 { coverage, value_format2, pair_sets } => f.debug_struct("PairPosFormat1").field("coverage", coverage).field("value_format2", value_format2).field("pair_sets", pair_sets).finish(),
+            Self::Format2 {
+                coverage,
+                value_format2,
+                class_def1,
+                class_def2,
+                class2_count,
+                class_records,
+            } => f
+                .debug_struct("PairPosFormat2")
+                .field("coverage", coverage)
+                .field("value_format2", value_format2)
+                .field("class_def1", class_def1)
+                .field("class_def2", class_def2)
+                .field("class2_count", class2_count)
+                .field("class_records", class_records)
+                .finish(),
+        }
+    }
+}
+
+impl PairPos {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        let coverage = Coverage::at(o, read_offset16(r)?)?;
+        let value_format1 = read_uint16(r)?;
+        let value_format2 = read_uint16(r)?;
+        match format {
+            1 => {
+                let pair_set_count = read_uint16(r)?;
+                let mut pair_sets = Vec::with_capacity(pair_set_count as usize);
+                for offset in read_array(r, pair_set_count as usize, read_offset16)? {
+                    // device offsets in a PairValueRecord are from the start of the PairSet
+                    let mut s = o.get(offset as usize..).ok_or("PairSet offset out of range")?;
+                    let ps = s;
+                    let pair_value_count = read_uint16(&mut s)?;
+                    let mut pair_set = Vec::with_capacity(pair_value_count as usize);
+                    for _ in 0..pair_value_count {
+                        pair_set.push(PairValueRecord {
+                            second_glyph: read_uint16(&mut s)?,
+                            value_record1: ValueRecord::from_bytes(&mut s, ps, value_format1)?,
+                            value_record2: ValueRecord::from_bytes(&mut s, ps, value_format2)?,
+                        });
+                    }
+                    pair_sets.push(pair_set);
+                }
+                Ok(Self::Format1 { coverage, value_format2, pair_sets })
+            }
+            2 => {
+                let class_def1 = ClassDef::at(o, read_offset16(r)?)?;
+                let class_def2 = ClassDef::at(o, read_offset16(r)?)?;
+                let class1_count = read_uint16(r)?;
+                let class2_count = read_uint16(r)?;
+                let count = class1_count as usize * class2_count as usize;
+                let mut class_records = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let value_record1 = ValueRecord::from_bytes(r, o, value_format1)?;
+                    let value_record2 = ValueRecord::from_bytes(r, o, value_format2)?;
+                    class_records.push((value_record1, value_record2));
+                }
+                Ok(Self::Format2 {
+                    coverage,
+                    value_format2,
+                    class_def1,
+                    class_def2,
+                    class2_count,
+                    class_records,
+                })
+            }
+            _ => Err(format!("unknown pair positioning format {}", format).into()),
+        }
+    }
+
+    /// Values for the first and second glyph of a pair
+    pub fn values(&self, first: GlyphId, second: GlyphId) -> Option<(&ValueRecord, &ValueRecord)> {
+        match self {
+            Self::Format1 { coverage, pair_sets, .. } => {
+                let pair_set = pair_sets.get(coverage.index(first)? as usize)?;
+                let pvr = &pair_set[pair_set.binary_search_by_key(&second, |pvr| pvr.second_glyph).ok()?];
+                Some((&pvr.value_record1, &pvr.value_record2))
+            }
+            Self::Format2 {
+                coverage,
+                class_def1,
+                class_def2,
+                class2_count,
+                class_records,
+                ..
+            } => {
+                coverage.index(first)?;
+                let class2 = class_def2.class(second);
+                if class2 >= *class2_count {
+                    return None;
+                }
+                let (vr1, vr2) = class_records.get(class_def1.class(first) as usize * *class2_count as usize + class2 as usize)?;
+                Some((vr1, vr2))
+            }
+        }
+    }
+
+    /// Adjust the glyph at `i` and the next one that isn't skipped, a second glyph with its own values isn't the first of another pair
+    fn apply(&self, ctx: &mut PositionContext, i: usize, skip: &dyn Fn(&GlyphInfo) -> bool) -> Option<usize> {
+        let j = next_unskipped(ctx.glyphs, i, skip)?;
+        let (vr1, vr2) = self.values(ctx.glyphs[i].glyph_id, ctx.glyphs[j].glyph_id)?;
+        vr1.apply(&mut ctx.positions[i], ctx.var_store, ctx.coords);
+        vr2.apply(&mut ctx.positions[j], ctx.var_store, ctx.coords);
+
+        let value_format2 = match self {
+            Self::Format1 { value_format2, .. } | Self::Format2 { value_format2, .. } => *value_format2,
+        };
+        Some(if 0 != value_format2 { j + 1 } else { j })
+    }
+}
+
+#[derive(Debug)]
+pub struct EntryExitRecord {
+    entry_anchor: Option<Anchor>,
+    exit_anchor: Option<Anchor>,
+}
+
+/// Connect the exit point of a glyph to the entry point of the next, as in cursive scripts
+pub struct CursivePos {
+    coverage: Coverage,
+    /// Ordered by coverage index
+    entry_exit_records: Vec<EntryExitRecord>,
+}
+
+impl Debug for CursivePos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CursivePos").field("coverage", &self.coverage).field("entry_exit_records", &self.entry_exit_records).finish()
+    }
+}
+
+impl CursivePos {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        if 1 != format {
+            return Err(format!("unknown cursive positioning format {}", format).into());
+        }
+        let coverage = Coverage::at(o, read_offset16(r)?)?;
+        let entry_exit_count = read_uint16(r)?;
+        let mut entry_exit_records = Vec::with_capacity(entry_exit_count as usize);
+        for _ in 0..entry_exit_count {
+            entry_exit_records.push(EntryExitRecord {
+                entry_anchor: Anchor::at(o, read_offset16(r)?)?,
+                exit_anchor: Anchor::at(o, read_offset16(r)?)?,
+            });
+        }
+
+        Ok(Self { coverage, entry_exit_records })
+    }
+
+    pub fn entry_exit(&self, glyph_id: GlyphId) -> Option<(Option<&Anchor>, Option<&Anchor>)> {
+        let eer = self.entry_exit_records.get(self.coverage.index(glyph_id)? as usize)?;
+        Some((eer.entry_anchor.as_ref(), eer.exit_anchor.as_ref()))
+    }
+
+    /// Join the glyph at `i` to the previous glyph's exit, the advances meet at the anchors and the lookup flag picks which glyph stays on the baseline
+    fn apply(&self, ctx: &mut PositionContext, i: usize, lookup_flag: u16, skip: &dyn Fn(&GlyphInfo) -> bool) -> Option<usize> {
+        let (Some(entry), _) = self.entry_exit(ctx.glyphs[i].glyph_id)? else {
+            return None;
+        };
+        let prev = prev_unskipped(ctx.glyphs, i, skip)?;
+        let (_, Some(exit)) = self.entry_exit(ctx.glyphs[prev].glyph_id)? else {
+            return None;
+        };
+
+        let (exit_x, exit_y) = exit.position(ctx.var_store, ctx.coords);
+        let (entry_x, entry_y) = entry.position(ctx.var_store, ctx.coords);
+        let p = &mut *ctx.positions;
+        if ctx.right_to_left {
+            let d = exit_x.round() as i32 + p[prev].x_offset;
+            p[prev].x_advance -= d;
+            p[prev].x_offset -= d;
+            p[i].x_advance = entry_x.round() as i32 + p[i].x_offset;
+        } else {
+            p[prev].x_advance = exit_x.round() as i32 + p[prev].x_offset;
+            let d = entry_x.round() as i32 + p[i].x_offset;
+            p[i].x_advance -= d;
+            p[i].x_offset -= d;
+        }
+
+        // the child aligns its anchor with its parent's, the first glyph of a chain stays on the baseline unless the lookup is flagged right-to-left
+        let mut y_offset = (entry_y - exit_y).round() as i32;
+        let (child, parent) = if 0 != lookup_flag & RIGHT_TO_LEFT {
+            (prev, i)
+        } else {
+            y_offset = -y_offset;
+            (i, prev)
+        };
+        reverse_cursive_chain(p, child, parent);
+        p[child].attach_type = AttachType::Cursive;
+        p[child].attach_chain = parent as isize - child as isize;
+        p[child].y_offset = y_offset;
+
+        Some(i + 1)
+    }
+}
+
+/// Point the old cursive chain of `i` the other way, so the glyphs it was attached through now hang off its new parent
+fn reverse_cursive_chain(positions: &mut [GlyphPosition], i: usize, new_parent: usize) {
+    let chain = positions[i].attach_chain;
+    if 0 == chain || AttachType::Cursive != positions[i].attach_type {
+        return;
+    }
+    positions[i].attach_chain = 0;
+    let Some(j) = i.checked_add_signed(chain).filter(|&j| j < positions.len()) else {
+        return;
+    };
+    if j == new_parent {
+        return;
+    }
+    reverse_cursive_chain(positions, j, new_parent);
+    positions[j].y_offset = -positions[i].y_offset;
+    positions[j].attach_chain = -chain;
+    positions[j].attach_type = AttachType::Cursive;
+}
+
+#[derive(Debug)]
+pub struct MarkRecord {
+    /// Class defined for the associated mark
+    mark_class: u16,
+    mark_anchor: Anchor,
+}
+
+fn read_mark_array(mut b: &[u8]) -> Result<Vec<MarkRecord>, Box<dyn Error>> {
+    let o = b;
+    let r = &mut b;
+
+    let mark_count = read_uint16(r)?;
+    let mut mark_records = Vec::with_capacity(mark_count as usize);
+    for _ in 0..mark_count {
+        let mark_class = read_uint16(r)?;
+        let mark_anchor = Anchor::at(o, read_offset16(r)?)?.ok_or("MarkRecord has no anchor")?;
+        mark_records.push(MarkRecord { mark_class, mark_anchor });
+    }
+    Ok(mark_records)
+}
+
+/// Read an array of records with one optional anchor per mark class, as the base and mark2 arrays and each ligature attach are
+fn read_anchor_matrix(mut b: &[u8], mark_class_count: u16) -> Result<Vec<Vec<Option<Anchor>>>, Box<dyn Error>> {
+    let o = b;
+    let r = &mut b;
+
+    let count = read_uint16(r)?;
+    let mut records = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let mut anchors = Vec::with_capacity(mark_class_count as usize);
+        for offset in read_array(r, mark_class_count as usize, read_offset16)? {
+            anchors.push(Anchor::at(o, offset)?);
+        }
+        records.push(anchors);
+    }
+    Ok(records)
+}
+
+/// Move the mark at `i` so its anchor lands on `anchor` of the glyph at `j`
+fn attach_mark(ctx: &mut PositionContext, mark: &MarkRecord, anchor: &Anchor, i: usize, j: usize) {
+    let (mark_x, mark_y) = mark.mark_anchor.position(ctx.var_store, ctx.coords);
+    let (x, y) = anchor.position(ctx.var_store, ctx.coords);
+    let p = &mut ctx.positions[i];
+    p.x_offset = (x - mark_x).round() as i32;
+    p.y_offset = (y - mark_y).round() as i32;
+    p.attach_type = AttachType::Mark;
+    p.attach_chain = j as isize - i as isize;
+}
+
+/// Attach marks to the preceding base glyph
+pub struct MarkBasePos {
+    mark_coverage: Coverage,
+    base_coverage: Coverage,
+    mark_array: Vec<MarkRecord>,
+    /// One anchor per mark class for each base glyph, ordered by base coverage index
+    base_array: Vec<Vec<Option<Anchor>>>,
+}
+
+impl Debug for MarkBasePos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MarkBasePos")
+            .field("mark_coverage", &self.mark_coverage)
+            .field("base_coverage", &self.base_coverage)
+            .field("mark_array", &self.mark_array)
+            .field("base_array", &self.base_array)
+            .finish()
+    }
+}
+
+impl MarkBasePos {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        if 1 != format {
+            return Err(format!("unknown mark-to-base positioning format {}", format).into());
+        }
+        let mark_coverage = Coverage::at(o, read_offset16(r)?)?;
+        let base_coverage = Coverage::at(o, read_offset16(r)?)?;
+        let mark_class_count = read_uint16(r)?;
+        let mark_array_offset = read_offset16(r)?;
+        let base_array_offset = read_offset16(r)?;
+        let mark_array = read_mark_array(o.get(mark_array_offset as usize..).ok_or("MarkArray offset out of range")?)?;
+        let base_array = read_anchor_matrix(o.get(base_array_offset as usize..).ok_or("BaseArray offset out of range")?, mark_class_count)?;
+
+        Ok(Self {
+            mark_coverage,
+            base_coverage,
+            mark_array,
+            base_array,
+        })
+    }
+
+    /// Attach the mark at `i` to the closest glyph before it that isn't a mark
+    fn apply(&self, ctx: &mut PositionContext, i: usize, is_mark: &dyn Fn(&GlyphInfo) -> bool) -> Option<usize> {
+        let mark = self.mark_array.get(self.mark_coverage.index(ctx.glyphs[i].glyph_id)? as usize)?;
+        let base = prev_unskipped(ctx.glyphs, i, is_mark)?;
+        let anchors = self.base_array.get(self.base_coverage.index(ctx.glyphs[base].glyph_id)? as usize)?;
+        let anchor = anchors.get(mark.mark_class as usize)?.as_ref()?;
+        attach_mark(ctx, mark, anchor, i, base);
+        Some(i + 1)
+    }
+}
+
+/// Attach marks to a component of the preceding ligature
+pub struct MarkLigPos {
+    mark_coverage: Coverage,
+    ligature_coverage: Coverage,
+    mark_array: Vec<MarkRecord>,
+    /// For each ligature, ordered by coverage index, one anchor per mark class for each component
+    ligature_array: Vec<Vec<Vec<Option<Anchor>>>>,
+}
+
+impl Debug for MarkLigPos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MarkLigPos")
+            .field("mark_coverage", &self.mark_coverage)
+            .field("ligature_coverage", &self.ligature_coverage)
+            .field("mark_array", &self.mark_array)
+            .field("ligature_array", &self.ligature_array)
+            .finish()
+    }
+}
+
+impl MarkLigPos {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        if 1 != format {
+            return Err(format!("unknown mark-to-ligature positioning format {}", format).into());
+        }
+        let mark_coverage = Coverage::at(o, read_offset16(r)?)?;
+        let ligature_coverage = Coverage::at(o, read_offset16(r)?)?;
+        let mark_class_count = read_uint16(r)?;
+        let mark_array_offset = read_offset16(r)?;
+        let ligature_array_offset = read_offset16(r)?;
+        let mark_array = read_mark_array(o.get(mark_array_offset as usize..).ok_or("MarkArray offset out of range")?)?;
+
+        let mut la = o.get(ligature_array_offset as usize..).ok_or("LigatureArray offset out of range")?;
+        let l = la;
+        let ligature_count = read_uint16(&mut la)?;
+        let mut ligature_array = Vec::with_capacity(ligature_count as usize);
+        for offset in read_array(&mut la, ligature_count as usize, read_offset16)? {
+            ligature_array.push(read_anchor_matrix(l.get(offset as usize..).ok_or("LigatureAttach offset out of range")?, mark_class_count)?);
+        }
+
+        Ok(Self {
+            mark_coverage,
+            ligature_coverage,
+            mark_array,
+            ligature_array,
+        })
+    }
+
+    /// Attach the mark at `i` to the component of the preceding ligature it followed, or to the last component
+    fn apply(&self, ctx: &mut PositionContext, i: usize, is_mark: &dyn Fn(&GlyphInfo) -> bool) -> Option<usize> {
+        let mark = self.mark_array.get(self.mark_coverage.index(ctx.glyphs[i].glyph_id)? as usize)?;
+        let lig = prev_unskipped(ctx.glyphs, i, is_mark)?;
+        let components = self.ligature_array.get(self.ligature_coverage.index(ctx.glyphs[lig].glyph_id)? as usize)?;
+        if components.is_empty() {
+            return None;
+        }
+
+        let (m, l) = (&ctx.glyphs[i], &ctx.glyphs[lig]);
+        let component = if 0 != l.ligature_id && l.ligature_id == m.ligature_id && 0 < m.ligature_component {
+            (m.ligature_component as usize).min(components.len()) - 1
+        } else {
+            components.len() - 1
+        };
+        let anchor = components[component].get(mark.mark_class as usize)?.as_ref()?;
+        attach_mark(ctx, mark, anchor, i, lig);
+        Some(i + 1)
+    }
+}
+
+/// Attach marks to a preceding mark
+pub struct MarkMarkPos {
+    mark1_coverage: Coverage,
+    mark2_coverage: Coverage,
+    mark1_array: Vec<MarkRecord>,
+    /// One anchor per mark class for each mark2 glyph, ordered by mark2 coverage index
+    mark2_array: Vec<Vec<Option<Anchor>>>,
+}
+
+impl Debug for MarkMarkPos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MarkMarkPos")
+            .field("mark1_coverage", &self.mark1_coverage)
+            .field("mark2_coverage", &self.mark2_coverage)
+            .field("mark1_array", &self.mark1_array)
+            .field("mark2_array", &self.mark2_array)
+            .finish()
+    }
+}
+
+impl MarkMarkPos {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        if 1 != format {
+            return Err(format!("unknown mark-to-mark positioning format {}", format).into());
+        }
+        let mark1_coverage = Coverage::at(o, read_offset16(r)?)?;
+        let mark2_coverage = Coverage::at(o, read_offset16(r)?)?;
+        let mark_class_count = read_uint16(r)?;
+        let mark1_array_offset = read_offset16(r)?;
+        let mark2_array_offset = read_offset16(r)?;
+        let mark1_array = read_mark_array(o.get(mark1_array_offset as usize..).ok_or("Mark1Array offset out of range")?)?;
+        let mark2_array = read_anchor_matrix(o.get(mark2_array_offset as usize..).ok_or("Mark2Array offset out of range")?, mark_class_count)?;
+
+        Ok(Self {
+            mark1_coverage,
+            mark2_coverage,
+            mark1_array,
+            mark2_array,
+        })
+    }
+
+    /// Attach the mark at `i` to the previous mark, when both belong to the same base or the same ligature component
+    fn apply(&self, ctx: &mut PositionContext, i: usize, skip: &dyn Fn(&GlyphInfo) -> bool, is_mark: &dyn Fn(&GlyphInfo) -> bool) -> Option<usize> {
+        let mark1 = self.mark1_array.get(self.mark1_coverage.index(ctx.glyphs[i].glyph_id)? as usize)?;
+        let j = prev_unskipped(ctx.glyphs, i, skip)?;
+        if ctx.gdef.is_some() && !is_mark(&ctx.glyphs[j]) {
+            return None;
+        }
+
+        let (m1, m2) = (&ctx.glyphs[i], &ctx.glyphs[j]);
+        let same_base = if m1.ligature_id == m2.ligature_id {
+            0 == m1.ligature_id || m1.ligature_component == m2.ligature_component
+        } else {
+            // one of them is the ligature glyph itself
+            (0 < m1.ligature_id && 0 == m1.ligature_component) || (0 < m2.ligature_id && 0 == m2.ligature_component)
+        };
+        if !same_base {
+            return None;
+        }
+
+        let anchors = self.mark2_array.get(self.mark2_coverage.index(m2.glyph_id)? as usize)?;
+        let anchor = anchors.get(mark1.mark_class as usize)?.as_ref()?;
+        attach_mark(ctx, mark1, anchor, i, j);
+        Some(i + 1)
+    }
+}
+
+/// Fold the VariationIndex deltas of the GPOS bytes `b` at normalized `coords` into the values and anchors they adjust, for a static instance
+///
+/// `var_store` is the ItemVariationStore of GDEF, the Device offsets of folded values are cleared
+pub fn instance_device_deltas(b: &mut [u8], var_store: Option<&ItemVariationStore>, coords: &[F2Dot14]) -> Result<(), Box<dyn Error>> {
+    let mut folder = VariationIndexFolder::new(var_store, coords);
+    let lookup_list = get_uint16(b, 8)? as usize;
+    if 0 == lookup_list {
+        return Ok(());
+    }
+    for i in 0..get_uint16(b, lookup_list)? as usize {
+        let lookup = lookup_list + get_uint16(b, lookup_list + 2 + 2 * i)? as usize;
+        let lookup_type = get_uint16(b, lookup)?;
+        for j in 0..get_uint16(b, lookup + 4)? as usize {
+            let subtable = lookup + get_uint16(b, lookup + 6 + 2 * j)? as usize;
+            fold_subtable(b, &mut folder, lookup_type, subtable)?;
+        }
+    }
+    Ok(())
+}
+
+fn fold_subtable(b: &mut [u8], folder: &mut VariationIndexFolder, lookup_type: u16, st: usize) -> Result<(), Box<dyn Error>> {
+    if !folder.first_visit(st) {
+        return Ok(());
+    }
+    let format = get_uint16(b, st)?;
+    match (lookup_type, format) {
+        (SINGLE, 1) => fold_value_record(b, folder, st, st + 6, get_uint16(b, st + 4)?)?,
+        (SINGLE, 2) => {
+            let value_format = get_uint16(b, st + 4)?;
+            for k in 0..get_uint16(b, st + 6)? as usize {
+                fold_value_record(b, folder, st, st + 8 + k * value_record_size(value_format), value_format)?;
+            }
+        }
+        (PAIR, 1) => {
+            let (value_format1, value_format2) = (get_uint16(b, st + 4)?, get_uint16(b, st + 6)?);
+            for k in 0..get_uint16(b, st + 8)? as usize {
+                // device offsets in a PairValueRecord are from the start of the PairSet
+                let pair_set = st + get_uint16(b, st + 10 + 2 * k)? as usize;
+                if !folder.first_visit(pair_set) {
+                    continue;
+                }
+                let mut record = pair_set + 2;
+                for _ in 0..get_uint16(b, pair_set)? {
+                    fold_value_record(b, folder, pair_set, record + 2, value_format1)?;
+                    fold_value_record(b, folder, pair_set, record + 2 + value_record_size(value_format1), value_format2)?;
+                    record += 2 + value_record_size(value_format1) + value_record_size(value_format2);
+                }
+            }
+        }
+        (PAIR, 2) => {
+            let (value_format1, value_format2) = (get_uint16(b, st + 4)?, get_uint16(b, st + 6)?);
+            let count = get_uint16(b, st + 12)? as usize * get_uint16(b, st + 14)? as usize;
+            let mut record = st + 16;
+            for _ in 0..count {
+                fold_value_record(b, folder, st, record, value_format1)?;
+                fold_value_record(b, folder, st, record + value_record_size(value_format1), value_format2)?;
+                record += value_record_size(value_format1) + value_record_size(value_format2);
+            }
+        }
+        (CURSIVE, 1) => {
+            for k in 0..get_uint16(b, st + 4)? as usize {
+                fold_anchor(b, folder, st, st + 6 + 4 * k)?;
+                fold_anchor(b, folder, st, st + 8 + 4 * k)?;
+            }
+        }
+        (MARK_TO_BASE | MARK_TO_MARK, 1) => {
+            let mark_class_count = get_uint16(b, st + 6)? as usize;
+            fold_mark_array(b, folder, st + get_uint16(b, st + 8)? as usize)?;
+            fold_anchor_matrix(b, folder, st + get_uint16(b, st + 10)? as usize, mark_class_count)?;
+        }
+        (MARK_TO_LIGATURE, 1) => {
+            let mark_class_count = get_uint16(b, st + 6)? as usize;
+            fold_mark_array(b, folder, st + get_uint16(b, st + 8)? as usize)?;
+            let ligature_array = st + get_uint16(b, st + 10)? as usize;
+            if folder.first_visit(ligature_array) {
+                for k in 0..get_uint16(b, ligature_array)? as usize {
+                    let ligature_attach = ligature_array + get_uint16(b, ligature_array + 2 + 2 * k)? as usize;
+                    fold_anchor_matrix(b, folder, ligature_attach, mark_class_count)?;
+                }
+            }
+        }
+        (EXTENSION_POSITIONING, 1) => fold_subtable(b, folder, get_uint16(b, st + 2)?, st + get_uint32(b, st + 4)? as usize)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Bytes of a ValueRecord with `value_format`
+fn value_record_size(value_format: u16) -> usize {
+    (value_format & 0xFF).count_ones() as usize * 2
+}
+
+/// Device offsets follow the values, each device adjusts the value of the same kind when the record has it
+fn fold_value_record(b: &mut [u8], folder: &VariationIndexFolder, base: usize, pos: usize, value_format: u16) -> Result<(), Box<dyn Error>> {
+    let mut p = pos;
+    let mut values = [None; 4];
+    for (i, flag) in [X_PLACEMENT, Y_PLACEMENT, X_ADVANCE, Y_ADVANCE].into_iter().enumerate() {
+        if 0 != value_format & flag {
+            values[i] = Some(p);
+            p += 2;
+        }
+    }
+    for (i, flag) in [X_PLACEMENT_DEVICE, Y_PLACEMENT_DEVICE, X_ADVANCE_DEVICE, Y_ADVANCE_DEVICE].into_iter().enumerate() {
+        if 0 != value_format & flag {
+            folder.fold(b, base, p, values[i])?;
+            p += 2;
+        }
+    }
+    Ok(())
+}
+
+/// A format 3 anchor at the Offset16 `offset_pos` from `base`, shared anchors are folded once
+fn fold_anchor(b: &mut [u8], folder: &mut VariationIndexFolder, base: usize, offset_pos: usize) -> Result<(), Box<dyn Error>> {
+    let offset = get_uint16(b, offset_pos)? as usize;
+    if 0 == offset || !folder.first_visit(base + offset) {
+        return Ok(());
+    }
+    let anchor = base + offset;
+    if 3 == get_uint16(b, anchor)? {
+        folder.fold(b, anchor, anchor + 6, Some(anchor + 2))?;
+        folder.fold(b, anchor, anchor + 8, Some(anchor + 4))?;
+    }
+    Ok(())
+}
+
+fn fold_mark_array(b: &mut [u8], folder: &mut VariationIndexFolder, mark_array: usize) -> Result<(), Box<dyn Error>> {
+    if !folder.first_visit(mark_array) {
+        return Ok(());
+    }
+    for k in 0..get_uint16(b, mark_array)? as usize {
+        fold_anchor(b, folder, mark_array, mark_array + 4 + 4 * k)?;
+    }
+    Ok(())
+}
+
+/// BaseArray, Mark2Array and LigatureAttach tables: a count of rows with one anchor offset per mark class
+fn fold_anchor_matrix(b: &mut [u8], folder: &mut VariationIndexFolder, matrix: usize, mark_class_count: usize) -> Result<(), Box<dyn Error>> {
+    if !folder.first_visit(matrix) {
+        return Ok(());
+    }
+    for k in 0..get_uint16(b, matrix)? as usize * mark_class_count {
+        fold_anchor(b, folder, matrix, matrix + 2 + 2 * k)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::builders::*;
+
+    fn anchor(w: &mut Vec<u8>, x: i16, y: i16) {
+        write_uint16(w, 1);
+        write_int16(w, x);
+        write_int16(w, y);
+    }
+
+    /// Pair positioning format 1 moving `second` by `x_advance` after `first`
+    fn pair_glyphs(first: GlyphId, second: GlyphId, x_advance: i16) -> Vec<u8> {
+        let mut w = Vec::new();
+        [1, 18, X_ADVANCE, 0, 1, 12, 1, second].iter().for_each(|&v| write_uint16(&mut w, v));
+        write_int16(&mut w, x_advance);
+        w.extend(coverage(&[first]));
+        w
+    }
+
+    /// Pair positioning format 2 changing the advance of `first` by `x_advance` before glyphs 5 and 6, which are class 1
+    fn pair_classes(first: GlyphId, x_advance: i16) -> Vec<u8> {
+        let mut w = Vec::new();
+        [2, 20, X_ADVANCE, 0, 0, 26, 1, 2].iter().for_each(|&v| write_uint16(&mut w, v));
+        write_int16(&mut w, 0);
+        write_int16(&mut w, x_advance);
+        w.extend(coverage(&[first]));
+        [1, 5, 2, 1, 1].iter().for_each(|&v| write_uint16(&mut w, v));
+        w
+    }
+
+    /// Optional entry and exit anchor of a glyph
+    type EntryExit = (Option<(i16, i16)>, Option<(i16, i16)>);
+
+    /// Cursive attachment of `glyphs`, each with its entry and exit anchor
+    fn cursive(glyphs: &[GlyphId], entry_exit: &[EntryExit]) -> Vec<u8> {
+        let mut w = Vec::new();
+        let coverage = coverage(glyphs);
+        write_uint16(&mut w, 1);
+        write_uint16(&mut w, 6 + 4 * entry_exit.len() as u16);
+        write_uint16(&mut w, entry_exit.len() as u16);
+        let mut offset = 6 + 4 * entry_exit.len() + coverage.len();
+        let mut anchors = Vec::new();
+        for &a in entry_exit.iter().flat_map(|(entry, exit)| [entry, exit]) {
+            match a {
+                Some((x, y)) => {
+                    write_uint16(&mut w, offset as u16);
+                    anchor(&mut anchors, x, y);
+                    offset += 6;
+                }
+                None => write_uint16(&mut w, 0),
+            }
+        }
+        w.extend(coverage);
+        w.extend(anchors);
+        w
+    }
+
+    /// Mark-to-base or mark-to-mark positioning of `mark` with its anchor at `mark_anchor` on `target` with its anchor at `target_anchor`
+    fn mark_attachment(mark: GlyphId, mark_anchor: (i16, i16), target: GlyphId, target_anchor: (i16, i16)) -> Vec<u8> {
+        let mut w = Vec::new();
+        [1, 12, 18, 1, 24, 36].iter().for_each(|&v| write_uint16(&mut w, v));
+        w.extend(coverage(&[mark]));
+        w.extend(coverage(&[target]));
+        [1, 0, 6].iter().for_each(|&v| write_uint16(&mut w, v));
+        anchor(&mut w, mark_anchor.0, mark_anchor.1);
+        [1, 4].iter().for_each(|&v| write_uint16(&mut w, v));
+        anchor(&mut w, target_anchor.0, target_anchor.1);
+        w
+    }
+
+    /// Mark-to-base positioning of `mark` with its anchor at (100, 0) on `base` with its anchor at (250, 600)
+    fn mark_to_base(mark: GlyphId, base: GlyphId) -> Vec<u8> {
+        mark_attachment(mark, (100, 0), base, (250, 600))
+    }
+
+    /// Mark-to-ligature positioning of `mark` with its anchor at (0, 0) on a two component `ligature` with anchors at (100, 500) and (400, 500)
+    fn mark_to_ligature(mark: GlyphId, ligature: GlyphId) -> Vec<u8> {
+        let mut w = Vec::new();
+        [1, 12, 18, 1, 24, 36].iter().for_each(|&v| write_uint16(&mut w, v));
+        w.extend(coverage(&[mark]));
+        w.extend(coverage(&[ligature]));
+        [1, 0, 6].iter().for_each(|&v| write_uint16(&mut w, v));
+        anchor(&mut w, 0, 0);
+        // LigatureArray with one LigatureAttach at 4, its component anchors at 6 and 12 from there
+        [1, 4, 2, 6, 12].iter().for_each(|&v| write_uint16(&mut w, v));
+        anchor(&mut w, 100, 500);
+        anchor(&mut w, 400, 500);
+        w
+    }
+
+    fn gpos(lookups: &[Vec<u8>]) -> GlyphPositioningTable {
+        GlyphPositioningTable::from_bytes(&layout_table(lookups)).unwrap()
+    }
+
+    /// Run every lookup of `gpos` over glyphs that each advance 500 units
+    fn position(gpos: &GlyphPositioningTable, glyph_ids: &[GlyphId]) -> Vec<(i32, i32, i32)> {
+        let glyphs: Vec<_> = glyph_ids.iter().enumerate().map(|(i, &g)| GlyphInfo::new(g, i)).collect();
+        position_glyphs(gpos, &glyphs, None)
+    }
+
+    fn position_glyphs(gpos: &GlyphPositioningTable, glyphs: &[GlyphInfo], gdef: Option<&GlyphDefinitionTable>) -> Vec<(i32, i32, i32)> {
+        let mut positions = vec![GlyphPosition::new(500, 0); glyphs.len()];
+        let lookups: Vec<_> = (0..gpos.lookup_list().lookups().len() as u16).collect();
+        gpos.position(glyphs, &mut positions, &lookups, &[], false, gdef);
+        positions.iter().map(|p| (p.x_advance, p.x_offset, p.y_offset)).collect()
+    }
+
+    #[test]
+    fn pair_adjustment_by_glyph() {
+        let gpos = gpos(&[lookup(PAIR, 0, &[pair_glyphs(1, 2, -50)])]);
+        assert_eq!(position(&gpos, &[1, 2, 1, 3, 2]), [(450, 0, 0), (500, 0, 0), (500, 0, 0), (500, 0, 0), (500, 0, 0)]);
+    }
+
+    #[test]
+    fn pair_adjustment_by_class() {
+        let gpos = gpos(&[lookup(PAIR, 0, &[pair_classes(1, -30)])]);
+        assert_eq!(position(&gpos, &[1, 5, 1, 6, 1, 7]), [(470, 0, 0), (500, 0, 0), (470, 0, 0), (500, 0, 0), (500, 0, 0), (500, 0, 0)]);
+    }
+
+    #[test]
+    fn cursive_attachment() {
+        // glyph 1 only exits, glyph 3 only enters, each exit is 200 units above the entry it joins
+        let st = cursive(&[1, 2, 3], &[(None, Some((450, 200))), (Some((50, 0)), Some((450, 200))), (Some((50, 0)), None)]);
+
+        // the first glyph of the chain stays on the baseline and the others climb
+        let left_to_right = gpos(&[lookup(CURSIVE, 0, std::slice::from_ref(&st))]);
+        assert_eq!(position(&left_to_right, &[1, 2, 3]), [(450, 0, 0), (400, -50, 200), (450, -50, 400)]);
+
+        // right-to-left lookups keep the last glyph on the baseline instead
+        let right_to_left = gpos(&[lookup(CURSIVE, RIGHT_TO_LEFT, &[st])]);
+        assert_eq!(position(&right_to_left, &[1, 2, 3]), [(450, 0, -400), (400, -50, -200), (450, -50, 0)]);
+    }
+
+    #[test]
+    fn mark_to_base_attachment() {
+        let gpos = gpos(&[lookup(MARK_TO_BASE, 0, &[mark_to_base(2, 1)])]);
+        // the anchors line up 150 units right of the mark's origin, less the base advance the pen has already moved past
+        assert_eq!(position(&gpos, &[1, 2, 3, 2]), [(500, 0, 0), (500, -350, 600), (500, 0, 0), (500, 0, 0)]);
+    }
+
+    #[test]
+    fn mark_to_base_skips_other_marks() {
+        let mut b = Vec::new();
+        [1, 0, 12, 0, 0, 0].iter().for_each(|&v| write_uint16(&mut b, v));
+        b.extend(class_def(&[(1, 1, 1), (2, 3, 3)]));
+        let gdef = GlyphDefinitionTable::from_bytes(&b).unwrap();
+        let glyphs: Vec<_> = [1, 3, 2].iter().enumerate().map(|(i, &g)| GlyphInfo::new(g, i)).collect();
+
+        let gpos = gpos(&[lookup(MARK_TO_BASE, 0, &[mark_to_base(2, 1)])]);
+        assert_eq!(position_glyphs(&gpos, &glyphs, Some(&gdef)), [(500, 0, 0), (500, 0, 0), (500, -850, 600)]);
+        // without glyph classes the mark in between is taken for the base
+        assert_eq!(position_glyphs(&gpos, &glyphs, None), [(500, 0, 0), (500, 0, 0), (500, 0, 0)]);
+    }
+
+    #[test]
+    fn mark_to_ligature_attachment() {
+        let gpos = gpos(&[lookup(MARK_TO_LIGATURE, 0, &[mark_to_ligature(2, 10)])]);
+
+        // a mark that followed the second component of the ligature takes that component's anchor
+        let mut glyphs = vec![GlyphInfo::new(10, 0), GlyphInfo::new(2, 2)];
+        glyphs[0].ligature_id = 1;
+        (glyphs[1].ligature_id, glyphs[1].ligature_component) = (1, 2);
+        assert_eq!(position_glyphs(&gpos, &glyphs, None), [(500, 0, 0), (500, -100, 500)]);
+
+        // a mark from outside the ligature goes on its last component
+        glyphs[1].ligature_id = 2;
+        assert_eq!(position_glyphs(&gpos, &glyphs, None), [(500, 0, 0), (500, -100, 500)]);
+        (glyphs[1].ligature_id, glyphs[1].ligature_component) = (1, 1);
+        assert_eq!(position_glyphs(&gpos, &glyphs, None), [(500, 0, 0), (500, -400, 500)]);
+    }
+
+    #[test]
+    fn mark_to_mark_attachment() {
+        let gpos = gpos(&[lookup(MARK_TO_BASE, 0, &[mark_to_base(2, 1)]), lookup(MARK_TO_MARK, 0, &[mark_attachment(3, (0, 0), 2, (50, 700))])]);
+        // the second mark stacks on the first one, wherever that one went
+        assert_eq!(position(&gpos, &[1, 2, 3]), [(500, 0, 0), (500, -350, 600), (500, -800, 1300)]);
+        assert_eq!(position(&gpos, &[1, 3]), [(500, 0, 0), (500, 0, 0)]);
+    }
+}
@@ -0,0 +1,601 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::layout::*;
+use super::t_gdef::*;
+use super::types::*;
+use super::utils::*;
+
+pub struct GlyphSubstitutionTable {
+    /// Major version of the GSUB table, = 1
+    major_version: u16,
+    /// Minor version of the GSUB table, = 0 or 1
+    minor_version: u16,
+    script_list: ScriptList,
+    feature_list: FeatureList,
+    lookup_list: LookupList<SubstitutionSubtable>,
+    /// Version 1.1 only
+    feature_variations: Option<FeatureVariations>,
+}
+
+impl Debug for GlyphSubstitutionTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GlyphSubstitutionTable")
+            .field("major_version", &self.major_version)
+            .field("minor_version", &self.minor_version)
+            .field("script_list", &self.script_list)
+            .field("feature_list", &self.feature_list)
+            .field("lookup_list", &self.lookup_list)
+            .field("feature_variations", &self.feature_variations)
+            .finish()
+    }
+}
+
+impl GlyphSubstitutionTable {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let major_version = read_uint16(r)?;
+        let minor_version = read_uint16(r)?;
+        if 1 != major_version {
+            return Err(format!("unsupported GSUB version {}.{}", major_version, minor_version).into());
+        }
+
+        let script_list_offset = read_offset16(r)?;
+        let feature_list_offset = read_offset16(r)?;
+        let lookup_list_offset = read_offset16(r)?;
+        let feature_variations_offset = if 1 <= minor_version { read_offset32(r)? } else { 0 };
+
+        let script_list = ScriptList::from_bytes(o.get(script_list_offset as usize..).ok_or("GSUB ScriptList offset out of range")?)?;
+        let feature_list = FeatureList::from_bytes(o.get(feature_list_offset as usize..).ok_or("GSUB FeatureList offset out of range")?)?;
+        let lookup_list = LookupList::from_bytes(o.get(lookup_list_offset as usize..).ok_or("GSUB LookupList offset out of range")?)?;
+        let feature_variations = match feature_variations_offset {
+            0 => None,
+            offset => Some(FeatureVariations::from_bytes(o.get(offset as usize..).ok_or("GSUB FeatureVariations offset out of range")?)?),
+        };
+
+        Ok(Self {
+            major_version,
+            minor_version,
+            script_list,
+            feature_list,
+            lookup_list,
+            feature_variations,
+        })
+    }
+
+    pub fn script_list(&self) -> &ScriptList {
+        &self.script_list
+    }
+
+    pub fn feature_list(&self) -> &FeatureList {
+        &self.feature_list
+    }
+
+    pub fn lookup_list(&self) -> &LookupList<SubstitutionSubtable> {
+        &self.lookup_list
+    }
+
+    pub fn feature_variations(&self) -> Option<&FeatureVariations> {
+        self.feature_variations.as_ref()
+    }
+
+    /// Apply `features` to the glyph buffer, the lookups run in LookupList order as the spec requires,
+    /// and without GDEF lookup flags can't skip any glyph
+    pub fn substitute(&self, glyphs: &mut Vec<GlyphInfo>, script: Tag, language: Option<Tag>, features: &[Tag], coords: &[F2Dot14], gdef: Option<&GlyphDefinitionTable>) {
+        for lookup_index in select_lookups(&self.script_list, &self.feature_list, self.feature_variations.as_ref(), script, language, features, coords) {
+            self.apply_lookup(lookup_index, glyphs, gdef);
+        }
+    }
+
+    /// Apply one lookup across the whole buffer
+    pub fn apply_lookup(&self, lookup_index: u16, glyphs: &mut Vec<GlyphInfo>, gdef: Option<&GlyphDefinitionTable>) {
+        let Some(lookup) = self.lookup_list.get(lookup_index) else {
+            return;
+        };
+        let skip = |g: &GlyphInfo| lookup_skips(gdef, lookup, g);
+
+        if REVERSE_CHAINING_CONTEXTUAL_SINGLE == lookup.lookup_type() {
+            // reverse chaining substitutions run from the end of the buffer and never change its length
+            for i in (0..glyphs.len()).rev() {
+                if !skip(&glyphs[i]) {
+                    self.apply_at(lookup_index, glyphs, i, gdef, 0);
+                }
+            }
+            return;
+        }
+
+        // bound the work done on malicious fonts that keep growing the buffer
+        let mut budget = glyphs.len().max(1) * 64;
+        let mut i = 0;
+        while i < glyphs.len() && budget > 0 {
+            budget -= 1;
+            if skip(&glyphs[i]) {
+                i += 1;
+                continue;
+            }
+            i = match self.apply_at(lookup_index, glyphs, i, gdef, 0) {
+                Some(next) => next,
+                None => i + 1,
+            };
+        }
+    }
+
+    /// Apply the first subtable of a lookup that matches at `i`, giving the position to continue from
+    fn apply_at(&self, lookup_index: u16, glyphs: &mut Vec<GlyphInfo>, i: usize, gdef: Option<&GlyphDefinitionTable>, depth: usize) -> Option<usize> {
+        if depth > MAX_NESTING_LEVEL {
+            return None;
+        }
+        let lookup = self.lookup_list.get(lookup_index)?;
+        let skip = |g: &GlyphInfo| lookup_skips(gdef, lookup, g);
+
+        let glyph_id = glyphs.get(i)?.glyph_id;
+        for subtable in lookup.subtables() {
+            let next = match subtable {
+                SubstitutionSubtable::Single(st) => st.substitute(glyph_id).map(|g| {
+                    glyphs[i].glyph_id = g;
+                    i + 1
+                }),
+                SubstitutionSubtable::Multiple(st) => st.sequence(glyph_id).map(|seq| {
+                    let info = glyphs[i];
+                    glyphs.splice(i..=i, seq.iter().map(|&g| GlyphInfo { glyph_id: g, ..info }));
+                    i + seq.len()
+                }),
+                SubstitutionSubtable::Alternate(st) => st.alternates(glyph_id).and_then(|alternates| alternates.first()).map(|&g| {
+                    glyphs[i].glyph_id = g;
+                    i + 1
+                }),
+                SubstitutionSubtable::Ligature(st) => st.apply(glyphs, i, &skip),
+                SubstitutionSubtable::Context(st) => st.matches(glyphs, i, &skip).map(|(positions, records)| self.apply_records(glyphs, positions, records, gdef, depth)),
+                SubstitutionSubtable::ChainedContext(st) => st.matches(glyphs, i, &skip).map(|(positions, records)| self.apply_records(glyphs, positions, records, gdef, depth)),
+                SubstitutionSubtable::ReverseChainSingle(st) => st.substitute(glyphs, i, &skip).map(|g| {
+                    glyphs[i].glyph_id = g;
+                    i + 1
+                }),
+            };
+            if next.is_some() {
+                return next;
+            }
+        }
+        None
+    }
+
+    /// Apply the nested lookups of a matched context, positions after a change move with the buffer length
+    fn apply_records(&self, glyphs: &mut Vec<GlyphInfo>, mut positions: Vec<usize>, records: &[SequenceLookupRecord], gdef: Option<&GlyphDefinitionTable>, depth: usize) -> usize {
+        let mut end = positions.last().map_or(0, |&p| p + 1);
+        for record in records {
+            let Some(&pos) = positions.get(record.sequence_index as usize) else {
+                continue;
+            };
+            if pos >= glyphs.len() {
+                continue;
+            }
+
+            let before = glyphs.len();
+            self.apply_at(record.lookup_list_index, glyphs, pos, gdef, depth + 1);
+            let delta = glyphs.len() as isize - before as isize;
+            if 0 != delta {
+                for p in positions.iter_mut().skip(record.sequence_index as usize + 1) {
+                    *p = p.saturating_add_signed(delta);
+                }
+                end = end.saturating_add_signed(delta);
+            }
+        }
+        end
+    }
+}
+
+const SINGLE: u16 = 1;
+const MULTIPLE: u16 = 2;
+const ALTERNATE: u16 = 3;
+const LIGATURE: u16 = 4;
+const CONTEXT: u16 = 5;
+const CHAINING_CONTEXT: u16 = 6;
+const EXTENSION_SUBSTITUTION: u16 = 7;
+const REVERSE_CHAINING_CONTEXTUAL_SINGLE: u16 = 8;
+
+pub enum SubstitutionSubtable {
+    Single(SingleSubst),
+    Multiple(MultipleSubst),
+    Alternate(AlternateSubst),
+    Ligature(LigatureSubst),
+    Context(SequenceContext),
+    ChainedContext(ChainedSequenceContext),
+    ReverseChainSingle(ReverseChainSingleSubst),
+}
+
+impl Debug for SubstitutionSubtable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Single(st) => st.fmt(f),
+            Self::Multiple(st) => st.fmt(f),
+            Self::Alternate(st) => st.fmt(f),
+            Self::Ligature(st) => st.fmt(f),
+            Self::Context(st) => f.debug_tuple("Context").field(st).finish(),
+            Self::ChainedContext(st) => f.debug_tuple("ChainedContext").field(st).finish(),
+            Self::ReverseChainSingle(st) => st.fmt(f),
+        }
+    }
+}
+
+impl LookupSubtable for SubstitutionSubtable {
+    const EXTENSION_LOOKUP_TYPE: u16 = EXTENSION_SUBSTITUTION;
+
+    fn from_bytes(b: &[u8], lookup_type: u16) -> Result<Self, Box<dyn Error>> {
+        match lookup_type {
+            SINGLE => Ok(Self::Single(SingleSubst::from_bytes(b)?)),
+            MULTIPLE => Ok(Self::Multiple(MultipleSubst::from_bytes(b)?)),
+            ALTERNATE => Ok(Self::Alternate(AlternateSubst::from_bytes(b)?)),
+            LIGATURE => Ok(Self::Ligature(LigatureSubst::from_bytes(b)?)),
+            CONTEXT => Ok(Self::Context(SequenceContext::from_bytes(b)?)),
+            CHAINING_CONTEXT => Ok(Self::ChainedContext(ChainedSequenceContext::from_bytes(b)?)),
+            REVERSE_CHAINING_CONTEXTUAL_SINGLE => Ok(Self::ReverseChainSingle(ReverseChainSingleSubst::from_bytes(b)?)),
+            _ => Err(format!("unknown GSUB lookup type {}", lookup_type).into()),
+        }
+    }
+}
+
+pub enum SingleSubst {
+    /// Add a delta to the glyph ID, modulo 65536
+    Format1 { coverage: Coverage, delta_glyph_id: i16 },
+    /// Substitute glyphs ordered by coverage index
+    Format2 { coverage: Coverage, substitute_glyph_ids: Vec<GlyphId> },
+}
+
+impl Debug for SingleSubst {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Format1 { coverage, delta_glyph_id } => f.debug_struct("SingleSubstFormat1").field("coverage", coverage).field("delta_glyph_id", delta_glyph_id).finish(),
+            Self::Format2 { coverage, substitute_glyph_ids } => f.debug_struct("SingleSubstFormat2").field("coverage", coverage).field("substitute_glyph_ids", substitute_glyph_ids).finish(),
+        }
+    }
+}
+
+impl SingleSubst {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        let coverage = Coverage::at(o, read_offset16(r)?)?;
+        match format {
+            1 => Ok(Self::Format1 { coverage, delta_glyph_id: read_int16(r)? }),
+            2 => {
+                let glyph_count = read_uint16(r)?;
+                Ok(Self::Format2 {
+                    coverage,
+                    substitute_glyph_ids: read_array(r, glyph_count as usize, read_uint16)?,
+                })
+            }
+            _ => Err(format!("unknown single substitution format {}", format).into()),
+        }
+    }
+
+    pub fn substitute(&self, glyph_id: GlyphId) -> Option<GlyphId> {
+        match self {
+            Self::Format1 { coverage, delta_glyph_id } => coverage.index(glyph_id).map(|_| glyph_id.wrapping_add_signed(*delta_glyph_id)),
+            Self::Format2 { coverage, substitute_glyph_ids } => substitute_glyph_ids.get(coverage.index(glyph_id)? as usize).copied(),
+        }
+    }
+}
+
+/// Arrays of glyphs selected by coverage index
+type GlyphSequences = Vec<Vec<GlyphId>>;
+
+/// Read a coverage and its glyph sequences, as multiple and alternate substitutions store them
+fn read_glyph_sequences(mut b: &[u8]) -> Result<(Coverage, GlyphSequences), Box<dyn Error>> {
+    let o = b;
+    let r = &mut b;
+
+    let _format = read_uint16(r)?;
+    let coverage = Coverage::at(o, read_offset16(r)?)?;
+    let count = read_uint16(r)?;
+    let mut sequences = Vec::with_capacity(count as usize);
+    for offset in read_array(r, count as usize, read_offset16)? {
+        let mut s = o.get(offset as usize..).ok_or("glyph sequence offset out of range")?;
+        let glyph_count = read_uint16(&mut s)?;
+        sequences.push(read_array(&mut s, glyph_count as usize, read_uint16)?);
+    }
+    Ok((coverage, sequences))
+}
+
+/// Replace a single glyph with a sequence of glyphs
+pub struct MultipleSubst {
+    coverage: Coverage,
+    sequences: Vec<Vec<GlyphId>>,
+}
+
+impl Debug for MultipleSubst {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MultipleSubst").field("coverage", &self.coverage).field("sequences", &self.sequences).finish()
+    }
+}
+
+impl MultipleSubst {
+    pub fn from_bytes(b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let (coverage, sequences) = read_glyph_sequences(b)?;
+        Ok(Self { coverage, sequences })
+    }
+
+    pub fn sequence(&self, glyph_id: GlyphId) -> Option<&[GlyphId]> {
+        self.sequences.get(self.coverage.index(glyph_id)? as usize).map(|v| v.as_slice())
+    }
+}
+
+/// Replace a glyph with one of several alternates, the engine picks the first
+pub struct AlternateSubst {
+    coverage: Coverage,
+    alternate_sets: Vec<Vec<GlyphId>>,
+}
+
+impl Debug for AlternateSubst {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AlternateSubst").field("coverage", &self.coverage).field("alternate_sets", &self.alternate_sets).finish()
+    }
+}
+
+impl AlternateSubst {
+    pub fn from_bytes(b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let (coverage, alternate_sets) = read_glyph_sequences(b)?;
+        Ok(Self { coverage, alternate_sets })
+    }
+
+    pub fn alternates(&self, glyph_id: GlyphId) -> Option<&[GlyphId]> {
+        self.alternate_sets.get(self.coverage.index(glyph_id)? as usize).map(|v| v.as_slice())
+    }
+}
+
+/// Replace a sequence of glyphs with one ligature glyph
+pub struct LigatureSubst {
+    coverage: Coverage,
+    /// Ligatures by the coverage index of their first component, in preference order
+    ligature_sets: Vec<Vec<Ligature>>,
+}
+
+impl Debug for LigatureSubst {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LigatureSubst").field("coverage", &self.coverage).field("ligature_sets", &self.ligature_sets).finish()
+    }
+}
+
+impl LigatureSubst {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        if 1 != format {
+            return Err(format!("unknown ligature substitution format {}", format).into());
+        }
+        let coverage = Coverage::at(o, read_offset16(r)?)?;
+        let ligature_set_count = read_uint16(r)?;
+        let mut ligature_sets = Vec::with_capacity(ligature_set_count as usize);
+        for offset in read_array(r, ligature_set_count as usize, read_offset16)? {
+            let mut s = o.get(offset as usize..).ok_or("LigatureSet offset out of range")?;
+            let ls = s;
+            let ligature_count = read_uint16(&mut s)?;
+            let mut ligatures = Vec::with_capacity(ligature_count as usize);
+            for ligature_offset in read_array(&mut s, ligature_count as usize, read_offset16)? {
+                ligatures.push(Ligature::from_bytes(ls.get(ligature_offset as usize..).ok_or("Ligature offset out of range")?)?);
+            }
+            ligature_sets.push(ligatures);
+        }
+
+        Ok(Self { coverage, ligature_sets })
+    }
+
+    pub fn ligatures(&self, glyph_id: GlyphId) -> Option<&[Ligature]> {
+        self.ligature_sets.get(self.coverage.index(glyph_id)? as usize).map(|v| v.as_slice())
+    }
+
+    /// Form the first ligature that matches at `i`, skipped glyphs in between stay and remember the component they followed
+    pub fn apply(&self, glyphs: &mut Vec<GlyphInfo>, i: usize, skip: &dyn Fn(&GlyphInfo) -> bool) -> Option<usize> {
+        let ligatures = self.ligatures(glyphs.get(i)?.glyph_id)?;
+        let (ligature, positions) = ligatures.iter().find_map(|l| Some((l, match_input(glyphs, i, l.component_glyph_ids.len(), skip, |k, g| g == l.component_glyph_ids[k])?)))?;
+
+        let ligature_id = glyphs.iter().map(|g| g.ligature_id).max().unwrap_or(0).wrapping_add(1).max(1);
+        let last = *positions.last()?;
+        let mut component = 1;
+        for (j, g) in glyphs.iter_mut().enumerate().take(last).skip(i + 1) {
+            if positions.contains(&j) {
+                component += 1;
+            } else {
+                g.ligature_id = ligature_id;
+                g.ligature_component = component;
+            }
+        }
+
+        glyphs[i].glyph_id = ligature.ligature_glyph;
+        glyphs[i].ligature_id = ligature_id;
+        glyphs[i].ligature_component = 0;
+        for &p in positions[1..].iter().rev() {
+            glyphs.remove(p);
+        }
+        Some(i + 1)
+    }
+}
+
+#[derive(Debug)]
+pub struct Ligature {
+    /// glyph ID of ligature to substitute
+    ligature_glyph: GlyphId,
+    /// Array of component glyph IDs — start with the second component, ordered in writing direction
+    component_glyph_ids: Vec<GlyphId>,
+}
+
+impl Ligature {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let ligature_glyph = read_uint16(r)?;
+        let component_count = read_uint16(r)?;
+        let component_glyph_ids = read_array(r, (component_count as usize).saturating_sub(1), read_uint16)?;
+
+        Ok(Self { ligature_glyph, component_glyph_ids })
+    }
+
+    pub fn ligature_glyph(&self) -> GlyphId {
+        self.ligature_glyph
+    }
+
+    pub fn component_glyph_ids(&self) -> &[GlyphId] {
+        &self.component_glyph_ids
+    }
+}
+
+/// Single substitution in a context, applied from the end of the buffer towards its start
+pub struct ReverseChainSingleSubst {
+    coverage: Coverage,
+    /// Closest glyph first
+    backtrack_coverages: Vec<Coverage>,
+    lookahead_coverages: Vec<Coverage>,
+    /// Substitute glyphs ordered by coverage index
+    substitute_glyph_ids: Vec<GlyphId>,
+}
+
+impl Debug for ReverseChainSingleSubst {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReverseChainSingleSubst")
+            .field("coverage", &self.coverage)
+            .field("backtrack_coverages", &self.backtrack_coverages)
+            .field("lookahead_coverages", &self.lookahead_coverages)
+            .field("substitute_glyph_ids", &self.substitute_glyph_ids)
+            .finish()
+    }
+}
+
+impl ReverseChainSingleSubst {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        if 1 != format {
+            return Err(format!("unknown reverse chaining substitution format {}", format).into());
+        }
+        let coverage = Coverage::at(o, read_offset16(r)?)?;
+        let backtrack_glyph_count = read_uint16(r)?;
+        let mut backtrack_coverages = Vec::with_capacity(backtrack_glyph_count as usize);
+        for offset in read_array(r, backtrack_glyph_count as usize, read_offset16)? {
+            backtrack_coverages.push(Coverage::at(o, offset)?);
+        }
+        let lookahead_glyph_count = read_uint16(r)?;
+        let mut lookahead_coverages = Vec::with_capacity(lookahead_glyph_count as usize);
+        for offset in read_array(r, lookahead_glyph_count as usize, read_offset16)? {
+            lookahead_coverages.push(Coverage::at(o, offset)?);
+        }
+        let glyph_count = read_uint16(r)?;
+        let substitute_glyph_ids = read_array(r, glyph_count as usize, read_uint16)?;
+
+        Ok(Self {
+            coverage,
+            backtrack_coverages,
+            lookahead_coverages,
+            substitute_glyph_ids,
+        })
+    }
+
+    /// Substitute for the glyph at `i` when its context matches
+    pub fn substitute(&self, glyphs: &[GlyphInfo], i: usize, skip: &dyn Fn(&GlyphInfo) -> bool) -> Option<GlyphId> {
+        let index = self.coverage.index(glyphs.get(i)?.glyph_id)?;
+        match_backtrack(glyphs, i, self.backtrack_coverages.len(), skip, |k, g| self.backtrack_coverages[k].contains(g))?;
+        match_lookahead(glyphs, i, self.lookahead_coverages.len(), skip, |k, g| self.lookahead_coverages[k].contains(g))?;
+        self.substitute_glyph_ids.get(index as usize).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::builders::*;
+
+    /// Single substitution format 2 of each glyph in `from` by the glyph at the same index in `to`
+    fn single(from: &[GlyphId], to: &[GlyphId]) -> Vec<u8> {
+        let mut w = Vec::new();
+        write_uint16(&mut w, 2);
+        write_uint16(&mut w, 6 + 2 * to.len() as u16);
+        write_uint16(&mut w, to.len() as u16);
+        to.iter().for_each(|&g| write_uint16(&mut w, g));
+        w.extend(coverage(from));
+        w
+    }
+
+    /// Ligature substitution of `components` by `ligature_glyph`
+    fn ligature(components: &[GlyphId], ligature_glyph: GlyphId) -> Vec<u8> {
+        let mut w = Vec::new();
+        [1, 8, 1, 14].iter().for_each(|&v| write_uint16(&mut w, v));
+        w.extend(coverage(&components[..1]));
+        [1, 4, ligature_glyph, components.len() as u16].iter().for_each(|&v| write_uint16(&mut w, v));
+        components[1..].iter().for_each(|&g| write_uint16(&mut w, g));
+        w
+    }
+
+    /// Sequence context format 3 over `input`, applying `lookup_list_index` at `sequence_index`
+    fn context(input: &[GlyphId], sequence_index: u16, lookup_list_index: u16) -> Vec<u8> {
+        let mut w = Vec::new();
+        write_uint16(&mut w, 3);
+        write_uint16(&mut w, input.len() as u16);
+        write_uint16(&mut w, 1);
+        let start = 6 + 2 * input.len() + 4;
+        (0..input.len()).for_each(|k| write_uint16(&mut w, (start + 6 * k) as u16));
+        write_uint16(&mut w, sequence_index);
+        write_uint16(&mut w, lookup_list_index);
+        input.iter().for_each(|&g| w.extend(coverage(&[g])));
+        w
+    }
+
+    /// Extension subtable pointing to `subtable` of `lookup_type`
+    fn extension(lookup_type: u16, subtable: Vec<u8>) -> Vec<u8> {
+        let mut w = Vec::new();
+        write_uint16(&mut w, 1);
+        write_uint16(&mut w, lookup_type);
+        write_uint32(&mut w, 8);
+        w.extend(subtable);
+        w
+    }
+
+    fn gsub(lookups: &[Vec<u8>]) -> Result<GlyphSubstitutionTable, Box<dyn Error>> {
+        GlyphSubstitutionTable::from_bytes(&layout_table(lookups))
+    }
+
+    fn apply(gsub: &GlyphSubstitutionTable, lookup_index: u16, glyph_ids: &[GlyphId]) -> Vec<GlyphInfo> {
+        let mut glyphs: Vec<_> = glyph_ids.iter().enumerate().map(|(i, &g)| GlyphInfo::new(g, i)).collect();
+        gsub.apply_lookup(lookup_index, &mut glyphs, None);
+        glyphs
+    }
+
+    fn glyph_ids(glyphs: &[GlyphInfo]) -> Vec<GlyphId> {
+        glyphs.iter().map(|g| g.glyph_id).collect()
+    }
+
+    #[test]
+    fn extension_lookup_with_several_subtables() {
+        let gsub = gsub(&[lookup(EXTENSION_SUBSTITUTION, 0, &[extension(SINGLE, single(&[1], &[11])), extension(SINGLE, single(&[2], &[12]))])]).unwrap();
+        let lookup = gsub.lookup_list().get(0).unwrap();
+        assert_eq!(lookup.lookup_type(), SINGLE);
+        assert_eq!(lookup.subtables().len(), 2);
+        assert_eq!(glyph_ids(&apply(&gsub, 0, &[1, 2, 3])), [11, 12, 3]);
+    }
+
+    #[test]
+    fn extension_subtables_must_share_their_type() {
+        assert!(gsub(&[lookup(EXTENSION_SUBSTITUTION, 0, &[extension(SINGLE, single(&[1], &[11])), extension(LIGATURE, ligature(&[1, 2], 20))])]).is_err());
+        assert!(gsub(&[lookup(EXTENSION_SUBSTITUTION, 0, &[extension(EXTENSION_SUBSTITUTION, single(&[1], &[11]))])]).is_err());
+    }
+
+    #[test]
+    fn ligature_substitution() {
+        let gsub = gsub(&[lookup(LIGATURE, 0, &[ligature(&[1, 2, 3], 20)])]).unwrap();
+        let glyphs = apply(&gsub, 0, &[1, 2, 3, 4, 1, 2]);
+        assert_eq!(glyph_ids(&glyphs), [20, 4, 1, 2]);
+        assert_eq!(glyphs.iter().map(|g| g.cluster).collect::<Vec<_>>(), [0, 3, 4, 5]);
+        assert_eq!(glyphs[0].ligature_id, 1);
+    }
+
+    #[test]
+    fn context_substitution() {
+        let gsub = gsub(&[lookup(SINGLE, 0, &[single(&[6], &[16])]), lookup(CONTEXT, 0, &[context(&[5, 6], 1, 0)])]).unwrap();
+        assert_eq!(glyph_ids(&apply(&gsub, 1, &[6, 5, 6, 6])), [6, 5, 16, 6]);
+    }
+}
@@ -0,0 +1,466 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::t_glyf::*;
+use super::types::*;
+use super::utils::*;
+use super::variations::*;
+
+/// glyphVariationDataOffsets are Offset32 rather than halved Offset16
+const LONG_OFFSETS: u16 = 0x0001;
+
+/// tupleVariationCount flag, point numbers shared by all tuples precede the serialized data
+const SHARED_POINT_NUMBERS: u16 = 0x8000;
+const COUNT_MASK: u16 = 0x0FFF;
+
+/// tupleIndex flags
+const EMBEDDED_PEAK_TUPLE: u16 = 0x8000;
+const INTERMEDIATE_REGION: u16 = 0x4000;
+const PRIVATE_POINT_NUMBERS: u16 = 0x2000;
+const TUPLE_INDEX_MASK: u16 = 0x0FFF;
+
+/// Packed point number run control bits
+const POINTS_ARE_WORDS: u8 = 0x80;
+const POINT_RUN_COUNT_MASK: u8 = 0x7F;
+
+/// Packed delta run control bits
+const DELTAS_ARE_ZERO: u8 = 0x80;
+const DELTAS_ARE_WORDS: u8 = 0x40;
+const DELTA_RUN_COUNT_MASK: u8 = 0x3F;
+
+/// Per-glyph point deltas of a variable TrueType font
+pub struct GlyphVariationsTable {
+    /// Major version number of the glyph variations table — set to 1
+    major_version: u16,
+    /// Minor version number of the glyph variations table — set to 0
+    minor_version: u16,
+    /// The number of variation axes for this font. This must be the same number as axisCount in the 'fvar' table
+    axis_count: u16,
+    /// Peak tuples that tuple variation headers refer to by index
+    shared_tuples: Vec<Vec<F2Dot14>>,
+    /// Start and end of each glyph's variation data within `data`, empty for glyphs without variations
+    glyph_variation_data_ranges: Vec<(usize, usize)>,
+    /// Raw table, glyph variation data is decoded on demand
+    data: Vec<u8>,
+}
+
+impl Debug for GlyphVariationsTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GlyphVariationsTable")
+            .field("major_version", &self.major_version)
+            .field("minor_version", &self.minor_version)
+            .field("axis_count", &self.axis_count)
+            .field("shared_tuples", &self.shared_tuples)
+            .field_with("glyph_variation_data_ranges", |f| write!(f, "[(usize, usize); {}]", self.glyph_variation_data_ranges.len()))
+            .field_with("data", |f| write!(f, "[u8; {}]", self.data.len()))
+            .finish()
+    }
+}
+
+impl GlyphVariationsTable {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let major_version = read_uint16(r)?;
+        let minor_version = read_uint16(r)?;
+        if 1 != major_version {
+            return Err(format!("unsupported gvar version {}.{}", major_version, minor_version).into());
+        }
+        let axis_count = read_uint16(r)?;
+        let shared_tuple_count = read_uint16(r)?;
+        let shared_tuples_offset = read_offset32(r)?;
+        let glyph_count = read_uint16(r)?;
+        let flags = read_uint16(r)?;
+        let glyph_variation_data_array_offset = read_offset32(r)?;
+        let offsets = if 0 != flags & LONG_OFFSETS {
+            read_array(r, glyph_count as usize + 1, read_offset32)?
+        } else {
+            read_array(r, glyph_count as usize + 1, |r| Ok(read_offset16(r)? as u32 * 2))?
+        };
+
+        let mut s = o.get(shared_tuples_offset as usize..).ok_or("gvar shared tuples offset out of range")?;
+        let mut shared_tuples = Vec::with_capacity(shared_tuple_count as usize);
+        for _ in 0..shared_tuple_count {
+            shared_tuples.push(read_array(&mut s, axis_count as usize, read_f2dot14)?);
+        }
+
+        let base = glyph_variation_data_array_offset as usize;
+        let mut glyph_variation_data_ranges = Vec::with_capacity(glyph_count as usize);
+        for w in offsets.windows(2) {
+            let (start, end) = (base + w[0] as usize, base + w[1] as usize);
+            if start > end || end > o.len() {
+                return Err("gvar glyph variation data lies outside the table".into());
+            }
+            glyph_variation_data_ranges.push((start, end));
+        }
+
+        Ok(Self {
+            major_version,
+            minor_version,
+            axis_count,
+            shared_tuples,
+            glyph_variation_data_ranges,
+            data: o.to_vec(),
+        })
+    }
+
+    pub fn axis_count(&self) -> u16 {
+        self.axis_count
+    }
+
+    pub fn glyph_count(&self) -> usize {
+        self.glyph_variation_data_ranges.len()
+    }
+
+    /// Decode the tuple variations of one glyph, `None` when the glyph has no variation data
+    pub fn glyph_variation_data(&self, glyph_id: GlyphId, point_count: usize) -> Result<Option<GlyphVariationData>, Box<dyn Error>> {
+        let Some(&(start, end)) = self.glyph_variation_data_ranges.get(glyph_id as usize) else {
+            return Ok(None);
+        };
+        if start == end {
+            return Ok(None);
+        }
+        Ok(Some(GlyphVariationData::from_bytes(&self.data[start..end], self.axis_count, &self.shared_tuples, point_count)?))
+    }
+
+    /// Move the points of a glyph, phantom points last, by their accumulated deltas at `coords`
+    ///
+    /// `end_pts` holds the last point of each contour, untouched points of a contour have their deltas
+    /// inferred from the unvaried positions of the touched points around them
+    pub fn apply_deltas(&self, glyph_id: GlyphId, coords: &[F2Dot14], points: &mut [Point], end_pts: &[usize]) -> Result<(), Box<dyn Error>> {
+        if coords.iter().all(|&c| 0.0 == c) {
+            return Ok(());
+        }
+        let Some(gvd) = self.glyph_variation_data(glyph_id, points.len())? else {
+            return Ok(());
+        };
+
+        let mut deltas = vec![(0.0, 0.0); points.len()];
+        for tv in &gvd.tuple_variations {
+            let scalar = tv.scalar(coords);
+            if 0.0 == scalar {
+                continue;
+            }
+            match &tv.point_numbers {
+                None => {
+                    for (d, (&x, &y)) in deltas.iter_mut().zip(tv.x_deltas.iter().zip(&tv.y_deltas)) {
+                        d.0 += x as f32 * scalar;
+                        d.1 += y as f32 * scalar;
+                    }
+                }
+                Some(point_numbers) => {
+                    let mut tuple_deltas: Vec<Option<(f32, f32)>> = vec![None; points.len()];
+                    for (&p, (&x, &y)) in point_numbers.iter().zip(tv.x_deltas.iter().zip(&tv.y_deltas)) {
+                        if let Some(d) = tuple_deltas.get_mut(p as usize) {
+                            // a point listed twice keeps its last delta
+                            *d = Some((x as f32, y as f32));
+                        }
+                    }
+                    infer_deltas(points, end_pts, &mut tuple_deltas);
+                    for (d, td) in deltas.iter_mut().zip(tuple_deltas) {
+                        if let Some((x, y)) = td {
+                            d.0 += x * scalar;
+                            d.1 += y * scalar;
+                        }
+                    }
+                }
+            }
+        }
+        for (p, (dx, dy)) in points.iter_mut().zip(deltas) {
+            p.x += dx;
+            p.y += dy;
+        }
+        Ok(())
+    }
+}
+
+/// Interpolate the deltas of untouched points (IUP) contour by contour, points outside any contour stay untouched
+fn infer_deltas(points: &[Point], end_pts: &[usize], deltas: &mut [Option<(f32, f32)>]) {
+    let mut start = 0;
+    for &end in end_pts {
+        if end < start || end >= points.len() {
+            break;
+        }
+        let touched: Vec<usize> = (start..=end).filter(|&i| deltas[i].is_some()).collect();
+        match touched.as_slice() {
+            [] => {}
+            &[only] => {
+                let d = deltas[only];
+                deltas[start..=end].fill(d);
+            }
+            _ => {
+                // each run of untouched points lies between two touched points, wrapping around the contour
+                for (k, &prev) in touched.iter().enumerate() {
+                    let next = touched[(k + 1) % touched.len()];
+                    let mut i = if prev == end { start } else { prev + 1 };
+                    while i != next {
+                        let (dp, dn) = (deltas[prev].unwrap_or_default(), deltas[next].unwrap_or_default());
+                        let x = interpolate(points[i].x, points[prev].x, points[next].x, dp.0, dn.0);
+                        let y = interpolate(points[i].y, points[prev].y, points[next].y, dp.1, dn.1);
+                        deltas[i] = Some((x, y));
+                        i = if i == end { start } else { i + 1 };
+                    }
+                }
+            }
+        }
+        start = end + 1;
+    }
+}
+
+/// Delta of coordinate `v` between reference coordinates `a` and `b`, clamped to the nearer reference outside of them
+fn interpolate(v: f32, a: f32, b: f32, da: f32, db: f32) -> f32 {
+    if a == b {
+        return if da == db { da } else { 0.0 };
+    }
+    let ((lo, dlo), (hi, dhi)) = if a < b { ((a, da), (b, db)) } else { ((b, db), (a, da)) };
+    if v <= lo {
+        dlo
+    } else if v >= hi {
+        dhi
+    } else {
+        dlo + (v - lo) * (dhi - dlo) / (hi - lo)
+    }
+}
+
+/// The tuple variations of one glyph
+pub struct GlyphVariationData {
+    tuple_variations: Vec<TupleVariation>,
+}
+
+impl Debug for GlyphVariationData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GlyphVariationData").field("tuple_variations", &self.tuple_variations).finish()
+    }
+}
+
+impl GlyphVariationData {
+    /// `point_count` includes the four phantom points, it gives the length of tuples that apply to all points
+    pub fn from_bytes(b: &[u8], axis_count: u16, shared_tuples: &[Vec<F2Dot14>], point_count: usize) -> Result<Self, Box<dyn Error>> {
+        let mut r = b;
+        let tuple_variations = read_tuple_variations(b, &mut r, axis_count, shared_tuples, point_count, 2)?;
+        Ok(Self { tuple_variations })
+    }
+
+    pub fn tuple_variations(&self) -> &[TupleVariation] {
+        &self.tuple_variations
+    }
+}
+
+/// Deltas for the points of a glyph, weighted by how far `coords` are into the tuple's region
+pub struct TupleVariation {
+    peak_tuple: Vec<F2Dot14>,
+    /// Start and end tuples, when absent the region spans from 0 to the peak on each axis
+    intermediate_region: Option<(Vec<F2Dot14>, Vec<F2Dot14>)>,
+    /// Points the deltas apply to, `None` for all points in order
+    point_numbers: Option<Vec<u16>>,
+    x_deltas: Vec<i32>,
+    y_deltas: Vec<i32>,
+}
+
+impl Debug for TupleVariation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TupleVariation")
+            .field("peak_tuple", &self.peak_tuple)
+            .field("intermediate_region", &self.intermediate_region)
+            .field("point_numbers", &self.point_numbers)
+            .field_with("x_deltas", |f| write!(f, "[i32; {}]", self.x_deltas.len()))
+            .field_with("y_deltas", |f| write!(f, "[i32; {}]", self.y_deltas.len()))
+            .finish()
+    }
+}
+
+impl TupleVariation {
+    pub fn peak_tuple(&self) -> &[F2Dot14] {
+        &self.peak_tuple
+    }
+
+    pub fn point_numbers(&self) -> Option<&[u16]> {
+        self.point_numbers.as_deref()
+    }
+
+    pub fn x_deltas(&self) -> &[i32] {
+        &self.x_deltas
+    }
+
+    pub fn y_deltas(&self) -> &[i32] {
+        &self.y_deltas
+    }
+
+    /// How much of the deltas apply at `coords`, axes without a coordinate are at their default
+    pub fn scalar(&self, coords: &[F2Dot14]) -> f32 {
+        let mut scalar = 1.0;
+        for (i, &peak) in self.peak_tuple.iter().enumerate() {
+            let (start, end) = match &self.intermediate_region {
+                Some((start, end)) => (start[i], end[i]),
+                None => (peak.min(0.0), peak.max(0.0)),
+            };
+            scalar *= RegionAxisCoordinates::new(start, peak, end).scalar(coords.get(i).copied().unwrap_or(0.0));
+            if 0.0 == scalar {
+                break;
+            }
+        }
+        scalar
+    }
+}
+
+/// Tuple variation headers at `r` and their serialized data at the header's data offset from `o`, the layout gvar and cvar share
+///
+/// gvar deltas have an x and a y per point, cvar deltas a single value kept as the x deltas
+pub(super) fn read_tuple_variations(o: &[u8], r: &mut &[u8], axis_count: u16, shared_tuples: &[Vec<F2Dot14>], point_count: usize, dimensions: usize) -> Result<Vec<TupleVariation>, Box<dyn Error>> {
+    let tuple_variation_count = read_uint16(r)?;
+    let data_offset = read_offset16(r)?;
+
+    let mut headers = Vec::with_capacity((tuple_variation_count & COUNT_MASK) as usize);
+    for _ in 0..tuple_variation_count & COUNT_MASK {
+        let variation_data_size = read_uint16(r)?;
+        let tuple_index = read_uint16(r)?;
+        let peak_tuple = if 0 != tuple_index & EMBEDDED_PEAK_TUPLE {
+            read_array(r, axis_count as usize, read_f2dot14)?
+        } else {
+            shared_tuples.get((tuple_index & TUPLE_INDEX_MASK) as usize).ok_or("shared tuple index out of range")?.clone()
+        };
+        let intermediate_region = if 0 != tuple_index & INTERMEDIATE_REGION {
+            Some((read_array(r, axis_count as usize, read_f2dot14)?, read_array(r, axis_count as usize, read_f2dot14)?))
+        } else {
+            None
+        };
+        headers.push((variation_data_size, tuple_index, peak_tuple, intermediate_region));
+    }
+
+    let mut s = o.get(data_offset as usize..).ok_or("tuple variation serialized data offset out of range")?;
+    let shared_point_numbers = if 0 != tuple_variation_count & SHARED_POINT_NUMBERS { read_packed_point_numbers(&mut s)? } else { None };
+
+    let mut tuple_variations = Vec::with_capacity(headers.len());
+    for (variation_data_size, tuple_index, peak_tuple, intermediate_region) in headers {
+        let mut t = s.get(..variation_data_size as usize).ok_or("tuple variation data out of range")?;
+        s = &s[variation_data_size as usize..];
+
+        let point_numbers = if 0 != tuple_index & PRIVATE_POINT_NUMBERS { read_packed_point_numbers(&mut t)? } else { shared_point_numbers.clone() };
+        let count = point_numbers.as_ref().map_or(point_count, |p| p.len());
+        let x_deltas = read_packed_deltas(&mut t, count)?;
+        let y_deltas = if dimensions > 1 { read_packed_deltas(&mut t, count)? } else { Vec::new() };
+
+        tuple_variations.push(TupleVariation {
+            peak_tuple,
+            intermediate_region,
+            point_numbers,
+            x_deltas,
+            y_deltas,
+        });
+    }
+    Ok(tuple_variations)
+}
+
+/// Point numbers stored as runs of increments, `None` when the count is 0 which means all points
+fn read_packed_point_numbers(r: &mut &[u8]) -> Result<Option<Vec<u16>>, Box<dyn Error>> {
+    let first = read_uint8(r)?;
+    let count = if 0 != first & POINTS_ARE_WORDS { ((first & POINT_RUN_COUNT_MASK) as usize) << 8 | read_uint8(r)? as usize } else { first as usize };
+    if 0 == count {
+        return Ok(None);
+    }
+
+    let mut point_numbers = Vec::with_capacity(count);
+    let mut p: u16 = 0;
+    while point_numbers.len() < count {
+        let control = read_uint8(r)?;
+        for _ in 0..=(control & POINT_RUN_COUNT_MASK) {
+            let increment = if 0 != control & POINTS_ARE_WORDS { read_uint16(r)? } else { read_uint8(r)? as u16 };
+            p = p.wrapping_add(increment);
+            point_numbers.push(p);
+        }
+    }
+    point_numbers.truncate(count);
+    Ok(Some(point_numbers))
+}
+
+/// `count` deltas stored as runs of zeros, bytes, words or, with both flags set, 32 bit values
+fn read_packed_deltas(r: &mut &[u8], count: usize) -> Result<Vec<i32>, Box<dyn Error>> {
+    let mut deltas = Vec::with_capacity(count);
+    while deltas.len() < count {
+        let control = read_uint8(r)?;
+        for _ in 0..=(control & DELTA_RUN_COUNT_MASK) {
+            let delta = match (0 != control & DELTAS_ARE_ZERO, 0 != control & DELTAS_ARE_WORDS) {
+                (true, true) => read_int32(r)?,
+                (true, false) => 0,
+                (false, true) => read_int16(r)? as i32,
+                (false, false) => read_int8(r)? as i32,
+            };
+            deltas.push(delta);
+        }
+    }
+    deltas.truncate(count);
+    Ok(deltas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f32, y: f32) -> Point {
+        Point { x, y, on_curve: true }
+    }
+
+    /// One contour along the baseline, (0, 0) (50, 0) (100, 0) (150, 0), and the four phantom points at the origin
+    fn points() -> Vec<Point> {
+        let mut points = vec![point(0.0, 0.0), point(50.0, 0.0), point(100.0, 0.0), point(150.0, 0.0)];
+        points.extend([point(0.0, 0.0); 4]);
+        points
+    }
+
+    /// gvar with one axis and one glyph, whose single tuple peaks at 1.0 and moves points 0 and 2 by (0, 0) and (20, 10)
+    fn gvar() -> GlyphVariationsTable {
+        let mut w = Vec::new();
+        [1, 0, 1, 0].iter().for_each(|&v| write_uint16(&mut w, v));
+        write_uint32(&mut w, 24);
+        [1, 0].iter().for_each(|&v| write_uint16(&mut w, v));
+        write_uint32(&mut w, 24);
+        [0, 10].iter().for_each(|&v| write_uint16(&mut w, v));
+        [1, 10, 10, EMBEDDED_PEAK_TUPLE | PRIVATE_POINT_NUMBERS].iter().for_each(|&v| write_uint16(&mut w, v));
+        write_f2dot14(&mut w, 1.0);
+        w.extend([2, 1, 0, 2, 1, 0, 20, 1, 0, 10]);
+        GlyphVariationsTable::from_bytes(&w).unwrap()
+    }
+
+    #[test]
+    fn packed_point_numbers() {
+        let b = [5, 0x02, 1, 2, 3, POINTS_ARE_WORDS | 0x01, 0x01, 0x00, 0x00, 0x01];
+        assert_eq!(read_packed_point_numbers(&mut b.as_slice()).unwrap(), Some(vec![1, 3, 6, 262, 263]));
+        let b = [POINTS_ARE_WORDS, 2, 0x01, 4, 1];
+        assert_eq!(read_packed_point_numbers(&mut b.as_slice()).unwrap(), Some(vec![4, 5]));
+        assert_eq!(read_packed_point_numbers(&mut [0].as_slice()).unwrap(), None);
+        assert!(read_packed_point_numbers(&mut [3, 0x02, 1].as_slice()).is_err());
+    }
+
+    #[test]
+    fn packed_deltas() {
+        let b = [DELTAS_ARE_ZERO | 0x01, DELTAS_ARE_WORDS | 0x01, 0x01, 0x2C, 0xFE, 0xD4, 0x02, 0xFF, 2, 3, DELTAS_ARE_ZERO | DELTAS_ARE_WORDS, 0x00, 0x01, 0x86, 0xA0];
+        let mut r = b.as_slice();
+        assert_eq!(read_packed_deltas(&mut r, 8).unwrap(), [0, 0, 300, -300, -1, 2, 3, 100000]);
+        assert!(r.is_empty());
+        assert!(read_packed_deltas(&mut [0x02, 1].as_slice(), 3).is_err());
+    }
+
+    #[test]
+    fn infer_untouched_deltas() {
+        let mut points = points();
+        points.truncate(6);
+        let mut deltas = vec![Some((0.0, 0.0)), None, Some((20.0, 10.0)), None, None, Some((5.0, 5.0))];
+        infer_deltas(&points, &[3, 5], &mut deltas);
+        // x interpolates between the touched points or takes the nearer delta outside them, y has equal references so only equal deltas carry over
+        assert_eq!(deltas, [Some((0.0, 0.0)), Some((10.0, 0.0)), Some((20.0, 10.0)), Some((20.0, 0.0)), Some((5.0, 5.0)), Some((5.0, 5.0))]);
+    }
+
+    #[test]
+    fn apply_deltas() {
+        let gvar = gvar();
+        let mut points = points();
+        gvar.apply_deltas(0, &[0.5], &mut points, &[3]).unwrap();
+        assert_eq!(points[..4], [point(0.0, 0.0), point(55.0, 0.0), point(110.0, 5.0), point(160.0, 0.0)]);
+        assert_eq!(points[4..], [point(0.0, 0.0); 4]);
+
+        let mut unvaried = self::points();
+        gvar.apply_deltas(0, &[-0.5], &mut unvaried, &[3]).unwrap();
+        assert_eq!(unvaried, self::points());
+    }
+}
@@ -96,4 +96,13 @@ impl FontHeaderTable {
             glyph_data_format,
         })
     }
+
+    pub fn units_per_em(&self) -> u16 {
+        self.units_per_em
+    }
+
+    /// 0 for short offsets (Offset16), 1 for long (Offset32)
+    pub fn index_to_loc_format(&self) -> i16 {
+        self.index_to_loc_format
+    }
 }
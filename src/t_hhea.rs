@@ -1 +1,159 @@
-pub struct HorizontalHeaderTable {}
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::utils::*;
+
+pub struct HorizontalHeaderTable {
+    major_version: u16,
+    minor_version: u16,
+    /// Typographic ascent
+    ascender: i16,
+    /// Typographic descent
+    descender: i16,
+    /// Typographic line gap
+    line_gap: i16,
+    /// Maximum advance width value in 'hmtx' table
+    advance_width_max: u16,
+    /// Minimum left sidebearing value in 'hmtx' table for glyphs with contours (empty glyphs should be ignored)
+    min_left_side_bearing: i16,
+    /// Minimum right sidebearing value; calculated as min(aw - (lsb + xMax - xMin)) for glyphs with contours
+    min_right_side_bearing: i16,
+    /// Max(lsb + (xMax - xMin))
+    x_max_extent: i16,
+    /// Used to calculate the slope of the cursor (rise/run); 1 for vertical
+    caret_slope_rise: i16,
+    /// 0 for vertical
+    caret_slope_run: i16,
+    /// The amount by which a slanted highlight on a glyph needs to be shifted to produce the best appearance. Set to 0 for non-slanted fonts
+    caret_offset: i16,
+    reserved: [i16; 4],
+    /// 0 for current format
+    metric_data_format: i16,
+    /// Number of hMetric entries in 'hmtx' table
+    number_of_h_metrics: u16,
+}
+
+impl Debug for HorizontalHeaderTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HorizontalHeaderTable")
+            .field("major_version", &self.major_version)
+            .field("minor_version", &self.minor_version)
+            .field("ascender", &self.ascender)
+            .field("descender", &self.descender)
+            .field("line_gap", &self.line_gap)
+            .field("advance_width_max", &self.advance_width_max)
+            .field("min_left_side_bearing", &self.min_left_side_bearing)
+            .field("min_right_side_bearing", &self.min_right_side_bearing)
+            .field("x_max_extent", &self.x_max_extent)
+            .field("caret_slope_rise", &self.caret_slope_rise)
+            .field("caret_slope_run", &self.caret_slope_run)
+            .field("caret_offset", &self.caret_offset)
+            .field("reserved", &self.reserved)
+            .field("metric_data_format", &self.metric_data_format)
+            .field("number_of_h_metrics", &self.number_of_h_metrics)
+            .finish()
+    }
+}
+
+impl HorizontalHeaderTable {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let major_version = read_uint16(r)?;
+        let minor_version = read_uint16(r)?;
+        let ascender = read_int16(r)?;
+        let descender = read_int16(r)?;
+        let line_gap = read_int16(r)?;
+        let advance_width_max = read_uint16(r)?;
+        let min_left_side_bearing = read_int16(r)?;
+        let min_right_side_bearing = read_int16(r)?;
+        let x_max_extent = read_int16(r)?;
+        let caret_slope_rise = read_int16(r)?;
+        let caret_slope_run = read_int16(r)?;
+        let caret_offset = read_int16(r)?;
+        let reserved = [read_int16(r)?, read_int16(r)?, read_int16(r)?, read_int16(r)?];
+        let metric_data_format = read_int16(r)?;
+        let number_of_h_metrics = read_uint16(r)?;
+
+        Ok(Self {
+            major_version,
+            minor_version,
+            ascender,
+            descender,
+            line_gap,
+            advance_width_max,
+            min_left_side_bearing,
+            min_right_side_bearing,
+            x_max_extent,
+            caret_slope_rise,
+            caret_slope_run,
+            caret_offset,
+            reserved,
+            metric_data_format,
+            number_of_h_metrics,
+        })
+    }
+
+    pub fn ascender(&self) -> i16 {
+        self.ascender
+    }
+
+    pub fn descender(&self) -> i16 {
+        self.descender
+    }
+
+    pub fn line_gap(&self) -> i16 {
+        self.line_gap
+    }
+
+    pub fn advance_width_max(&self) -> u16 {
+        self.advance_width_max
+    }
+
+    pub fn min_left_side_bearing(&self) -> i16 {
+        self.min_left_side_bearing
+    }
+
+    pub fn min_right_side_bearing(&self) -> i16 {
+        self.min_right_side_bearing
+    }
+
+    pub fn x_max_extent(&self) -> i16 {
+        self.x_max_extent
+    }
+
+    pub fn caret_slope_rise(&self) -> i16 {
+        self.caret_slope_rise
+    }
+
+    pub fn caret_slope_run(&self) -> i16 {
+        self.caret_slope_run
+    }
+
+    pub fn caret_offset(&self) -> i16 {
+        self.caret_offset
+    }
+
+    pub fn number_of_h_metrics(&self) -> u16 {
+        self.number_of_h_metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields() {
+        let mut w = Vec::new();
+        for v in [1, 0, 880, -120, 90, 1200, -50, -30, 1100, 1, 0, 0, 0, 0, 0, 0, 0, 3] {
+            write_int16(&mut w, v);
+        }
+        let hhea = HorizontalHeaderTable::from_bytes(&w).unwrap();
+        assert_eq!((880, -120, 90), (hhea.ascender(), hhea.descender(), hhea.line_gap()));
+        assert_eq!((1200, -50, -30, 1100), (hhea.advance_width_max(), hhea.min_left_side_bearing(), hhea.min_right_side_bearing(), hhea.x_max_extent()));
+        assert_eq!((1, 0, 0), (hhea.caret_slope_rise(), hhea.caret_slope_run(), hhea.caret_offset()));
+        assert_eq!(3, hhea.number_of_h_metrics());
+        assert!(HorizontalHeaderTable::from_bytes(&w[..34]).is_err());
+    }
+}
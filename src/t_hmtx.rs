@@ -0,0 +1,97 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::types::*;
+use super::utils::*;
+
+pub struct HorizontalMetricsTable {
+    /// Paired advance width and left side bearing values for each glyph. Records are indexed by glyph ID
+    h_metrics: Vec<LongHorMetric>,
+    /// Left side bearings for glyph IDs greater than or equal to numberOfHMetrics
+    left_side_bearings: Vec<i16>,
+}
+
+impl Debug for HorizontalMetricsTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HorizontalMetricsTable")
+            .field_with("h_metrics", |f| write!(f, "[LongHorMetric; {}]", self.h_metrics.len()))
+            .field_with("left_side_bearings", |f| write!(f, "[i16; {}]", self.left_side_bearings.len()))
+            .finish()
+    }
+}
+
+impl HorizontalMetricsTable {
+    /// `number_of_h_metrics` comes from hhea, `num_glyphs` from maxp
+    pub fn from_bytes(mut b: &[u8], number_of_h_metrics: u16, num_glyphs: u16) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        if 0 == number_of_h_metrics || number_of_h_metrics > num_glyphs {
+            return Err(format!("hhea numberOfHMetrics {} must be in 1..={} (maxp numGlyphs)", number_of_h_metrics, num_glyphs).into());
+        }
+
+        let h_metrics = read_array(r, number_of_h_metrics as usize, LongHorMetric::from_bytes)?;
+        let left_side_bearings = read_array(r, num_glyphs.saturating_sub(number_of_h_metrics) as usize, read_int16)?;
+
+        Ok(Self { h_metrics, left_side_bearings })
+    }
+
+    pub fn num_glyphs(&self) -> usize {
+        self.h_metrics.len() + self.left_side_bearings.len()
+    }
+
+    /// Glyphs past numberOfHMetrics share the advance width of the last hMetrics record
+    pub fn advance_width(&self, glyph_id: GlyphId) -> Option<u16> {
+        if glyph_id as usize >= self.num_glyphs() {
+            return None;
+        }
+        Some(self.h_metrics[(glyph_id as usize).min(self.h_metrics.len() - 1)].advance_width)
+    }
+
+    pub fn left_side_bearing(&self, glyph_id: GlyphId) -> Option<i16> {
+        match self.h_metrics.get(glyph_id as usize) {
+            Some(hm) => Some(hm.lsb),
+            None => self.left_side_bearings.get(glyph_id as usize - self.h_metrics.len()).copied(),
+        }
+    }
+}
+
+pub struct LongHorMetric {
+    /// Advance width, in font design units
+    advance_width: u16,
+    /// Glyph left side bearing, in font design units
+    lsb: i16,
+}
+
+impl Debug for LongHorMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LongHorMetric").field("advance_width", &self.advance_width).field("lsb", &self.lsb).finish()
+    }
+}
+
+impl LongHorMetric {
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let advance_width = read_uint16(r)?;
+        let lsb = read_int16(r)?;
+
+        Ok(Self { advance_width, lsb })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailing_glyphs_share_the_last_advance() {
+        let mut w = Vec::new();
+        for v in [500, 10, 600, 20, -5, 7] {
+            write_int16(&mut w, v);
+        }
+        let hmtx = HorizontalMetricsTable::from_bytes(&w, 2, 4).unwrap();
+        assert_eq!(vec![Some(500), Some(600), Some(600), Some(600), None], (0..5).map(|g| hmtx.advance_width(g)).collect::<Vec<_>>());
+        assert_eq!(vec![Some(10), Some(20), Some(-5), Some(7), None], (0..5).map(|g| hmtx.left_side_bearing(g)).collect::<Vec<_>>());
+        assert!(HorizontalMetricsTable::from_bytes(&w, 0, 4).is_err());
+        assert!(HorizontalMetricsTable::from_bytes(&w, 5, 4).is_err());
+        assert!(HorizontalMetricsTable::from_bytes(&w, 2, 5).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::types::*;
+use super::utils::*;
+use super::variations::*;
+
+/// Variations of the advance widths and side bearings in hmtx
+pub struct HorizontalMetricsVariationsTable {
+    /// Major version number of the horizontal metrics variations table — set to 1
+    major_version: u16,
+    /// Minor version number of the horizontal metrics variations table — set to 0
+    minor_version: u16,
+    item_variation_store: ItemVariationStore,
+    /// Without a mapping glyph IDs index the first ItemVariationData directly
+    advance_width_mapping: Option<DeltaSetIndexMap>,
+    lsb_mapping: Option<DeltaSetIndexMap>,
+    rsb_mapping: Option<DeltaSetIndexMap>,
+}
+
+impl Debug for HorizontalMetricsVariationsTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HorizontalMetricsVariationsTable")
+            .field("major_version", &self.major_version)
+            .field("minor_version", &self.minor_version)
+            .field("item_variation_store", &self.item_variation_store)
+            .field("advance_width_mapping", &self.advance_width_mapping)
+            .field("lsb_mapping", &self.lsb_mapping)
+            .field("rsb_mapping", &self.rsb_mapping)
+            .finish()
+    }
+}
+
+impl HorizontalMetricsVariationsTable {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let major_version = read_uint16(r)?;
+        let minor_version = read_uint16(r)?;
+        if 1 != major_version {
+            return Err(format!("unsupported HVAR version {}.{}", major_version, minor_version).into());
+        }
+        let item_variation_store_offset = read_offset32(r)?;
+        let advance_width_mapping_offset = read_offset32(r)?;
+        let lsb_mapping_offset = read_offset32(r)?;
+        let rsb_mapping_offset = read_offset32(r)?;
+
+        let item_variation_store = ItemVariationStore::from_bytes(o.get(item_variation_store_offset as usize..).ok_or("HVAR ItemVariationStore offset out of range")?)?;
+        let mapping = |offset: Offset32| match offset {
+            0 => Ok(None),
+            offset => DeltaSetIndexMap::from_bytes(o.get(offset as usize..).ok_or("HVAR DeltaSetIndexMap offset out of range")?).map(Some),
+        };
+        let advance_width_mapping = mapping(advance_width_mapping_offset)?;
+        let lsb_mapping = mapping(lsb_mapping_offset)?;
+        let rsb_mapping = mapping(rsb_mapping_offset)?;
+
+        Ok(Self {
+            major_version,
+            minor_version,
+            item_variation_store,
+            advance_width_mapping,
+            lsb_mapping,
+            rsb_mapping,
+        })
+    }
+
+    pub fn item_variation_store(&self) -> &ItemVariationStore {
+        &self.item_variation_store
+    }
+
+    /// Advance width delta of a glyph at normalized `coords`
+    pub fn advance_width_delta(&self, glyph_id: GlyphId, coords: &[F2Dot14]) -> Option<f32> {
+        let (outer, inner) = match &self.advance_width_mapping {
+            Some(mapping) => mapping.map(glyph_id as u32)?,
+            None => (0, glyph_id),
+        };
+        self.item_variation_store.delta(outer, inner, coords)
+    }
+
+    /// Left side bearing delta of a glyph, `None` when HVAR has no lsb mapping and the bearing must come from the varied outline
+    pub fn lsb_delta(&self, glyph_id: GlyphId, coords: &[F2Dot14]) -> Option<f32> {
+        let (outer, inner) = self.lsb_mapping.as_ref()?.map(glyph_id as u32)?;
+        self.item_variation_store.delta(outer, inner, coords)
+    }
+
+    /// Right side bearing delta of a glyph, `None` when HVAR has no rsb mapping
+    pub fn rsb_delta(&self, glyph_id: GlyphId, coords: &[F2Dot14]) -> Option<f32> {
+        let (outer, inner) = self.rsb_mapping.as_ref()?.map(glyph_id as u32)?;
+        self.item_variation_store.delta(outer, inner, coords)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variations::builders::*;
+
+    /// HVAR over three items growing by 10, 20 and 30 units at the max of the single axis, with the given mappings
+    fn hvar(advance_width_mapping: &[u8], lsb_mapping: &[u8]) -> Vec<u8> {
+        let ivs = item_variation_store(&[&[(0.0, 1.0, 1.0)]], &[&[10], &[20], &[30]]);
+        let mut w = Vec::new();
+        [1, 0].iter().for_each(|&v| write_uint16(&mut w, v));
+        let mut offset = 20 + ivs.len();
+        write_uint32(&mut w, 20);
+        for mapping in [advance_width_mapping, lsb_mapping] {
+            write_uint32(&mut w, if mapping.is_empty() { 0 } else { offset as u32 });
+            offset += mapping.len();
+        }
+        write_uint32(&mut w, 0);
+        [&ivs[..], advance_width_mapping, lsb_mapping].iter().for_each(|b| w.extend(*b));
+        w
+    }
+
+    #[test]
+    fn glyph_ids_index_the_first_subtable_without_a_mapping() {
+        let hvar = HorizontalMetricsVariationsTable::from_bytes(&hvar(&[], &[])).unwrap();
+        assert_eq!(vec![Some(5.0), Some(10.0), Some(15.0), None], (0..4).map(|g| hvar.advance_width_delta(g, &[0.5])).collect::<Vec<_>>());
+        assert_eq!((None, None), (hvar.lsb_delta(0, &[0.5]), hvar.rsb_delta(0, &[0.5])));
+    }
+
+    #[test]
+    fn mapped_glyphs() {
+        // glyph 0 to item 2, the rest to item 0
+        let hvar = HorizontalMetricsVariationsTable::from_bytes(&hvar(&[0, 0x01, 0, 2, 2, 0], &[0, 0x01, 0, 1, 1])).unwrap();
+        assert_eq!(vec![Some(30.0), Some(10.0), Some(10.0)], (0..3).map(|g| hvar.advance_width_delta(g, &[1.0])).collect::<Vec<_>>());
+        assert_eq!((Some(20.0), Some(20.0)), (hvar.lsb_delta(0, &[1.0]), hvar.lsb_delta(5, &[1.0])));
+        assert_eq!(None, hvar.rsb_delta(0, &[1.0]));
+    }
+}
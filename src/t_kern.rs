@@ -0,0 +1,394 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::types::*;
+use super::utils::*;
+
+/// Microsoft coverage bits
+const MS_HORIZONTAL: u16 = 0x0001;
+const MS_MINIMUM: u16 = 0x0002;
+const MS_CROSS_STREAM: u16 = 0x0004;
+const MS_OVERRIDE: u16 = 0x0008;
+
+/// Apple coverage bits
+const APPLE_VERTICAL: u16 = 0x8000;
+const APPLE_CROSS_STREAM: u16 = 0x4000;
+const APPLE_VARIATION: u16 = 0x2000;
+
+/// Legacy kerning, with either the Microsoft version 0 or the Apple version 1 header
+pub struct KerningTable {
+    /// 0 for Microsoft, 0x00010000 for Apple
+    version: u32,
+    subtables: Vec<KernSubtable>,
+}
+
+impl Debug for KerningTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KerningTable")
+            .field_with("version", |f| write!(f, "0x{:08x}", &self.version))
+            .field("subtables", &self.subtables)
+            .finish()
+    }
+}
+
+impl KerningTable {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        // the Microsoft header has a 16 bit version of 0, Apple's a 32 bit version of 1.0
+        let apple = 0 != read_uint16(&mut &o[..])?;
+        let (version, n_tables) = if apple {
+            (read_uint32(r)?, read_uint32(r)?)
+        } else {
+            (read_uint16(r)? as u32, read_uint16(r)? as u32)
+        };
+        if apple && 0x00010000 != version {
+            return Err(format!("unsupported kern version 0x{:08x}", version).into());
+        }
+
+        let mut subtables = Vec::with_capacity(n_tables.min(64) as usize);
+        let mut start = o.len() - r.len();
+        for _ in 0..n_tables {
+            let Some(s) = o.get(start..).filter(|s| !s.is_empty()) else {
+                break;
+            };
+            let (subtable, length) = KernSubtable::from_bytes(s, apple)?;
+            subtables.push(subtable);
+            start += length;
+        }
+
+        Ok(Self { version, subtables })
+    }
+
+    pub fn is_apple(&self) -> bool {
+        0 != self.version
+    }
+
+    pub fn subtables(&self) -> &[KernSubtable] {
+        &self.subtables
+    }
+
+    /// Kerning of a pair from the horizontal or vertical subtables, accumulated in table order
+    pub fn pair_kerning(&self, left: GlyphId, right: GlyphId, vertical: bool) -> PairKerning {
+        let mut pk = PairKerning::default();
+        for st in &self.subtables {
+            if st.is_horizontal() == vertical || st.is_variation() {
+                continue;
+            }
+            let Some(value) = st.kerning(left, right) else {
+                continue;
+            };
+            if st.is_minimum() {
+                pk.minimum = Some(pk.minimum.map_or(value, |m| m.max(value)));
+                continue;
+            }
+            let v = if st.is_cross_stream() { &mut pk.cross_stream } else { &mut pk.value };
+            *v = if st.is_override() { value } else { v.saturating_add(value) };
+        }
+        pk
+    }
+}
+
+/// Kerning of one glyph pair in font design units
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PairKerning {
+    /// Adjustment along the text direction
+    pub value: i16,
+    /// Adjustment perpendicular to the text direction, from cross-stream subtables
+    pub cross_stream: i16,
+    /// Value of subtables that hold minimum values rather than kerning values
+    pub minimum: Option<i16>,
+}
+
+pub struct KernSubtable {
+    apple: bool,
+    coverage: u16,
+    /// Apple only, the variation tuple the values apply to
+    tuple_index: u16,
+    data: KernSubtableData,
+}
+
+impl Debug for KernSubtable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KernSubtable")
+            .field("apple", &self.apple)
+            .field_with("coverage", |f| write!(f, "0x{:04x}", &self.coverage))
+            .field("tuple_index", &self.tuple_index)
+            .field("data", &self.data)
+            .finish()
+    }
+}
+
+impl KernSubtable {
+    /// Read a subtable and the number of bytes it spans
+    pub fn from_bytes(mut b: &[u8], apple: bool) -> Result<(Self, usize), Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let (length, coverage, format, tuple_index) = if apple {
+            let length = read_uint32(r)? as usize;
+            let coverage = read_uint16(r)?;
+            (length, coverage, coverage & 0x00FF, read_uint16(r)?)
+        } else {
+            let _version = read_uint16(r)?;
+            let length = read_uint16(r)? as usize;
+            let coverage = read_uint16(r)?;
+            (length, coverage, coverage >> 8, 0)
+        };
+
+        let data = match format {
+            0 => {
+                let n_pairs = read_uint16(r)?;
+                let _search_range = read_uint16(r)?;
+                let _entry_selector = read_uint16(r)?;
+                let _range_shift = read_uint16(r)?;
+                let mut pairs = Vec::with_capacity(n_pairs as usize);
+                for _ in 0..n_pairs {
+                    let left = read_uint16(r)?;
+                    let right = read_uint16(r)?;
+                    pairs.push(((left as u32) << 16 | right as u32, read_int16(r)?));
+                }
+                pairs.sort_by_key(|&(key, _)| key);
+                KernSubtableData::Format0(pairs)
+            }
+            // offsets in a format 2 subtable are from its start, header included
+            2 => KernSubtableData::Format2 {
+                row_width: read_uint16(r)?,
+                left_class_table: read_class_table(o, read_offset16(r)?)?,
+                right_class_table: read_class_table(o, read_offset16(r)?)?,
+                array_offset: read_offset16(r)?,
+                data: o.get(..length).unwrap_or(o).to_vec(),
+            },
+            format => KernSubtableData::Unsupported(format),
+        };
+
+        // Microsoft format 0 lengths overflow 16 bits in large tables, the pair count is authoritative
+        let length = match &data {
+            KernSubtableData::Format0(pairs) if !apple => 14 + pairs.len() * 6,
+            _ => length,
+        };
+        if 0 == length {
+            return Err("kern subtable has zero length".into());
+        }
+
+        Ok((Self { apple, coverage, tuple_index, data }, length))
+    }
+
+    pub fn format(&self) -> u16 {
+        match &self.data {
+            KernSubtableData::Format0(_) => 0,
+            KernSubtableData::Format2 { .. } => 2,
+            KernSubtableData::Unsupported(format) => *format,
+        }
+    }
+
+    pub fn is_horizontal(&self) -> bool {
+        if self.apple { 0 == self.coverage & APPLE_VERTICAL } else { 0 != self.coverage & MS_HORIZONTAL }
+    }
+
+    /// Values move glyphs perpendicular to the text direction
+    pub fn is_cross_stream(&self) -> bool {
+        0 != self.coverage & if self.apple { APPLE_CROSS_STREAM } else { MS_CROSS_STREAM }
+    }
+
+    /// Values are minimums rather than kerning values, Microsoft only
+    pub fn is_minimum(&self) -> bool {
+        !self.apple && 0 != self.coverage & MS_MINIMUM
+    }
+
+    /// Values replace the kerning accumulated so far, Microsoft only
+    pub fn is_override(&self) -> bool {
+        !self.apple && 0 != self.coverage & MS_OVERRIDE
+    }
+
+    /// Values apply to the variation tuple `tuple_index`, Apple only
+    pub fn is_variation(&self) -> bool {
+        self.apple && 0 != self.coverage & APPLE_VARIATION
+    }
+
+    pub fn tuple_index(&self) -> u16 {
+        self.tuple_index
+    }
+
+    /// Value for a pair, `None` when the subtable doesn't kern it or its format isn't supported
+    pub fn kerning(&self, left: GlyphId, right: GlyphId) -> Option<i16> {
+        match &self.data {
+            KernSubtableData::Format0(pairs) => {
+                let key = (left as u32) << 16 | right as u32;
+                pairs.binary_search_by_key(&key, |&(k, _)| k).ok().map(|i| pairs[i].1)
+            }
+            KernSubtableData::Format2 {
+                left_class_table,
+                right_class_table,
+                array_offset,
+                data,
+                ..
+            } => {
+                // left classes are offsets of rows, right classes offsets within a row
+                let offset = left_class_table.class(left) as usize + right_class_table.class(right) as usize;
+                if offset < *array_offset as usize {
+                    return None;
+                }
+                read_int16(&mut data.get(offset..)?).ok()
+            }
+            KernSubtableData::Unsupported(_) => None,
+        }
+    }
+}
+
+pub enum KernSubtableData {
+    /// Pairs keyed by left << 16 | right, sorted
+    Format0(Vec<(u32, i16)>),
+    /// Two-dimensional array of values indexed by glyph classes
+    Format2 {
+        row_width: u16,
+        left_class_table: KernClassTable,
+        right_class_table: KernClassTable,
+        array_offset: Offset16,
+        /// The subtable bytes the class offsets point into
+        data: Vec<u8>,
+    },
+    /// Apple state table or other format, kept so the subtable count stays right
+    Unsupported(u16),
+}
+
+impl Debug for KernSubtableData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Format0(pairs) => f.debug_struct("Format0").field_with("pairs", |f| write!(f, "[(u32, i16); {}]", pairs.len())).finish(),
+            Self::Format2 {
+                row_width,
+                left_class_table,
+                right_class_table,
+                array_offset,
+                ..
+            } => f
+                .debug_struct("Format2")
+                .field("row_width", row_width)
+                .field("left_class_table", left_class_table)
+                .field("right_class_table", right_class_table)
+                .field("array_offset", array_offset)
+                .finish(),
+            Self::Unsupported(format) => f.debug_tuple("Unsupported").field(format).finish(),
+        }
+    }
+}
+
+fn read_class_table(o: &[u8], offset: Offset16) -> Result<KernClassTable, Box<dyn Error>> {
+    let mut s = o.get(offset as usize..).ok_or("kern class table offset out of range")?;
+    let first_glyph = read_uint16(&mut s)?;
+    let n_glyphs = read_uint16(&mut s)?;
+    let offsets = read_array(&mut s, n_glyphs as usize, read_uint16)?;
+    Ok(KernClassTable { first_glyph, offsets })
+}
+
+/// Class values of a run of glyphs, glyphs outside the run have value 0
+#[derive(Debug)]
+pub struct KernClassTable {
+    first_glyph: GlyphId,
+    offsets: Vec<u16>,
+}
+
+impl KernClassTable {
+    pub fn class(&self, glyph_id: GlyphId) -> u16 {
+        glyph_id.checked_sub(self.first_glyph).and_then(|i| self.offsets.get(i as usize)).copied().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Format 0 data with `pairs` in the order given
+    fn format0(pairs: &[(GlyphId, GlyphId, i16)]) -> Vec<u8> {
+        let mut w = Vec::new();
+        [pairs.len() as u16, 0, 0, 0].iter().for_each(|&v| write_uint16(&mut w, v));
+        for &(left, right, value) in pairs {
+            write_uint16(&mut w, left);
+            write_uint16(&mut w, right);
+            write_int16(&mut w, value);
+        }
+        w
+    }
+
+    /// Format 2 data behind a `header` byte subtable header: left glyphs 10 and 11 are rows 0 and 1, right glyph 20 is column 1 and every other right glyph column 0
+    fn format2(header: u16) -> Vec<u8> {
+        let mut w = Vec::new();
+        [4, header + 8, header + 16, header + 22].iter().for_each(|&v| write_uint16(&mut w, v));
+        [10, 2, header + 22, header + 26].iter().for_each(|&v| write_uint16(&mut w, v));
+        [20, 1, 2].iter().for_each(|&v| write_uint16(&mut w, v));
+        [0, -40, 0, -60].iter().for_each(|&v| write_int16(&mut w, v));
+        w
+    }
+
+    fn microsoft(subtables: &[(u16, Vec<u8>)]) -> KerningTable {
+        let mut w = Vec::new();
+        write_uint16(&mut w, 0);
+        write_uint16(&mut w, subtables.len() as u16);
+        for (coverage, data) in subtables {
+            [0, 6 + data.len() as u16, *coverage].iter().for_each(|&v| write_uint16(&mut w, v));
+            w.extend(data);
+        }
+        KerningTable::from_bytes(&w).unwrap()
+    }
+
+    fn apple(subtables: &[(u16, Vec<u8>)]) -> KerningTable {
+        let mut w = Vec::new();
+        write_uint32(&mut w, 0x00010000);
+        write_uint32(&mut w, subtables.len() as u32);
+        for (coverage, data) in subtables {
+            write_uint32(&mut w, 8 + data.len() as u32);
+            write_uint16(&mut w, *coverage);
+            write_uint16(&mut w, 0);
+            w.extend(data);
+        }
+        KerningTable::from_bytes(&w).unwrap()
+    }
+
+    #[test]
+    fn format0_pairs() {
+        let kern = microsoft(&[(MS_HORIZONTAL, format0(&[(5, 1, 15), (1, 2, -50), (1, 9, -20), (3, 2, 10)]))]);
+        assert!(!kern.is_apple());
+        let st = &kern.subtables()[0];
+        assert_eq!((0, true), (st.format(), st.is_horizontal()));
+        // pairs are searched by their left << 16 | right key whatever order the font stores them in
+        assert_eq!(vec![Some(-50), Some(-20), Some(10), Some(15), None], [(1, 2), (1, 9), (3, 2), (5, 1), (2, 1)].iter().map(|&(l, r)| st.kerning(l, r)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn format2_classes() {
+        let kern = microsoft(&[(2 << 8 | MS_HORIZONTAL, format2(6))]);
+        let st = &kern.subtables()[0];
+        assert_eq!(2, st.format());
+        assert_eq!(vec![Some(-40), Some(-60), Some(0), None], [(10, 20), (11, 20), (10, 21), (12, 20)].iter().map(|&(l, r)| st.kerning(l, r)).collect::<Vec<_>>());
+
+        // Apple subtable headers are 8 bytes, the offsets still count from the subtable start
+        let kern = apple(&[(2, format2(8))]);
+        assert!(kern.is_apple());
+        assert_eq!(Some(-60), kern.subtables()[0].kerning(11, 20));
+    }
+
+    #[test]
+    fn microsoft_coverage() {
+        let kern = microsoft(&[
+            (MS_HORIZONTAL, format0(&[(1, 2, -50)])),
+            (MS_HORIZONTAL | MS_CROSS_STREAM, format0(&[(1, 2, 30)])),
+            (MS_HORIZONTAL | MS_MINIMUM, format0(&[(1, 2, -20)])),
+            (MS_HORIZONTAL | MS_MINIMUM, format0(&[(1, 2, -10)])),
+            (0, format0(&[(1, 2, -99)])),
+        ]);
+        assert_eq!(PairKerning { value: -50, cross_stream: 30, minimum: Some(-10) }, kern.pair_kerning(1, 2, false));
+        assert_eq!(PairKerning { value: -99, cross_stream: 0, minimum: None }, kern.pair_kerning(1, 2, true));
+
+        let kern = microsoft(&[(MS_HORIZONTAL, format0(&[(1, 2, -50)])), (MS_HORIZONTAL | MS_OVERRIDE, format0(&[(1, 2, -5)])), (MS_HORIZONTAL, format0(&[(1, 2, -1)]))]);
+        assert_eq!(-6, kern.pair_kerning(1, 2, false).value);
+    }
+
+    #[test]
+    fn apple_coverage() {
+        let kern = apple(&[(0, format0(&[(1, 2, -70)])), (APPLE_CROSS_STREAM, format0(&[(1, 2, 25)])), (APPLE_VARIATION, format0(&[(1, 2, -99)])), (APPLE_VERTICAL, format0(&[(1, 2, -30)]))]);
+        assert_eq!(PairKerning { value: -70, cross_stream: 25, minimum: None }, kern.pair_kerning(1, 2, false));
+        assert_eq!(-30, kern.pair_kerning(1, 2, true).value);
+    }
+}
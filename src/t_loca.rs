@@ -0,0 +1,63 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::types::*;
+use super::utils::*;
+
+pub struct IndexToLocationTable {
+    /// Offsets of each glyph into glyf, plus one extra entry for the end of the last glyph
+    offsets: Vec<Offset32>,
+}
+
+impl Debug for IndexToLocationTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IndexToLocationTable").field_with("offsets", |f| write!(f, "[Offset32; {}]", self.offsets.len())).finish()
+    }
+}
+
+impl IndexToLocationTable {
+    /// `index_to_loc_format` comes from head, `num_glyphs` from maxp
+    pub fn from_bytes(mut b: &[u8], index_to_loc_format: i16, num_glyphs: u16) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let count = num_glyphs as usize + 1;
+        let offsets = match index_to_loc_format {
+            // the short version stores the actual offset divided by 2
+            0 => read_array(r, count, read_uint16)?.into_iter().map(|o| o as u32 * 2).collect(),
+            1 => read_array(r, count, read_offset32)?,
+            _ => return Err(format!("unknown head indexToLocFormat {}", index_to_loc_format).into()),
+        };
+
+        Ok(Self { offsets })
+    }
+
+    /// Build the table for freshly laid out glyph data, `offsets` has one entry per glyph plus the end of the last glyph
+    pub fn from_offsets(offsets: Vec<Offset32>) -> Self {
+        Self { offsets }
+    }
+
+    /// Encode in the head `index_to_loc_format`, the short format needs even offsets below 0x20000
+    pub fn to_bytes(&self, index_to_loc_format: i16) -> Vec<u8> {
+        let mut w = Vec::new();
+        for &o in &self.offsets {
+            if 0 == index_to_loc_format { write_uint16(&mut w, (o / 2) as u16) } else { write_uint32(&mut w, o) }
+        }
+        w
+    }
+
+    pub fn num_glyphs(&self) -> usize {
+        self.offsets.len().saturating_sub(1)
+    }
+
+    /// Byte range of a glyph in glyf, empty for glyphs without outline
+    pub fn glyph_range(&self, glyph_id: GlyphId) -> Option<std::ops::Range<usize>> {
+        let start = *self.offsets.get(glyph_id as usize)? as usize;
+        let end = *self.offsets.get(glyph_id as usize + 1)? as usize;
+        if start > end { None } else { Some(start..end) }
+    }
+
+    /// End of the last glyph, must not be past the end of glyf
+    pub fn glyf_length(&self) -> usize {
+        self.offsets.last().copied().unwrap_or(0) as usize
+    }
+}
@@ -0,0 +1,214 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::types::*;
+use super::utils::*;
+
+pub struct MaximumProfileTable {
+    /// 0x00005000 for CFF outlines, 0x00010000 for TrueType outlines
+    version: Version16Dot16,
+    /// The number of glyphs in the font
+    num_glyphs: u16,
+    /// Maximum points in a non-composite glyph
+    max_points: Option<u16>, // ver 1.0
+    /// Maximum contours in a non-composite glyph
+    max_contours: Option<u16>, // ver 1.0
+    /// Maximum points in a composite glyph
+    max_composite_points: Option<u16>, // ver 1.0
+    /// Maximum contours in a composite glyph
+    max_composite_contours: Option<u16>, // ver 1.0
+    /// 1 if instructions do not use the twilight zone (Z0), or 2 if instructions do use Z0; should be set to 2 in most cases
+    max_zones: Option<u16>, // ver 1.0
+    /// Maximum points used in Z0
+    max_twilight_points: Option<u16>, // ver 1.0
+    /// Number of Storage Area locations
+    max_storage: Option<u16>, // ver 1.0
+    /// Number of FDEFs, equal to the highest function number + 1
+    max_function_defs: Option<u16>, // ver 1.0
+    /// Number of IDEFs
+    max_instruction_defs: Option<u16>, // ver 1.0
+    /// Maximum stack depth across Font Program ('fpgm' table), CVT Program ('prep' table) and all glyph instructions (in the 'glyf' table)
+    max_stack_elements: Option<u16>, // ver 1.0
+    /// Maximum byte count for glyph instructions
+    max_size_of_instructions: Option<u16>, // ver 1.0
+    /// Maximum number of components referenced at "top level" for any composite glyph
+    max_component_elements: Option<u16>, // ver 1.0
+    /// Maximum levels of recursion; 1 for simple components
+    max_component_depth: Option<u16>, // ver 1.0
+}
+
+impl Debug for MaximumProfileTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MaximumProfileTable")
+            .field("version", &self.version)
+            .field("num_glyphs", &self.num_glyphs)
+            .field("max_points", &self.max_points)
+            .field("max_contours", &self.max_contours)
+            .field("max_composite_points", &self.max_composite_points)
+            .field("max_composite_contours", &self.max_composite_contours)
+            .field("max_zones", &self.max_zones)
+            .field("max_twilight_points", &self.max_twilight_points)
+            .field("max_storage", &self.max_storage)
+            .field("max_function_defs", &self.max_function_defs)
+            .field("max_instruction_defs", &self.max_instruction_defs)
+            .field("max_stack_elements", &self.max_stack_elements)
+            .field("max_size_of_instructions", &self.max_size_of_instructions)
+            .field("max_component_elements", &self.max_component_elements)
+            .field("max_component_depth", &self.max_component_depth)
+            .finish()
+    }
+}
+
+impl MaximumProfileTable {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let version = read_version16dot16(r)?;
+        let num_glyphs = read_uint16(r)?;
+
+        match version.0 {
+            [0, 0x5000] => {
+                return Ok(Self {
+                    version,
+                    num_glyphs,
+                    max_points: None,
+                    max_contours: None,
+                    max_composite_points: None,
+                    max_composite_contours: None,
+                    max_zones: None,
+                    max_twilight_points: None,
+                    max_storage: None,
+                    max_function_defs: None,
+                    max_instruction_defs: None,
+                    max_stack_elements: None,
+                    max_size_of_instructions: None,
+                    max_component_elements: None,
+                    max_component_depth: None,
+                });
+            }
+            [1, 0] => {}
+            _ => return Err(format!("unsupported maxp version {:?}", version).into()),
+        }
+
+        let max_points = read_uint16(r)?;
+        let max_contours = read_uint16(r)?;
+        let max_composite_points = read_uint16(r)?;
+        let max_composite_contours = read_uint16(r)?;
+        let max_zones = read_uint16(r)?;
+        let max_twilight_points = read_uint16(r)?;
+        let max_storage = read_uint16(r)?;
+        let max_function_defs = read_uint16(r)?;
+        let max_instruction_defs = read_uint16(r)?;
+        let max_stack_elements = read_uint16(r)?;
+        let max_size_of_instructions = read_uint16(r)?;
+        let max_component_elements = read_uint16(r)?;
+        let max_component_depth = read_uint16(r)?;
+
+        Ok(Self {
+            version,
+            num_glyphs,
+            max_points: Some(max_points),
+            max_contours: Some(max_contours),
+            max_composite_points: Some(max_composite_points),
+            max_composite_contours: Some(max_composite_contours),
+            max_zones: Some(max_zones),
+            max_twilight_points: Some(max_twilight_points),
+            max_storage: Some(max_storage),
+            max_function_defs: Some(max_function_defs),
+            max_instruction_defs: Some(max_instruction_defs),
+            max_stack_elements: Some(max_stack_elements),
+            max_size_of_instructions: Some(max_size_of_instructions),
+            max_component_elements: Some(max_component_elements),
+            max_component_depth: Some(max_component_depth),
+        })
+    }
+
+    pub fn num_glyphs(&self) -> u16 {
+        self.num_glyphs
+    }
+
+    /// Whether the glyph ID names a glyph of the font
+    pub fn contains(&self, glyph_id: GlyphId) -> bool {
+        glyph_id < self.num_glyphs
+    }
+
+    pub fn max_points(&self) -> Option<u16> {
+        self.max_points
+    }
+
+    pub fn max_contours(&self) -> Option<u16> {
+        self.max_contours
+    }
+
+    pub fn max_composite_points(&self) -> Option<u16> {
+        self.max_composite_points
+    }
+
+    pub fn max_composite_contours(&self) -> Option<u16> {
+        self.max_composite_contours
+    }
+
+    pub fn max_zones(&self) -> Option<u16> {
+        self.max_zones
+    }
+
+    pub fn max_twilight_points(&self) -> Option<u16> {
+        self.max_twilight_points
+    }
+
+    pub fn max_storage(&self) -> Option<u16> {
+        self.max_storage
+    }
+
+    pub fn max_function_defs(&self) -> Option<u16> {
+        self.max_function_defs
+    }
+
+    pub fn max_instruction_defs(&self) -> Option<u16> {
+        self.max_instruction_defs
+    }
+
+    pub fn max_stack_elements(&self) -> Option<u16> {
+        self.max_stack_elements
+    }
+
+    pub fn max_size_of_instructions(&self) -> Option<u16> {
+        self.max_size_of_instructions
+    }
+
+    pub fn max_component_elements(&self) -> Option<u16> {
+        self.max_component_elements
+    }
+
+    pub fn max_component_depth(&self) -> Option<u16> {
+        self.max_component_depth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions() {
+        let mut w = Vec::new();
+        write_uint32(&mut w, 0x5000);
+        write_uint16(&mut w, 12);
+        let maxp = MaximumProfileTable::from_bytes(&w).unwrap();
+        assert_eq!(12, maxp.num_glyphs());
+        assert!(maxp.contains(11) && !maxp.contains(12));
+        assert_eq!(None, maxp.max_points());
+
+        let mut w = Vec::new();
+        write_uint32(&mut w, 0x10000);
+        for v in [12, 40, 3, 80, 6, 2, 16, 8, 20, 0, 256, 1024, 2, 1] {
+            write_uint16(&mut w, v);
+        }
+        let maxp = MaximumProfileTable::from_bytes(&w).unwrap();
+        assert_eq!((Some(40), Some(2), Some(16), Some(256), Some(1)), (maxp.max_points(), maxp.max_zones(), maxp.max_twilight_points(), maxp.max_stack_elements(), maxp.max_component_depth()));
+        assert!(MaximumProfileTable::from_bytes(&w[..20]).is_err());
+
+        w[0..4].copy_from_slice(&0x20000u32.to_be_bytes());
+        assert!(MaximumProfileTable::from_bytes(&w).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::types::*;
+use super::utils::*;
+use super::variations::*;
+
+/// OS/2 sTypoAscender, hhea ascender is not varied
+pub const HORIZONTAL_ASCENDER: Tag = Tag::new(b"hasc");
+/// OS/2 sTypoDescender, hhea descender is not varied
+pub const HORIZONTAL_DESCENDER: Tag = Tag::new(b"hdsc");
+/// OS/2 sTypoLineGap, hhea lineGap is not varied
+pub const HORIZONTAL_LINE_GAP: Tag = Tag::new(b"hlgp");
+/// OS/2 usWinAscent
+pub const HORIZONTAL_CLIPPING_ASCENT: Tag = Tag::new(b"hcla");
+/// OS/2 usWinDescent
+pub const HORIZONTAL_CLIPPING_DESCENT: Tag = Tag::new(b"hcld");
+/// hhea caretSlopeRise
+pub const HORIZONTAL_CARET_RISE: Tag = Tag::new(b"hcrs");
+/// hhea caretSlopeRun
+pub const HORIZONTAL_CARET_RUN: Tag = Tag::new(b"hcrn");
+/// hhea caretOffset
+pub const HORIZONTAL_CARET_OFFSET: Tag = Tag::new(b"hcof");
+/// vhea ascent
+pub const VERTICAL_ASCENDER: Tag = Tag::new(b"vasc");
+/// vhea descent
+pub const VERTICAL_DESCENDER: Tag = Tag::new(b"vdsc");
+/// vhea lineGap
+pub const VERTICAL_LINE_GAP: Tag = Tag::new(b"vlgp");
+/// OS/2 sxHeight
+pub const X_HEIGHT: Tag = Tag::new(b"xhgt");
+/// OS/2 sCapHeight
+pub const CAP_HEIGHT: Tag = Tag::new(b"cpht");
+/// OS/2 yStrikeoutSize
+pub const STRIKEOUT_SIZE: Tag = Tag::new(b"strs");
+/// OS/2 yStrikeoutPosition
+pub const STRIKEOUT_OFFSET: Tag = Tag::new(b"stro");
+/// post underlineThickness
+pub const UNDERLINE_SIZE: Tag = Tag::new(b"unds");
+/// post underlinePosition
+pub const UNDERLINE_OFFSET: Tag = Tag::new(b"undo");
+
+/// Variations of font-wide metrics in OS/2, hhea, vhea, post and gasp
+pub struct MetricsVariationsTable {
+    /// Major version number of the metrics variations table — set to 1
+    major_version: u16,
+    /// Minor version number of the metrics variations table — set to 0
+    minor_version: u16,
+    /// Sorted by value tag
+    value_records: Vec<ValueRecord>,
+    /// May be absent when there are no value records
+    item_variation_store: Option<ItemVariationStore>,
+}
+
+impl Debug for MetricsVariationsTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetricsVariationsTable")
+            .field("major_version", &self.major_version)
+            .field("minor_version", &self.minor_version)
+            .field("value_records", &self.value_records)
+            .field("item_variation_store", &self.item_variation_store)
+            .finish()
+    }
+}
+
+impl MetricsVariationsTable {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let major_version = read_uint16(r)?;
+        let minor_version = read_uint16(r)?;
+        if 1 != major_version {
+            return Err(format!("unsupported MVAR version {}.{}", major_version, minor_version).into());
+        }
+        let _reserved = read_uint16(r)?;
+        let value_record_size = read_uint16(r)?;
+        let value_record_count = read_uint16(r)?;
+        let item_variation_store_offset = read_offset16(r)?;
+
+        // records are read at their stride, fields added by later minor versions are ignored
+        if value_record_size < 8 {
+            return Err(format!("MVAR valueRecordSize {} is too small", value_record_size).into());
+        }
+        let mut value_records = Vec::with_capacity(value_record_count as usize);
+        for i in 0..value_record_count as usize {
+            let start = 12 + i * value_record_size as usize;
+            value_records.push(ValueRecord::from_bytes(&mut o.get(start..).ok_or("MVAR value record out of range")?)?);
+        }
+
+        let item_variation_store = match item_variation_store_offset {
+            0 => None,
+            offset => Some(ItemVariationStore::from_bytes(o.get(offset as usize..).ok_or("MVAR ItemVariationStore offset out of range")?)?),
+        };
+
+        Ok(Self {
+            major_version,
+            minor_version,
+            value_records,
+            item_variation_store,
+        })
+    }
+
+    pub fn value_records(&self) -> &[ValueRecord] {
+        &self.value_records
+    }
+
+    pub fn item_variation_store(&self) -> Option<&ItemVariationStore> {
+        self.item_variation_store.as_ref()
+    }
+
+    /// Delta of the metric `value_tag` at normalized `coords`, 0 for metrics MVAR doesn't vary
+    pub fn delta(&self, value_tag: Tag, coords: &[F2Dot14]) -> f32 {
+        let (Ok(i), Some(ivs)) = (self.value_records.binary_search_by_key(&value_tag, |vr| vr.value_tag), &self.item_variation_store) else {
+            return 0.0;
+        };
+        let vr = &self.value_records[i];
+        ivs.delta(vr.delta_set_outer_index, vr.delta_set_inner_index, coords).unwrap_or(0.0)
+    }
+}
+
+pub struct ValueRecord {
+    /// Four-byte tag identifying a font-wide measure
+    value_tag: Tag,
+    /// A delta-set outer index — used to select an item variation data subtable within the item variation store
+    delta_set_outer_index: u16,
+    /// A delta-set inner index — used to select a delta-set row within an item variation data subtable
+    delta_set_inner_index: u16,
+}
+
+impl Debug for ValueRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ValueRecord")
+            .field("value_tag", &self.value_tag)
+            .field("delta_set_outer_index", &self.delta_set_outer_index)
+            .field("delta_set_inner_index", &self.delta_set_inner_index)
+            .finish()
+    }
+}
+
+impl ValueRecord {
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let value_tag = read_tag(r)?;
+        let delta_set_outer_index = read_uint16(r)?;
+        let delta_set_inner_index = read_uint16(r)?;
+
+        Ok(Self {
+            value_tag,
+            delta_set_outer_index,
+            delta_set_inner_index,
+        })
+    }
+
+    pub fn value_tag(&self) -> Tag {
+        self.value_tag
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variations::builders::*;
+
+    /// MVAR with 10-byte value records for cpht, hasc and xhgt, varying by 30, 10 and 20 units at the max of the single axis
+    fn mvar() -> Vec<u8> {
+        let mut w = Vec::new();
+        [1, 0, 0, 10, 3, 42].iter().for_each(|&v| write_uint16(&mut w, v));
+        for (tag, inner) in [(CAP_HEIGHT, 2), (HORIZONTAL_ASCENDER, 0), (X_HEIGHT, 1)] {
+            write_tag(&mut w, tag);
+            [0, inner, 0xFFFF].iter().for_each(|&v| write_uint16(&mut w, v));
+        }
+        w.extend(item_variation_store(&[&[(0.0, 1.0, 1.0)]], &[&[10], &[20], &[30]]));
+        w
+    }
+
+    #[test]
+    fn deltas() {
+        let mvar = MetricsVariationsTable::from_bytes(&mvar()).unwrap();
+        assert_eq!(vec![CAP_HEIGHT, HORIZONTAL_ASCENDER, X_HEIGHT], mvar.value_records().iter().map(|vr| vr.value_tag()).collect::<Vec<_>>());
+        let deltas = [CAP_HEIGHT, HORIZONTAL_ASCENDER, X_HEIGHT, HORIZONTAL_DESCENDER, UNDERLINE_OFFSET].map(|tag| mvar.delta(tag, &[0.5]));
+        assert_eq!([15.0, 5.0, 10.0, 0.0, 0.0], deltas);
+        assert_eq!(0.0, mvar.delta(X_HEIGHT, &[]));
+    }
+
+    #[test]
+    fn value_records_without_a_store() {
+        let mut b = mvar();
+        put_uint16(&mut b, 10, 0).unwrap();
+        let mvar = MetricsVariationsTable::from_bytes(&b).unwrap();
+        assert!(mvar.item_variation_store().is_none());
+        assert_eq!(0.0, mvar.delta(HORIZONTAL_ASCENDER, &[1.0]));
+        put_uint16(&mut b, 6, 6).unwrap();
+        assert!(MetricsVariationsTable::from_bytes(&b).is_err());
+    }
+}
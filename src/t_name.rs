@@ -39,7 +39,9 @@ impl NamingTable {
 
         for _ in 0..count {
             let mut nr = NameRecord::from_bytes(r)?;
-            nr.content = Some(String::from_utf16be(&o[{ (storage_offset + nr.string_offset) as usize }..{ (storage_offset + nr.string_offset + nr.length) as usize }])?);
+            let start = storage_offset as usize + nr.string_offset as usize;
+            nr.data = o.get(start..start + nr.length as usize).ok_or("name string out of range")?.to_vec();
+            nr.content = Some(String::from_utf16be(&nr.data)?);
             name_record.push(nr);
         }
 
@@ -59,18 +61,107 @@ impl NamingTable {
 
         for _ in 0..lang_tag_count {
             let mut ltr = LangTagRecord::from_bytes(r)?;
-            ltr.content = Some(String::from_utf16be(&o[{ (storage_offset + ltr.lang_tag_offset) as usize }..{ (storage_offset + ltr.lang_tag_offset + ltr.length) as usize }])?);
+            let start = storage_offset as usize + ltr.lang_tag_offset as usize;
+            ltr.data = o.get(start..start + ltr.length as usize).ok_or("language tag string out of range")?.to_vec();
+            ltr.content = Some(String::from_utf16be(&ltr.data)?);
             lang_tag_record.push(ltr);
         }
 
-        return Ok(Self {
+        Ok(Self {
             version,
             count,
             storage_offset,
             name_record,
             lang_tag_count: Some(lang_tag_count),
             lang_tag_record: Some(lang_tag_record),
-        });
+        })
+    }
+
+    /// A name string, preferring Windows English (United States), then any Windows or Unicode record, then Macintosh English
+    pub fn name(&self, name_id: u16) -> Option<String> {
+        let rank = |nr: &NameRecord| match (nr.platform_id, nr.language_id) {
+            (3, 0x0409) => 0,
+            (3, _) => 1,
+            (0, _) => 2,
+            (1, 0) => 3,
+            _ => 4,
+        };
+        self.name_record.iter().filter(|nr| name_id == nr.name_id && nr.content.is_some()).min_by_key(|nr| rank(nr)).and_then(|nr| nr.content.clone())
+    }
+
+    /// Replace the Windows and Unicode English names and the Macintosh Roman English name of `name_id`, adding a Windows English record when there is none
+    pub fn set_name(&mut self, name_id: u16, value: &str) {
+        let english = |nr: &NameRecord| match nr.platform_id {
+            0 => true,
+            1 => 0 == nr.encoding_id && 0 == nr.language_id,
+            3 => 0x0409 == nr.language_id,
+            _ => false,
+        };
+        let mut windows = false;
+        for nr in self.name_record.iter_mut().filter(|nr| name_id == nr.name_id && english(nr)) {
+            windows |= 3 == nr.platform_id;
+            nr.set_content(value);
+        }
+        if !windows {
+            let mut nr = NameRecord {
+                platform_id: 3,
+                encoding_id: 1,
+                language_id: 0x0409,
+                name_id,
+                length: 0,
+                string_offset: 0,
+                content: None,
+                data: Vec::new(),
+            };
+            nr.set_content(value);
+            self.name_record.push(nr);
+        }
+    }
+
+    /// Drop every record of `name_id`
+    pub fn remove_names(&mut self, name_id: u16) {
+        self.name_record.retain(|nr| name_id != nr.name_id);
+    }
+
+    /// Encode the table with its records sorted and each distinct string stored once
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut records: Vec<_> = self.name_record.iter().collect();
+        records.sort_by_key(|nr| (nr.platform_id, nr.encoding_id, nr.language_id, nr.name_id));
+        let lang_tags = self.lang_tag_record.as_deref().unwrap_or_default();
+
+        let mut storage: Vec<u8> = Vec::new();
+        let mut store = |data: &[u8]| match storage.windows(data.len().max(1)).position(|w| w == data).filter(|_| !data.is_empty()) {
+            Some(offset) => offset as u16,
+            None => {
+                storage.extend(data);
+                (storage.len() - data.len()) as u16
+            }
+        };
+        let record_offsets: Vec<_> = records.iter().map(|nr| store(&nr.data)).collect();
+        let lang_tag_offsets: Vec<_> = lang_tags.iter().map(|ltr| store(&ltr.data)).collect();
+
+        let mut w = Vec::new();
+        let header_len = 6 + 12 * records.len() + if 0 == self.version { 0 } else { 2 + 4 * lang_tags.len() };
+        write_uint16(&mut w, self.version);
+        write_uint16(&mut w, records.len() as u16);
+        write_uint16(&mut w, header_len as u16);
+        for (nr, offset) in records.iter().zip(record_offsets) {
+            write_uint16(&mut w, nr.platform_id);
+            write_uint16(&mut w, nr.encoding_id);
+            write_uint16(&mut w, nr.language_id);
+            write_uint16(&mut w, nr.name_id);
+            write_uint16(&mut w, nr.data.len() as u16);
+            write_uint16(&mut w, offset);
+        }
+        if 0 != self.version {
+            write_uint16(&mut w, lang_tags.len() as u16);
+            for (ltr, offset) in lang_tags.iter().zip(lang_tag_offsets) {
+                write_uint16(&mut w, ltr.data.len() as u16);
+                write_uint16(&mut w, offset);
+            }
+        }
+        w.extend(storage);
+        w
     }
 }
 
@@ -83,18 +174,21 @@ pub struct NameRecord {
     /// String offset from start of storage area (in bytes)
     string_offset: Offset16,
     content: Option<String>,
+    /// The encoded string, kept so names in encodings that aren't decoded can be written back
+    data: Vec<u8>,
 }
 
 impl Debug for NameRecord {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("NameRecord")
-            .field_with("platform_id", |f| write!(f, "{}({})", get_platform_desc(self.platform_id), &self.platform_id))
-            .field_with("encoding_id", |f| write!(f, "{}({})", get_encoding_desc(self.platform_id, self.encoding_id), &self.encoding_id))
-            .field_with("language_id", |f| write!(f, "{}({})", get_language_desc(self.platform_id, self.language_id), &self.language_id))
-            .field_with("name_id", |f| write!(f, "{}({})", Self::get_name_desc(self.name_id), &self.name_id))
+            .field_with("platform_id", |f| write!(f, "{}({})", get_platform_desc(self.platform_id), self.platform_id))
+            .field_with("encoding_id", |f| write!(f, "{}({})", get_encoding_desc(self.platform_id, self.encoding_id), self.encoding_id))
+            .field_with("language_id", |f| write!(f, "{}({})", get_language_desc(self.platform_id, self.language_id), self.language_id))
+            .field_with("name_id", |f| write!(f, "{}({})", Self::get_name_desc(self.name_id), self.name_id))
             .field("length", &self.length)
             .field("string_offset", &self.string_offset)
             .field("content", &self.content)
+            .field_with("data", |f| write!(f, "[u8; {}]", self.data.len()))
             .finish()
     }
 }
@@ -116,9 +210,20 @@ impl NameRecord {
             length,
             string_offset,
             content: None,
+            data: Vec::new(),
         })
     }
 
+    /// Encode `value` for the record's platform, characters Macintosh Roman lacks become '?'
+    fn set_content(&mut self, value: &str) {
+        self.data = match self.platform_id {
+            1 => value.chars().map(|c| unicode_to_mac_roman(c as u32).unwrap_or(b'?')).collect(),
+            _ => value.encode_utf16().flat_map(|u| u.to_be_bytes()).collect(),
+        };
+        self.length = self.data.len() as u16;
+        self.content = Some(value.to_string());
+    }
+
     fn get_name_desc(name_id: u16) -> &'static str {
         match name_id {
             0 => "Copyright notice",
@@ -159,6 +264,7 @@ pub struct LangTagRecord {
     /// Language-tag string offset from start of storage area (in bytes).
     lang_tag_offset: Offset16,
     content: Option<String>,
+    data: Vec<u8>,
 }
 
 impl LangTagRecord {
@@ -166,7 +272,12 @@ impl LangTagRecord {
         let length = read_uint16(r)?;
         let lang_tag_offset = read_offset16(r)?;
 
-        Ok(Self { length, lang_tag_offset, content: None })
+        Ok(Self {
+            length,
+            lang_tag_offset,
+            content: None,
+            data: Vec::new(),
+        })
     }
 }
 
@@ -0,0 +1,564 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::types::*;
+use super::utils::*;
+
+pub struct OS2Table {
+    version: u16,
+    /// Average weighted escapement
+    x_avg_char_width: i16,
+    /// Weight class
+    us_weight_class: u16,
+    /// Width class
+    us_width_class: u16,
+    /// Type flags, font embedding licensing rights
+    fs_type: u16,
+    y_subscript_x_size: i16,
+    y_subscript_y_size: i16,
+    y_subscript_x_offset: i16,
+    y_subscript_y_offset: i16,
+    y_superscript_x_size: i16,
+    y_superscript_y_size: i16,
+    y_superscript_x_offset: i16,
+    y_superscript_y_offset: i16,
+    y_strikeout_size: i16,
+    y_strikeout_position: i16,
+    /// Font-family class and subclass
+    s_family_class: i16,
+    /// PANOSE classification number
+    panose: [u8; 10],
+    /// Unicode Character Range, bits 0–31 in ul_unicode_range[0] through bits 96–127 in ul_unicode_range[3]
+    ul_unicode_range: [u32; 4],
+    /// Font Vendor Identification
+    ach_vend_id: Tag,
+    /// Font selection flags
+    fs_selection: u16,
+    /// The minimum Unicode index (character code) in this font
+    us_first_char_index: u16,
+    /// The maximum Unicode index (character code) in this font
+    us_last_char_index: u16,
+    s_typo_ascender: Option<i16>,  // ver 0, missing from some early Apple fonts
+    s_typo_descender: Option<i16>, // ver 0, missing from some early Apple fonts
+    s_typo_line_gap: Option<i16>,  // ver 0, missing from some early Apple fonts
+    us_win_ascent: Option<u16>,    // ver 0, missing from some early Apple fonts
+    us_win_descent: Option<u16>,   // ver 0, missing from some early Apple fonts
+    /// Code Page Character Range, bits 0–31 in ul_code_page_range[0] and bits 32–63 in ul_code_page_range[1]
+    ul_code_page_range: Option<[u32; 2]>, // ver 1
+    sx_height: Option<i16>,                  // ver 2
+    s_cap_height: Option<i16>,               // ver 2
+    us_default_char: Option<u16>,            // ver 2
+    us_break_char: Option<u16>,              // ver 2
+    us_max_context: Option<u16>,             // ver 2
+    /// Lower value of the optical size range, in TWIPs
+    us_lower_optical_point_size: Option<u16>, // ver 5
+    /// Upper value of the optical size range, in TWIPs
+    us_upper_optical_point_size: Option<u16>, // ver 5
+}
+
+impl Debug for OS2Table {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OS2Table")
+            .field("version", &self.version)
+            .field("x_avg_char_width", &self.x_avg_char_width)
+            .field_with("us_weight_class", |f| write!(f, "{}({})", Self::get_weight_desc(self.us_weight_class), self.us_weight_class))
+            .field_with("us_width_class", |f| write!(f, "{}({})", Self::get_width_desc(self.us_width_class), self.us_width_class))
+            .field("fs_type", &self.fs_type())
+            .field("y_subscript_x_size", &self.y_subscript_x_size)
+            .field("y_subscript_y_size", &self.y_subscript_y_size)
+            .field("y_subscript_x_offset", &self.y_subscript_x_offset)
+            .field("y_subscript_y_offset", &self.y_subscript_y_offset)
+            .field("y_superscript_x_size", &self.y_superscript_x_size)
+            .field("y_superscript_y_size", &self.y_superscript_y_size)
+            .field("y_superscript_x_offset", &self.y_superscript_x_offset)
+            .field("y_superscript_y_offset", &self.y_superscript_y_offset)
+            .field("y_strikeout_size", &self.y_strikeout_size)
+            .field("y_strikeout_position", &self.y_strikeout_position)
+            .field("s_family_class", &self.s_family_class)
+            .field("panose", &self.panose)
+            .field_with("ul_unicode_range", |f| write!(f, "{:08x?}", self.ul_unicode_range))
+            .field("ach_vend_id", &self.ach_vend_id)
+            .field("fs_selection", &self.fs_selection())
+            .field("us_first_char_index", &self.us_first_char_index)
+            .field("us_last_char_index", &self.us_last_char_index)
+            .field("s_typo_ascender", &self.s_typo_ascender)
+            .field("s_typo_descender", &self.s_typo_descender)
+            .field("s_typo_line_gap", &self.s_typo_line_gap)
+            .field("us_win_ascent", &self.us_win_ascent)
+            .field("us_win_descent", &self.us_win_descent)
+            .field_with("ul_code_page_range", |f| write!(f, "{:08x?}", self.ul_code_page_range))
+            .field("sx_height", &self.sx_height)
+            .field("s_cap_height", &self.s_cap_height)
+            .field("us_default_char", &self.us_default_char)
+            .field("us_break_char", &self.us_break_char)
+            .field("us_max_context", &self.us_max_context)
+            .field("us_lower_optical_point_size", &self.us_lower_optical_point_size)
+            .field("us_upper_optical_point_size", &self.us_upper_optical_point_size)
+            .finish()
+    }
+}
+
+impl OS2Table {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let version = read_uint16(r)?;
+        if version > 5 {
+            return Err(format!("unsupported OS/2 version {}", version).into());
+        }
+
+        let x_avg_char_width = read_int16(r)?;
+        let us_weight_class = read_uint16(r)?;
+        let us_width_class = read_uint16(r)?;
+        let fs_type = read_uint16(r)?;
+        let y_subscript_x_size = read_int16(r)?;
+        let y_subscript_y_size = read_int16(r)?;
+        let y_subscript_x_offset = read_int16(r)?;
+        let y_subscript_y_offset = read_int16(r)?;
+        let y_superscript_x_size = read_int16(r)?;
+        let y_superscript_y_size = read_int16(r)?;
+        let y_superscript_x_offset = read_int16(r)?;
+        let y_superscript_y_offset = read_int16(r)?;
+        let y_strikeout_size = read_int16(r)?;
+        let y_strikeout_position = read_int16(r)?;
+        let s_family_class = read_int16(r)?;
+        let mut panose = [0; 10];
+        for p in &mut panose {
+            *p = read_uint8(r)?;
+        }
+        let ul_unicode_range = [read_uint32(r)?, read_uint32(r)?, read_uint32(r)?, read_uint32(r)?];
+        let ach_vend_id = read_tag(r)?;
+        let fs_selection = read_uint16(r)?;
+        let us_first_char_index = read_uint16(r)?;
+        let us_last_char_index = read_uint16(r)?;
+
+        let mut os2 = Self {
+            version,
+            x_avg_char_width,
+            us_weight_class,
+            us_width_class,
+            fs_type,
+            y_subscript_x_size,
+            y_subscript_y_size,
+            y_subscript_x_offset,
+            y_subscript_y_offset,
+            y_superscript_x_size,
+            y_superscript_y_size,
+            y_superscript_x_offset,
+            y_superscript_y_offset,
+            y_strikeout_size,
+            y_strikeout_position,
+            s_family_class,
+            panose,
+            ul_unicode_range,
+            ach_vend_id,
+            fs_selection,
+            us_first_char_index,
+            us_last_char_index,
+            s_typo_ascender: None,
+            s_typo_descender: None,
+            s_typo_line_gap: None,
+            us_win_ascent: None,
+            us_win_descent: None,
+            ul_code_page_range: None,
+            sx_height: None,
+            s_cap_height: None,
+            us_default_char: None,
+            us_break_char: None,
+            us_max_context: None,
+            us_lower_optical_point_size: None,
+            us_upper_optical_point_size: None,
+        };
+
+        // the 68 byte version 0 table of early Apple fonts stops here
+        if 0 == version && r.is_empty() {
+            return Ok(os2);
+        }
+
+        os2.s_typo_ascender = Some(read_int16(r)?);
+        os2.s_typo_descender = Some(read_int16(r)?);
+        os2.s_typo_line_gap = Some(read_int16(r)?);
+        os2.us_win_ascent = Some(read_uint16(r)?);
+        os2.us_win_descent = Some(read_uint16(r)?);
+
+        if version < 1 {
+            return Ok(os2);
+        }
+
+        os2.ul_code_page_range = Some([read_uint32(r)?, read_uint32(r)?]);
+
+        if version < 2 {
+            return Ok(os2);
+        }
+
+        os2.sx_height = Some(read_int16(r)?);
+        os2.s_cap_height = Some(read_int16(r)?);
+        os2.us_default_char = Some(read_uint16(r)?);
+        os2.us_break_char = Some(read_uint16(r)?);
+        os2.us_max_context = Some(read_uint16(r)?);
+
+        if version < 5 {
+            return Ok(os2);
+        }
+
+        os2.us_lower_optical_point_size = Some(read_uint16(r)?);
+        os2.us_upper_optical_point_size = Some(read_uint16(r)?);
+
+        Ok(os2)
+    }
+
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    pub fn x_avg_char_width(&self) -> i16 {
+        self.x_avg_char_width
+    }
+
+    /// Visual weight, 100 to 1000, 400 being Regular and 700 Bold
+    pub fn weight_class(&self) -> u16 {
+        self.us_weight_class
+    }
+
+    /// Relative width, 1 (Ultra-condensed) to 9 (Ultra-expanded), 5 being Medium (normal)
+    pub fn width_class(&self) -> u16 {
+        self.us_width_class
+    }
+
+    pub fn fs_type(&self) -> FsType {
+        FsType(self.fs_type)
+    }
+
+    /// (x size, y size, x offset, y offset)
+    pub fn subscript(&self) -> (i16, i16, i16, i16) {
+        (self.y_subscript_x_size, self.y_subscript_y_size, self.y_subscript_x_offset, self.y_subscript_y_offset)
+    }
+
+    /// (x size, y size, x offset, y offset)
+    pub fn superscript(&self) -> (i16, i16, i16, i16) {
+        (self.y_superscript_x_size, self.y_superscript_y_size, self.y_superscript_x_offset, self.y_superscript_y_offset)
+    }
+
+    pub fn strikeout_size(&self) -> i16 {
+        self.y_strikeout_size
+    }
+
+    pub fn strikeout_position(&self) -> i16 {
+        self.y_strikeout_position
+    }
+
+    /// (class, subclass)
+    pub fn family_class(&self) -> (u8, u8) {
+        let [class, subclass] = self.s_family_class.to_be_bytes();
+        (class, subclass)
+    }
+
+    pub fn panose(&self) -> &[u8; 10] {
+        &self.panose
+    }
+
+    /// Whether Unicode range `bit` (0..=127) is flagged as functional
+    pub fn unicode_range(&self, bit: u8) -> bool {
+        bit < 128 && 0 != self.ul_unicode_range[(bit / 32) as usize] & (1 << (bit % 32))
+    }
+
+    pub fn vendor_id(&self) -> &Tag {
+        &self.ach_vend_id
+    }
+
+    pub fn fs_selection(&self) -> FsSelection {
+        FsSelection(self.fs_selection)
+    }
+
+    pub fn first_char_index(&self) -> u16 {
+        self.us_first_char_index
+    }
+
+    pub fn last_char_index(&self) -> u16 {
+        self.us_last_char_index
+    }
+
+    pub fn typo_ascender(&self) -> Option<i16> {
+        self.s_typo_ascender
+    }
+
+    pub fn typo_descender(&self) -> Option<i16> {
+        self.s_typo_descender
+    }
+
+    pub fn typo_line_gap(&self) -> Option<i16> {
+        self.s_typo_line_gap
+    }
+
+    pub fn win_ascent(&self) -> Option<u16> {
+        self.us_win_ascent
+    }
+
+    pub fn win_descent(&self) -> Option<u16> {
+        self.us_win_descent
+    }
+
+    /// Whether code page `bit` (0..=63) is flagged as functional, `None` before version 1
+    pub fn code_page_range(&self, bit: u8) -> Option<bool> {
+        let ranges = self.ul_code_page_range?;
+        Some(bit < 64 && 0 != ranges[(bit / 32) as usize] & (1 << (bit % 32)))
+    }
+
+    pub fn x_height(&self) -> Option<i16> {
+        self.sx_height
+    }
+
+    pub fn cap_height(&self) -> Option<i16> {
+        self.s_cap_height
+    }
+
+    pub fn default_char(&self) -> Option<u16> {
+        self.us_default_char
+    }
+
+    pub fn break_char(&self) -> Option<u16> {
+        self.us_break_char
+    }
+
+    pub fn max_context(&self) -> Option<u16> {
+        self.us_max_context
+    }
+
+    /// Optical size range in points as (lower inclusive, upper exclusive), stored in TWIPs (1/20 point)
+    pub fn optical_point_size(&self) -> Option<(f32, f32)> {
+        Some((self.us_lower_optical_point_size? as f32 / 20.0, self.us_upper_optical_point_size? as f32 / 20.0))
+    }
+
+    fn get_weight_desc(weight_class: u16) -> &'static str {
+        match weight_class {
+            100 => "Thin",
+            200 => "Extra-light (Ultra-light)",
+            300 => "Light",
+            400 => "Normal (Regular)",
+            500 => "Medium",
+            600 => "Semi-bold (Demi-bold)",
+            700 => "Bold",
+            800 => "Extra-bold (Ultra-bold)",
+            900 => "Black (Heavy)",
+            1..=1000 => "Intermediate weight",
+            _ => "error weight class",
+        }
+    }
+
+    fn get_width_desc(width_class: u16) -> &'static str {
+        match width_class {
+            1 => "Ultra-condensed",
+            2 => "Extra-condensed",
+            3 => "Condensed",
+            4 => "Semi-condensed",
+            5 => "Medium (normal)",
+            6 => "Semi-expanded",
+            7 => "Expanded",
+            8 => "Extra-expanded",
+            9 => "Ultra-expanded",
+            _ => "error width class",
+        }
+    }
+}
+
+/// Embedding permissions requested by the font vendor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddingPermission {
+    /// The font may be embedded, and may be permanently installed for use on a remote system
+    Installable,
+    /// The font must not be modified, embedded or exchanged without the legal owner's permission
+    Restricted,
+    /// The font may be embedded and temporarily loaded, documents containing it must be opened read-only
+    PreviewAndPrint,
+    /// The font may be embedded and temporarily loaded, documents containing it may be edited
+    Editable,
+}
+
+/// OS/2 fsType flags
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct FsType(pub u16);
+
+impl Debug for FsType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FsType")
+            .field("permission", &self.permission())
+            .field("no_subsetting", &self.no_subsetting())
+            .field("bitmap_embedding_only", &self.bitmap_embedding_only())
+            .finish()
+    }
+}
+
+impl FsType {
+    /// The usage permissions of bits 0–3, when several are set the least restrictive one wins
+    pub fn permission(&self) -> EmbeddingPermission {
+        if 0 != self.0 & 0x0008 {
+            EmbeddingPermission::Editable
+        } else if 0 != self.0 & 0x0004 {
+            EmbeddingPermission::PreviewAndPrint
+        } else if 0 != self.0 & 0x0002 {
+            EmbeddingPermission::Restricted
+        } else {
+            EmbeddingPermission::Installable
+        }
+    }
+
+    /// The font may not be subsetted prior to embedding
+    pub fn no_subsetting(&self) -> bool {
+        0 != self.0 & 0x0100
+    }
+
+    /// Only bitmaps contained in the font may be embedded
+    pub fn bitmap_embedding_only(&self) -> bool {
+        0 != self.0 & 0x0200
+    }
+}
+
+/// OS/2 fsSelection flags
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct FsSelection(pub u16);
+
+impl Debug for FsSelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FsSelection")
+            .field("italic", &self.italic())
+            .field("underscore", &self.underscore())
+            .field("negative", &self.negative())
+            .field("outlined", &self.outlined())
+            .field("strikeout", &self.strikeout())
+            .field("bold", &self.bold())
+            .field("regular", &self.regular())
+            .field("use_typo_metrics", &self.use_typo_metrics())
+            .field("wws", &self.wws())
+            .field("oblique", &self.oblique())
+            .finish()
+    }
+}
+
+impl FsSelection {
+    /// Font contains italic or oblique glyphs, otherwise they are upright
+    pub fn italic(&self) -> bool {
+        0 != self.0 & 0x0001
+    }
+
+    /// Glyphs are underscored
+    pub fn underscore(&self) -> bool {
+        0 != self.0 & 0x0002
+    }
+
+    /// Glyphs have their foreground and background reversed
+    pub fn negative(&self) -> bool {
+        0 != self.0 & 0x0004
+    }
+
+    /// Outline (hollow) glyphs, otherwise they are solid
+    pub fn outlined(&self) -> bool {
+        0 != self.0 & 0x0008
+    }
+
+    /// Glyphs are overstruck
+    pub fn strikeout(&self) -> bool {
+        0 != self.0 & 0x0010
+    }
+
+    /// Glyphs are emboldened
+    pub fn bold(&self) -> bool {
+        0 != self.0 & 0x0020
+    }
+
+    /// Glyphs are in the standard weight/style for the font
+    pub fn regular(&self) -> bool {
+        0 != self.0 & 0x0040
+    }
+
+    /// Use sTypoAscender - sTypoDescender + sTypoLineGap as the default line spacing
+    pub fn use_typo_metrics(&self) -> bool {
+        0 != self.0 & 0x0080
+    }
+
+    /// The font has name table strings consistent with a weight/width/slope family without requiring use of name IDs 21 and 22
+    pub fn wws(&self) -> bool {
+        0 != self.0 & 0x0100
+    }
+
+    /// Font contains oblique glyphs
+    pub fn oblique(&self) -> bool {
+        0 != self.0 & 0x0200
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A version 5 table with bold italic weight 700, truncated to the length of `version`
+    fn os2(version: u16) -> Vec<u8> {
+        let mut w = Vec::new();
+        for v in [version, 520, 700, 5, 0x0104] {
+            write_uint16(&mut w, v);
+        }
+        for v in [650, 600, 0, 75, 650, 600, 0, 350, 50, 250, 0x0801] {
+            write_int16(&mut w, v);
+        }
+        w.extend([2, 11, 8, 3, 0, 0, 0, 0, 0, 0]);
+        for v in [0x1, 0, 0x0800_0000, 0] {
+            write_uint32(&mut w, v);
+        }
+        write_tag(&mut w, Tag(*b"ADBE"));
+        for v in [0x0021, 0x20, 0xFFFF] {
+            write_uint16(&mut w, v);
+        }
+        for v in [800, -200, 100] {
+            write_int16(&mut w, v);
+        }
+        for v in [1000, 300] {
+            write_uint16(&mut w, v);
+        }
+        for v in [0x2_0001, 0] {
+            write_uint32(&mut w, v);
+        }
+        for v in [500, 700, 0, 32, 2, 160, 400] {
+            write_uint16(&mut w, v);
+        }
+        w.truncate(match version {
+            0 => 78,
+            1 => 86,
+            2..=4 => 96,
+            _ => 100,
+        });
+        w
+    }
+
+    #[test]
+    fn version0() {
+        let table = OS2Table::from_bytes(&os2(0)).unwrap();
+        assert_eq!((700, 5), (table.weight_class(), table.width_class()));
+        assert_eq!((EmbeddingPermission::PreviewAndPrint, true, false), (table.fs_type().permission(), table.fs_type().no_subsetting(), table.fs_type().bitmap_embedding_only()));
+        assert_eq!(((8, 1), 2), (table.family_class(), table.panose()[0]));
+        assert!(table.unicode_range(0) && table.unicode_range(91) && !table.unicode_range(1) && !table.unicode_range(200));
+        assert_eq!(table.vendor_id(), b"ADBE");
+        let fs_selection = table.fs_selection();
+        assert!(fs_selection.italic() && fs_selection.bold() && !fs_selection.regular() && !fs_selection.use_typo_metrics());
+        assert_eq!((Some(800), Some(-200), Some(100), Some(1000), Some(300)), (table.typo_ascender(), table.typo_descender(), table.typo_line_gap(), table.win_ascent(), table.win_descent()));
+        assert_eq!(None, table.code_page_range(0));
+
+        // the short Apple version 0 table
+        let table = OS2Table::from_bytes(&os2(0)[..68]).unwrap();
+        assert_eq!(None, table.typo_ascender());
+    }
+
+    #[test]
+    fn later_versions() {
+        let table = OS2Table::from_bytes(&os2(1)).unwrap();
+        assert_eq!((Some(true), Some(true), Some(false)), (table.code_page_range(0), table.code_page_range(17), table.code_page_range(1)));
+        assert_eq!(None, table.x_height());
+
+        let table = OS2Table::from_bytes(&os2(4)).unwrap();
+        assert_eq!((Some(500), Some(700), Some(32), Some(2)), (table.x_height(), table.cap_height(), table.break_char(), table.max_context()));
+        assert_eq!(None, table.optical_point_size());
+
+        let table = OS2Table::from_bytes(&os2(5)).unwrap();
+        assert_eq!(Some((8.0, 20.0)), table.optical_point_size());
+        assert!(OS2Table::from_bytes(&os2(5)[..98]).is_err());
+        assert!(OS2Table::from_bytes(&os2(6)).is_err());
+    }
+}
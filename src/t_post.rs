@@ -0,0 +1,233 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::types::*;
+use super::utils::*;
+
+pub struct PostScriptTable {
+    /// 0x00010000 for version 1.0, 0x00020000 for version 2.0, 0x00025000 for version 2.5 (deprecated), 0x00030000 for version 3.0
+    version: Version16Dot16,
+    /// Italic angle in counter-clockwise degrees from the vertical. Zero for upright text, negative for text that leans to the right (forward).
+    italic_angle: Fixed,
+    /// Suggested distance of the top of the underline from the baseline (negative values indicate below baseline).
+    underline_position: i16,
+    /// Suggested values for the underline thickness.
+    underline_thickness: i16,
+    /// Set to 0 if the font is proportionally spaced, non-zero if the font is not proportionally spaced (i.e. monospaced).
+    is_fixed_pitch: u32,
+    /// Minimum memory usage when an OpenType font is downloaded.
+    min_mem_type42: u32,
+    /// Maximum memory usage when an OpenType font is downloaded.
+    max_mem_type42: u32,
+    /// Minimum memory usage when an OpenType font is downloaded as a Type 1 font.
+    min_mem_type1: u32,
+    /// Maximum memory usage when an OpenType font is downloaded as a Type 1 font.
+    max_mem_type1: u32,
+    /// Number of glyphs (this should be the same as numGlyphs in 'maxp' table).
+    num_glyphs: Option<u16>, // ver 2.0, 2.5
+    /// Array of indices into the string data. See below for details.
+    glyph_name_index: Option<Vec<u16>>, // ver 2.0
+    /// Storage for the string data.
+    string_data: Option<Vec<String>>, // ver 2.0
+    /// Difference between the graphic index and the standard order of the glyph.
+    offset: Option<Vec<i8>>, // ver 2.5
+}
+
+impl Debug for PostScriptTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PostScriptTable")
+            .field("version", &self.version)
+            .field("italic_angle", &self.italic_angle)
+            .field("underline_position", &self.underline_position)
+            .field("underline_thickness", &self.underline_thickness)
+            .field("is_fixed_pitch", &self.is_fixed_pitch)
+            .field("min_mem_type42", &self.min_mem_type42)
+            .field("max_mem_type42", &self.max_mem_type42)
+            .field("min_mem_type1", &self.min_mem_type1)
+            .field("max_mem_type1", &self.max_mem_type1)
+            .field("num_glyphs", &self.num_glyphs)
+            .field_with("glyph_name_index", |f| write!(f, "{:?}", self.glyph_name_index.as_ref().map(|v| v.len())))
+            .field_with("string_data", |f| write!(f, "{:?}", self.string_data.as_ref().map(|v| v.len())))
+            .field_with("offset", |f| write!(f, "{:?}", self.offset.as_ref().map(|v| v.len())))
+            .finish()
+    }
+}
+
+impl PostScriptTable {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let version = read_version16dot16(r)?;
+        let italic_angle = read_fixed(r)?;
+        let underline_position = read_int16(r)?;
+        let underline_thickness = read_int16(r)?;
+        let is_fixed_pitch = read_uint32(r)?;
+        let min_mem_type42 = read_uint32(r)?;
+        let max_mem_type42 = read_uint32(r)?;
+        let min_mem_type1 = read_uint32(r)?;
+        let max_mem_type1 = read_uint32(r)?;
+
+        let mut post = Self {
+            version,
+            italic_angle,
+            underline_position,
+            underline_thickness,
+            is_fixed_pitch,
+            min_mem_type42,
+            max_mem_type42,
+            min_mem_type1,
+            max_mem_type1,
+            num_glyphs: None,
+            glyph_name_index: None,
+            string_data: None,
+            offset: None,
+        };
+
+        match post.version.0 {
+            [2, 0] => {
+                let num_glyphs = read_uint16(r)?;
+                let glyph_name_index = read_array(r, num_glyphs as usize, read_uint16)?;
+
+                // Pascal strings, a length byte followed by that many ASCII characters
+                let mut string_data = Vec::new();
+                while let Ok(length) = read_uint8(r) {
+                    let Some(s) = r.get(..length as usize) else {
+                        break;
+                    };
+                    string_data.push(String::from_utf8_lossy(s).into_owned());
+                    *r = &r[length as usize..];
+                }
+
+                post.num_glyphs = Some(num_glyphs);
+                post.glyph_name_index = Some(glyph_name_index);
+                post.string_data = Some(string_data);
+            }
+            [2, 0x5000] => {
+                let num_glyphs = read_uint16(r)?;
+                post.num_glyphs = Some(num_glyphs);
+                post.offset = Some(read_array(r, num_glyphs as usize, read_int8)?);
+            }
+            _ => {}
+        }
+
+        Ok(post)
+    }
+
+    pub fn italic_angle(&self) -> Fixed {
+        self.italic_angle
+    }
+
+    pub fn underline_position(&self) -> i16 {
+        self.underline_position
+    }
+
+    pub fn underline_thickness(&self) -> i16 {
+        self.underline_thickness
+    }
+
+    pub fn is_fixed_pitch(&self) -> bool {
+        0 != self.is_fixed_pitch
+    }
+
+    /// Whether the table carries glyph names, version 3.0 deliberately doesn't
+    pub fn has_glyph_names(&self) -> bool {
+        matches!(self.version.0, [1, 0] | [2, 0] | [2, 0x5000])
+    }
+
+    pub fn glyph_name(&self, glyph_id: GlyphId) -> Option<&str> {
+        match self.version.0 {
+            [1, 0] => MAC_GLYPH_NAMES.get(glyph_id as usize).copied(),
+            [2, 0] => {
+                let index = *self.glyph_name_index.as_ref()?.get(glyph_id as usize)? as usize;
+                if index < MAC_GLYPH_NAMES.len() {
+                    Some(MAC_GLYPH_NAMES[index])
+                } else {
+                    self.string_data.as_ref()?.get(index - MAC_GLYPH_NAMES.len()).map(|s| s.as_str())
+                }
+            }
+            [2, 0x5000] => {
+                let offset = *self.offset.as_ref()?.get(glyph_id as usize)?;
+                MAC_GLYPH_NAMES.get(usize::try_from(glyph_id as isize + offset as isize).ok()?).copied()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The standard Macintosh ordering of the first 258 glyph names
+const MAC_GLYPH_NAMES: [&str; 258] = [
+    ".notdef", ".null", "nonmarkingreturn", "space", "exclam", "quotedbl", "numbersign", "dollar", "percent", "ampersand", "quotesingle", "parenleft",
+    "parenright", "asterisk", "plus", "comma", "hyphen", "period", "slash", "zero", "one", "two", "three", "four", "five", "six", "seven", "eight",
+    "nine", "colon", "semicolon", "less", "equal", "greater", "question", "at", "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N",
+    "O", "P", "Q", "R", "S", "T", "U", "V", "W", "X", "Y", "Z", "bracketleft", "backslash", "bracketright", "asciicircum", "underscore", "grave", "a",
+    "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m", "n", "o", "p", "q", "r", "s", "t", "u", "v", "w", "x", "y", "z", "braceleft", "bar",
+    "braceright", "asciitilde", "Adieresis", "Aring", "Ccedilla", "Eacute", "Ntilde", "Odieresis", "Udieresis", "aacute", "agrave", "acircumflex",
+    "adieresis", "atilde", "aring", "ccedilla", "eacute", "egrave", "ecircumflex", "edieresis", "iacute", "igrave", "icircumflex", "idieresis",
+    "ntilde", "oacute", "ograve", "ocircumflex", "odieresis", "otilde", "uacute", "ugrave", "ucircumflex", "udieresis", "dagger", "degree", "cent",
+    "sterling", "section", "bullet", "paragraph", "germandbls", "registered", "copyright", "trademark", "acute", "dieresis", "notequal", "AE",
+    "Oslash", "infinity", "plusminus", "lessequal", "greaterequal", "yen", "mu", "partialdiff", "summation", "product", "pi", "integral",
+    "ordfeminine", "ordmasculine", "Omega", "ae", "oslash", "questiondown", "exclamdown", "logicalnot", "radical", "florin", "approxequal", "Delta",
+    "guillemotleft", "guillemotright", "ellipsis", "nonbreakingspace", "Agrave", "Atilde", "Otilde", "OE", "oe", "endash", "emdash", "quotedblleft",
+    "quotedblright", "quoteleft", "quoteright", "divide", "lozenge", "ydieresis", "Ydieresis", "fraction", "currency", "guilsinglleft",
+    "guilsinglright", "fi", "fl", "daggerdbl", "periodcentered", "quotesinglbase", "quotedblbase", "perthousand", "Acircumflex", "Ecircumflex",
+    "Aacute", "Edieresis", "Egrave", "Iacute", "Icircumflex", "Idieresis", "Igrave", "Oacute", "Ocircumflex", "apple", "Ograve", "Uacute",
+    "Ucircumflex", "Ugrave", "dotlessi", "circumflex", "tilde", "macron", "breve", "dotaccent", "ring", "cedilla", "hungarumlaut", "ogonek", "caron",
+    "Lslash", "lslash", "Scaron", "scaron", "Zcaron", "zcaron", "brokenbar", "Eth", "eth", "Yacute", "yacute", "Thorn", "thorn", "minus", "multiply",
+    "onesuperior", "twosuperior", "threesuperior", "onehalf", "onequarter", "threequarters", "franc", "Gbreve", "gbreve", "Idotaccent", "Scedilla",
+    "scedilla", "Cacute", "cacute", "Ccaron", "ccaron", "dcroat",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(version: u32) -> Vec<u8> {
+        let mut w = Vec::new();
+        write_uint32(&mut w, version);
+        write_fixed(&mut w, -12.5);
+        write_int16(&mut w, -100);
+        write_int16(&mut w, 50);
+        write_uint32(&mut w, 1);
+        for _ in 0..4 {
+            write_uint32(&mut w, 0);
+        }
+        w
+    }
+
+    #[test]
+    fn format1_and_3() {
+        let post = PostScriptTable::from_bytes(&header(0x10000)).unwrap();
+        assert_eq!((-12.5, -100, 50, true), (post.italic_angle(), post.underline_position(), post.underline_thickness(), post.is_fixed_pitch()));
+        assert_eq!(Some("A"), post.glyph_name(36));
+        assert_eq!(None, post.glyph_name(258));
+
+        let post = PostScriptTable::from_bytes(&header(0x30000)).unwrap();
+        assert!(!post.has_glyph_names());
+        assert_eq!(None, post.glyph_name(36));
+    }
+
+    #[test]
+    fn format2_names() {
+        let mut w = header(0x20000);
+        write_uint16(&mut w, 3);
+        for index in [0, 259, 258] {
+            write_uint16(&mut w, index);
+        }
+        for name in ["f_f", "alt"] {
+            write_uint8(&mut w, name.len() as u8);
+            w.extend(name.as_bytes());
+        }
+        let post = PostScriptTable::from_bytes(&w).unwrap();
+        assert_eq!(vec![Some(".notdef"), Some("alt"), Some("f_f"), None], (0..4).map(|g| post.glyph_name(g)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn format2_5_offsets() {
+        let mut w = header(0x25000);
+        write_uint16(&mut w, 2);
+        write_int8(&mut w, 0);
+        write_int8(&mut w, 35);
+        let post = PostScriptTable::from_bytes(&w).unwrap();
+        assert_eq!((Some(".notdef"), Some("A")), (post.glyph_name(0), post.glyph_name(1)));
+    }
+}
@@ -0,0 +1,467 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::t_name::*;
+use super::types::*;
+use super::utils::*;
+
+/// Axis value flag, the value applies to older fonts of the family that predate this one
+const OLDER_SIBLING_FONT_ATTRIBUTE: u16 = 0x0001;
+/// Axis value flag, the value name can be left out when composing a style name
+const ELIDABLE_AXIS_VALUE_NAME: u16 = 0x0002;
+
+/// Design axes and named axis values used to name and link the styles of a family
+pub struct StyleAttributesTable {
+    /// Major version number of the style attributes table — set to 1
+    major_version: u16,
+    /// Minor version number of the style attributes table — set to 2
+    minor_version: u16,
+    design_axes: Vec<AxisRecord>,
+    axis_values: Vec<AxisValue>,
+    /// Name ID used as fallback when projection of names into a particular font model produces a subfamily name containing only elidable elements, ver 1.1
+    elided_fallback_name_id: Option<u16>,
+}
+
+impl Debug for StyleAttributesTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StyleAttributesTable")
+            .field("major_version", &self.major_version)
+            .field("minor_version", &self.minor_version)
+            .field("design_axes", &self.design_axes)
+            .field("axis_values", &self.axis_values)
+            .field("elided_fallback_name_id", &self.elided_fallback_name_id)
+            .finish()
+    }
+}
+
+impl StyleAttributesTable {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let major_version = read_uint16(r)?;
+        let minor_version = read_uint16(r)?;
+        if 1 != major_version {
+            return Err(format!("unsupported STAT version {}.{}", major_version, minor_version).into());
+        }
+        let design_axis_size = read_uint16(r)?;
+        let design_axis_count = read_uint16(r)?;
+        let design_axes_offset = read_offset32(r)?;
+        let axis_value_count = read_uint16(r)?;
+        let offset_to_axis_value_offsets = read_offset32(r)?;
+        let elided_fallback_name_id = if 1 <= minor_version { Some(read_uint16(r)?) } else { None };
+
+        // axis records are read at their stride, fields added by later minor versions are ignored
+        if 0 < design_axis_count && design_axis_size < 8 {
+            return Err(format!("STAT designAxisSize {} is too small", design_axis_size).into());
+        }
+        let mut design_axes = Vec::with_capacity(design_axis_count as usize);
+        for i in 0..design_axis_count as usize {
+            let start = design_axes_offset as usize + i * design_axis_size as usize;
+            design_axes.push(AxisRecord::from_bytes(&mut o.get(start..).ok_or("STAT axis record out of range")?)?);
+        }
+
+        // axis value offsets are from the start of the offsets array
+        let mut axis_values = Vec::with_capacity(axis_value_count as usize);
+        if 0 < axis_value_count {
+            let a = o.get(offset_to_axis_value_offsets as usize..).ok_or("STAT axis value offsets out of range")?;
+            for offset in read_array(&mut &a[..], axis_value_count as usize, read_offset16)? {
+                axis_values.push(AxisValue::from_bytes(a.get(offset as usize..).ok_or("STAT axis value offset out of range")?)?);
+            }
+        }
+        if let Some(av) = axis_values.iter().find(|av| av.axis_indexes().any(|i| i as usize >= design_axes.len())) {
+            return Err(format!("STAT axis value {:?} references a missing design axis", av).into());
+        }
+
+        Ok(Self {
+            major_version,
+            minor_version,
+            design_axes,
+            axis_values,
+            elided_fallback_name_id,
+        })
+    }
+
+    pub fn design_axes(&self) -> &[AxisRecord] {
+        &self.design_axes
+    }
+
+    pub fn axis_values(&self) -> &[AxisValue] {
+        &self.axis_values
+    }
+
+    /// Name ID for a style whose axis value names are all elided, version 1.0 tables fall back to the subfamily name ID 2
+    pub fn elided_fallback_name_id(&self) -> u16 {
+        self.elided_fallback_name_id.unwrap_or(2)
+    }
+
+    /// Axis values describing the user `coords`, one per design axis, in axis ordering
+    ///
+    /// A format 4 value matching several axes wins over the single axis values of those axes, and an exact match wins over a range.
+    pub fn axis_values_at(&self, coords: &[Option<Fixed>]) -> Vec<&AxisValue> {
+        let mut matches: Vec<_> = self.axis_values.iter().filter(|av| av.matches(coords)).collect();
+        // format 4 values with the most axes first, then exact matches ahead of ranges, the sort is stable so table order breaks ties
+        matches.sort_by_key(|av| match av {
+            AxisValue::Format4 { axis_values, .. } => (0, usize::MAX - axis_values.len()),
+            AxisValue::Format2 { axis_index, nominal_value, .. } if coords.get(*axis_index as usize) != Some(&Some(*nominal_value)) => (2, 0),
+            _ => (1, 0),
+        });
+
+        let mut seen = vec![false; self.design_axes.len()];
+        let mut results = Vec::new();
+        for av in matches {
+            if av.axis_indexes().any(|i| seen[i as usize]) {
+                continue;
+            }
+            av.axis_indexes().for_each(|i| seen[i as usize] = true);
+            results.push(av);
+        }
+
+        let ordering = |av: &AxisValue| av.axis_indexes().map(|i| self.design_axes[i as usize].axis_ordering).min();
+        results.sort_by_key(|av| ordering(av));
+        results
+    }
+}
+
+pub struct AxisRecord {
+    /// A tag identifying the axis of design variation
+    axis_tag: Tag,
+    /// The name ID for entries in the 'name' table that provide a display string for this axis
+    axis_name_id: u16,
+    /// A value that applications can use to determine primary sorting of face names, or for ordering of labels when composing family or face names
+    axis_ordering: u16,
+}
+
+impl Debug for AxisRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AxisRecord")
+            .field("axis_tag", &self.axis_tag)
+            .field("axis_name_id", &self.axis_name_id)
+            .field("axis_ordering", &self.axis_ordering)
+            .finish()
+    }
+}
+
+impl AxisRecord {
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let axis_tag = read_tag(r)?;
+        let axis_name_id = read_uint16(r)?;
+        let axis_ordering = read_uint16(r)?;
+
+        Ok(Self {
+            axis_tag,
+            axis_name_id,
+            axis_ordering,
+        })
+    }
+
+    pub fn axis_tag(&self) -> Tag {
+        self.axis_tag
+    }
+
+    pub fn axis_name_id(&self) -> u16 {
+        self.axis_name_id
+    }
+
+    pub fn axis_ordering(&self) -> u16 {
+        self.axis_ordering
+    }
+
+    pub fn axis_name(&self, name: &NamingTable) -> Option<String> {
+        name.name(self.axis_name_id)
+    }
+}
+
+/// A named value or range of values on one design axis, or a named combination of values on several
+pub enum AxisValue {
+    /// A single value on an axis
+    Format1 { axis_index: u16, flags: u16, value_name_id: u16, value: Fixed },
+    /// A range of values on an axis, with the value most representative of the range
+    Format2 {
+        axis_index: u16,
+        flags: u16,
+        value_name_id: u16,
+        nominal_value: Fixed,
+        range_min_value: Fixed,
+        range_max_value: Fixed,
+    },
+    /// A single value on an axis, linked to the value of its style-linked counterpart, such as Regular to Bold
+    Format3 { axis_index: u16, flags: u16, value_name_id: u16, value: Fixed, linked_value: Fixed },
+    /// A combination of values on several axes
+    Format4 { flags: u16, value_name_id: u16, axis_values: Vec<AxisValueRecord> },
+}
+
+impl Debug for AxisValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Format1 { axis_index, flags, value_name_id, value } => f
+                .debug_struct("Format1")
+                .field("axis_index", axis_index)
+                .field_with("flags", |f| write!(f, "0x{:04x}", flags))
+                .field("value_name_id", value_name_id)
+                .field("value", value)
+                .finish(),
+            Self::Format2 {
+                axis_index,
+                flags,
+                value_name_id,
+                nominal_value,
+                range_min_value,
+                range_max_value,
+            } => f
+                .debug_struct("Format2")
+                .field("axis_index", axis_index)
+                .field_with("flags", |f| write!(f, "0x{:04x}", flags))
+                .field("value_name_id", value_name_id)
+                .field("nominal_value", nominal_value)
+                .field("range_min_value", range_min_value)
+                .field("range_max_value", range_max_value)
+                .finish(),
+            Self::Format3 { axis_index, flags, value_name_id, value, linked_value } => f
+                .debug_struct("Format3")
+                .field("axis_index", axis_index)
+                .field_with("flags", |f| write!(f, "0x{:04x}", flags))
+                .field("value_name_id", value_name_id)
+                .field("value", value)
+                .field("linked_value", linked_value)
+                .finish(),
+            Self::Format4 { flags, value_name_id, axis_values } => f
+                .debug_struct("Format4")
+                .field_with("flags", |f| write!(f, "0x{:04x}", flags))
+                .field("value_name_id", value_name_id)
+                .field("axis_values", axis_values)
+                .finish(),
+        }
+    }
+}
+
+impl AxisValue {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        match format {
+            1 => Ok(Self::Format1 {
+                axis_index: read_uint16(r)?,
+                flags: read_uint16(r)?,
+                value_name_id: read_uint16(r)?,
+                value: read_fixed(r)?,
+            }),
+            2 => Ok(Self::Format2 {
+                axis_index: read_uint16(r)?,
+                flags: read_uint16(r)?,
+                value_name_id: read_uint16(r)?,
+                nominal_value: read_fixed(r)?,
+                range_min_value: read_fixed(r)?,
+                range_max_value: read_fixed(r)?,
+            }),
+            3 => Ok(Self::Format3 {
+                axis_index: read_uint16(r)?,
+                flags: read_uint16(r)?,
+                value_name_id: read_uint16(r)?,
+                value: read_fixed(r)?,
+                linked_value: read_fixed(r)?,
+            }),
+            4 => {
+                let axis_count = read_uint16(r)?;
+                if 0 == axis_count {
+                    return Err("STAT format 4 axis value has no axes".into());
+                }
+                let flags = read_uint16(r)?;
+                let value_name_id = read_uint16(r)?;
+                let axis_values = read_array(r, axis_count as usize, AxisValueRecord::from_bytes)?;
+                Ok(Self::Format4 { flags, value_name_id, axis_values })
+            }
+            _ => Err(format!("unknown STAT axis value format {}", format).into()),
+        }
+    }
+
+    pub fn flags(&self) -> u16 {
+        match self {
+            Self::Format1 { flags, .. } | Self::Format2 { flags, .. } | Self::Format3 { flags, .. } | Self::Format4 { flags, .. } => *flags,
+        }
+    }
+
+    pub fn value_name_id(&self) -> u16 {
+        match self {
+            Self::Format1 { value_name_id, .. } | Self::Format2 { value_name_id, .. } | Self::Format3 { value_name_id, .. } | Self::Format4 { value_name_id, .. } => *value_name_id,
+        }
+    }
+
+    pub fn value_name(&self, name: &NamingTable) -> Option<String> {
+        name.name(self.value_name_id())
+    }
+
+    pub fn is_older_sibling_font_attribute(&self) -> bool {
+        0 != self.flags() & OLDER_SIBLING_FONT_ATTRIBUTE
+    }
+
+    pub fn is_elidable(&self) -> bool {
+        0 != self.flags() & ELIDABLE_AXIS_VALUE_NAME
+    }
+
+    /// Design axes the value is on
+    pub fn axis_indexes(&self) -> impl Iterator<Item = u16> + '_ {
+        let (single, multiple) = match self {
+            Self::Format1 { axis_index, .. } | Self::Format2 { axis_index, .. } | Self::Format3 { axis_index, .. } => (Some(*axis_index), &[][..]),
+            Self::Format4 { axis_values, .. } => (None, &axis_values[..]),
+        };
+        single.into_iter().chain(multiple.iter().map(|avr| avr.axis_index))
+    }
+
+    /// Design axis and value of the style-linked counterpart, format 3 only
+    pub fn linked_value(&self) -> Option<(u16, Fixed)> {
+        match self {
+            Self::Format3 { axis_index, linked_value, .. } => Some((*axis_index, *linked_value)),
+            _ => None,
+        }
+    }
+
+    /// Whether the value describes user `coords`, given per design axis, axes without a coordinate match nothing
+    pub fn matches(&self, coords: &[Option<Fixed>]) -> bool {
+        let coord = |axis_index: u16| coords.get(axis_index as usize).copied().flatten();
+        match self {
+            Self::Format1 { axis_index, value, .. } | Self::Format3 { axis_index, value, .. } => Some(*value) == coord(*axis_index),
+            Self::Format2 {
+                axis_index,
+                range_min_value,
+                range_max_value,
+                ..
+            } => coord(*axis_index).is_some_and(|v| (*range_min_value..=*range_max_value).contains(&v)),
+            Self::Format4 { axis_values, .. } => axis_values.iter().all(|avr| Some(avr.value) == coord(avr.axis_index)),
+        }
+    }
+}
+
+pub struct AxisValueRecord {
+    /// Zero-base index into the axis record array identifying the axis to which this value applies
+    axis_index: u16,
+    /// A numeric value for this attribute value
+    value: Fixed,
+}
+
+impl Debug for AxisValueRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AxisValueRecord").field("axis_index", &self.axis_index).field("value", &self.value).finish()
+    }
+}
+
+impl AxisValueRecord {
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let axis_index = read_uint16(r)?;
+        let value = read_fixed(r)?;
+
+        Ok(Self { axis_index, value })
+    }
+
+    pub fn axis_index(&self) -> u16 {
+        self.axis_index
+    }
+
+    pub fn value(&self) -> Fixed {
+        self.value
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod builders {
+    use super::*;
+
+    /// STAT 1.`minor_version` with design axes of (tag, axis ordering), version 1.1 and later name 17 as the elided fallback
+    pub fn stat(minor_version: u16, axes: &[(Tag, u16)], axis_values: &[Vec<u8>]) -> Vec<u8> {
+        let header_size = if 0 == minor_version { 18 } else { 20 };
+        let offsets_start = header_size + 8 * axes.len();
+        let mut w = Vec::new();
+        [1, minor_version, 8, axes.len() as u16].iter().for_each(|&v| write_uint16(&mut w, v));
+        write_uint32(&mut w, header_size as u32);
+        write_uint16(&mut w, axis_values.len() as u16);
+        write_uint32(&mut w, offsets_start as u32);
+        if 0 < minor_version {
+            write_uint16(&mut w, 17);
+        }
+        for &(tag, axis_ordering) in axes {
+            write_tag(&mut w, tag);
+            [256, axis_ordering].iter().for_each(|&v| write_uint16(&mut w, v));
+        }
+        let mut offset = 2 * axis_values.len();
+        for av in axis_values {
+            write_uint16(&mut w, offset as u16);
+            offset += av.len();
+        }
+        axis_values.iter().for_each(|av| w.extend(av));
+        w
+    }
+
+    pub fn format1(axis_index: u16, flags: u16, value_name_id: u16, value: Fixed) -> Vec<u8> {
+        let mut w = Vec::new();
+        [1, axis_index, flags, value_name_id].iter().for_each(|&v| write_uint16(&mut w, v));
+        write_fixed(&mut w, value);
+        w
+    }
+
+    pub fn format2(axis_index: u16, value_name_id: u16, nominal_value: Fixed, range_min_value: Fixed, range_max_value: Fixed) -> Vec<u8> {
+        let mut w = Vec::new();
+        [2, axis_index, 0, value_name_id].iter().for_each(|&v| write_uint16(&mut w, v));
+        [nominal_value, range_min_value, range_max_value].iter().for_each(|&v| write_fixed(&mut w, v));
+        w
+    }
+
+    pub fn format4(value_name_id: u16, axis_values: &[(u16, Fixed)]) -> Vec<u8> {
+        let mut w = Vec::new();
+        [4, axis_values.len() as u16, 0, value_name_id].iter().for_each(|&v| write_uint16(&mut w, v));
+        for &(axis_index, value) in axis_values {
+            write_uint16(&mut w, axis_index);
+            write_fixed(&mut w, value);
+        }
+        w
+    }
+
+    /// wght then ital, ordered ital first, with Regular (258) and Upright (261) elidable, a Bold range from 600 to 800 (259)
+    /// and a Semibold (260) at 650 on wght, Italic (263) on ital, and a Bold Italic (262) combining both axes
+    pub fn weight_and_italic(minor_version: u16) -> Vec<u8> {
+        let axis_values = [
+            format1(0, ELIDABLE_AXIS_VALUE_NAME, 258, 400.0),
+            format2(0, 259, 700.0, 600.0, 800.0),
+            format1(0, 0, 260, 650.0),
+            format1(1, ELIDABLE_AXIS_VALUE_NAME, 261, 0.0),
+            format4(262, &[(0, 700.0), (1, 1.0)]),
+            format1(1, 0, 263, 1.0),
+        ];
+        stat(minor_version, &[(Tag::new(b"wght"), 1), (Tag::new(b"ital"), 0)], &axis_values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::builders::*;
+    use super::*;
+
+    #[test]
+    fn axis_value_ranking() {
+        let stat = StyleAttributesTable::from_bytes(&weight_and_italic(1)).unwrap();
+        let names = |coords: &[Option<Fixed>]| stat.axis_values_at(coords).iter().map(|av| av.value_name_id()).collect::<Vec<_>>();
+        // an exact value beats a range, and the results follow the axis ordering rather than the design axis order
+        assert_eq!(names(&[Some(650.0), Some(0.0)]), [261, 260]);
+        assert_eq!(names(&[Some(750.0), Some(0.0)]), [261, 259]);
+        assert_eq!(names(&[Some(700.0), Some(0.0)]), [261, 259]);
+        // a format 4 value covers both axes
+        assert_eq!(names(&[Some(700.0), Some(1.0)]), [262]);
+        assert_eq!(names(&[Some(400.0), Some(1.0)]), [263, 258]);
+        assert_eq!(names(&[None, Some(1.0)]), [263]);
+        assert_eq!(names(&[Some(900.0)]), [] as [u16; 0]);
+    }
+
+    #[test]
+    fn elided_fallback_name_id() {
+        assert_eq!(2, StyleAttributesTable::from_bytes(&weight_and_italic(0)).unwrap().elided_fallback_name_id());
+        assert_eq!(17, StyleAttributesTable::from_bytes(&weight_and_italic(2)).unwrap().elided_fallback_name_id());
+    }
+
+    #[test]
+    fn malformed_axis_values() {
+        let wght = [(Tag::new(b"wght"), 0)];
+        assert!(StyleAttributesTable::from_bytes(&stat(1, &wght, &[format4(258, &[])])).is_err());
+        assert!(StyleAttributesTable::from_bytes(&stat(1, &wght, &[format1(1, 0, 258, 400.0)])).is_err());
+        assert!(StyleAttributesTable::from_bytes(&stat(1, &wght, &[format4(258, &[(0, 400.0), (1, 1.0)])])).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::types::*;
+use super::utils::*;
+
+pub struct VerticalHeaderTable {
+    /// 0x00010000 or 0x00011000
+    version: Version16Dot16,
+    /// Distance from the centerline to the previous line's descent (vertTypoAscender in version 1.1)
+    ascender: i16,
+    /// Distance from the centerline to the next line's ascent (vertTypoDescender in version 1.1)
+    descender: i16,
+    /// Reserved in version 1.0, vertTypoLineGap in version 1.1
+    line_gap: i16,
+    /// The maximum advance height measurement in font units found in the font
+    advance_height_max: i16,
+    /// The minimum top side bearing measured in font units
+    min_top_side_bearing: i16,
+    /// The minimum bottom side bearing measured in font units
+    min_bottom_side_bearing: i16,
+    /// minTopSideBearing + (yMax - yMin)
+    y_max_extent: i16,
+    /// The value of the caretSlopeRise field divided by the value of the caretSlopeRun field determines the slope of the caret; 0 for vertical
+    caret_slope_rise: i16,
+    /// 1 for vertical
+    caret_slope_run: i16,
+    /// The amount by which the highlight on a slanted glyph needs to be shifted away from the glyph in order to produce the best appearance
+    caret_offset: i16,
+    reserved: [i16; 4],
+    /// 0 for current format
+    metric_data_format: i16,
+    /// Number of advance heights in the Vertical Metrics table
+    num_of_long_ver_metrics: u16,
+}
+
+impl Debug for VerticalHeaderTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VerticalHeaderTable")
+            .field("version", &self.version)
+            .field("ascender", &self.ascender)
+            .field("descender", &self.descender)
+            .field("line_gap", &self.line_gap)
+            .field("advance_height_max", &self.advance_height_max)
+            .field("min_top_side_bearing", &self.min_top_side_bearing)
+            .field("min_bottom_side_bearing", &self.min_bottom_side_bearing)
+            .field("y_max_extent", &self.y_max_extent)
+            .field("caret_slope_rise", &self.caret_slope_rise)
+            .field("caret_slope_run", &self.caret_slope_run)
+            .field("caret_offset", &self.caret_offset)
+            .field("reserved", &self.reserved)
+            .field("metric_data_format", &self.metric_data_format)
+            .field("num_of_long_ver_metrics", &self.num_of_long_ver_metrics)
+            .finish()
+    }
+}
+
+impl VerticalHeaderTable {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let version = read_version16dot16(r)?;
+        if 1 != version.0[0] {
+            return Err(format!("unsupported vhea version {:?}", version).into());
+        }
+        let ascender = read_int16(r)?;
+        let descender = read_int16(r)?;
+        let line_gap = read_int16(r)?;
+        let advance_height_max = read_int16(r)?;
+        let min_top_side_bearing = read_int16(r)?;
+        let min_bottom_side_bearing = read_int16(r)?;
+        let y_max_extent = read_int16(r)?;
+        let caret_slope_rise = read_int16(r)?;
+        let caret_slope_run = read_int16(r)?;
+        let caret_offset = read_int16(r)?;
+        let reserved = [read_int16(r)?, read_int16(r)?, read_int16(r)?, read_int16(r)?];
+        let metric_data_format = read_int16(r)?;
+        let num_of_long_ver_metrics = read_uint16(r)?;
+
+        Ok(Self {
+            version,
+            ascender,
+            descender,
+            line_gap,
+            advance_height_max,
+            min_top_side_bearing,
+            min_bottom_side_bearing,
+            y_max_extent,
+            caret_slope_rise,
+            caret_slope_run,
+            caret_offset,
+            reserved,
+            metric_data_format,
+            num_of_long_ver_metrics,
+        })
+    }
+
+    pub fn ascender(&self) -> i16 {
+        self.ascender
+    }
+
+    pub fn descender(&self) -> i16 {
+        self.descender
+    }
+
+    pub fn line_gap(&self) -> i16 {
+        self.line_gap
+    }
+
+    pub fn advance_height_max(&self) -> i16 {
+        self.advance_height_max
+    }
+
+    pub fn min_top_side_bearing(&self) -> i16 {
+        self.min_top_side_bearing
+    }
+
+    pub fn min_bottom_side_bearing(&self) -> i16 {
+        self.min_bottom_side_bearing
+    }
+
+    pub fn y_max_extent(&self) -> i16 {
+        self.y_max_extent
+    }
+
+    pub fn caret_slope_rise(&self) -> i16 {
+        self.caret_slope_rise
+    }
+
+    pub fn caret_slope_run(&self) -> i16 {
+        self.caret_slope_run
+    }
+
+    pub fn caret_offset(&self) -> i16 {
+        self.caret_offset
+    }
+
+    pub fn num_of_long_ver_metrics(&self) -> u16 {
+        self.num_of_long_ver_metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields() {
+        let mut w = Vec::new();
+        write_uint32(&mut w, 0x00011000);
+        for v in [500, -500, 0, 1200, -40, -60, 1150, 0, 1, 0, 0, 0, 0, 0, 0, 2] {
+            write_int16(&mut w, v);
+        }
+        let vhea = VerticalHeaderTable::from_bytes(&w).unwrap();
+        assert_eq!((500, -500, 0), (vhea.ascender(), vhea.descender(), vhea.line_gap()));
+        assert_eq!((1200, -40, -60, 1150), (vhea.advance_height_max(), vhea.min_top_side_bearing(), vhea.min_bottom_side_bearing(), vhea.y_max_extent()));
+        assert_eq!((0, 1, 0), (vhea.caret_slope_rise(), vhea.caret_slope_run(), vhea.caret_offset()));
+        assert_eq!(2, vhea.num_of_long_ver_metrics());
+        assert!(VerticalHeaderTable::from_bytes(&w[..34]).is_err());
+        w[1] = 2;
+        assert!(VerticalHeaderTable::from_bytes(&w).is_err());
+    }
+}
@@ -0,0 +1,97 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::types::*;
+use super::utils::*;
+
+pub struct VerticalMetricsTable {
+    /// Paired advance height and top side bearing values for each glyph. Records are indexed by glyph ID
+    v_metrics: Vec<LongVerMetric>,
+    /// Top side bearings for glyph IDs greater than or equal to numOfLongVerMetrics
+    top_side_bearings: Vec<i16>,
+}
+
+impl Debug for VerticalMetricsTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VerticalMetricsTable")
+            .field_with("v_metrics", |f| write!(f, "[LongVerMetric; {}]", self.v_metrics.len()))
+            .field_with("top_side_bearings", |f| write!(f, "[i16; {}]", self.top_side_bearings.len()))
+            .finish()
+    }
+}
+
+impl VerticalMetricsTable {
+    /// `num_of_long_ver_metrics` comes from vhea, `num_glyphs` from maxp
+    pub fn from_bytes(mut b: &[u8], num_of_long_ver_metrics: u16, num_glyphs: u16) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        if 0 == num_of_long_ver_metrics || num_of_long_ver_metrics > num_glyphs {
+            return Err(format!("vhea numOfLongVerMetrics {} must be in 1..={} (maxp numGlyphs)", num_of_long_ver_metrics, num_glyphs).into());
+        }
+
+        let v_metrics = read_array(r, num_of_long_ver_metrics as usize, LongVerMetric::from_bytes)?;
+        let top_side_bearings = read_array(r, num_glyphs.saturating_sub(num_of_long_ver_metrics) as usize, read_int16)?;
+
+        Ok(Self { v_metrics, top_side_bearings })
+    }
+
+    pub fn num_glyphs(&self) -> usize {
+        self.v_metrics.len() + self.top_side_bearings.len()
+    }
+
+    /// Glyphs past numOfLongVerMetrics share the advance height of the last vMetrics record
+    pub fn advance_height(&self, glyph_id: GlyphId) -> Option<u16> {
+        if glyph_id as usize >= self.num_glyphs() {
+            return None;
+        }
+        Some(self.v_metrics[(glyph_id as usize).min(self.v_metrics.len() - 1)].advance_height)
+    }
+
+    pub fn top_side_bearing(&self, glyph_id: GlyphId) -> Option<i16> {
+        match self.v_metrics.get(glyph_id as usize) {
+            Some(vm) => Some(vm.top_side_bearing),
+            None => self.top_side_bearings.get(glyph_id as usize - self.v_metrics.len()).copied(),
+        }
+    }
+}
+
+pub struct LongVerMetric {
+    /// The advance height of the glyph. Unsigned integer in font design units
+    advance_height: u16,
+    /// The top side bearing of the glyph. Signed integer in font design units
+    top_side_bearing: i16,
+}
+
+impl Debug for LongVerMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LongVerMetric").field("advance_height", &self.advance_height).field("top_side_bearing", &self.top_side_bearing).finish()
+    }
+}
+
+impl LongVerMetric {
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let advance_height = read_uint16(r)?;
+        let top_side_bearing = read_int16(r)?;
+
+        Ok(Self { advance_height, top_side_bearing })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailing_glyphs_share_the_last_advance() {
+        let mut w = Vec::new();
+        for v in [1000, 80, 900, 60, 45, -10] {
+            write_int16(&mut w, v);
+        }
+        let vmtx = VerticalMetricsTable::from_bytes(&w, 2, 4).unwrap();
+        assert_eq!(vec![Some(1000), Some(900), Some(900), Some(900), None], (0..5).map(|g| vmtx.advance_height(g)).collect::<Vec<_>>());
+        assert_eq!(vec![Some(80), Some(60), Some(45), Some(-10), None], (0..5).map(|g| vmtx.top_side_bearing(g)).collect::<Vec<_>>());
+        assert!(VerticalMetricsTable::from_bytes(&w, 0, 4).is_err());
+        assert!(VerticalMetricsTable::from_bytes(&w, 5, 4).is_err());
+        assert!(VerticalMetricsTable::from_bytes(&w, 2, 5).is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::types::*;
+use super::utils::*;
+
+/// Y coordinates of the vertical origins of CFF glyphs
+pub struct VerticalOriginTable {
+    /// Major version (starting at 1). Set to 1
+    major_version: u16,
+    /// Minor version (starting at 0). Set to 0
+    minor_version: u16,
+    /// The y coordinate of a glyph's vertical origin, used when the glyph has no record
+    default_vert_origin_y: i16,
+    /// Sorted by glyph ID
+    vert_origin_y_metrics: Vec<VertOriginYMetrics>,
+}
+
+impl Debug for VerticalOriginTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VerticalOriginTable")
+            .field("major_version", &self.major_version)
+            .field("minor_version", &self.minor_version)
+            .field("default_vert_origin_y", &self.default_vert_origin_y)
+            .field("vert_origin_y_metrics", &self.vert_origin_y_metrics)
+            .finish()
+    }
+}
+
+impl VerticalOriginTable {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let major_version = read_uint16(r)?;
+        let minor_version = read_uint16(r)?;
+        if 1 != major_version {
+            return Err(format!("unsupported VORG version {}.{}", major_version, minor_version).into());
+        }
+        let default_vert_origin_y = read_int16(r)?;
+        let num_vert_origin_y_metrics = read_uint16(r)?;
+        let vert_origin_y_metrics = read_array(r, num_vert_origin_y_metrics as usize, VertOriginYMetrics::from_bytes)?;
+
+        Ok(Self {
+            major_version,
+            minor_version,
+            default_vert_origin_y,
+            vert_origin_y_metrics,
+        })
+    }
+
+    pub fn default_vert_origin_y(&self) -> i16 {
+        self.default_vert_origin_y
+    }
+
+    pub fn vert_origin_y(&self, glyph_id: GlyphId) -> i16 {
+        match self.vert_origin_y_metrics.binary_search_by_key(&glyph_id, |m| m.glyph_index) {
+            Ok(i) => self.vert_origin_y_metrics[i].vert_origin_y,
+            Err(_) => self.default_vert_origin_y,
+        }
+    }
+}
+
+pub struct VertOriginYMetrics {
+    /// Glyph index
+    glyph_index: GlyphId,
+    /// Y coordinate, in the font's design coordinate system, of the glyph's vertical origin
+    vert_origin_y: i16,
+}
+
+impl Debug for VertOriginYMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VertOriginYMetrics").field("glyph_index", &self.glyph_index).field("vert_origin_y", &self.vert_origin_y).finish()
+    }
+}
+
+impl VertOriginYMetrics {
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let glyph_index = read_uint16(r)?;
+        let vert_origin_y = read_int16(r)?;
+
+        Ok(Self { glyph_index, vert_origin_y })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glyphs_without_a_record_use_the_default() {
+        let mut w = Vec::new();
+        for v in [1, 0, 880, 2, 3, 900, 7, -20] {
+            write_int16(&mut w, v);
+        }
+        let vorg = VerticalOriginTable::from_bytes(&w).unwrap();
+        assert_eq!(880, vorg.default_vert_origin_y());
+        assert_eq!(vec![880, 900, 880, -20, 880], [0, 3, 5, 7, 9].iter().map(|&g| vorg.vert_origin_y(g)).collect::<Vec<_>>());
+        w[1] = 2;
+        assert!(VerticalOriginTable::from_bytes(&w).is_err());
+    }
+}
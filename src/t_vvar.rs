@@ -0,0 +1,103 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::types::*;
+use super::utils::*;
+use super::variations::*;
+
+/// Variations of the advance heights and side bearings in vmtx and of the vertical origins in VORG
+pub struct VerticalMetricsVariationsTable {
+    /// Major version number of the vertical metrics variations table — set to 1
+    major_version: u16,
+    /// Minor version number of the vertical metrics variations table — set to 0
+    minor_version: u16,
+    item_variation_store: ItemVariationStore,
+    /// Without a mapping glyph IDs index the first ItemVariationData directly
+    advance_height_mapping: Option<DeltaSetIndexMap>,
+    tsb_mapping: Option<DeltaSetIndexMap>,
+    bsb_mapping: Option<DeltaSetIndexMap>,
+    v_org_mapping: Option<DeltaSetIndexMap>,
+}
+
+impl Debug for VerticalMetricsVariationsTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VerticalMetricsVariationsTable")
+            .field("major_version", &self.major_version)
+            .field("minor_version", &self.minor_version)
+            .field("item_variation_store", &self.item_variation_store)
+            .field("advance_height_mapping", &self.advance_height_mapping)
+            .field("tsb_mapping", &self.tsb_mapping)
+            .field("bsb_mapping", &self.bsb_mapping)
+            .field("v_org_mapping", &self.v_org_mapping)
+            .finish()
+    }
+}
+
+impl VerticalMetricsVariationsTable {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let major_version = read_uint16(r)?;
+        let minor_version = read_uint16(r)?;
+        if 1 != major_version {
+            return Err(format!("unsupported VVAR version {}.{}", major_version, minor_version).into());
+        }
+        let item_variation_store_offset = read_offset32(r)?;
+        let advance_height_mapping_offset = read_offset32(r)?;
+        let tsb_mapping_offset = read_offset32(r)?;
+        let bsb_mapping_offset = read_offset32(r)?;
+        let v_org_mapping_offset = read_offset32(r)?;
+
+        let item_variation_store = ItemVariationStore::from_bytes(o.get(item_variation_store_offset as usize..).ok_or("VVAR ItemVariationStore offset out of range")?)?;
+        let mapping = |offset: Offset32| match offset {
+            0 => Ok(None),
+            offset => DeltaSetIndexMap::from_bytes(o.get(offset as usize..).ok_or("VVAR DeltaSetIndexMap offset out of range")?).map(Some),
+        };
+        let advance_height_mapping = mapping(advance_height_mapping_offset)?;
+        let tsb_mapping = mapping(tsb_mapping_offset)?;
+        let bsb_mapping = mapping(bsb_mapping_offset)?;
+        let v_org_mapping = mapping(v_org_mapping_offset)?;
+
+        Ok(Self {
+            major_version,
+            minor_version,
+            item_variation_store,
+            advance_height_mapping,
+            tsb_mapping,
+            bsb_mapping,
+            v_org_mapping,
+        })
+    }
+
+    pub fn item_variation_store(&self) -> &ItemVariationStore {
+        &self.item_variation_store
+    }
+
+    /// Advance height delta of a glyph at normalized `coords`
+    pub fn advance_height_delta(&self, glyph_id: GlyphId, coords: &[F2Dot14]) -> Option<f32> {
+        let (outer, inner) = match &self.advance_height_mapping {
+            Some(mapping) => mapping.map(glyph_id as u32)?,
+            None => (0, glyph_id),
+        };
+        self.item_variation_store.delta(outer, inner, coords)
+    }
+
+    /// Top side bearing delta of a glyph, `None` when VVAR has no tsb mapping and the bearing must come from the varied outline
+    pub fn tsb_delta(&self, glyph_id: GlyphId, coords: &[F2Dot14]) -> Option<f32> {
+        let (outer, inner) = self.tsb_mapping.as_ref()?.map(glyph_id as u32)?;
+        self.item_variation_store.delta(outer, inner, coords)
+    }
+
+    /// Bottom side bearing delta of a glyph, `None` when VVAR has no bsb mapping
+    pub fn bsb_delta(&self, glyph_id: GlyphId, coords: &[F2Dot14]) -> Option<f32> {
+        let (outer, inner) = self.bsb_mapping.as_ref()?.map(glyph_id as u32)?;
+        self.item_variation_store.delta(outer, inner, coords)
+    }
+
+    /// Vertical origin delta of a glyph, `None` when VVAR has no VORG mapping
+    pub fn v_org_delta(&self, glyph_id: GlyphId, coords: &[F2Dot14]) -> Option<f32> {
+        let (outer, inner) = self.v_org_mapping.as_ref()?.map(glyph_id as u32)?;
+        self.item_variation_store.delta(outer, inner, coords)
+    }
+}
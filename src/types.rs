@@ -11,9 +11,35 @@ pub type Offset24 = u32;
 pub type Offset32 = u32;
 /// 32-bit signed fixed-point number (16.16)
 pub type Fixed = f32;
+/// 16-bit signed fixed number with the low 14 bits of fraction (2.14)
+pub type F2Dot14 = f32;
+/// Glyph index into the font's glyph set
+pub type GlyphId = u16;
 
+/// Receives the contours of a glyph outline, in font design units
+pub trait OutlineSink {
+    fn move_to(&mut self, x: f32, y: f32);
+    fn line_to(&mut self, x: f32, y: f32);
+    /// Quadratic Bézier curve with one control point
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32);
+    /// Cubic Bézier curve with two control points
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32);
+    fn close(&mut self);
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Tag(pub(super) [u8; 4]);
 
+impl Tag {
+    pub const fn new(tag: &[u8; 4]) -> Self {
+        Self(*tag)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 4] {
+        &self.0
+    }
+}
+
 impl Debug for Tag {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_tuple("Tag").field_with(|f| write!(f, "{}", unsafe { str::from_utf8_unchecked(&self.0) })).finish()
@@ -7,49 +7,49 @@ use super::types::*;
 
 pub fn read_uint8<R: Read>(r: &mut R) -> Result<u8, Box<dyn Error>> {
     let mut bs = [0];
-    r.read(&mut bs)?;
+    r.read_exact(&mut bs)?;
     Ok(bs[0])
 }
 
 pub fn read_int8<R: Read>(r: &mut R) -> Result<i8, Box<dyn Error>> {
     let mut bs = [0];
-    r.read(&mut bs)?;
+    r.read_exact(&mut bs)?;
     Ok(i8::from_be_bytes(bs))
 }
 
 pub fn read_uint16<R: Read>(r: &mut R) -> Result<u16, Box<dyn Error>> {
     let mut bs = [0, 0];
-    r.read(&mut bs)?;
+    r.read_exact(&mut bs)?;
     Ok(u16::from_be_bytes(bs))
 }
 
 pub fn read_int16<R: Read>(r: &mut R) -> Result<i16, Box<dyn Error>> {
     let mut bs = [0, 0];
-    r.read(&mut bs)?;
+    r.read_exact(&mut bs)?;
     Ok(i16::from_be_bytes(bs))
 }
 
 pub fn read_uint24<R: Read>(r: &mut R) -> Result<u32, Box<dyn Error>> {
     let mut bs = [0, 0, 0, 0];
-    r.read(&mut bs[1..4])?;
+    r.read_exact(&mut bs[1..4])?;
     Ok(u32::from_be_bytes(bs))
 }
 
 pub fn read_uint32<R: Read>(r: &mut R) -> Result<u32, Box<dyn Error>> {
     let mut bs = [0, 0, 0, 0];
-    r.read(&mut bs)?;
+    r.read_exact(&mut bs)?;
     Ok(u32::from_be_bytes(bs))
 }
 
 pub fn read_int32<R: Read>(r: &mut R) -> Result<i32, Box<dyn Error>> {
     let mut bs = [0, 0, 0, 0];
-    r.read(&mut bs)?;
+    r.read_exact(&mut bs)?;
     Ok(i32::from_be_bytes(bs))
 }
 
 pub fn read_tag<R: Read>(r: &mut R) -> Result<Tag, Box<dyn Error>> {
     let mut bs = [0, 0, 0, 0];
-    r.read(&mut bs)?;
+    r.read_exact(&mut bs)?;
     Ok(Tag(bs))
 }
 
@@ -75,8 +75,8 @@ pub fn read_offset32<R: Read>(r: &mut R) -> Result<Offset32, Box<dyn Error>> {
 
 pub fn read_version16dot16<R: Read>(r: &mut R) -> Result<Version16Dot16, Box<dyn Error>> {
     let mut bs = [0, 0, 0, 0];
-    r.read(&mut bs)?;
-    Ok(Version16Dot16([(bs[0] as u16) << 8 + bs[1], (bs[2] as u16) << 8 + bs[3]]))
+    r.read_exact(&mut bs)?;
+    Ok(Version16Dot16([u16::from_be_bytes([bs[0], bs[1]]), u16::from_be_bytes([bs[2], bs[3]])]))
 }
 
 pub fn read_fixed<R: Read>(r: &mut R) -> Result<Fixed, Box<dyn Error>> {
@@ -84,12 +84,118 @@ pub fn read_fixed<R: Read>(r: &mut R) -> Result<Fixed, Box<dyn Error>> {
     Ok(i as f32 / (1 << 16) as f32)
 }
 
+pub fn read_f2dot14<R: Read>(r: &mut R) -> Result<F2Dot14, Box<dyn Error>> {
+    let i = read_int16(r)?;
+    Ok(i as f32 / (1 << 14) as f32)
+}
+
 pub fn read_longdatetime<R: Read>(r: &mut R) -> Result<LongDateTime, Box<dyn Error>> {
     let mut bs = [0, 0, 0, 0, 0, 0, 0, 0];
-    r.read(&mut bs)?;
+    r.read_exact(&mut bs)?;
     Ok(LongDateTime(i64::from_be_bytes(bs)))
 }
 
+pub fn read_array<R: Read, T>(r: &mut R, count: usize, f: fn(&mut R) -> Result<T, Box<dyn Error>>) -> Result<Vec<T>, Box<dyn Error>> {
+    // counts come straight from font data, so don't trust them for the allocation
+    let mut v = Vec::with_capacity(count.min(u16::MAX as usize));
+    for _ in 0..count {
+        v.push(f(r)?);
+    }
+    Ok(v)
+}
+
+pub fn write_uint8(w: &mut Vec<u8>, v: u8) {
+    w.push(v);
+}
+
+pub fn write_int8(w: &mut Vec<u8>, v: i8) {
+    w.extend(v.to_be_bytes());
+}
+
+pub fn write_uint16(w: &mut Vec<u8>, v: u16) {
+    w.extend(v.to_be_bytes());
+}
+
+pub fn write_int16(w: &mut Vec<u8>, v: i16) {
+    w.extend(v.to_be_bytes());
+}
+
+pub fn write_uint32(w: &mut Vec<u8>, v: u32) {
+    w.extend(v.to_be_bytes());
+}
+
+pub fn write_int32(w: &mut Vec<u8>, v: i32) {
+    w.extend(v.to_be_bytes());
+}
+
+pub fn write_tag(w: &mut Vec<u8>, v: Tag) {
+    w.extend(v.0);
+}
+
+pub fn write_fixed(w: &mut Vec<u8>, v: Fixed) {
+    write_int32(w, (v as f64 * 65536.0).round() as i32);
+}
+
+pub fn write_f2dot14(w: &mut Vec<u8>, v: F2Dot14) {
+    write_int16(w, (v * 16384.0).round() as i16);
+}
+
+/// The big endian u16 at `pos`, for walking table bytes that are patched in place
+pub fn get_uint16(b: &[u8], pos: usize) -> Result<u16, Box<dyn Error>> {
+    read_uint16(&mut b.get(pos..).ok_or("table position out of range")?)
+}
+
+pub fn get_int16(b: &[u8], pos: usize) -> Result<i16, Box<dyn Error>> {
+    Ok(get_uint16(b, pos)? as i16)
+}
+
+pub fn get_uint32(b: &[u8], pos: usize) -> Result<u32, Box<dyn Error>> {
+    read_uint32(&mut b.get(pos..).ok_or("table position out of range")?)
+}
+
+/// Overwrite the big endian u16 at `pos`, for patching fields of a table in place
+pub fn put_uint16(b: &mut [u8], pos: usize, v: u16) -> Result<(), Box<dyn Error>> {
+    b.get_mut(pos..pos + 2).ok_or("patch position out of range")?.copy_from_slice(&v.to_be_bytes());
+    Ok(())
+}
+
+pub fn put_int16(b: &mut [u8], pos: usize, v: i16) -> Result<(), Box<dyn Error>> {
+    put_uint16(b, pos, v as u16)
+}
+
+pub fn put_uint32(b: &mut [u8], pos: usize, v: u32) -> Result<(), Box<dyn Error>> {
+    b.get_mut(pos..pos + 4).ok_or("patch position out of range")?.copy_from_slice(&v.to_be_bytes());
+    Ok(())
+}
+
+/// Round half up, the way font tools round varied values back to integers
+pub fn ot_round(v: f32) -> i32 {
+    (v + 0.5).floor() as i32
+}
+
+/// Unicode values of the Mac OS Roman codes 0x80..=0xFF, codes below 0x80 are ASCII
+const MAC_ROMAN: [u16; 128] = [
+    0x00C4, 0x00C5, 0x00C7, 0x00C9, 0x00D1, 0x00D6, 0x00DC, 0x00E1, 0x00E0, 0x00E2, 0x00E4, 0x00E3, 0x00E5, 0x00E7, 0x00E9, 0x00E8,
+    0x00EA, 0x00EB, 0x00ED, 0x00EC, 0x00EE, 0x00EF, 0x00F1, 0x00F3, 0x00F2, 0x00F4, 0x00F6, 0x00F5, 0x00FA, 0x00F9, 0x00FB, 0x00FC,
+    0x2020, 0x00B0, 0x00A2, 0x00A3, 0x00A7, 0x2022, 0x00B6, 0x00DF, 0x00AE, 0x00A9, 0x2122, 0x00B4, 0x00A8, 0x2260, 0x00C6, 0x00D8,
+    0x221E, 0x00B1, 0x2264, 0x2265, 0x00A5, 0x00B5, 0x2202, 0x2211, 0x220F, 0x03C0, 0x222B, 0x00AA, 0x00BA, 0x03A9, 0x00E6, 0x00F8,
+    0x00BF, 0x00A1, 0x00AC, 0x221A, 0x0192, 0x2248, 0x2206, 0x00AB, 0x00BB, 0x2026, 0x00A0, 0x00C0, 0x00C3, 0x00D5, 0x0152, 0x0153,
+    0x2013, 0x2014, 0x201C, 0x201D, 0x2018, 0x2019, 0x00F7, 0x25CA, 0x00FF, 0x0178, 0x2044, 0x20AC, 0x2039, 0x203A, 0xFB01, 0xFB02,
+    0x2021, 0x00B7, 0x201A, 0x201E, 0x2030, 0x00C2, 0x00CA, 0x00C1, 0x00CB, 0x00C8, 0x00CD, 0x00CE, 0x00CF, 0x00CC, 0x00D3, 0x00D4,
+    0xF8FF, 0x00D2, 0x00DA, 0x00DB, 0x00D9, 0x0131, 0x02C6, 0x02DC, 0x00AF, 0x02D8, 0x02D9, 0x02DA, 0x00B8, 0x02DD, 0x02DB, 0x02C7,
+];
+
+pub fn mac_roman_to_unicode(code: u8) -> char {
+    if code < 0x80 { code as char } else { char::from_u32(MAC_ROMAN[(code - 0x80) as usize] as u32).unwrap_or(char::REPLACEMENT_CHARACTER) }
+}
+
+pub fn unicode_to_mac_roman(code_point: u32) -> Option<u8> {
+    if code_point < 0x80 {
+        return Some(code_point as u8);
+    }
+    MAC_ROMAN.iter().position(|&u| u as u32 == code_point).map(|i| 0x80 + i as u8)
+}
+
 pub fn get_platform_desc(platform_id: u16) -> &'static str {
     match platform_id {
         0 => "Unicode",
@@ -0,0 +1,410 @@
+use std::error::Error;
+use std::fmt::Debug;
+
+use super::types::*;
+use super::utils::*;
+
+/// Delta sets for variable values, shared by CFF2, GDEF, HVAR, VVAR and MVAR
+pub struct ItemVariationStore {
+    format: u16,
+    variation_region_list: VariationRegionList,
+    item_variation_data: Vec<ItemVariationData>,
+}
+
+impl Debug for ItemVariationStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ItemVariationStore")
+            .field("format", &self.format)
+            .field("variation_region_list", &self.variation_region_list)
+            .field("item_variation_data", &self.item_variation_data)
+            .finish()
+    }
+}
+
+impl ItemVariationStore {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let o = b;
+        let r = &mut b;
+
+        let format = read_uint16(r)?;
+        if 1 != format {
+            return Err(format!("unsupported ItemVariationStore format {}", format).into());
+        }
+
+        let variation_region_list_offset = read_offset32(r)?;
+        let item_variation_data_count = read_uint16(r)?;
+        let item_variation_data_offsets = read_array(r, item_variation_data_count as usize, read_offset32)?;
+
+        let variation_region_list = VariationRegionList::from_bytes(o.get(variation_region_list_offset as usize..).ok_or("VariationRegionList offset out of range")?)?;
+
+        let mut item_variation_data = Vec::with_capacity(item_variation_data_offsets.len());
+        for offset in item_variation_data_offsets {
+            let ivd = ItemVariationData::from_bytes(o.get(offset as usize..).ok_or("ItemVariationData offset out of range")?)?;
+            if ivd.region_indexes.iter().any(|&i| i as usize >= variation_region_list.regions.len()) {
+                return Err("ItemVariationData references a region past the VariationRegionList".into());
+            }
+            item_variation_data.push(ivd);
+        }
+
+        Ok(Self {
+            format,
+            variation_region_list,
+            item_variation_data,
+        })
+    }
+
+    pub fn axis_count(&self) -> u16 {
+        self.variation_region_list.axis_count
+    }
+
+    pub fn item_variation_data(&self) -> &[ItemVariationData] {
+        &self.item_variation_data
+    }
+
+    /// Number of regions the subtable `outer` refers to, CFF2 blend reads that many deltas per value
+    pub fn region_index_count(&self, outer: u16) -> Option<usize> {
+        Some(self.item_variation_data.get(outer as usize)?.region_indexes.len())
+    }
+
+    /// Scalar of each region referenced by subtable `outer`, in the subtable's region order
+    pub fn region_scalars(&self, outer: u16, coords: &[F2Dot14]) -> Option<Vec<f32>> {
+        let ivd = self.item_variation_data.get(outer as usize)?;
+        Some(ivd.region_indexes.iter().map(|&i| self.variation_region_list.regions[i as usize].scalar(coords)).collect())
+    }
+
+    /// Interpolated delta of item `inner` in subtable `outer`
+    pub fn delta(&self, outer: u16, inner: u16, coords: &[F2Dot14]) -> Option<f32> {
+        let ivd = self.item_variation_data.get(outer as usize)?;
+        let deltas = ivd.delta_sets.get(inner as usize)?;
+
+        let mut v = 0.0;
+        for (&region_index, &delta) in ivd.region_indexes.iter().zip(deltas) {
+            if 0 == delta {
+                continue;
+            }
+            v += delta as f32 * self.variation_region_list.regions[region_index as usize].scalar(coords);
+        }
+        Some(v)
+    }
+}
+
+pub struct VariationRegionList {
+    /// The number of variation axes for this font. This must be the same number as axisCount in the 'fvar' table.
+    axis_count: u16,
+    regions: Vec<VariationRegion>,
+}
+
+impl Debug for VariationRegionList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VariationRegionList").field("axis_count", &self.axis_count).field("regions", &self.regions).finish()
+    }
+}
+
+impl VariationRegionList {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let axis_count = read_uint16(r)?;
+        let region_count = read_uint16(r)?;
+        let mut regions = Vec::with_capacity(region_count as usize);
+        for _ in 0..region_count {
+            regions.push(VariationRegion::from_bytes(r, axis_count)?);
+        }
+
+        Ok(Self { axis_count, regions })
+    }
+}
+
+/// Region of the design space, one start/peak/end triple per axis
+pub struct VariationRegion {
+    region_axes: Vec<RegionAxisCoordinates>,
+}
+
+impl Debug for VariationRegion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.region_axes.iter()).finish()
+    }
+}
+
+impl VariationRegion {
+    pub fn from_bytes(r: &mut &[u8], axis_count: u16) -> Result<Self, Box<dyn Error>> {
+        let mut region_axes = Vec::with_capacity(axis_count as usize);
+        for _ in 0..axis_count {
+            region_axes.push(RegionAxisCoordinates::from_bytes(r)?);
+        }
+        Ok(Self { region_axes })
+    }
+
+    /// How much of a delta applies at `coords`, axes without a coordinate are at their default
+    pub fn scalar(&self, coords: &[F2Dot14]) -> f32 {
+        let mut scalar = 1.0;
+        for (i, axis) in self.region_axes.iter().enumerate() {
+            scalar *= axis.scalar(coords.get(i).copied().unwrap_or(0.0));
+            if 0.0 == scalar {
+                break;
+            }
+        }
+        scalar
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegionAxisCoordinates {
+    start_coord: F2Dot14,
+    peak_coord: F2Dot14,
+    end_coord: F2Dot14,
+}
+
+impl RegionAxisCoordinates {
+    pub fn from_bytes(r: &mut &[u8]) -> Result<Self, Box<dyn Error>> {
+        let start_coord = read_f2dot14(r)?;
+        let peak_coord = read_f2dot14(r)?;
+        let end_coord = read_f2dot14(r)?;
+
+        Ok(Self { start_coord, peak_coord, end_coord })
+    }
+
+    pub fn new(start_coord: F2Dot14, peak_coord: F2Dot14, end_coord: F2Dot14) -> Self {
+        Self { start_coord, peak_coord, end_coord }
+    }
+
+    /// Tent function peaking at 1 on `peak_coord`, invalid or zero peak axes don't restrict the region
+    pub fn scalar(&self, coord: F2Dot14) -> f32 {
+        let (start, peak, end) = (self.start_coord, self.peak_coord, self.end_coord);
+        if start > peak || peak > end || (start < 0.0 && end > 0.0) || 0.0 == peak {
+            return 1.0;
+        }
+        if coord == peak {
+            1.0
+        } else if coord <= start || coord >= end {
+            0.0
+        } else if coord < peak {
+            (coord - start) / (peak - start)
+        } else {
+            (end - coord) / (end - peak)
+        }
+    }
+}
+
+const LONG_WORDS: u16 = 0x8000;
+const WORD_DELTA_COUNT_MASK: u16 = 0x7FFF;
+
+pub struct ItemVariationData {
+    /// Array of indices into the variation region list for the regions referenced by this item variation data table
+    region_indexes: Vec<u16>,
+    /// One row of deltas per item, one delta per referenced region
+    delta_sets: Vec<Vec<i32>>,
+}
+
+impl Debug for ItemVariationData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ItemVariationData")
+            .field("region_indexes", &self.region_indexes)
+            .field_with("delta_sets", |f| write!(f, "[[i32; {}]; {}]", self.region_indexes.len(), self.delta_sets.len()))
+            .finish()
+    }
+}
+
+impl ItemVariationData {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let item_count = read_uint16(r)?;
+        let word_delta_count = read_uint16(r)?;
+        let region_index_count = read_uint16(r)?;
+        let region_indexes = read_array(r, region_index_count as usize, read_uint16)?;
+
+        // the first `word_count` deltas of a row are words, the rest bytes, and with LONG_WORDS set words are 32 bit and bytes 16 bit
+        let long_words = 0 != word_delta_count & LONG_WORDS;
+        let word_count = (word_delta_count & WORD_DELTA_COUNT_MASK) as usize;
+        if word_count > region_index_count as usize {
+            return Err("ItemVariationData has more word deltas than regions".into());
+        }
+
+        let mut delta_sets = Vec::with_capacity(item_count as usize);
+        for _ in 0..item_count {
+            let mut row = Vec::with_capacity(region_index_count as usize);
+            for i in 0..region_index_count as usize {
+                let delta = match (i < word_count, long_words) {
+                    (true, true) => read_int32(r)?,
+                    (true, false) | (false, true) => read_int16(r)? as i32,
+                    (false, false) => read_int8(r)? as i32,
+                };
+                row.push(delta);
+            }
+            delta_sets.push(row);
+        }
+
+        Ok(Self { region_indexes, delta_sets })
+    }
+
+    pub fn item_count(&self) -> usize {
+        self.delta_sets.len()
+    }
+
+    pub fn region_indexes(&self) -> &[u16] {
+        &self.region_indexes
+    }
+}
+
+const INNER_INDEX_BIT_COUNT_MASK: u8 = 0x0F;
+const MAP_ENTRY_SIZE_MASK: u8 = 0x30;
+
+/// Maps an item index, such as a glyph ID, to the outer and inner indices of an ItemVariationStore delta set
+pub struct DeltaSetIndexMap {
+    format: u8,
+    /// A packed field that describes the compressed representation of delta-set indices
+    entry_format: u8,
+    /// Outer and inner index of each entry
+    map_data: Vec<(u16, u16)>,
+}
+
+impl Debug for DeltaSetIndexMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeltaSetIndexMap")
+            .field("format", &self.format)
+            .field_with("entry_format", |f| write!(f, "0x{:02x}", &self.entry_format))
+            .field_with("map_data", |f| write!(f, "[(u16, u16); {}]", self.map_data.len()))
+            .finish()
+    }
+}
+
+impl DeltaSetIndexMap {
+    pub fn from_bytes(mut b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let r = &mut b;
+
+        let format = read_uint8(r)?;
+        let entry_format = read_uint8(r)?;
+        let map_count = match format {
+            0 => read_uint16(r)? as u32,
+            1 => read_uint32(r)?,
+            _ => return Err(format!("unsupported DeltaSetIndexMap format {}", format).into()),
+        };
+
+        // entries are 1 to 4 byte big endian integers, the low bits hold the inner index and the rest the outer index
+        let entry_size = (((entry_format & MAP_ENTRY_SIZE_MASK) >> 4) + 1) as usize;
+        let inner_bit_count = (entry_format & INNER_INDEX_BIT_COUNT_MASK) + 1;
+        let data = r.get(..map_count as usize * entry_size).ok_or("DeltaSetIndexMap data out of range")?;
+        let map_data = data
+            .chunks_exact(entry_size)
+            .map(|c| {
+                let entry = c.iter().fold(0u32, |v, &b| v << 8 | b as u32);
+                ((entry >> inner_bit_count) as u16, (entry & ((1 << inner_bit_count) - 1)) as u16)
+            })
+            .collect();
+
+        Ok(Self { format, entry_format, map_data })
+    }
+
+    pub fn map_count(&self) -> usize {
+        self.map_data.len()
+    }
+
+    /// Outer and inner delta set index of item `index`, items past the end of the map use its last entry
+    pub fn map(&self, index: u32) -> Option<(u16, u16)> {
+        self.map_data.get(index as usize).or(self.map_data.last()).copied()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod builders {
+    use super::*;
+
+    /// ItemVariationStore with one ItemVariationData referencing every region in order, each item a row of word deltas
+    pub fn item_variation_store(regions: &[&[(F2Dot14, F2Dot14, F2Dot14)]], items: &[&[i16]]) -> Vec<u8> {
+        let axis_count = regions.first().map_or(0, |r| r.len());
+        let mut w = Vec::new();
+        write_uint16(&mut w, 1);
+        write_uint32(&mut w, 12);
+        write_uint16(&mut w, 1);
+        write_uint32(&mut w, 12 + 4 + 6 * (axis_count * regions.len()) as u32);
+        [axis_count, regions.len()].iter().for_each(|&v| write_uint16(&mut w, v as u16));
+        regions.iter().flat_map(|r| r.iter()).for_each(|&(start, peak, end)| [start, peak, end].iter().for_each(|&v| write_f2dot14(&mut w, v)));
+        [items.len(), regions.len(), regions.len()].iter().for_each(|&v| write_uint16(&mut w, v as u16));
+        (0..regions.len()).for_each(|i| write_uint16(&mut w, i as u16));
+        items.iter().flat_map(|item| item.iter()).for_each(|&v| write_int16(&mut w, v));
+        w
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::builders::*;
+    use super::*;
+
+    #[test]
+    fn region_scalars() {
+        let axis = RegionAxisCoordinates::new;
+        assert_eq!(vec![0.0, 0.5, 1.0, 0.5, 0.0], [0.0, 0.25, 0.5, 0.75, 1.0].iter().map(|&c| axis(0.0, 0.5, 1.0).scalar(c)).collect::<Vec<_>>());
+        assert_eq!(vec![0.0, 0.5, 1.0], [0.0, -0.5, -1.0].iter().map(|&c| axis(-1.0, -1.0, 0.0).scalar(c)).collect::<Vec<_>>());
+        // zero peaks, unordered triples and ranges crossing the default don't restrict a region
+        assert_eq!((1.0, 1.0, 1.0), (axis(0.0, 0.0, 0.0).scalar(0.7), axis(0.5, 0.2, 1.0).scalar(0.7), axis(-0.5, 0.5, 1.0).scalar(0.7)));
+    }
+
+    #[test]
+    fn deltas() {
+        let b = item_variation_store(&[&[(0.0, 1.0, 1.0), (0.0, 0.0, 0.0)], &[(0.0, 1.0, 1.0), (-1.0, -1.0, 0.0)]], &[&[300, -20], &[-1000, 5]]);
+        let ivs = ItemVariationStore::from_bytes(&b).unwrap();
+        assert_eq!((2, Some(2)), (ivs.axis_count(), ivs.region_index_count(0)));
+        assert_eq!(Some(vec![0.5, 0.25]), ivs.region_scalars(0, &[0.5, -0.5]));
+        assert_eq!(Some(0.0), ivs.delta(0, 0, &[]));
+        assert_eq!(Some(150.0), ivs.delta(0, 0, &[0.5]));
+        assert_eq!(Some(145.0), ivs.delta(0, 0, &[0.5, -0.5]));
+        assert_eq!(Some(-995.0), ivs.delta(0, 1, &[1.0, -1.0]));
+        assert_eq!((None, None), (ivs.delta(0, 2, &[1.0]), ivs.delta(1, 0, &[1.0])));
+    }
+
+    #[test]
+    fn regions_past_the_region_list() {
+        let mut b = item_variation_store(&[&[(0.0, 1.0, 1.0)]], &[&[100]]);
+        // the single region index
+        put_uint16(&mut b, 28, 1).unwrap();
+        assert!(ItemVariationStore::from_bytes(&b).is_err());
+    }
+
+    #[test]
+    fn delta_sizes() {
+        // a word then a byte delta per row
+        let mut w = Vec::new();
+        [2, 1, 2, 0, 1, 300].iter().for_each(|&v| write_int16(&mut w, v));
+        write_int8(&mut w, -3);
+        write_int16(&mut w, -300);
+        write_int8(&mut w, 4);
+        let ivd = ItemVariationData::from_bytes(&w).unwrap();
+        assert_eq!((2, &[0, 1][..]), (ivd.item_count(), ivd.region_indexes()));
+        assert_eq!(vec![vec![300, -3], vec![-300, 4]], ivd.delta_sets);
+
+        // with LONG_WORDS, 32-bit words then 16-bit bytes
+        let mut w = Vec::new();
+        [1, 0x8001, 2, 0, 1].iter().for_each(|&v| write_uint16(&mut w, v));
+        write_int32(&mut w, 100000);
+        write_int16(&mut w, -300);
+        assert_eq!(vec![vec![100000, -300]], ItemVariationData::from_bytes(&w).unwrap().delta_sets);
+
+        let mut w = Vec::new();
+        [1, 2, 1, 0].iter().for_each(|&v| write_uint16(&mut w, v));
+        assert!(ItemVariationData::from_bytes(&w).is_err());
+    }
+
+    #[test]
+    fn delta_set_index_map_entry_formats() {
+        // 1-byte entries with 1 inner bit
+        let map = DeltaSetIndexMap::from_bytes(&[0, 0x00, 0, 3, 0b101, 0b010, 0b001]).unwrap();
+        assert_eq!(3, map.map_count());
+        assert_eq!(vec![Some((2, 1)), Some((1, 0)), Some((0, 1)), Some((0, 1))], (0..4).map(|i| map.map(i)).collect::<Vec<_>>());
+
+        // 2-byte entries with 4 inner bits, format 1 has a 32-bit count
+        let map = DeltaSetIndexMap::from_bytes(&[1, 0x13, 0, 0, 0, 2, 0x01, 0x23, 0x00, 0x0F]).unwrap();
+        assert_eq!((Some((0x12, 3)), Some((0, 15))), (map.map(0), map.map(1)));
+
+        // 3 and 4-byte entries with 16 inner bits
+        let map = DeltaSetIndexMap::from_bytes(&[0, 0x2F, 0, 1, 0x01, 0x00, 0x02]).unwrap();
+        assert_eq!(Some((1, 2)), map.map(0));
+        let map = DeltaSetIndexMap::from_bytes(&[0, 0x3F, 0, 1, 0x00, 0x03, 0x00, 0x04]).unwrap();
+        assert_eq!(Some((3, 4)), map.map(7));
+
+        assert_eq!(None, DeltaSetIndexMap::from_bytes(&[0, 0, 0, 0]).unwrap().map(0));
+        assert!(DeltaSetIndexMap::from_bytes(&[0, 0x10, 0, 2, 0, 0, 0]).is_err());
+        assert!(DeltaSetIndexMap::from_bytes(&[2, 0, 0, 0]).is_err());
+    }
+}